@@ -610,4 +610,27 @@ data:extend {
         },
         power_consumption = "100W",
     },
+    {
+        type = "warehouse",
+        order = "m-0",
+        name = "warehouse",
+        label = "Warehouse",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 0.6,
+        },
+        kind = "factory",
+        n_trucks = 2,
+        n_workers = 5,
+        capacity = {
+            {"cereal", 100},
+            {"flour", 100},
+            {"bread", 50},
+            {"vegetable", 100},
+        },
+        size = 60.0,
+        asset = "assets/sprites/cement.jpg",
+        price = 2000,
+        power_consumption = "500W",
+    },
 }
@@ -0,0 +1,4 @@
+return {
+    name = "base",
+    dependencies = {},
+}
@@ -3,6 +3,7 @@ data:extend {
         type = "road-vehicle",
         order = "a-1",
         name = "simple_car",
+        kind = "car",
         label = "Simple Car",
         max_speed = 50.0,
         acceleration = 8.0,
@@ -14,10 +15,12 @@ data:extend {
         type = "road-vehicle",
         order = "b-1",
         name = "simple_truck",
+        kind = "truck",
         label = "simple truck",
         max_speed = 22.0,
         acceleration = 6.0,
         deceleration = 10.0,
+        capacity = 20,
         asset = "truck.glb",
         price = 100.0,
     }
@@ -35,6 +35,7 @@ data:extend {
         max_speed = 160.0,
         acc_force = 0.0,
         dec_force = 480.0,
+        capacity = 50,
         asset = "wagon_freight.glb",
         price = 100,
     },
@@ -27,6 +27,22 @@ pub fn parse_f64(x: &str) -> fast_float::Result<(f64, &str)> {
         .map(|(v, l)| (v, if l == x.len() { "" } else { &x[l..] }))
 }
 
+/// Player inputs that name their sender, such as chat messages.
+/// The server stamps each input it receives with the identity of the player that sent it,
+/// so that players cannot impersonate each other.
+pub trait StampSender {
+    /// `color` is the chat color of the player as 0xRRGGBB
+    fn stamp_sender(&mut self, name: &str, color: u32);
+}
+
+/// Compares secrets without leaking how many leading bytes match through the time it takes
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 pub struct AccessCmp<'a, T, F>(pub &'a T, pub F);
 
 impl<'a, T, F, U> PartialOrd<Self> for AccessCmp<'a, T, F>
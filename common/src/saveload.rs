@@ -125,24 +125,38 @@ impl Encoder for Bincode {
     }
 }
 
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(data, 1) // bigger level values take far too long and only compress a bit better (about 5%)
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_zlib(data)
+        .map_err(|_| std::io::Error::new(ErrorKind::Other, "could not decode zipped file"))
+}
+
 pub struct CompressedBincode;
 
 impl Encoder for CompressedBincode {
     const EXTENSION: &'static str = "zip";
 
     fn encode(x: &impl Serialize) -> Result<Vec<u8>> {
-        let encoded = &*Bincode::encode(x)?;
-        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(encoded, 1); // bigger level values take far too long and only compress a bit better (about 5%)
-        Ok(compressed)
+        Ok(compress(&Bincode::encode(x)?))
     }
 
     fn decode<T: DeserializeOwned>(x: &[u8]) -> Result<T> {
-        let v = &miniz_oxide::inflate::decompress_to_vec_zlib(x)
-            .map_err(|_| std::io::Error::new(ErrorKind::Other, "could not decode zipped file"))?;
-        Bincode::decode(v)
+        Bincode::decode(&decompress(x)?)
     }
 }
 
+/// Something that can be split into named blobs encoded separately, so that the blobs that did
+/// not change can be reused instead of being sent again
+pub trait Blobs: Sized {
+    /// Identical parts must give identical blobs, in the same order
+    fn to_blobs(&self) -> Result<Vec<(String, Vec<u8>)>>;
+
+    fn from_blobs(blobs: Vec<(String, Vec<u8>)>) -> Option<Self>;
+}
+
 pub struct JSON;
 
 impl Encoder for JSON {
@@ -0,0 +1,345 @@
+//! Admin commands of the dedicated server, read from the console and from an optional
+//! TCP control socket speaking line-delimited JSON.
+//!
+//! The first line sent on the socket must be `{"token": "<admin token>"}`,
+//! then each line is a command such as `{"command": "kick", "name": "bob"}`
+//! and is answered by a single line of JSON.
+//! The socket only accepts local connections unless another bind address is given.
+
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use networking::PlayerInfo;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum AdminCommand {
+    Players,
+    Kick {
+        name: String,
+    },
+    /// Frees the name so anyone can use it again, for example when its player lost its token
+    Forget {
+        name: String,
+    },
+    /// Accepts the inputs of the player
+    Allow {
+        name: String,
+    },
+    /// Ignores the inputs of the player, who can still watch
+    Deny {
+        name: String,
+    },
+    Save,
+    Pause,
+    Resume,
+    Speed {
+        multiplier: f64,
+    },
+    Say {
+        text: String,
+    },
+    Stats,
+}
+
+impl AdminCommand {
+    /// Parses a console line such as "kick bob", "speed 2" or "say hello everyone"
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (cmd, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+
+        let no_arg = |c: AdminCommand| {
+            if !arg.is_empty() {
+                return Err(format!("{} does not take an argument", cmd));
+            }
+            Ok(c)
+        };
+
+        match cmd {
+            "players" => no_arg(AdminCommand::Players),
+            "save" => no_arg(AdminCommand::Save),
+            "pause" => no_arg(AdminCommand::Pause),
+            "resume" => no_arg(AdminCommand::Resume),
+            "stats" => no_arg(AdminCommand::Stats),
+            "kick" | "forget" | "allow" | "deny" if arg.is_empty() => {
+                Err(format!("usage: {} <name>", cmd))
+            }
+            "kick" => Ok(AdminCommand::Kick {
+                name: arg.to_string(),
+            }),
+            "forget" => Ok(AdminCommand::Forget {
+                name: arg.to_string(),
+            }),
+            "allow" => Ok(AdminCommand::Allow {
+                name: arg.to_string(),
+            }),
+            "deny" => Ok(AdminCommand::Deny {
+                name: arg.to_string(),
+            }),
+            "say" if !arg.is_empty() => Ok(AdminCommand::Say {
+                text: arg.to_string(),
+            }),
+            "say" => Err("usage: say <text>".to_string()),
+            "speed" => arg
+                .parse()
+                .map(|multiplier| AdminCommand::Speed { multiplier })
+                .map_err(|_| "usage: speed <multiplier>".to_string()),
+            _ => Err(format!(
+                "unknown command {:?}, available commands: players, kick, forget, allow, deny, save, pause, resume, speed, say, stats",
+                cmd
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStats {
+    pub frame: u64,
+    pub paused: bool,
+    pub speed: f64,
+    pub tick_time_ms: f64,
+    pub players: usize,
+    pub population: usize,
+    pub money: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum AdminResponse {
+    Ok,
+    Error { message: String },
+    Players { players: Vec<PlayerInfo> },
+    Stats(ServerStats),
+}
+
+impl Display for AdminResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdminResponse::Ok => write!(f, "ok"),
+            AdminResponse::Error { message } => write!(f, "error: {}", message),
+            AdminResponse::Players { players } => {
+                if players.is_empty() {
+                    return write!(f, "no players connected");
+                }
+                for (i, p) in players.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{} ({}) {}", p.name, p.addr, p.state)?;
+                    if let Some(lag) = p.lag {
+                        write!(f, ", {} frames behind", lag)?;
+                    }
+                }
+                Ok(())
+            }
+            AdminResponse::Stats(s) => write!(
+                f,
+                "frame {}{}, speed x{}, tick {:.2}ms, {} players, {} people, {} in the bank",
+                s.frame,
+                if s.paused { " (paused)" } else { "" },
+                s.speed,
+                s.tick_time_ms,
+                s.players,
+                s.population,
+                s.money
+            ),
+        }
+    }
+}
+
+/// Time given to a new connection to send its token
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Authenticated connections are closed after being idle this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+/// Longer lines close the connection, no command needs that much
+const MAX_LINE_LEN: usize = 16 * 1024;
+const MAX_CONNECTIONS: usize = 4;
+
+/// A command waiting to be handled by the server loop
+pub struct AdminRequest {
+    pub command: AdminCommand,
+    pub reply: Sender<AdminResponse>,
+}
+
+/// Starts reading commands from stdin, and from the control socket if a port is given
+pub fn start(bind: &str, port: Option<u16>, token: Option<String>) -> Receiver<AdminRequest> {
+    let (tx, rx) = channel();
+
+    let console_tx = tx.clone();
+    std::thread::spawn(move || console(console_tx));
+
+    if let (Some(port), Some(token)) = (port, token) {
+        match TcpListener::bind((bind, port)) {
+            Ok(listener) => {
+                log::info!("admin socket listening on {}:{}", bind, port);
+                std::thread::spawn(move || {
+                    let open = Arc::new(AtomicUsize::new(0));
+                    for mut stream in listener.incoming().flatten() {
+                        if open.load(Ordering::SeqCst) >= MAX_CONNECTIONS {
+                            let _ = respond(
+                                &mut stream,
+                                &AdminResponse::Error {
+                                    message: "too many admin connections".to_string(),
+                                },
+                            );
+                            continue;
+                        }
+                        open.fetch_add(1, Ordering::SeqCst);
+                        let open = open.clone();
+                        let tx = tx.clone();
+                        let token = token.clone();
+                        std::thread::spawn(move || {
+                            let addr = stream.peer_addr().ok();
+                            if let Err(e) = admin_connection(stream, &token, tx) {
+                                log::warn!("admin connection {:?} closed: {}", addr, e);
+                            }
+                            open.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    }
+                });
+            }
+            Err(e) => log::error!(
+                "could not bind the admin socket on {}:{}: {}",
+                bind,
+                port,
+                e
+            ),
+        }
+    }
+
+    rx
+}
+
+fn request(tx: &Sender<AdminRequest>, command: AdminCommand) -> Option<AdminResponse> {
+    let (reply, reply_rx) = channel();
+    tx.send(AdminRequest { command, reply }).ok()?;
+    reply_rx.recv().ok()
+}
+
+fn console(tx: Sender<AdminRequest>) {
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match AdminCommand::parse(&line) {
+            Ok(command) => match request(&tx, command) {
+                Some(r) => r,
+                None => return,
+            },
+            Err(message) => AdminResponse::Error { message },
+        };
+        println!("{}", response);
+    }
+}
+
+#[derive(Deserialize)]
+struct AdminAuth {
+    token: String,
+}
+
+fn respond(w: &mut impl Write, response: &AdminResponse) -> std::io::Result<()> {
+    let mut json = serde_json::to_string(response)?;
+    json.push('\n');
+    w.write_all(json.as_bytes())
+}
+
+/// Reads a line without its newline, None at the end of the stream.
+/// Fails on lines longer than [`MAX_LINE_LEN`] instead of buffering them.
+fn read_line(r: &mut impl BufRead) -> std::io::Result<Option<String>> {
+    let mut buf = Vec::new();
+    r.by_ref()
+        .take(MAX_LINE_LEN as u64 + 1)
+        .read_until(b'\n', &mut buf)?;
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+    } else if buf.len() > MAX_LINE_LEN {
+        return Err(std::io::Error::new(ErrorKind::InvalidData, "line too long"));
+    }
+    String::from_utf8(buf)
+        .map(Some)
+        .map_err(|e| std::io::Error::new(ErrorKind::InvalidData, e))
+}
+
+fn admin_connection(
+    stream: TcpStream,
+    token: &str,
+    tx: Sender<AdminRequest>,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let authenticated = read_line(&mut reader)?
+        .and_then(|l| serde_json::from_str::<AdminAuth>(&l).ok())
+        .map_or(false, |auth| {
+            common::constant_time_eq(auth.token.as_bytes(), token.as_bytes())
+        });
+    if !authenticated {
+        respond(
+            &mut writer,
+            &AdminResponse::Error {
+                message: "invalid token".to_string(),
+            },
+        )?;
+        return Ok(());
+    }
+    respond(&mut writer, &AdminResponse::Ok)?;
+    reader.get_ref().set_read_timeout(Some(IDLE_TIMEOUT))?;
+
+    while let Some(line) = read_line(&mut reader)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<AdminCommand>(&line) {
+            Ok(command) => match request(&tx, command) {
+                Some(r) => r,
+                None => return Ok(()),
+            },
+            Err(e) => AdminResponse::Error {
+                message: e.to_string(),
+            },
+        };
+        respond(&mut writer, &response)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_lines_are_refused() {
+        let mut r = "a\n\nb".as_bytes();
+        assert_eq!(read_line(&mut r).unwrap().as_deref(), Some("a"));
+        assert_eq!(read_line(&mut r).unwrap().as_deref(), Some(""));
+        assert_eq!(read_line(&mut r).unwrap().as_deref(), Some("b"));
+        assert_eq!(read_line(&mut r).unwrap(), None);
+
+        let long = "a".repeat(MAX_LINE_LEN + 10);
+        assert!(read_line(&mut long.as_bytes()).is_err());
+        let max = format!("{}\n", "a".repeat(MAX_LINE_LEN));
+        assert!(read_line(&mut max.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn tokens_are_compared_fully() {
+        assert!(common::constant_time_eq(b"secret", b"secret"));
+        assert!(!common::constant_time_eq(b"secret", b"secreT"));
+        assert!(!common::constant_time_eq(b"secret", b"secret2"));
+        assert!(!common::constant_time_eq(b"", b"secret"));
+    }
+}
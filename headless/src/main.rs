@@ -1,11 +1,21 @@
+use crate::admin::{AdminCommand, AdminResponse, ServerStats};
 use common::logger::MyLog;
+use common::saveload::{CompressedBincode, Encoder, JSON};
 use common::unwrap_or;
-use networking::{Frame, Server, ServerConfiguration, ServerPollResult};
-use simulation::world_command::WorldCommands;
-use simulation::Simulation;
+use networking::{Frame, GameSpeed, PlayerRegistry, Server, ServerConfiguration, ServerPollResult};
+use prototypes::{GameTime, Tick};
+use simulation::economy::Government;
+use simulation::multiplayer::chat::{Message, MessageKind};
+use simulation::world_command::{WorldCommand, WorldCommands};
+use simulation::{Replay, Simulation};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
+mod admin;
+
 const VERSION: &str = include_str!("../../VERSION");
 
 #[derive(StructOpt, Debug)]
@@ -27,13 +37,208 @@ struct Opt {
     /// i.e. 20ms = 50FPS
     #[structopt(long, default_value = "20")]
     timestep: u64,
+
+    /// Check the prototypes of the mods and exit, with a non-zero code if there are errors
+    #[structopt(long)]
+    validate_mods: bool,
+
+    /// Write the JSON schema of each prototype type to this directory and exit
+    #[structopt(long)]
+    export_schema: Option<PathBuf>,
+
+    /// Write the loaded prototypes as JSON to this file and exit
+    #[structopt(long)]
+    dump_prototypes: Option<PathBuf>,
+
+    /// Players must vote to pause or change the speed, a majority is needed
+    #[structopt(long)]
+    speed_vote: bool,
+
+    /// Record the played commands to world/<name>_replay.json, starting from a copy of the
+    /// current world saved to world/<name>_start. The name cannot be "world", whose replay is
+    /// written along the save
+    #[structopt(long)]
+    record: Option<String>,
+
+    /// Password the players must enter to join
+    #[structopt(long)]
+    password: Option<String>,
+
+    /// Port of the admin control socket, disabled if not given
+    #[structopt(long, requires = "admin-token")]
+    admin_port: Option<u16>,
+
+    /// Address the admin control socket listens on, only local connections by default
+    #[structopt(long, default_value = "127.0.0.1")]
+    admin_bind: String,
+
+    /// Token admin clients must send to authenticate
+    #[structopt(long)]
+    admin_token: Option<String>,
+}
+
+/// Records the commands played by the server, so the game can be watched again later
+struct Recording {
+    name: String,
+    replay: Replay,
+    /// Commands were recorded since the last write
+    dirty: bool,
+}
+
+impl Recording {
+    fn start(name: String, w: &Simulation) -> Option<Self> {
+        let base_save = format!("{}_start", name);
+        CompressedBincode::save(w, &base_save)?;
+        Some(Self {
+            replay: Replay::starting_from(base_save, Tick(w.get_tick())),
+            name,
+            dirty: false,
+        })
+    }
+
+    fn record(&mut self, tick: Tick, commands: &[WorldCommand]) {
+        self.dirty |= !commands.is_empty();
+        self.replay.record(tick, commands);
+    }
+
+    fn save(&mut self) {
+        JSON::save_silent(&self.replay, &format!("{}_replay", self.name));
+        self.dirty = false;
+    }
+}
+
+fn save(
+    w: &Simulation,
+    server: &Server<Simulation, WorldCommands>,
+    recording: Option<&mut Recording>,
+) {
+    w.save_to_disk("world");
+    server.registry().save_to_disk();
+    if let Some(recording) = recording {
+        recording.save();
+    }
+}
+
+/// Runs an admin command against the running server
+fn handle_admin(
+    command: AdminCommand,
+    server: &mut Server<Simulation, WorldCommands>,
+    w: &Simulation,
+    recording: Option<&mut Recording>,
+    tick_time: Duration,
+) -> AdminResponse {
+    match command {
+        AdminCommand::Players => AdminResponse::Players {
+            players: server.players(),
+        },
+        AdminCommand::Kick { name } => {
+            if !server.kick(&name) {
+                return AdminResponse::Error {
+                    message: format!("no player named {}", name),
+                };
+            }
+            log::info!("kicked {}", name);
+            AdminResponse::Ok
+        }
+        AdminCommand::Forget { name } => {
+            if !server.registry_mut().forget(&name) {
+                return AdminResponse::Error {
+                    message: format!("no player named {}", name),
+                };
+            }
+            AdminResponse::Ok
+        }
+        AdminCommand::Allow { ref name } | AdminCommand::Deny { ref name } => {
+            let can_play = matches!(command, AdminCommand::Allow { .. });
+            let Some(player) = server.registry_mut().get_mut(name) else {
+                return AdminResponse::Error {
+                    message: format!("no player named {}", name),
+                };
+            };
+            player.permissions.can_play = can_play;
+            AdminResponse::Ok
+        }
+        AdminCommand::Save => {
+            save(w, server, recording);
+            AdminResponse::Ok
+        }
+        AdminCommand::Pause | AdminCommand::Resume => {
+            server.set_speed(GameSpeed {
+                paused: matches!(command, AdminCommand::Pause),
+                ..server.speed()
+            });
+            AdminResponse::Ok
+        }
+        AdminCommand::Speed { multiplier } => {
+            if !(GameSpeed::MIN_MULTIPLIER..=GameSpeed::MAX_MULTIPLIER).contains(&multiplier) {
+                return AdminResponse::Error {
+                    message: format!(
+                        "the multiplier must be between {} and {}",
+                        GameSpeed::MIN_MULTIPLIER,
+                        GameSpeed::MAX_MULTIPLIER
+                    ),
+                };
+            }
+            server.set_speed(GameSpeed {
+                multiplier,
+                ..server.speed()
+            });
+            AdminResponse::Ok
+        }
+        AdminCommand::Say { text } => {
+            let message = Message {
+                name: "server".to_string(),
+                text,
+                sent_at: w.read::<GameTime>().instant(),
+                color: geom::Color::WHITE,
+                kind: MessageKind::Info,
+            };
+            server.send_input(vec![WorldCommand::SendMessage { message }].into());
+            AdminResponse::Ok
+        }
+        AdminCommand::Stats => AdminResponse::Stats(ServerStats {
+            frame: w.get_tick(),
+            paused: server.speed().paused,
+            speed: server.speed().multiplier,
+            tick_time_ms: tick_time.as_secs_f64() * 1000.0,
+            players: server.players().len(),
+            population: w.world().humans.len(),
+            money: w.read::<Government>().money.to_string(),
+        }),
+    }
 }
 
 fn main() {
     let opt: Opt = Opt::from_args();
     MyLog::init();
+
+    if opt.validate_mods {
+        prototypes::validate_mods_and_exit("./");
+    }
+
+    if let Some(ref dir) = opt.export_schema {
+        if let Err(e) = prototypes::write_schemas(dir) {
+            log::error!("could not write the schemas: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     simulation::init::init();
 
+    if let Some(ref path) = opt.dump_prototypes {
+        if let Err(e) = prototypes::write_dump(path) {
+            log::error!("could not write the prototypes: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if opt.record.as_deref() == Some("world") {
+        log::error!("cannot record to world, its replay is written along the save");
+        std::process::exit(1);
+    }
+
     log::info!("starting server with version: {}", VERSION);
 
     let mut w = unwrap_or!(Simulation::load_from_disk("world"), {
@@ -49,7 +254,11 @@ fn main() {
         port: opt.port,
         virtual_client: None,
         version: VERSION.to_string(),
+        mods: prototypes::active_mod_ids(),
         always_run: opt.always_run,
+        speed_vote: opt.speed_vote,
+        password: opt.password.clone(),
+        players: PlayerRegistry::load_from_disk(),
     }) {
         Ok(x) => x,
         Err(e) => {
@@ -59,19 +268,57 @@ fn main() {
     };
     log::info!("server started!");
 
+    let mut recording = opt.record.clone().and_then(|name| {
+        let r = Recording::start(name, &w);
+        if r.is_none() {
+            log::error!("could not save the start of the recording, not recording");
+        }
+        r
+    });
+
+    let admin = admin::start(&opt.admin_bind, opt.admin_port, opt.admin_token.clone());
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_handler = stop.clone();
+    if let Err(e) = ctrlc::set_handler(move || stop_handler.store(true, Ordering::SeqCst)) {
+        log::error!(
+            "could not listen for the stop signal, stopping won't save: {}",
+            e
+        );
+    }
+
     let mut last_saved = Instant::now();
+    let mut tick_time = Duration::ZERO;
 
     loop {
         if let ServerPollResult::Input(inputs) = server.poll(&w, Frame(w.get_tick()), None) {
             for frame in inputs {
                 assert_eq!(frame.frame.0, w.get_tick() + 1);
                 let merged: WorldCommands = frame.inputs.into_iter().map(|x| x.inp).collect();
+                if let Some(ref mut recording) = recording {
+                    recording.record(Tick(w.get_tick()), merged.as_ref());
+                }
+                let start = Instant::now();
                 w.tick(&mut sched, merged.as_ref());
+                tick_time = start.elapsed();
             }
         }
 
+        while let Ok(req) = admin.try_recv() {
+            let response =
+                handle_admin(req.command, &mut server, &w, recording.as_mut(), tick_time);
+            let _ = req.reply.send(response);
+        }
+
+        if stop.load(Ordering::SeqCst) {
+            log::info!("stopping the server");
+            save(&w, &server, recording.as_mut());
+            return;
+        }
+
         if last_saved.elapsed().as_secs() > opt.autosave {
-            w.save_to_disk("world");
+            // the recording only grows, it is rewritten when it has new commands
+            save(&w, &server, recording.as_mut().filter(|r| r.dirty));
             last_saved = Instant::now();
         }
 
@@ -3,7 +3,7 @@
 use crate::game_loop::Timings;
 use crate::gui::{GuiState, InspectedEntity};
 use crate::uiworld::UiWorld;
-use simulation::map_dynamic::ParkingManagement;
+use simulation::map_dynamic::{ModalShare, ParkingManagement, TransportMode};
 use simulation::transportation::TransportGrid;
 use simulation::{Simulation, TrainID};
 use std::time::{Duration, Instant};
@@ -259,6 +259,14 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
         ui.label(format!("{} pedestrians", sim.world().humans.len()));
         ui.label(format!("{} vehicles", sim.world().vehicles.len()));
 
+        let share = sim.read::<ModalShare>();
+        ui.label(format!(
+            "modal share: {:.0}% walk, {:.0}% bike, {:.0}% car",
+            share.share(TransportMode::Walk) * 100.0,
+            share.share(TransportMode::Bike) * 100.0,
+            share.share(TransportMode::Car) * 100.0,
+        ));
+
         ui.separator();
         ui.label("Game system times");
 
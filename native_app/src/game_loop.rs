@@ -21,6 +21,7 @@ use crate::gui::windows::settings::{manage_settings, Settings};
 use crate::gui::UiTextures;
 use crate::gui::{render_newgui, ExitState, GuiState, TimeAlways, Tool};
 use crate::inputmap::{Bindings, InputAction, InputMap};
+use crate::network::NetworkState;
 use crate::rendering::{InstancedRender, MapRenderOptions, MapRenderer, OrbitCamera};
 use crate::uiworld::{SaveLoadState, UiWorld};
 use prototypes::GameTime;
@@ -116,10 +117,12 @@ impl engine::framework::State for State {
                 cpy.read().unwrap().save_to_disk("world");
                 status.store(false, Ordering::SeqCst);
             });
+            self.uiw.read::<NetworkState>().save_players();
         }
         drop(slstate);
 
         crate::network::sim_update(self);
+        crate::prototype_watcher::check_prototype_updates(self);
 
         if std::mem::take(&mut self.uiw.write::<SaveLoadState>().render_reset) {
             self.reset(ctx);
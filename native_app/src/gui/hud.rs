@@ -1,11 +1,14 @@
 use std::time::{Duration, Instant};
 
-use goryak::{image_button, minrow, on_secondary_container, textc};
+use goryak::{
+    blur_bg, error, image_button, mincolumn, minrow, on_error, on_secondary_container, padxy, textc,
+};
 use ordered_float::OrderedFloat;
 use prototypes::ItemID;
 use yakui::{reflow, Alignment, Color, Dim2, Pivot, Vec2};
 
 use simulation::map_dynamic::ElectricityFlow;
+use simulation::world_command::CommandError;
 use simulation::Simulation;
 
 use crate::gui::hud::menu::menu_bar;
@@ -35,6 +38,7 @@ pub fn render_newgui(uiworld: &UiWorld, sim: &Simulation) {
 
     yakui::column(|| {
         power_errors(uiworld, sim);
+        command_errors(uiworld);
         new_toolbox(uiworld, sim);
         menu_bar(uiworld, sim);
         chat::chat(uiworld, sim);
@@ -58,6 +62,42 @@ fn auto_save(uiworld: &UiWorld) {
     }
 }
 
+/// Commands sent by this client that were rejected by the simulation
+#[derive(Default)]
+pub struct CommandErrors {
+    pub errors: Vec<(Instant, CommandError)>,
+}
+
+const COMMAND_ERROR_DURATION: Duration = Duration::from_secs(5);
+
+fn command_errors(uiworld: &UiWorld) {
+    let mut state = uiworld.write::<CommandErrors>();
+    state
+        .errors
+        .retain(|(at, _)| at.elapsed() < COMMAND_ERROR_DURATION);
+
+    if state.errors.is_empty() {
+        return;
+    }
+
+    reflow(
+        Alignment::TOP_CENTER,
+        Pivot::TOP_CENTER,
+        Dim2::pixels(0.0, 60.0),
+        || {
+            blur_bg(error().with_alpha(0.7), 5.0, || {
+                padxy(10.0, 5.0, || {
+                    mincolumn(5.0, || {
+                        for (_, e) in &state.errors {
+                            textc(on_error(), format!("Cannot do that: {e}"));
+                        }
+                    });
+                });
+            });
+        },
+    );
+}
+
 fn power_errors(uiworld: &UiWorld, sim: &Simulation) {
     profiling::scope!("hud::power_errors");
     let map = sim.map();
@@ -12,6 +12,7 @@ use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
+use crate::network::NetworkState;
 use crate::uiworld::UiWorld;
 
 #[derive(Default)]
@@ -102,7 +103,7 @@ pub fn chat(uiw: &UiWorld, sim: &Simulation) {
                                     name: "player".to_string(),
                                     text: state.cur_msg.take(),
                                     sent_at: sim.read::<GameTime>().instant(),
-                                    color: geom::Color::WHITE,
+                                    color: uiw.read::<NetworkState>().chat_color(),
                                     kind: MessageKind::PlayerChat,
                                 },
                             });
@@ -14,14 +14,18 @@ use simulation::Simulation;
 use crate::gui::windows::settings::Settings;
 use crate::gui::GuiState;
 use crate::inputmap::{InputAction, InputMap};
+use crate::network::NetworkState;
 use crate::uiworld::UiWorld;
 
 pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
     profiling::scope!("hud::time_controls");
     let time = sim.read::<GameTime>().daytime;
     let warp = &mut uiworld.write::<Settings>().time_warp;
+    let fast_forward_warp = uiworld.read::<NetworkState>().fast_forward_warp();
     let mut gui = uiworld.write::<GuiState>();
+    let gui = &mut *gui;
     let depause_warp = &mut gui.depause_warp;
+    let requested_warp = &mut gui.requested_warp;
     if uiworld
         .read::<InputMap>()
         .just_act
@@ -33,6 +37,7 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
             *depause_warp = *warp;
             *warp = 0;
         }
+        *requested_warp = Some(*warp);
     }
 
     if *warp == 0 {
@@ -78,14 +83,20 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
                         }
                     }
                     *warp = b_warp;
+                    *requested_warp = Some(b_warp);
                 }
             };
 
             time_button("pause", 0);
             time_button("play", 1);
             time_button("forward", 3);
-            time_button("fast-forward", 1000);
+            time_button("fast-forward", fast_forward_warp);
         });
+        if let Some(vote) = uiworld.read::<NetworkState>().speed_vote() {
+            padx(5.0, || {
+                monospace(on_secondary_container(), vote);
+            });
+        }
     };
 
     reflow(
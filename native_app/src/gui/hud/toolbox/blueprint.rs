@@ -0,0 +1,53 @@
+use yakui::widgets::List;
+use yakui::{CrossAxisAlignment, MainAxisAlignment};
+
+use goryak::{
+    button_secondary, checkbox_value, on_secondary_container, padxy, selectable_label_primary,
+    textc,
+};
+
+use crate::gui::blueprint::{BlueprintLibrary, BlueprintResource};
+use crate::uiworld::UiWorld;
+
+/// Blueprint browser: pick the blueprint to paste or go back to capturing a new one
+pub fn blueprint_properties(uiw: &UiWorld) {
+    let state = &mut *uiw.write::<BlueprintResource>();
+    let library = &mut *uiw.write::<BlueprintLibrary>();
+
+    padxy(0.0, 10.0, || {
+        let mut l = List::row();
+        l.main_axis_alignment = MainAxisAlignment::Center;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 10.0;
+        l.show(|| {
+            if selectable_label_primary(state.selected.is_none(), "Capture").clicked {
+                state.selected = None;
+            }
+
+            if library.blueprints.is_empty() {
+                textc(
+                    on_secondary_container(),
+                    "Drag a rectangle on the map to capture a blueprint",
+                );
+                return;
+            }
+
+            for (i, bp) in library.blueprints.iter().enumerate() {
+                if selectable_label_primary(state.selected == Some(i), &bp.name).clicked {
+                    state.selected = Some(i);
+                }
+            }
+
+            let Some(selected) = state.selected else {
+                return;
+            };
+
+            checkbox_value(&mut state.mirror, on_secondary_container(), "Mirror");
+
+            if button_secondary("Delete").show().clicked {
+                library.blueprints.remove(selected);
+                state.selected = None;
+            }
+        });
+    });
+}
@@ -16,8 +16,8 @@ use goryak::{
     padxy, primary, secondary_container, textc, titlec, HorizScrollSize,
 };
 use prototypes::{
-    prototypes_iter, BuildingPrototypeID, GoodsCompanyID, GoodsCompanyPrototype, Prototype,
-    RenderAsset,
+    prototypes_iter, BuildingPrototypeID, GoodsCompanyID, GoodsCompanyPrototype, LeisurePrototype,
+    Prototype, RenderAsset,
 };
 use simulation::map::{BuildingKind, Zone};
 use simulation::world_command::WorldCommand;
@@ -186,6 +186,40 @@ pub fn special_building_properties(uiw: &UiWorld) {
                         }
                     });
                 }
+
+                for descr in prototypes_iter::<LeisurePrototype>() {
+                    let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
+                        continue;
+                    };
+
+                    let resp = image_button(
+                        *tex_id,
+                        Vec2::splat(64.0),
+                        Color::WHITE,
+                        primary(),
+                        Color::WHITE.with_alpha(0.5),
+                        "",
+                    );
+
+                    if resp.clicked {
+                        let bkind = BuildingKind::Leisure(descr.id);
+                        let bgen = descr.bgen;
+                        state.opt = Some(SpecialBuildKind {
+                            road_snap: true,
+                            make: Box::new(move |args| {
+                                vec![WorldCommand::MapBuildSpecialBuilding {
+                                    pos: args.obb,
+                                    kind: bkind,
+                                    gen: bgen,
+                                    zone: None,
+                                    connected_road: args.connected_road,
+                                }]
+                            }),
+                            size: descr.size,
+                            asset: descr.asset.clone(),
+                        });
+                    }
+                }
             });
         });
     });
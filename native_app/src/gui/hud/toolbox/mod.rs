@@ -5,8 +5,8 @@ use yakui::{
 };
 
 use goryak::{
-    blur_bg, button_primary, constrained_viewport, fixed_spacer, icon_button, image_button,
-    monospace, on_primary, outline, padxy, primary, primary_container, round_rect,
+    blur_bg, button_primary, button_secondary, constrained_viewport, fixed_spacer, icon_button,
+    image_button, monospace, on_primary, outline, padxy, primary, primary_container, round_rect,
     secondary_container,
 };
 use simulation::Simulation;
@@ -16,6 +16,7 @@ use crate::gui::Tool;
 use crate::inputmap::{InputAction, InputMap};
 use crate::uiworld::UiWorld;
 
+pub mod blueprint;
 pub mod building;
 pub mod roadbuild;
 pub mod roadedit;
@@ -31,6 +32,16 @@ pub fn new_toolbox(uiworld: &UiWorld, sim: &Simulation) {
         *uiworld.write::<Tool>() = Tool::Hand;
     }
 
+    {
+        let inp = uiworld.read::<InputMap>();
+        if inp.just_act.contains(&InputAction::Undo) {
+            uiworld.commands().map_undo();
+        }
+        if inp.just_act.contains(&InputAction::Redo) {
+            uiworld.commands().map_redo();
+        }
+    }
+
     reflow(Alignment::TOP_LEFT, Pivot::TOP_LEFT, Dim2::ZERO, || {
         constrained_viewport(|| {
             let mut l = List::column();
@@ -89,6 +100,9 @@ fn tool_properties(uiw: &UiWorld, _sim: &Simulation) -> bool {
         Tool::Terraforming => {
             terraforming::terraform_properties(uiw);
         }
+        Tool::Blueprint => {
+            blueprint::blueprint_properties(uiw);
+        }
     }
     true
 }
@@ -131,6 +145,26 @@ fn tools_list(uiworld: &UiWorld) {
             }
         });
     }
+
+    column(|| {
+        let enabled = *uiworld.read::<Tool>() == Tool::Blueprint;
+        let mut b = icon_button(if enabled {
+            button_primary("object-group")
+        } else {
+            button_secondary("object-group")
+        });
+        b.padding = Pad::balanced(18.0, 18.0);
+        b.style.text.font_size = 28.0;
+        b.down_style.text.font_size = 28.0;
+        b.hover_style.text.font_size = 28.0;
+        if b.show().clicked {
+            *uiworld.write::<Tool>() = Tool::Blueprint;
+        }
+
+        if enabled {
+            select_triangle(uiworld);
+        }
+    });
 }
 
 pub(crate) fn select_triangle(uiworld: &UiWorld) {
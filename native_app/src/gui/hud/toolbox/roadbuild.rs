@@ -4,7 +4,9 @@ use yakui::{
     Pivot, Vec2,
 };
 
-use goryak::{image_button, mincolumn, minrow, padxy, primary};
+use goryak::{
+    checkbox_value, image_button, mincolumn, minrow, on_secondary_container, padxy, primary,
+};
 use simulation::map::LanePatternBuilder;
 
 use crate::gui::hud::toolbox::updown_value;
@@ -131,114 +133,125 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
             // Road elevation
             updown_value(&mut state.height_offset, 2.0, "m");
 
-            // image name, label, builder
-            let builders: &[(&str, &str, LanePatternBuilder)] = &[
-                ("roadtypes_street", "Street", LanePatternBuilder::new()),
-                (
-                    "roadtypes_street_1way",
-                    "Street one-way",
-                    LanePatternBuilder::new().one_way(true),
-                ),
-                (
-                    "roadtypes_avenue",
-                    "Avenue",
-                    LanePatternBuilder::new().n_lanes(2).speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_avenue_1way",
-                    "Avenue one-way",
-                    LanePatternBuilder::new()
-                        .n_lanes(2)
-                        .one_way(true)
-                        .speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_drive",
-                    "Drive",
-                    LanePatternBuilder::new()
-                        .parking(false)
-                        .sidewalks(false)
-                        .speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_drive_1way",
-                    "Drive one-way",
-                    LanePatternBuilder::new()
-                        .parking(false)
-                        .sidewalks(false)
-                        .one_way(true)
-                        .speed_limit(13.0),
-                ),
-                (
-                    "roadtypes_highway",
-                    "Highway",
-                    LanePatternBuilder::new()
-                        .n_lanes(3)
-                        .speed_limit(25.0)
-                        .parking(false)
-                        .sidewalks(false),
-                ),
-                (
-                    "roadtypes_highway_1way",
-                    "Highway one-way",
-                    LanePatternBuilder::new()
-                        .n_lanes(3)
-                        .speed_limit(25.0)
-                        .parking(false)
-                        .sidewalks(false)
-                        .one_way(true),
-                ),
-                (
-                    "roadtypes_rail",
-                    "Rail",
-                    LanePatternBuilder::new().rail(true),
-                ),
-                (
-                    "roadtypes_rail_1way",
-                    "Rail one-way",
-                    LanePatternBuilder::new().rail(true).one_way(true),
-                ),
-            ];
+            road_types(uiw, &mut state.pattern_builder);
+        });
+    });
+}
 
-            for (icon, label, builder) in builders {
-                let mut l = List::column();
-                l.main_axis_size = MainAxisSize::Min;
-                l.show(|| {
-                    let is_active = &state.pattern_builder == builder;
-                    let (default_col, hover_col) = if is_active {
-                        let c = Color::WHITE.adjust(0.5);
-                        (c, c)
-                    } else {
-                        (Color::WHITE, Color::WHITE.with_alpha(0.7))
-                    };
-                    if image_button(
-                        uiw.read::<UiTextures>().get(icon),
-                        Vec2::new(64.0, 64.0),
-                        default_col,
-                        hover_col,
-                        primary(),
-                        *label,
-                    )
-                    .clicked
-                    {
-                        state.pattern_builder = *builder;
-                    }
+/// Buttons to choose one of the predefined road types, bike lanes can be added to any of them
+pub fn road_types(uiw: &UiWorld, pattern_builder: &mut LanePatternBuilder) {
+    // image name, label, builder
+    let builders: &[(&str, &str, LanePatternBuilder)] = &[
+        ("roadtypes_street", "Street", LanePatternBuilder::new()),
+        (
+            "roadtypes_street_1way",
+            "Street one-way",
+            LanePatternBuilder::new().one_way(true),
+        ),
+        (
+            "roadtypes_avenue",
+            "Avenue",
+            LanePatternBuilder::new().n_lanes(2).speed_limit(13.0),
+        ),
+        (
+            "roadtypes_avenue_1way",
+            "Avenue one-way",
+            LanePatternBuilder::new()
+                .n_lanes(2)
+                .one_way(true)
+                .speed_limit(13.0),
+        ),
+        (
+            "roadtypes_drive",
+            "Drive",
+            LanePatternBuilder::new()
+                .parking(false)
+                .sidewalks(false)
+                .speed_limit(13.0),
+        ),
+        (
+            "roadtypes_drive_1way",
+            "Drive one-way",
+            LanePatternBuilder::new()
+                .parking(false)
+                .sidewalks(false)
+                .one_way(true)
+                .speed_limit(13.0),
+        ),
+        (
+            "roadtypes_highway",
+            "Highway",
+            LanePatternBuilder::new()
+                .n_lanes(3)
+                .speed_limit(25.0)
+                .parking(false)
+                .sidewalks(false),
+        ),
+        (
+            "roadtypes_highway_1way",
+            "Highway one-way",
+            LanePatternBuilder::new()
+                .n_lanes(3)
+                .speed_limit(25.0)
+                .parking(false)
+                .sidewalks(false)
+                .one_way(true),
+        ),
+        (
+            "roadtypes_rail",
+            "Rail",
+            LanePatternBuilder::new().rail(true),
+        ),
+        (
+            "roadtypes_rail_1way",
+            "Rail one-way",
+            LanePatternBuilder::new().rail(true).one_way(true),
+        ),
+    ];
+
+    for (icon, label, builder) in builders {
+        let mut l = List::column();
+        l.main_axis_size = MainAxisSize::Min;
+        l.show(|| {
+            let is_active = pattern_builder.bike_lanes(false) == *builder;
+            let (default_col, hover_col) = if is_active {
+                let c = Color::WHITE.adjust(0.5);
+                (c, c)
+            } else {
+                (Color::WHITE, Color::WHITE.with_alpha(0.7))
+            };
+            if image_button(
+                uiw.read::<UiTextures>().get(icon),
+                Vec2::new(64.0, 64.0),
+                default_col,
+                hover_col,
+                primary(),
+                *label,
+            )
+            .clicked
+            {
+                *pattern_builder = builder.bike_lanes(pattern_builder.bike_lanes);
+            }
 
-                    if is_active {
-                        reflow(
-                            Alignment::CENTER_LEFT,
-                            Pivot::TOP_LEFT,
-                            Dim2::pixels(0.0, 32.0),
-                            || {
-                                image(
-                                    uiw.read::<UiTextures>().get("select_triangle_under"),
-                                    Vec2::new(64.0, 10.0),
-                                );
-                            },
+            if is_active {
+                reflow(
+                    Alignment::CENTER_LEFT,
+                    Pivot::TOP_LEFT,
+                    Dim2::pixels(0.0, 32.0),
+                    || {
+                        image(
+                            uiw.read::<UiTextures>().get("select_triangle_under"),
+                            Vec2::new(64.0, 10.0),
                         );
-                    }
-                });
+                    },
+                );
             }
         });
-    });
+    }
+
+    checkbox_value(
+        &mut pattern_builder.bike_lanes,
+        on_secondary_container(),
+        "Bike lanes",
+    );
 }
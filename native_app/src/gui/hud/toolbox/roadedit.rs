@@ -3,20 +3,18 @@ use yakui::{
     column, image, reflow, Alignment, CrossAxisAlignment, Dim2, MainAxisAlignment, Pivot, Vec2,
 };
 
-use goryak::{padxy, primary_image_button};
-use simulation::map::LightPolicy;
+use goryak::{padxy, primary_image_button, selectable_label_primary};
+use simulation::map::{JunctionKind, LightPolicy};
 
 use crate::gui::hud::toolbox;
+use crate::gui::hud::toolbox::roadbuild::road_types;
 use crate::gui::hud::toolbox::select_triangle;
-use crate::gui::roadeditor::RoadEditorResource;
+use crate::gui::roadeditor::{RoadEditorMode, RoadEditorResource};
 use crate::gui::textures::UiTextures;
 use crate::uiworld::UiWorld;
 
 pub fn roadedit_properties(uiw: &UiWorld) {
     let state = &mut *uiw.write::<RoadEditorResource>();
-    let Some(ref mut v) = state.inspect else {
-        return;
-    };
 
     padxy(0.0, 10.0, || {
         let mut l = List::row();
@@ -24,6 +22,43 @@ pub fn roadedit_properties(uiw: &UiWorld) {
         l.cross_axis_alignment = CrossAxisAlignment::Center;
         l.item_spacing = 10.0;
         l.show(|| {
+            let mut l = List::column();
+            l.item_spacing = 5.0;
+            l.show(|| {
+                for (mode, label) in [
+                    (RoadEditorMode::Intersection, "Intersections"),
+                    (RoadEditorMode::Upgrade, "Lanes"),
+                ] {
+                    if selectable_label_primary(state.mode == mode, label).clicked {
+                        state.mode = mode;
+                    }
+                }
+            });
+
+            if state.mode == RoadEditorMode::Upgrade {
+                road_types(uiw, &mut state.pattern_builder);
+                return;
+            }
+
+            let Some(ref mut v) = state.inspect else {
+                return;
+            };
+
+            let mut l = List::column();
+            l.item_spacing = 5.0;
+            l.show(|| {
+                for (junction, label) in [
+                    (JunctionKind::AtGrade, "At grade"),
+                    (JunctionKind::Ramp, "Ramp"),
+                    (JunctionKind::Merge, "Merge"),
+                ] {
+                    if selectable_label_primary(v.turn_policy.junction == junction, label).clicked {
+                        v.turn_policy.junction = junction;
+                        state.dirty = true;
+                    }
+                }
+            });
+
             let texs = uiw.read::<UiTextures>();
 
             let light_policy_choices = &[
@@ -1,9 +1,17 @@
-use goryak::{mincolumn, minrow, outline, padxy};
+use goryak::{mincolumn, minrow, outline, padxy, selectable_label_primary};
 use prototypes::{prototypes_iter, RollingStockID, RollingStockPrototype};
 use yakui::widgets::List;
 use yakui::{button, divider, label, CrossAxisAlignment, MainAxisAlignment};
 
-use crate::gui::addtrain::TrainSpawnResource;
+use geom::Vec2;
+use prototypes::{BuildingGen, RenderAsset, Size2D};
+use simulation::map::{BuildingKind, LanePatternBuilder, MapProject};
+use simulation::transportation::signals::RailSignalKind;
+use simulation::world_command::WorldCommand;
+
+use crate::gui::addtrain::{TrainSpawnResource, TrainToolMode};
+use crate::gui::specialbuilding::{SpecialBuildKind, SpecialBuildingResource};
+use crate::gui::Tool;
 use crate::uiworld::UiWorld;
 
 pub fn train_properties(uiw: &UiWorld) {
@@ -15,6 +23,35 @@ pub fn train_properties(uiw: &UiWorld) {
         l.cross_axis_alignment = CrossAxisAlignment::Center;
         l.item_spacing = 10.0;
         l.show(|| {
+            mincolumn(0.1, || {
+                for (name, mode) in [
+                    ("spawn train", TrainToolMode::SpawnTrain),
+                    (
+                        "block signal",
+                        TrainToolMode::PlaceSignal(RailSignalKind::Block),
+                    ),
+                    (
+                        "chain signal",
+                        TrainToolMode::PlaceSignal(RailSignalKind::Chain),
+                    ),
+                    ("remove signal", TrainToolMode::RemoveSignal),
+                ] {
+                    if selectable_label_primary(state.mode == mode, name).clicked {
+                        state.mode = mode;
+                    }
+                }
+                if button("train depot").clicked {
+                    place_depot(uiw);
+                }
+                if let Some(signal) = state.hovered_signal {
+                    label(format!("{:?} signal", signal.kind));
+                    match signal.reserved_by {
+                        Some(train) => label(format!("Reserved by {:?}", train)),
+                        None => label("Free"),
+                    };
+                }
+            });
+
             mincolumn(0.1, || {
                 if button("remove train").clicked {
                     state.wagons.clear();
@@ -60,6 +97,45 @@ pub fn train_properties(uiw: &UiWorld) {
     });
 }
 
+/// Selects the special building tool to place a train depot along a new rail track
+fn place_depot(uiworld: &UiWorld) {
+    *uiworld.write::<Tool>() = Tool::SpecialBuilding;
+
+    uiworld.write::<SpecialBuildingResource>().opt = Some(SpecialBuildKind {
+        make: Box::new(move |args| {
+            let obb = args.obb;
+            let c = obb.center().z(0.0);
+
+            let [offx, offy] = obb.axis().map(|x| x.normalize().z(0.0));
+
+            let pat = LanePatternBuilder::new().rail(true).build();
+
+            vec![
+                WorldCommand::MapMakeConnection {
+                    from: MapProject::ground(c - offx * 45.0 - offy * 100.0),
+                    to: MapProject::ground(c - offx * 45.0 + offy * 100.0),
+                    inter: None,
+                    pat,
+                },
+                WorldCommand::MapBuildSpecialBuilding {
+                    pos: obb,
+                    kind: BuildingKind::TrainDepot,
+                    gen: BuildingGen::NoWalkway {
+                        door_pos: Vec2::ZERO,
+                    },
+                    zone: None,
+                    connected_road: args.connected_road,
+                },
+            ]
+        }),
+        size: Size2D { w: 160.0, h: 200.0 },
+        asset: RenderAsset::Mesh {
+            path: "rail_freight_station.glb".into(),
+        },
+        road_snap: false,
+    });
+}
+
 /*
 if ui.button(freightstation).clicked() {
    *uiworld.write::<Tool>() = Tool::SpecialBuilding;
@@ -10,13 +10,18 @@ use yakui::{
 use engine::Tesselator;
 use geom::AABB;
 use goryak::{
-    constrained_viewport, mincolumn, minrow, on_primary_container, padxy, pady,
-    selectable_label_primary, sized_canvas, textc, VertScrollSize, Window,
+    button_primary, constrained_viewport, error, mincolumn, minrow, on_primary_container, padxy,
+    pady, primary, selectable_label_primary, sized_canvas, textc, ProgressBar, VertScrollSize,
+    Window,
 };
-use prototypes::{ItemID, DELTA_F64};
+use prototypes::{ItemID, Money, DELTA_F64};
 use simulation::economy::{
-    EcoStats, ItemHistories, Market, HISTORY_SIZE, LEVEL_FREQS, LEVEL_NAMES,
+    EcoStats, Government, ItemHistories, Market, BANKRUPTCY_DAYS, HISTORY_SIZE, LEVEL_FREQS,
+    LEVEL_NAMES,
 };
+use simulation::map_dynamic::{Dispatcher, TourState};
+use simulation::souls::demographics::{Demographics, AGE_BRACKET, N_AGE_BRACKETS};
+use simulation::souls::desire::WorkKind;
 use simulation::Simulation;
 
 use crate::uiworld::UiWorld;
@@ -27,6 +32,10 @@ pub enum EconomyTab {
     ImportExports,
     InternalTrade,
     MarketPrices,
+    Loans,
+    Households,
+    Population,
+    Logistics,
 }
 
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
@@ -41,8 +50,15 @@ pub struct EconomyState {
     pub curlevel: usize,
     pub tab: EconomyTab,
     pub hist_type: HistoryType,
+    /// Index in [`LOAN_AMOUNTS`]
+    pub loan_amount: usize,
+    /// Index in [`LOAN_TERMS`]
+    pub loan_term: usize,
 }
 
+const LOAN_AMOUNTS: [i64; 4] = [10_000, 50_000, 100_000, 250_000];
+const LOAN_TERMS: [u32; 3] = [30, 90, 365];
+
 /// Economy window
 /// Shows the economy stats
 pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
@@ -61,6 +77,10 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
                 ("Import/Exports", EconomyTab::ImportExports),
                 ("Internal Trade", EconomyTab::InternalTrade),
                 ("Market Prices", EconomyTab::MarketPrices),
+                ("Loans", EconomyTab::Loans),
+                ("Households", EconomyTab::Households),
+                ("Population", EconomyTab::Population),
+                ("Logistics", EconomyTab::Logistics),
             ];
 
             for (label, tab) in tabs {
@@ -101,8 +121,9 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             .collect();
         let EconomyState {
             curlevel,
-            ref tab,
+            tab,
             hist_type,
+            ..
         } = *state;
 
         let render_history = |history: &ItemHistories, hist_type: HistoryType| {
@@ -324,6 +345,268 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             EconomyTab::MarketPrices => {
                 render_market_prices(sim);
             }
+            EconomyTab::Loans => {
+                render_loans(uiw, sim, &ecostats, curlevel, &mut state);
+            }
+            EconomyTab::Households => {
+                render_households(sim);
+            }
+            EconomyTab::Population => {
+                render_population(sim);
+            }
+            EconomyTab::Logistics => {
+                render_logistics(sim);
+            }
+        }
+    });
+}
+
+fn render_loans(
+    uiw: &UiWorld,
+    sim: &Simulation,
+    ecostats: &EcoStats,
+    curlevel: usize,
+    state: &mut EconomyState,
+) {
+    let gvt = sim.read::<Government>();
+
+    mincolumn(10.0, || {
+        if gvt.bankrupt {
+            textc(error(), "Bankrupt: nothing can be built anymore");
+        } else if gvt.is_insolvent() {
+            textc(
+                error(),
+                format!(
+                    "Insolvent for {} days, bankrupt after {} days",
+                    gvt.insolvent_days, BANKRUPTCY_DAYS
+                ),
+            );
+        }
+
+        let interest: Money = ecostats
+            .interest
+            .level(curlevel)
+            .map(|l| l.past_ring.iter().copied().sum())
+            .unwrap_or_default();
+
+        let mut grid = CountGrid::col(2);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for (label, value) in [
+                ("Debt", gvt.debt()),
+                ("Available credit", gvt.available_credit()),
+                ("Interests paid", interest),
+            ] {
+                padxy(5.0, 3.0, || textc(on_primary_container(), label));
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), format!("{}$", value))
+                });
+            }
+        });
+
+        let mut grid = CountGrid::col(3);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for loan in &gvt.loans {
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), format!("{}$", loan.principal))
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{}$ remaining", loan.remaining),
+                    )
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{} days left", loan.days_left),
+                    )
+                });
+            }
+        });
+
+        minrow(10.0, || {
+            for (i, amount) in LOAN_AMOUNTS.iter().enumerate() {
+                if selectable_label_primary(state.loan_amount == i, &format!("{}$", amount)).clicked
+                {
+                    state.loan_amount = i;
+                }
+            }
+        });
+        minrow(10.0, || {
+            for (i, term) in LOAN_TERMS.iter().enumerate() {
+                if selectable_label_primary(state.loan_term == i, &format!("{} days", term)).clicked
+                {
+                    state.loan_term = i;
+                }
+            }
+        });
+
+        if button_primary("Take loan").show().clicked {
+            uiw.commands().take_loan(
+                Money::new_bucks(LOAN_AMOUNTS[state.loan_amount]),
+                LOAN_TERMS[state.loan_term],
+            );
+        }
+    });
+}
+
+/// Upper bounds of the buckets used to show the distribution of household money, in bucks
+const DISTRIBUTION_BUCKETS: [i64; 6] = [0, 50, 100, 250, 500, 1000];
+
+fn render_distribution(title: &str, values: impl Iterator<Item = Money>) {
+    let mut counts = [0; DISTRIBUTION_BUCKETS.len() + 1];
+    let mut total = 0;
+    for v in values {
+        let bucket = DISTRIBUTION_BUCKETS
+            .iter()
+            .position(|&b| v < Money::new_bucks(b))
+            .unwrap_or(DISTRIBUTION_BUCKETS.len());
+        counts[bucket] += 1;
+        total += 1;
+    }
+
+    textc(on_primary_container(), title);
+    let mut grid = CountGrid::col(2);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        for (i, count) in counts.iter().enumerate() {
+            let label = match i {
+                0 => format!("< {}$", DISTRIBUTION_BUCKETS[0]),
+                i if i == DISTRIBUTION_BUCKETS.len() => {
+                    format!(">= {}$", DISTRIBUTION_BUCKETS[i - 1])
+                }
+                i => format!(
+                    "{}$ - {}$",
+                    DISTRIBUTION_BUCKETS[i - 1],
+                    DISTRIBUTION_BUCKETS[i]
+                ),
+            };
+            padxy(5.0, 3.0, || textc(on_primary_container(), label));
+            ProgressBar {
+                value: *count as f32 / total.max(1) as f32,
+                size: Vec2::new(200.0, 20.0),
+                color: primary().adjust(0.7),
+            }
+            .show_children(|| {
+                textc(on_primary_container(), count.to_string());
+            });
+        }
+    });
+}
+
+fn render_households(sim: &Simulation) {
+    let humans = &sim.world().humans;
+    let employed = humans.values().filter(|h| h.work.is_some()).count();
+
+    mincolumn(10.0, || {
+        textc(
+            on_primary_container(),
+            format!("Employed: {}/{}", employed, humans.len()),
+        );
+        render_distribution(
+            "Income last day",
+            humans.values().map(|h| h.wallet.last_day_income),
+        );
+        render_distribution("Savings", humans.values().map(|h| h.wallet.money));
+    });
+}
+
+fn render_population(sim: &Simulation) {
+    let demographics = sim.read::<Demographics>();
+
+    mincolumn(10.0, || {
+        let mut grid = CountGrid::col(2);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for (label, value) in [
+                ("Population", demographics.population),
+                ("Unemployed", demographics.unemployed),
+                ("Open jobs", demographics.open_jobs),
+                ("Births", demographics.births),
+                ("Deaths", demographics.deaths),
+                ("Immigrants", demographics.immigrants),
+                ("Emigrants", demographics.emigrants),
+            ] {
+                padxy(5.0, 3.0, || textc(on_primary_container(), label));
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), value.to_string())
+                });
+            }
+        });
+
+        let max = demographics
+            .pyramid
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1) as f32;
+
+        let mut grid = CountGrid::col(3);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Age"));
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Men"));
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Women"));
+
+            for (i, [men, women]) in demographics.pyramid.iter().enumerate().rev() {
+                let lo = i as u8 * AGE_BRACKET;
+                let label = if i == N_AGE_BRACKETS - 1 {
+                    format!("{}+", lo)
+                } else {
+                    format!("{}-{}", lo, lo + AGE_BRACKET - 1)
+                };
+                padxy(5.0, 1.0, || textc(on_primary_container(), label));
+                for count in [men, women] {
+                    ProgressBar {
+                        value: *count as f32 / max,
+                        size: Vec2::new(150.0, 12.0),
+                        color: primary().adjust(0.7),
+                    }
+                    .show_children(|| {});
+                }
+            }
+        });
+    });
+}
+
+fn render_logistics(sim: &Simulation) {
+    let dispatcher = sim.read::<Dispatcher>();
+    let fleet = &dispatcher.fleet;
+
+    let mut drivers = 0;
+    let mut on_tour = 0;
+    for h in sim.world().humans.values() {
+        let Some(WorkKind::Driver { ref tour, .. }) = h.work.as_ref().map(|w| &w.kind) else {
+            continue;
+        };
+        drivers += 1;
+        if tour.state == TourState::Started {
+            on_tour += 1;
+        }
+    }
+
+    let mut grid = CountGrid::col(2);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        for (label, value) in [
+            ("Trucks on tour", format!("{}/{}", on_tour, drivers)),
+            ("Tours", fleet.tours.to_string()),
+            ("Stops per tour", format!("{:.1}", fleet.stops_per_tour())),
+            (
+                "Delivered stops",
+                format!("{}/{}", fleet.delivered_stops, fleet.stops),
+            ),
+            (
+                "Truck load factor",
+                format!("{:.0}%", fleet.load_factor() * 100.0),
+            ),
+        ] {
+            padxy(5.0, 3.0, || textc(on_primary_container(), label));
+            padxy(5.0, 3.0, || textc(on_primary_container(), value));
         }
     });
 }
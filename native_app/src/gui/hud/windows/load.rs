@@ -51,8 +51,7 @@ pub fn load(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
             {
                 let replay = Simulation::load_replay_from_disk("world");
 
-                if let Some(replay) = replay {
-                    let (mut sim, mut loader) = Simulation::from_replay(replay);
+                if let Some((mut sim, mut loader)) = replay.and_then(Simulation::from_replay) {
                     let mut s = SeqSchedule::default();
                     loader.advance_tick(&mut sim, &mut s); // advance by one tick to get the initial state (like map size info)
 
@@ -19,6 +19,11 @@ use crate::uiworld::UiWorld;
 pub struct NetworkConnectionInfo {
     pub name: String,
     pub ip: String,
+    /// Generated on the first connection, proves to the servers that we own our name
+    #[serde(default)]
+    pub token: String,
+    #[serde(skip)]
+    pub password: String,
     #[serde(skip)]
     pub error: String,
     #[serde(skip)]
@@ -62,6 +67,8 @@ pub fn network(uiworld: &UiWorld, sim: &Simulation, opened: &mut bool) {
                     return;
                 }
 
+                text_edit(200.0, &mut info.password, "Password (optional)");
+
                 if button_primary("Start server").show().clicked {
                     if let Some(server) = crate::network::start_server(&mut info, sim) {
                         *state = NetworkState::Server(server);
@@ -73,7 +80,13 @@ pub fn network(uiworld: &UiWorld, sim: &Simulation, opened: &mut bool) {
                 text_edit(200.0, &mut info.ip, "IP");
 
                 if button_primary("Connect").show().clicked {
-                    if let Some(c) = crate::network::start_client(&mut info) {
+                    if let Some(c) = crate::network::start_client(&mut info, false) {
+                        *state = NetworkState::Client(c);
+                    }
+                }
+
+                if button_primary("Spectate").show().clicked {
+                    if let Some(c) = crate::network::start_client(&mut info, true) {
                         *state = NetworkState::Client(c);
                     }
                 }
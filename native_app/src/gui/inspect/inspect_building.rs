@@ -1,11 +1,17 @@
 use goryak::{
-    dragvalue, fixed_spacer, minrow, on_secondary_container, primary, textc, ProgressBar, Window,
+    button_primary, button_secondary, dragvalue, fixed_spacer, minrow, on_secondary_container,
+    primary, selectable_label_primary, textc, ProgressBar, Window,
 };
-use prototypes::{ItemID, Recipe};
+use prototypes::{GameDuration, GameTime, ItemID, ItemPrototype, LeisurePrototypeID, Recipe};
 use simulation::economy::Market;
 use simulation::map::{Building, BuildingID, BuildingKind, Zone, MAX_ZONE_AREA};
 use simulation::map_dynamic::{BuildingInfos, ElectricityFlow};
 use simulation::souls::freight_station::FreightTrainState;
+use simulation::souls::goods_company::COMPANY_CLOSURE_DAYS;
+use simulation::transportation::depot::in_depot;
+use simulation::transportation::freight_route::{
+    FreightRouteID, FreightRouteState, FreightRoutes, Timetable,
+};
 use simulation::world_command::WorldCommand;
 use simulation::{Simulation, SoulID};
 use std::borrow::Cow;
@@ -31,7 +37,9 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
         BuildingKind::House => "House",
         BuildingKind::GoodsCompany(id) => &id.prototype().name,
         BuildingKind::RailFreightStation(id) => &id.prototype().name,
+        BuildingKind::Leisure(id) => &id.prototype().name,
         BuildingKind::TrainStation => "Train Station",
+        BuildingKind::TrainDepot => "Train Depot",
         BuildingKind::ExternalTrading => "External Trading",
     };
 
@@ -57,7 +65,9 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
                 render_freightstation(uiworld, sim, building);
             }
             BuildingKind::TrainStation => {}
+            BuildingKind::TrainDepot => render_depot(uiworld, sim, building),
             BuildingKind::ExternalTrading => {}
+            BuildingKind::Leisure(proto) => render_leisure(sim, building, proto),
         };
 
         if let Some(ref zone) = building.zone {
@@ -116,6 +126,18 @@ fn render_house(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     }
 }
 
+/// The freight route being created or edited from the inspector of a freight station
+#[derive(Default)]
+pub struct FreightRouteEditor {
+    /// The station whose inspector shows the editor, None when closed
+    from: Option<BuildingID>,
+    /// The route being edited, None for a new route
+    editing: Option<FreightRouteID>,
+    stations: Vec<BuildingID>,
+    items: Vec<ItemID>,
+    timetable: Timetable,
+}
+
 fn render_freightstation(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     let Some(SoulID::FreightStation(owner)) = sim.read::<BuildingInfos>().owner(b.id) else {
         return;
@@ -127,6 +149,68 @@ fn render_freightstation(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     label(format!("Waiting cargo: {}", freight.f.waiting_cargo));
     label(format!("Wanted cargo: {}", freight.f.wanted_cargo));
 
+    if !freight.f.cargo.is_empty() {
+        label("Cargo for the freight routes:");
+        minrow(5.0, || {
+            for (&item, &qty) in &freight.f.cargo {
+                item_icon_yakui(uiworld, item, qty as i32);
+            }
+        });
+    }
+
+    fixed_spacer((0.0, 10.0));
+    label("Freight routes:");
+    let routes = sim.read::<FreightRoutes>();
+    for (id, route) in routes.iter().filter(|(_, r)| r.serves(b.id)) {
+        minrow(5.0, || {
+            if let Some(train) = route.train {
+                entity_link(uiworld, sim, train);
+            }
+            label(match route.state {
+                FreightRouteState::NoTrain => "Waiting for a train".to_string(),
+                FreightRouteState::GoingTo(stop) => {
+                    format!("Going to stop {}/{}", stop + 1, route.stations.len())
+                }
+                FreightRouteState::Stopped { stop, .. } => {
+                    format!("Stopped at {}/{}", stop + 1, route.stations.len())
+                }
+            });
+            label(format!("{}/{}", route.load(), route.capacity));
+            if button_secondary("edit").show().clicked {
+                *uiworld.write::<FreightRouteEditor>() = FreightRouteEditor {
+                    from: Some(b.id),
+                    editing: Some(id),
+                    stations: route.stations.clone(),
+                    items: route.items.clone(),
+                    timetable: route.timetable,
+                };
+            }
+            if button_secondary("remove").show().clicked {
+                uiworld
+                    .commands()
+                    .push(WorldCommand::RemoveFreightRoute(id));
+            }
+        });
+        if route.train.is_some() && route.capacity == 0 {
+            label("The train has no wagon to carry goods");
+        }
+    }
+    drop(routes);
+
+    let mut editor = uiworld.write::<FreightRouteEditor>();
+    if editor.from == Some(b.id) {
+        render_route_editor(uiworld, sim, b, &mut editor);
+    } else if button_secondary("New freight route").show().clicked {
+        *editor = FreightRouteEditor {
+            from: Some(b.id),
+            editing: None,
+            stations: vec![b.id],
+            items: vec![],
+            timetable: Timetable::default(),
+        };
+    }
+    drop(editor);
+
     fixed_spacer((0.0, 10.0));
     label("Trains:");
     for (tid, state) in &freight.f.trains {
@@ -147,6 +231,171 @@ fn render_freightstation(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     }
 }
 
+fn render_route_editor(
+    uiworld: &UiWorld,
+    sim: &Simulation,
+    b: &Building,
+    editor: &mut FreightRouteEditor,
+) {
+    fixed_spacer((0.0, 10.0));
+    label(if editor.editing.is_some() {
+        "Edit the freight route"
+    } else {
+        "New freight route"
+    });
+
+    let map = sim.map();
+    let describe = |station: BuildingID| {
+        if station == b.id {
+            return "this station".to_string();
+        }
+        map.buildings()
+            .get(station)
+            .map_or("destroyed station".to_string(), |ob| {
+                format!(
+                    "station {:.0}m away",
+                    ob.door_pos.xy().distance(b.door_pos.xy())
+                )
+            })
+    };
+
+    label("Stops:");
+    let mut removed = None;
+    for (i, &stop) in editor.stations.iter().enumerate() {
+        minrow(5.0, || {
+            label(format!("{}. {}", i + 1, describe(stop)));
+            if button_secondary("remove").show().clicked {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        editor.stations.remove(i);
+    }
+
+    for (other, _) in map
+        .buildings()
+        .iter()
+        .filter(|(_, ob)| matches!(ob.kind, BuildingKind::RailFreightStation(_)))
+    {
+        if editor.stations.last() == Some(&other) {
+            continue;
+        }
+        if button_secondary(format!("Add {}", describe(other)))
+            .show()
+            .clicked
+        {
+            editor.stations.push(other);
+        }
+    }
+    drop(map);
+
+    fixed_spacer((0.0, 10.0));
+    label("Carried goods, any if none is selected:");
+    let items: Vec<&ItemPrototype> = ItemPrototype::iter().collect();
+    for row in items.chunks(4) {
+        minrow(5.0, || {
+            for item in row {
+                let selected = editor.items.contains(&item.id);
+                if selectable_label_primary(selected, &item.label).clicked {
+                    if selected {
+                        editor.items.retain(|&x| x != item.id);
+                    } else {
+                        editor.items.push(item.id);
+                    }
+                }
+            }
+        });
+    }
+
+    fixed_spacer((0.0, 10.0));
+    let timetable = &mut editor.timetable;
+    minrow(5.0, || {
+        let mut interval = timetable.interval.minutes() as u64;
+        if dragvalue().min(1.0).max(1440.0).show(&mut interval) {
+            timetable.interval = GameDuration::from_minutes(interval);
+        }
+        label("minutes between departures");
+    });
+    minrow(5.0, || {
+        let mut loading = timetable.loading_time.seconds() as u64;
+        if dragvalue().min(0.0).max(3600.0).show(&mut loading) {
+            timetable.loading_time = GameDuration::from_secs(loading);
+        }
+        label("seconds to load");
+    });
+    minrow(5.0, || {
+        let mut unloading = timetable.unloading_time.seconds() as u64;
+        if dragvalue().min(0.0).max(3600.0).show(&mut unloading) {
+            timetable.unloading_time = GameDuration::from_secs(unloading);
+        }
+        label("seconds to unload");
+    });
+
+    let valid =
+        editor.stations.len() >= 2 && editor.stations.iter().any(|&s| s != editor.stations[0]);
+    minrow(5.0, || {
+        let confirm = if editor.editing.is_some() {
+            "Save"
+        } else {
+            "Create"
+        };
+        if valid && button_primary(confirm).show().clicked {
+            let stations = std::mem::take(&mut editor.stations);
+            let items = std::mem::take(&mut editor.items);
+            let timetable = editor.timetable;
+            uiworld.commands().push(match editor.editing {
+                Some(id) => WorldCommand::UpdateFreightRoute {
+                    id,
+                    stations,
+                    items,
+                    timetable,
+                },
+                None => WorldCommand::AddFreightRoute {
+                    stations,
+                    items,
+                    timetable,
+                },
+            });
+            editor.from = None;
+        }
+        if button_secondary("Cancel").show().clicked {
+            editor.from = None;
+        }
+    });
+    if !valid {
+        label("A freight route needs at least two different stations");
+    }
+}
+
+fn render_depot(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    label("Trains in the depot:");
+    for (tid, t) in sim.world().trains.iter() {
+        if t.depot != Some(b.id) {
+            continue;
+        }
+        minrow(5.0, || {
+            entity_link(uiworld, sim, tid);
+            label(if in_depot(t) { "Waiting" } else { "Arriving" });
+        });
+    }
+}
+
+fn render_leisure(sim: &Simulation, b: &Building, proto: LeisurePrototypeID) {
+    let proto = proto.prototype();
+    let visitors = sim
+        .read::<BuildingInfos>()
+        .get(b.id)
+        .map_or(0, |info| info.inside.len());
+    let open = proto
+        .opening_hours
+        .is_active(&sim.read::<GameTime>().daytime);
+
+    label(if open { "Open" } else { "Closed" });
+    label(format!("Visitors: {}/{}", visitors, proto.capacity));
+    label(format!("Entry fee: {}", proto.entry_fee));
+}
+
 fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     let owner = sim.read::<BuildingInfos>().owner(b.id);
 
@@ -180,6 +429,16 @@ fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
             entity_link(uiworld, sim, driver);
         });
     }
+
+    label(format!("balance: {}", goods.money));
+    label(format!("profit last day: {}", goods.last_day_profit));
+    if goods.deficit_days > 0 {
+        label(format!(
+            "in deficit for {}/{} days",
+            goods.deficit_days, COMPANY_CLOSURE_DAYS
+        ));
+    }
+
     let productivity = c.productivity(proto, b.zone.as_ref(), map, elec_flow);
     if productivity < 1.0 {
         ProgressBar {
@@ -199,6 +458,26 @@ fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
         render_recipe(uiworld, r);
     }
 
+    if let Some(warehouse) = goods.warehouse() {
+        label("Stock");
+        for item in &warehouse.capacity {
+            let stock = market.capital(c_id.into(), item.id);
+            ProgressBar {
+                value: stock as f32 / item.amount.max(1) as f32,
+                size: Vec2::new(200.0, 25.0),
+                color: primary().adjust(0.7),
+            }
+            .show_children(|| {
+                label(format!(
+                    "{}: {}/{}",
+                    item.id.prototype().name,
+                    stock,
+                    item.amount
+                ));
+            });
+        }
+    }
+
     if let Some(net_id) = map.electricity.net_id(b.id) {
         let blackout = elec_flow.blackout(net_id);
 
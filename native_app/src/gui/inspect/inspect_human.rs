@@ -79,8 +79,15 @@ pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
                 label("Working at");
                 building_link(uiworld, sim, x.workplace);
                 match x.kind {
-                    WorkKind::Driver { .. } => {
+                    WorkKind::Driver { ref tour, .. } => {
                         label("as a driver");
+                        if !tour.stops.is_empty() {
+                            label(format!(
+                                "({}/{} stops delivered)",
+                                tour.n_delivered(),
+                                tour.stops.len()
+                            ));
+                        }
                     }
                     WorkKind::Worker => {
                         label("as a worker");
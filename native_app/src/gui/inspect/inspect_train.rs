@@ -1,6 +1,10 @@
+use crate::gui::addtrain::TrainSpawnResource;
 use crate::gui::inspect::follow_button;
 use crate::uiworld::UiWorld;
-use goryak::{on_secondary_container, textc, Window};
+use goryak::{button_secondary, minrow, on_secondary_container, textc, Window};
+use prototypes::RollingStockID;
+use simulation::transportation::depot::{has_depot, in_depot, recompose_cost};
+use simulation::world_command::WorldCommand;
 use simulation::{Simulation, TrainID};
 use yakui::widgets::Pad;
 
@@ -28,7 +32,60 @@ pub fn inspect_train(uiworld: &UiWorld, sim: &Simulation, id: TrainID) -> bool {
             format!("Going at {:.0}km/h", t.speed.0),
         );
 
-        follow_button(uiworld, id);
+        if !t.consist.is_empty() {
+            let names: Vec<_> = t
+                .consist
+                .iter()
+                .map(|&w| RollingStockID::prototype(w).label.as_str())
+                .collect();
+            textc(on_secondary_container(), names.join(", "));
+        }
+
+        if t.depot.is_some() {
+            textc(
+                on_secondary_container(),
+                if in_depot(t) {
+                    "Waiting in the depot"
+                } else {
+                    "Going to the depot"
+                },
+            );
+        }
+
+        minrow(5.0, || {
+            follow_button(uiworld, id);
+
+            if t.depot.is_some() {
+                if button_secondary("release").show().clicked {
+                    uiworld.commands().push(WorldCommand::ReleaseTrain(id));
+                }
+            } else if has_depot(&sim.map()) && button_secondary("recall").show().clicked {
+                uiworld.commands().push(WorldCommand::RecallTrain(id));
+            }
+
+            if t.speed.0 <= 0.1 && button_secondary("reverse").show().clicked {
+                uiworld.commands().push(WorldCommand::ReverseTrain(id));
+            }
+
+            if button_secondary("despawn").show().clicked {
+                uiworld.commands().push(WorldCommand::DespawnTrain(id));
+            }
+        });
+
+        if in_depot(t) {
+            let wagons = uiworld.read::<TrainSpawnResource>().wagons.clone();
+            let cost = recompose_cost(&t.consist, &wagons).filter(|_| !wagons.is_empty());
+            if let Some(cost) = cost {
+                if button_secondary(format!("Re-compose with the train tool wagons ({})", cost))
+                    .show()
+                    .clicked
+                {
+                    uiworld
+                        .commands()
+                        .push(WorldCommand::RecomposeTrain { train: id, wagons });
+                }
+            }
+        }
     });
 
     is_open
@@ -4,6 +4,7 @@ use crate::gui::{InspectedBuilding, InspectedEntity};
 use crate::uiworld::UiWorld;
 use goryak::{button_primary, primary_link};
 use inspect_building::inspect_building;
+pub use inspect_building::FreightRouteEditor;
 use inspect_human::inspect_human;
 use inspect_train::inspect_train;
 use inspect_vehicle::inspect_vehicle;
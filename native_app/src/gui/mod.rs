@@ -22,6 +22,8 @@ pub struct GuiState {
     pub windows: GUIWindows,
     pub last_save: Instant,
     pub depause_warp: u32,
+    /// The warp last picked in the time controls, asked to the server when playing online
+    pub requested_warp: Option<u32>,
     pub hidden: bool,
 }
 
@@ -32,6 +34,7 @@ impl Default for GuiState {
             windows: Default::default(),
             last_save: Instant::now(),
             depause_warp: 1,
+            requested_warp: None,
             hidden: false,
         }
     }
@@ -46,6 +49,7 @@ pub fn run_ui_systems(sim: &Simulation, uiworld: &UiWorld) {
     roadeditor::roadeditor(sim, uiworld);
     specialbuilding::specialbuilding(sim, uiworld);
     addtrain::addtrain(sim, uiworld);
+    blueprint::blueprint(sim, uiworld);
     zoneedit::zoneedit(sim, uiworld);
     terraforming::terraforming(sim, uiworld);
 
@@ -128,6 +132,7 @@ pub enum Tool {
     SpecialBuilding,
     Train,
     Terraforming,
+    Blueprint,
 }
 
 impl Tool {
@@ -4,14 +4,34 @@ use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
 use geom::{Color, OBB};
 use prototypes::RollingStockID;
-use simulation::map::LaneKind;
+use simulation::map::{LaneID, LaneKind, TraverseKind};
+use simulation::transportation::signals::{RailSignalKind, RailSignals};
 use simulation::transportation::train::{calculate_locomotive, wagons_positions_for_render};
 use simulation::world_command::WorldCommand;
-use simulation::Simulation;
+use simulation::{Simulation, TrainID};
 use std::option::Option::None;
 
+/// What clicking on a rail lane does with the train tool
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrainToolMode {
+    #[default]
+    SpawnTrain,
+    PlaceSignal(RailSignalKind),
+    RemoveSignal,
+}
+
+/// The signal under the cursor, shown in the tool properties
+#[derive(Clone, Copy, Debug)]
+pub struct HoveredSignal {
+    pub lane: LaneID,
+    pub kind: RailSignalKind,
+    pub reserved_by: Option<TrainID>,
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct TrainSpawnResource {
+    pub mode: TrainToolMode,
+    pub hovered_signal: Option<HoveredSignal>,
     pub wagons: Vec<RollingStockID>,
     /// m/s
     pub max_speed: f32,
@@ -33,6 +53,7 @@ pub fn addtrain(sim: &Simulation, uiworld: &UiWorld) {
     if !matches!(tool, Tool::Train) {
         state.wagons.clear();
         state.set_zero();
+        state.hovered_signal = None;
         return;
     }
 
@@ -45,17 +66,58 @@ pub fn addtrain(sim: &Simulation, uiworld: &UiWorld) {
 
     let mpos = unwrap_ret!(inp.unprojected);
 
+    let signals = sim.read::<RailSignals>();
+    for (lane, kind) in signals.iter() {
+        let Some(end) = map.lanes().get(lane).map(|l| l.points.last()) else {
+            continue;
+        };
+        let col = match kind {
+            RailSignalKind::Block => simulation::colors().gui_primary,
+            RailSignalKind::Chain => simulation::colors().gui_success,
+        };
+        draw.circle(end.up(0.5), 2.0).color(col);
+    }
+
     let nearbylane = map.nearest_lane(mpos, LaneKind::Rail, Some(20.0));
 
     let nearbylane = match nearbylane.and_then(|x| map.lanes().get(x)) {
         Some(x) => x,
         None => {
+            state.hovered_signal = None;
             draw.circle(mpos, 10.0)
                 .color(simulation::colors().gui_danger);
             return;
         }
     };
 
+    state.hovered_signal = signals.get(nearbylane.id).map(|kind| HoveredSignal {
+        lane: nearbylane.id,
+        kind,
+        reserved_by: signals.reserved_by(TraverseKind::Lane(nearbylane.id)),
+    });
+
+    let signal_kind = match state.mode {
+        TrainToolMode::SpawnTrain => None,
+        TrainToolMode::PlaceSignal(kind) => Some(Some(kind)),
+        TrainToolMode::RemoveSignal => Some(None),
+    };
+    if let Some(kind) = signal_kind {
+        let end = nearbylane.points.last();
+        draw.circle(end.up(0.5), 3.0)
+            .color(simulation::colors().gui_primary);
+
+        let cmd = WorldCommand::SetRailSignal {
+            lane: nearbylane.id,
+            kind,
+        };
+        if inp.just_act.contains(&InputAction::Select) {
+            commands.push(cmd);
+        } else {
+            potential.set(cmd);
+        }
+        return;
+    }
+
     if state.wagons.is_empty() {
         return;
     }
@@ -0,0 +1,105 @@
+use engine::AudioKind;
+use geom::{Degrees, Vec3, AABB};
+use serde::{Deserialize, Serialize};
+use simulation::map::{Blueprint, BlueprintTransform};
+use simulation::world_command::WorldCommand;
+use simulation::Simulation;
+
+use crate::gui::{PotentialCommands, Tool};
+use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::uiworld::UiWorld;
+
+/// Blueprints captured by the player, saved to disk
+#[derive(Default, Serialize, Deserialize)]
+pub struct BlueprintLibrary {
+    pub blueprints: Vec<Blueprint>,
+}
+
+#[derive(Default)]
+pub struct BlueprintResource {
+    /// Index of the blueprint being pasted, capturing a new one if None
+    pub selected: Option<usize>,
+    pub rotation: Degrees,
+    pub mirror: bool,
+    capture_start: Option<Vec3>,
+}
+
+/// Blueprint tool
+/// Allows to capture a part of the map as a blueprint by dragging a rectangle,
+/// and to paste it elsewhere, rotated or mirrored.
+pub fn blueprint(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::blueprint");
+    let mut state = uiworld.write::<BlueprintResource>();
+    let tool = *uiworld.read::<Tool>();
+
+    if !matches!(tool, Tool::Blueprint) {
+        state.capture_start = None;
+        return;
+    }
+
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let mut sound = uiworld.write::<ImmediateSound>();
+    let mut library = uiworld.write::<BlueprintLibrary>();
+    let map = sim.map();
+
+    let mpos = unwrap_ret!(inp.unprojected);
+
+    if let Some(bp) = state.selected.and_then(|i| library.blueprints.get(i)) {
+        if inp.act.contains(&InputAction::Rotate) {
+            state.rotation += Degrees(inp.wheel);
+            state.rotation.normalize();
+        }
+
+        let transform = BlueprintTransform {
+            pos: mpos.xy(),
+            rotation: state.rotation.vec2(),
+            mirror: state.mirror,
+        };
+        let paste = bp.paste(&map, &transform);
+
+        let col = simulation::colors().gui_primary;
+        for (from, to, _, _) in &paste.links {
+            draw.line(
+                paste.projects[*from].pos.up(0.5),
+                paste.projects[*to].pos.up(0.5),
+                3.0,
+            )
+            .color(col);
+        }
+        for b in &paste.buildings {
+            draw.obb(b.obb, mpos.z + 0.3).color(col.a(0.5));
+        }
+
+        let cmd = WorldCommand::MapBuildBlueprint(paste);
+        if inp.just_act.contains(&InputAction::Select) {
+            uiworld.commands().push(cmd);
+            sound.play("road_lay", AudioKind::Ui);
+        } else {
+            uiworld.write::<PotentialCommands>().set(cmd);
+        }
+        return;
+    }
+
+    let Some(start) = state.capture_start else {
+        if inp.just_act.contains(&InputAction::Select) {
+            state.capture_start = Some(mpos);
+        }
+        return;
+    };
+
+    let area = AABB::new_ll_ur(start.xy().min(mpos.xy()), start.xy().max(mpos.xy()));
+    draw.aabb(area, mpos.z + 0.3)
+        .color(simulation::colors().gui_primary.a(0.2));
+
+    if !inp.act.contains(&InputAction::Select) {
+        state.capture_start = None;
+
+        let name = format!("Blueprint {}", library.blueprints.len() + 1);
+        if let Some(bp) = Blueprint::capture(&map, name, area) {
+            library.blueprints.push(bp);
+            state.selected = Some(library.blueprints.len() - 1);
+        }
+    }
+}
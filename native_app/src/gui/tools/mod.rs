@@ -1,4 +1,5 @@
 pub mod addtrain;
+pub mod blueprint;
 pub mod bulldozer;
 pub mod inspected_aura;
 pub mod lotbrush;
@@ -1,10 +1,11 @@
-use crate::gui::Tool;
+use crate::gui::{PotentialCommands, Tool};
 use crate::inputmap::{InputAction, InputMap};
 use crate::rendering::immediate::ImmediateDraw;
 use crate::uiworld::UiWorld;
 use geom::Color;
-use simulation::map::{IntersectionID, LightPolicy, TurnPolicy};
+use simulation::map::{IntersectionID, LanePatternBuilder, LightPolicy, TurnPolicy};
 use simulation::map::{ProjectFilter, ProjectKind};
+use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 
 #[derive(Clone)]
@@ -14,14 +15,25 @@ pub struct IntersectionComponent {
     pub light_policy: LightPolicy,
 }
 
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+pub enum RoadEditorMode {
+    #[default]
+    Intersection,
+    /// Rebuilds the lanes of the clicked roads with the chosen pattern
+    Upgrade,
+}
+
 #[derive(Default)]
 pub struct RoadEditorResource {
+    pub mode: RoadEditorMode,
     pub inspect: Option<IntersectionComponent>,
     pub dirty: bool,
+    pub pattern_builder: LanePatternBuilder,
 }
 
 /// RoadEditor tool
 /// Allows to edit intersections properties like turns and signals
+/// and to change the lanes of existing roads
 pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::roadeditor");
     let tool = uiworld.read::<Tool>();
@@ -36,6 +48,50 @@ pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
         return;
     }
 
+    if state.mode == RoadEditorMode::Upgrade {
+        state.inspect = None;
+
+        let cur_proj = map.project(unwrap_ret!(inp.unprojected), 0.0, ProjectFilter::ROAD);
+        let ProjectKind::Road(id) = cur_proj.kind else {
+            imm_draw
+                .circle(cur_proj.pos.up(0.5), 2.0)
+                .color(simulation::colors().gui_disabled);
+            return;
+        };
+        let road = &map.roads()[id];
+        let pattern = state.pattern_builder.build();
+
+        let is_rail = road.lanes_iter().any(|(_, kind)| kind.is_rail());
+        let can_upgrade =
+            is_rail == state.pattern_builder.rail && road.pattern(map.lanes()) != pattern;
+
+        let col = if can_upgrade {
+            simulation::colors().gui_primary
+        } else {
+            simulation::colors().gui_disabled
+        };
+        imm_draw
+            .polyline(
+                road.points().iter().map(|p| p.up(0.5)).collect::<Vec<_>>(),
+                road.width,
+                false,
+            )
+            .color(col.a(0.5));
+
+        if !can_upgrade {
+            return;
+        }
+
+        if inp.just_act.contains(&InputAction::Select) {
+            commands.map_update_road_pattern(id, pattern);
+        } else {
+            uiworld
+                .write::<PotentialCommands>()
+                .set(WorldCommand::MapUpdateRoadPattern { road: id, pattern });
+        }
+        return;
+    }
+
     if let Some(id) = state.inspect.as_ref().map(|x| x.id) {
         if let Some(inter) = map.intersections().get(id) {
             let lanes = map.lanes();
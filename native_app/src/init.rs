@@ -1,9 +1,11 @@
 use crate::debug_gui::debug_window::{DebugObjs, DebugState, TestFieldProperties};
 use crate::game_loop::Timings;
 use crate::gui::addtrain::TrainSpawnResource;
+use crate::gui::blueprint::{BlueprintLibrary, BlueprintResource};
 use crate::gui::bulldozer::BulldozerState;
 use crate::gui::chat::GUIChatState;
 use crate::gui::follow::FollowEntity;
+use crate::gui::inspect::FreightRouteEditor;
 use crate::gui::keybinds::KeybindState;
 use crate::gui::lotbrush::LotBrushResource;
 use crate::gui::roadbuild::RoadBuildResource;
@@ -16,11 +18,12 @@ use crate::gui::windows::load::LoadState;
 use crate::gui::windows::settings::{Settings, SettingsState};
 use crate::gui::zoneedit::ZoneEditState;
 use crate::gui::{
-    ErrorTooltip, ExitState, GuiState, InspectedBuilding, InspectedEntity, PotentialCommands,
-    TimeAlways, Tool,
+    CommandErrors, ErrorTooltip, ExitState, GuiState, InspectedBuilding, InspectedEntity,
+    PotentialCommands, TimeAlways, Tool,
 };
 use crate::inputmap::{Bindings, InputMap};
 use crate::network::NetworkState;
+use crate::prototype_watcher::PrototypeWatcher;
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
 use crate::uiworld::{ReceivedCommands, SaveLoadState, UiWorld};
 use common::saveload::Encoder;
@@ -37,10 +40,13 @@ pub fn init() {
     register_resource::<crate::gui::windows::network::NetworkConnectionInfo>("netinfo");
     register_resource::<LotBrushResource>("lot_brush");
     register_resource::<Bindings>("bindings");
+    register_resource::<BlueprintLibrary>("blueprints");
 
     register_resource_noserialize::<GuiState>();
+    register_resource_noserialize::<CommandErrors>();
     register_resource_noserialize::<TerraformingResource>();
     register_resource_noserialize::<BulldozerState>();
+    register_resource_noserialize::<BlueprintResource>();
     register_resource_noserialize::<DebugObjs>();
     register_resource_noserialize::<DebugState>();
     register_resource_noserialize::<ErrorTooltip>();
@@ -55,6 +61,7 @@ pub fn init() {
     register_resource_noserialize::<InspectedBuilding>();
     register_resource_noserialize::<NetworkState>();
     register_resource_noserialize::<PotentialCommands>();
+    register_resource_noserialize::<PrototypeWatcher>();
     register_resource_noserialize::<ZoneEditState>();
     register_resource_noserialize::<TestFieldProperties>();
     register_resource_noserialize::<ReceivedCommands>();
@@ -70,6 +77,7 @@ pub fn init() {
     register_resource_noserialize::<EconomyState>();
     register_resource_noserialize::<SettingsState>();
     register_resource_noserialize::<BuildingIcons>();
+    register_resource_noserialize::<FreightRouteEditor>();
     register_resource_noserialize::<KeybindState>();
 }
 
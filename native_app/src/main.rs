@@ -19,6 +19,7 @@ mod gui;
 mod init;
 mod inputmap;
 mod network;
+mod prototype_watcher;
 mod rendering;
 
 fn main() {
@@ -26,6 +27,11 @@ fn main() {
     profiling::tracy_client::Client::start();
     profiling::register_thread!("Main Thread");
 
+    if std::env::args().any(|a| a == "--validate-mods") {
+        common::logger::MyLog::init();
+        prototypes::validate_mods_and_exit("./");
+    }
+
     engine::framework::init();
     init::init();
 
@@ -1,11 +1,13 @@
 pub use self::inner::*;
 use crate::game_loop::{State, Timings};
 use crate::gui::windows::settings::Settings;
-use crate::uiworld::{ReceivedCommands, SaveLoadState};
+use crate::gui::CommandErrors;
+use crate::uiworld::{ReceivedCommands, SaveLoadState, UiWorld};
 use common::timestep::Timestep;
 use simulation::utils::scheduler::SeqSchedule;
-use simulation::world_command::{WorldCommand, WorldCommands};
+use simulation::world_command::{RejectedCommands, WorldCommand, WorldCommands};
 use simulation::Simulation;
+use std::time::Instant;
 
 impl Default for NetworkState {
     fn default() -> Self {
@@ -22,11 +24,30 @@ mod inner {
         Singleplayer(Timestep),
     }
 
+    impl NetworkState {
+        pub fn chat_color(&self) -> geom::Color {
+            geom::Color::WHITE
+        }
+
+        pub fn save_players(&self) {}
+
+        pub fn speed_vote(&self) -> Option<String> {
+            None
+        }
+
+        pub fn fast_forward_warp(&self) -> u32 {
+            super::FAST_FORWARD_WARP
+        }
+    }
+
     pub fn sim_update(state: &mut State) {
         super::handle_singleplayer(state);
     }
 }
 
+/// The fastest warp of the time controls when playing alone
+const FAST_FORWARD_WARP: u32 = 1000;
+
 #[allow(dead_code)]
 fn handle_singleplayer(state: &mut State) {
     let mut sim = unwrap_orr!(state.sim.try_write(), return); // mut for tick
@@ -50,7 +71,13 @@ fn handle_singleplayer(state: &mut State) {
 
     if has_commands && commands.iter().all(WorldCommand::is_instant) {
         for v in commands.iter() {
-            v.apply(&mut sim);
+            if let Err(e) = v.apply(&mut sim) {
+                state
+                    .uiw
+                    .write::<CommandErrors>()
+                    .errors
+                    .push((Instant::now(), e));
+            }
         }
         commands = WorldCommands::default();
         has_commands = false;
@@ -69,6 +96,7 @@ fn handle_singleplayer(state: &mut State) {
     while step.tick() || (has_commands && commands_once.is_some()) {
         let t = sim.tick(sched, commands_once.take().unwrap_or_default().as_ref());
         timings.world_update.add_value(t.as_secs_f32());
+        record_rejected(&sim, &state.uiw, |_| true);
     }
 
     if commands_once.is_none() {
@@ -78,6 +106,20 @@ fn handle_singleplayer(state: &mut State) {
     }
 }
 
+/// Surfaces the commands of the last tick rejected by the simulation if they were sent by us
+fn record_rejected(sim: &Simulation, uiw: &UiWorld, sent_by_me: impl Fn(usize) -> bool) {
+    let rejected = sim.read::<RejectedCommands>();
+    if rejected.rejected.is_empty() {
+        return;
+    }
+    let mut errors = uiw.write::<CommandErrors>();
+    for (i, e) in &rejected.rejected {
+        if sent_by_me(*i) {
+            errors.errors.push((Instant::now(), e.clone()));
+        }
+    }
+}
+
 fn handle_replay(
     sim: &mut Simulation,
     schedule: &mut SeqSchedule,
@@ -102,11 +144,14 @@ fn handle_replay(
 mod inner {
     use crate::game_loop::{State, Timings, VERSION};
     use crate::gui::windows::network::NetworkConnectionInfo;
-    use crate::network::handle_replay;
+    use crate::gui::windows::settings::Settings;
+    use crate::gui::GuiState;
+    use crate::network::{handle_replay, record_rejected};
     use crate::uiworld::{ReceivedCommands, SaveLoadState};
     use common::timestep::Timestep;
     use networking::{
-        ConnectConf, Frame, PollResult, ServerConfiguration, ServerPollResult, VirtualClientConf,
+        ConnectConf, Frame, GameSpeed, PlayerRegistry, PlayerToken, PollResult,
+        ServerConfiguration, ServerPollResult, VirtualClientConf,
     };
     use prototypes::DELTA_F64;
     use simulation::world_command::WorldCommands;
@@ -125,11 +170,101 @@ mod inner {
         Server(Server),
     }
 
+    impl NetworkState {
+        /// Saves the players that joined our server, so they keep their name on the next session
+        pub fn save_players(&self) {
+            if let NetworkState::Server(ref server) = *self {
+                server.lock().unwrap().registry().save_to_disk();
+            }
+        }
+
+        /// Our chat color as registered by the server, white when playing alone or hosting
+        pub fn chat_color(&self) -> geom::Color {
+            if let NetworkState::Client(ref client) = *self {
+                if let Some(player) = client.lock().unwrap().player() {
+                    return geom::Color::from_hex(player.color as u64);
+                }
+            }
+            geom::Color::WHITE
+        }
+
+        /// The ongoing vote for a speed change, if any
+        pub fn speed_vote(&self) -> Option<String> {
+            let NetworkState::Client(ref client) = *self else {
+                return None;
+            };
+            let vote = client.lock().unwrap().speed_vote()?;
+            let what = if vote.speed.paused {
+                "pause".to_string()
+            } else {
+                format!("x{}", vote.speed.multiplier)
+            };
+            Some(format!("vote {}: {}/{}", what, vote.votes, vote.needed))
+        }
+
+        /// The server bounds the speed, so the fastest warp is lower when playing online
+        pub fn fast_forward_warp(&self) -> u32 {
+            match self {
+                NetworkState::Singleplayer(_) => super::FAST_FORWARD_WARP,
+                _ => GameSpeed::MAX_MULTIPLIER as u32,
+            }
+        }
+
+        fn speed(&mut self) -> Option<GameSpeed> {
+            match self {
+                NetworkState::Singleplayer(_) => None,
+                NetworkState::Client(client) => Some(client.get_mut().unwrap().speed()),
+                NetworkState::Server(server) => Some(server.get_mut().unwrap().speed()),
+            }
+        }
+    }
+
+    /// None when no warp matches the multiplier, as the admin console can set any of them
+    fn warp_of(speed: GameSpeed) -> Option<u32> {
+        if speed.paused {
+            return Some(0);
+        }
+        if speed.multiplier.fract() != 0.0 {
+            return None;
+        }
+        Some(speed.multiplier as u32)
+    }
+
+    /// Sends the speed picked in the time controls to the server, which decides of the speed
+    /// shown in return
+    fn sync_speed(net_state: &mut NetworkState, warp: &mut u32, requested: &mut Option<u32>) {
+        let Some(current) = net_state.speed() else {
+            return;
+        };
+
+        if let Some(requested) = requested.take() {
+            let wanted = GameSpeed {
+                paused: requested == 0,
+                multiplier: if requested == 0 {
+                    current.multiplier
+                } else {
+                    requested as f64
+                },
+            };
+            match net_state {
+                NetworkState::Singleplayer(_) => {}
+                NetworkState::Client(client) => client.get_mut().unwrap().request_speed(wanted),
+                NetworkState::Server(server) => server.get_mut().unwrap().set_speed(wanted),
+            }
+        }
+
+        // unwrap ok: checked above that we are in multiplayer
+        if let Some(shown) = warp_of(net_state.speed().unwrap()) {
+            *warp = shown;
+        }
+    }
+
     pub fn sim_update(state: &mut State) {
         if matches!(
             *state.uiw.read::<NetworkState>(),
             NetworkState::Singleplayer(_)
         ) {
+            state.uiw.write::<GuiState>().requested_warp = None;
             super::handle_singleplayer(state);
             return;
         }
@@ -149,6 +284,12 @@ mod inner {
 
         let mut net_state = state.uiw.write::<NetworkState>();
 
+        sync_speed(
+            &mut net_state,
+            &mut state.uiw.write::<Settings>().time_warp,
+            &mut state.uiw.write::<GuiState>().requested_warp,
+        );
+
         let mut inputs_to_apply = None;
         match &mut *net_state {
             NetworkState::Singleplayer(_) => unreachable!(),
@@ -197,10 +338,15 @@ mod inner {
             let mut merged = WorldCommands::default();
             for frame_commands in inputs {
                 assert_eq!(frame_commands.frame.0, sim.get_tick() + 1);
+                // the sender of each command once the batches are flattened
+                let mut sent_by_me = vec![];
                 let commands: WorldCommands = frame_commands
                     .inputs
                     .iter()
-                    .map(|x| x.inp.clone())
+                    .map(|x| {
+                        sent_by_me.extend(x.inp.iter().map(|_| x.sent_by_me));
+                        x.inp.clone()
+                    })
                     .collect();
                 let t = sim.tick(&mut state.game_schedule, commands.as_ref());
                 state
@@ -208,6 +354,9 @@ mod inner {
                     .write::<Timings>()
                     .world_update
                     .add_value(t.as_secs_f32());
+                record_rejected(&sim, &state.uiw, |i| {
+                    sent_by_me.get(i).copied().unwrap_or(false)
+                });
                 merged.merge(
                     &frame_commands
                         .inputs
@@ -230,7 +379,11 @@ mod inner {
                 name: info.name.to_string(),
             }),
             version: VERSION.to_string(),
+            mods: prototypes::active_mod_ids(),
             always_run: true,
+            speed_vote: false,
+            password: (!info.password.is_empty()).then(|| info.password.clone()),
+            players: PlayerRegistry::load_from_disk(),
         }) {
             Ok(x) => x,
             Err(e) => {
@@ -242,7 +395,7 @@ mod inner {
         Some(Mutex::new(server))
     }
 
-    pub fn start_client(info: &mut NetworkConnectionInfo) -> Option<Client> {
+    pub fn start_client(info: &mut NetworkConnectionInfo, spectator: bool) -> Option<Client> {
         let mut s = info.ip.to_string();
         if !s.contains(':') {
             s += ":23019"
@@ -263,12 +416,26 @@ mod inner {
 
         let port = parsed_addr.port();
 
+        if info.token.is_empty() {
+            info.token = PlayerToken::generate().as_str().to_string();
+        }
+
         let client = match networking::Client::connect(ConnectConf {
             name: info.name.clone(),
             addr: parsed_addr.ip(),
             port: if port != 23019 { Some(port) } else { None },
             frame_buffer_advance: 8,
             version: VERSION.to_string(),
+            mods: prototypes::active_mod_ids(),
+            token: PlayerToken::new(info.token.clone()),
+            password: (!info.password.is_empty()).then(|| info.password.clone()),
+            spectator,
+            // one cache per server, a cache only keeps the blobs of the last received world
+            world_cache: Some(
+                format!("world/cache/{}", parsed_addr)
+                    .replace(':', "_")
+                    .into(),
+            ),
         }) {
             Ok(x) => x,
             Err(e) => {
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::game_loop::State;
+use crate::network::NetworkState;
+
+/// Watches the lua files of the mods to reload the prototypes when one of them is modified
+#[derive(Default)]
+pub struct PrototypeWatcher {
+    last_check: Option<Instant>,
+    mtimes: BTreeMap<PathBuf, SystemTime>,
+}
+
+impl PrototypeWatcher {
+    const CHECK_PERIOD: Duration = Duration::from_secs(1);
+
+    /// Returns true if a lua file was added or modified since the last check
+    fn changed(&mut self) -> bool {
+        if self
+            .last_check
+            .map_or(false, |t| t.elapsed() < Self::CHECK_PERIOD)
+        {
+            return false;
+        }
+        let first = self.last_check.is_none();
+        self.last_check = Some(Instant::now());
+
+        let mut changed = false;
+        for dir in ["base_mod", "mods"] {
+            if !Path::new(dir).exists() {
+                continue;
+            }
+            for path in common::saveload::walkdir(Path::new(dir)) {
+                if path.extension().map_or(true, |e| e != "lua") {
+                    continue;
+                }
+                let mtime = unwrap_cont!(std::fs::metadata(&path).and_then(|m| m.modified()).ok());
+                if self.mtimes.insert(path, mtime) != Some(mtime) {
+                    changed = true;
+                }
+            }
+        }
+        changed && !first
+    }
+}
+
+/// Reloads the prototypes if a lua file was modified. Only in singleplayer since the other
+/// players would not reload at the same time.
+pub fn check_prototype_updates(state: &State) {
+    if !matches!(
+        *state.uiw.read::<NetworkState>(),
+        NetworkState::Singleplayer(_)
+    ) {
+        return;
+    }
+    if !state.uiw.write::<PrototypeWatcher>().changed() {
+        return;
+    }
+    // the save thread might be reading the simulation, try again later
+    let Ok(mut sim) = state.sim.try_write() else {
+        state.uiw.write::<PrototypeWatcher>().mtimes.clear();
+        return;
+    };
+
+    // Safety: the prototypes are only used on the main thread while the simulation is locked
+    match unsafe { sim.reload_prototypes("./") } {
+        Ok(report) => {
+            log::info!("reloaded prototypes");
+            if !report.removed.is_empty() {
+                log::warn!(
+                    "removed prototypes are kept until restart: {}",
+                    report.removed.join(", ")
+                );
+            }
+        }
+        Err(e) => log::error!("could not reload prototypes: {}", e),
+    }
+}
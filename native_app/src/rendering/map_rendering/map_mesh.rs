@@ -7,7 +7,7 @@ use engine::{
     MeshVertex, MetallicRoughness, SpriteBatch, SpriteBatchBuilder, Tesselator,
 };
 use geom::{minmax, vec2, vec3, Color, LinearColor, PolyLine3, Polygon, Radians, Vec2, Vec3};
-use prototypes::{FreightStationPrototype, GoodsCompanyPrototype, RenderAsset};
+use prototypes::{FreightStationPrototype, GoodsCompanyPrototype, LeisurePrototype, RenderAsset};
 use simulation::map::{
     Building, BuildingKind, CanonicalPosition, Environment, Intersection, LaneKind, Lanes, LotKind,
     Map, MapSubscriber, ProjectFilter, ProjectKind, PylonPosition, Road, Roads, SubscriberChunkID,
@@ -83,12 +83,24 @@ impl MapMeshHandler {
                 FreightStationPrototype::iter()
                     .map(|descr| (&descr.asset, BuildingKind::RailFreightStation(descr.id))),
             )
-            .chain([(
-                &RenderAsset::Mesh {
-                    path: "external_trading.glb".into(),
-                },
-                BuildingKind::ExternalTrading,
-            )])
+            .chain(
+                LeisurePrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::Leisure(descr.id))),
+            )
+            .chain([
+                (
+                    &RenderAsset::Mesh {
+                        path: "external_trading.glb".into(),
+                    },
+                    BuildingKind::ExternalTrading,
+                ),
+                (
+                    &RenderAsset::Mesh {
+                        path: "rail_freight_station.glb".into(),
+                    },
+                    BuildingKind::TrainDepot,
+                ),
+            ])
         {
             let RenderAsset::Mesh { path } = asset else {
                 continue;
@@ -684,7 +696,7 @@ impl MapBuilders {
                     &mut tess_map,
                     match l.kind {
                         LaneKind::Walking => hig_col,
-                        LaneKind::Parking => low_col,
+                        LaneKind::Parking | LaneKind::Biking => low_col,
                         _ => mid_col,
                     },
                     l.kind.width() - 0.25,
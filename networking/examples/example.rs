@@ -1,6 +1,9 @@
+use common::saveload::{Bincode, Blobs, Encoder};
+use common::StampSender;
 use log::LevelFilter;
 use networking::{
-    Client, ConnectConf, Frame, PollResult, Server, ServerConfiguration, ServerPollResult,
+    Client, ConnectConf, Frame, PlayerToken, PollResult, Server, ServerConfiguration,
+    ServerPollResult,
 };
 use serde::{Deserialize, Serialize};
 use std::net::Ipv4Addr;
@@ -28,6 +31,31 @@ impl World {
     }
 }
 
+/// The padding rarely changes so it is sent as its own blob
+impl Blobs for World {
+    fn to_blobs(&self) -> std::io::Result<Vec<(String, Vec<u8>)>> {
+        Ok(vec![
+            (
+                "counters".to_string(),
+                Bincode::encode(&(self.incr_a, self.incr_b, self.tick))?,
+            ),
+            ("pad".to_string(), self.pad.clone()),
+        ])
+    }
+
+    fn from_blobs(blobs: Vec<(String, Vec<u8>)>) -> Option<Self> {
+        let mut blobs = blobs.into_iter();
+        let (incr_a, incr_b, tick) = Bincode::decode(&blobs.next()?.1).ok()?;
+        let pad = blobs.next()?.1;
+        Some(Self {
+            incr_a,
+            incr_b,
+            tick,
+            pad,
+        })
+    }
+}
+
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, Default)]
 enum Action {
     #[default]
@@ -36,6 +64,11 @@ enum Action {
     IncrB,
 }
 
+/// The actions don't say who sent them
+impl StampSender for Action {
+    fn stamp_sender(&mut self, _: &str, _: u32) {}
+}
+
 const UP_DT: Duration = Duration::from_millis(50);
 
 pub fn main() {
@@ -54,6 +87,11 @@ pub fn main() {
         port: None,
         frame_buffer_advance: 10,
         version: "v1".to_string(),
+        mods: vec![],
+        token: PlayerToken::generate(),
+        password: None,
+        spectator: false,
+        world_cache: None,
     })
     .unwrap();
 
@@ -100,7 +138,11 @@ pub fn serv_c() {
         port: None,
         virtual_client: None,
         version: "v1".to_string(),
+        password: None,
+        players: Default::default(),
+        mods: vec![],
         always_run: true,
+        speed_vote: false,
     })
     .unwrap();
 
@@ -1,5 +1,7 @@
 use crate::connections::Connections;
+use crate::identity::{PlayerRegistry, PlayerToken};
 use crate::packets::{AuthentResponse, ServerReliablePacket, ServerUnreliablePacket};
+use crate::speed::GameSpeed;
 use crate::{encode, hash_str, Frame, UserID};
 use common::{FastMap, FastSet};
 use serde::{Deserialize, Serialize};
@@ -19,6 +21,10 @@ impl Display for AuthentID {
 
 impl AuthentID {
     pub const VIRTUAL_ID: AuthentID = AuthentID(0);
+    /// Inputs sent by the server itself, for example by the admin console
+    pub const SERVER_ID: AuthentID = AuthentID(u32::MAX);
+    /// Speed changes, carried by the frame from which they apply
+    pub const SPEED_ID: AuthentID = AuthentID(u32::MAX - 1);
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -28,6 +34,16 @@ pub(crate) enum ClientGameState {
     Playing,
 }
 
+impl Display for ClientGameState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ClientGameState::Downloading => "downloading",
+            ClientGameState::CatchingUp => "catching up",
+            ClientGameState::Playing => "playing",
+        })
+    }
+}
+
 pub(crate) struct Client {
     pub id: AuthentID,
     #[allow(dead_code)]
@@ -37,6 +53,7 @@ pub(crate) struct Client {
     pub udp_addr: SocketAddr,
     pub tcp_addr: SocketAddr,
     pub state: ClientGameState,
+    pub spectator: bool,
 }
 
 enum ClientConnectState {
@@ -55,10 +72,18 @@ pub(crate) struct Authent {
     n_connected_clients: u32,
     seq: u32,
     version: String,
+    mods: Vec<String>,
+    password: Option<String>,
+    pub registry: PlayerRegistry,
 }
 
 impl Authent {
-    pub fn new(version: String) -> Self {
+    pub fn new(
+        version: String,
+        mods: Vec<String>,
+        password: Option<String>,
+        registry: PlayerRegistry,
+    ) -> Self {
         Self {
             names: Default::default(),
             clients: Default::default(),
@@ -66,6 +91,9 @@ impl Authent {
             n_connected_clients: 0,
             seq: 1,
             version,
+            mods,
+            password,
+            registry,
         }
     }
 
@@ -74,13 +102,19 @@ impl Authent {
         !self.names.insert(name)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn tcp_client_auth(
         &mut self,
         addr: SocketAddr,
         ack: Frame,
         name: String,
+        token: PlayerToken,
+        password: Option<String>,
+        spectator: bool,
         version: String,
+        mods: Vec<String>,
         period: Duration,
+        speed: GameSpeed,
     ) -> Option<AuthentResponse> {
         let v = self.get_client_state_mut(addr)?;
 
@@ -93,21 +127,50 @@ impl Authent {
             log::info!("client authenticated: {}@{}", name, addr);
             let hash = hash_str(&name);
 
-            if self.register(name.clone()) {
+            if version != self.version {
                 return Some(AuthentResponse::Refused {
-                    reason: format!("name is already in use: {name}"),
+                    reason: format!(
+                        "Incompatible versions: serv: {} vs client: {}",
+                        self.version, version
+                    ),
                 });
             }
 
-            if version != self.version {
+            if mods != self.mods {
                 return Some(AuthentResponse::Refused {
                     reason: format!(
-                        "Incompatible versions: serv: {} vs client: {}",
-                        self.version, version
+                        "Incompatible mods: serv: [{}] vs client: [{}]",
+                        self.mods.join(", "),
+                        mods.join(", ")
                     ),
                 });
             }
 
+            let password_ok = match (&self.password, &password) {
+                (None, _) => true,
+                (Some(expected), Some(given)) => {
+                    common::constant_time_eq(expected.as_bytes(), given.as_bytes())
+                }
+                (Some(_), None) => false,
+            };
+            if !password_ok {
+                return Some(AuthentResponse::Refused {
+                    reason: "wrong password".to_string(),
+                });
+            }
+
+            if self.names.contains(&name) {
+                return Some(AuthentResponse::Refused {
+                    reason: format!("name is already in use: {name}"),
+                });
+            }
+
+            let player = match self.registry.authenticate(&name, token) {
+                Ok(player) => player,
+                Err(reason) => return Some(AuthentResponse::Refused { reason }),
+            };
+            self.register(name.clone());
+
             // Unwrap ok: already checked right before
             *self.get_client_state_mut(tcp_addr).unwrap() = ClientConnectState::Connected(Client {
                 id,
@@ -118,11 +181,17 @@ impl Authent {
                 udp_addr,
                 tcp_addr,
                 state: ClientGameState::Downloading,
+                spectator,
             });
 
             self.n_connected_clients += 1;
 
-            return Some(AuthentResponse::Accepted { id, period });
+            return Some(AuthentResponse::Accepted {
+                id,
+                period,
+                speed,
+                player,
+            });
         }
         None
     }
@@ -140,7 +209,8 @@ impl Authent {
         }
     }
 
-    pub fn tcp_connected(&mut self, tcp_addr: SocketAddr, net: &Connections) {
+    /// Challenges the new connection with the id it will use on udp
+    pub fn tcp_connected(&mut self, tcp_addr: SocketAddr, net: &Connections) -> AuthentID {
         log::info!("connected: {}", tcp_addr);
 
         let id = self.next_auth_id();
@@ -156,6 +226,7 @@ impl Authent {
         );
 
         net.send_tcp(tcp_addr, encode(&ServerReliablePacket::Challenge(id)));
+        id
     }
 
     pub fn disconnected(&mut self, tcp_addr: SocketAddr) -> Option<Client> {
@@ -199,10 +270,20 @@ impl Authent {
             .filter_map(ClientConnectState::as_connected_mut)
     }
 
+    /// Clients following the frame stream, including spectators
     pub fn iter_playing(&self) -> impl Iterator<Item = &Client> + Clone {
         self.iter().filter(|x| x.state == ClientGameState::Playing)
     }
 
+    /// Clients following the frame stream and sending inputs
+    pub fn iter_players(&self) -> impl Iterator<Item = &Client> + Clone {
+        self.iter_playing().filter(|x| !x.spectator)
+    }
+
+    pub fn iter_spectators(&self) -> impl Iterator<Item = &Client> + Clone {
+        self.iter_playing().filter(|x| x.spectator)
+    }
+
     fn next_auth_id(&mut self) -> AuthentID {
         self.seq += 1;
         AuthentID(self.seq)
@@ -1,4 +1,6 @@
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -7,14 +9,18 @@ use client_playout::ClientPlayoutBuffer;
 
 use crate::connection_client::ConnectionClient;
 use crate::connections::ConnectionsError;
+use crate::identity::{PlayerData, PlayerToken};
 use crate::packets::{
     AuthentResponse, ClientReliablePacket, ClientUnreliablePacket, ServerReliablePacket,
     ServerUnreliablePacket,
 };
-use crate::worldsend::WorldReceive;
+use crate::speed::{GameSpeed, SpeedVoteStatus};
+use crate::worldsend::{BlobCache, WorldReceive};
 use crate::{
-    decode, decode_merged, encode, AuthentID, Frame, PhantomSendSync, PlayerInput, DEFAULT_PORT,
+    decode, decode_merged, encode, speed_change, AuthentID, Frame, MergedInputs, PhantomSendSync,
+    PlayerInput, DEFAULT_PORT,
 };
+use common::saveload::Blobs;
 use common::timestep::Timestep;
 
 mod client_playout;
@@ -64,7 +70,16 @@ pub struct Client<WORLD: DeserializeOwned, INPUT: Serialize + DeserializeOwned +
     net: ConnectionClient,
 
     name: String,
+    token: PlayerToken,
+    password: Option<String>,
+    spectator: bool,
     version: String,
+    mods: Vec<String>,
+    player: Option<PlayerData>,
+    base_period: Duration,
+    speed: GameSpeed,
+    speed_vote: Option<SpeedVoteStatus>,
+    cache: Option<BlobCache>,
 
     state: ClientState<WORLD, INPUT>,
 
@@ -80,9 +95,18 @@ pub struct ConnectConf {
     pub port: Option<u16>,
     pub frame_buffer_advance: u64,
     pub version: String,
+    /// The mods loaded by the client, they must be the same as the server's
+    pub mods: Vec<String>,
+    /// Proves we own the name, it should be generated once and kept by the client
+    pub token: PlayerToken,
+    pub password: Option<String>,
+    /// Follow the game without playing, the inputs given to poll are dropped
+    pub spectator: bool,
+    /// Where the world is kept between sessions, so only the parts that changed are downloaded
+    pub world_cache: Option<PathBuf>,
 }
 
-impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I> {
+impl<W: DeserializeOwned + Blobs, I: Serialize + DeserializeOwned + Default> Client<W, I> {
     pub fn connect(conf: ConnectConf) -> Result<Self, ConnectionsError> {
         let addr = conf.addr;
         let port = conf.port.unwrap_or(DEFAULT_PORT);
@@ -94,10 +118,19 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
             net,
             state: ClientState::Connecting,
             name: conf.name,
+            token: conf.token,
+            password: conf.password,
+            spectator: conf.spectator,
+            player: None,
+            base_period: Timestep::default().period,
+            speed: GameSpeed::default(),
+            speed_vote: None,
+            cache: conf.world_cache.and_then(BlobCache::new),
             lag_compensate: conf.frame_buffer_advance,
             step: Timestep::default(),
             _phantom: Default::default(),
             version: conf.version,
+            mods: conf.mods,
         })
     }
 
@@ -226,17 +259,33 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                     assert!(to_consume <= advance);
 
                     let net = &mut self.net;
+                    let spectator = self.spectator;
 
+                    let mut frame = buffer.consumed_frame();
                     let multi: Vec<_> = (0..to_consume)
                         .map(move |_| {
                             // unwrap ok: to_consume must be less than advance
                             let (inp, pack) = buffer.try_consume(&mut mk_input).unwrap();
-                            net.send_udp(encode(&ClientUnreliablePacket::Input { input: pack }));
-                            decode_merged(id, inp, buffer.consumed_frame())
+                            let packet = if spectator {
+                                ClientUnreliablePacket::Ack(buffer.consumed_frame())
+                            } else {
+                                ClientUnreliablePacket::Input { input: pack }
+                            };
+                            net.send_udp(encode(&packet));
+                            inp
                         })
                         .collect();
                     //log::info!("consuming {:?} inputs from unreliable channel", multi.len());
-                    return PollResult::Input(multi);
+                    self.follow_speed(&multi);
+                    return PollResult::Input(
+                        multi
+                            .into_iter()
+                            .map(|inp| {
+                                frame.incr();
+                                decode_merged(id, inp, frame)
+                            })
+                            .collect(),
+                    );
                 }
             }
         }
@@ -246,11 +295,18 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
 
     fn message_reliable(&mut self, p: ServerReliablePacket) -> Option<()> {
         match p {
+            ServerReliablePacket::WorldManifest { frame, blobs } => {
+                if let ClientState::Downloading { ref mut wr, .. } = self.state {
+                    wr.handle_manifest(frame, blobs, self.cache.as_ref(), &self.net);
+                } else {
+                    log::error!("received world manifest but was not downloading.. weird");
+                }
+            }
             ServerReliablePacket::WorldSend(fragment) => {
                 log::info!("{}: received world fragment", self.name);
 
                 if let ClientState::Downloading { ref mut wr, .. } = self.state {
-                    wr.handle(fragment, &self.net);
+                    wr.handle(fragment, self.cache.as_ref(), &self.net);
                 } else {
                     log::error!("received world but was not downloading.. weird");
                 }
@@ -261,7 +317,12 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                     .send_udp(encode(&ClientUnreliablePacket::Connection(challenge)));
             }
             ServerReliablePacket::AuthentResponse(r) => match r {
-                AuthentResponse::Accepted { id, period: step } => {
+                AuthentResponse::Accepted {
+                    id,
+                    period,
+                    speed,
+                    player,
+                } => {
                     log::info!(
                         "{}: authent response is accepted. asking for world",
                         self.name
@@ -270,16 +331,22 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                         wr: WorldReceive::default(),
                         id,
                     };
-                    self.step = Timestep::new(step);
-                    self.net.send_tcp(encode(&ClientReliablePacket::WorldAck));
+                    self.base_period = period;
+                    self.speed = speed;
+                    self.step = Timestep::new(speed.period(period));
+                    self.player = Some(player);
                 }
                 AuthentResponse::Refused { reason } => {
                     log::error!("authent refused :( reason: {}", reason);
                     self.state = ClientState::Disconnected { reason };
                 }
             },
+            ServerReliablePacket::SpeedVote(status) => {
+                self.speed_vote = status;
+            }
             ServerReliablePacket::CatchUp { inputs } => {
                 log::info!("{}: received catch up inputs", self.name);
+                self.follow_speed(&inputs);
 
                 if let ClientState::CatchingUp {
                     ref mut next_inputs,
@@ -315,6 +382,7 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                     self.name,
                     final_consumed_frame
                 );
+                self.follow_speed(&final_inputs);
                 if let ClientState::CatchingUp {
                     next_inputs: None,
                     id,
@@ -366,13 +434,54 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                 log::info!("{}: received ready for auth", self.name);
                 let connect = ClientReliablePacket::Connect {
                     name: self.name.clone(),
+                    token: self.token.clone(),
+                    password: self.password.clone(),
+                    spectator: self.spectator,
                     version: self.version.clone(),
+                    mods: self.mods.clone(),
                 };
                 self.net.send_tcp(encode(&connect));
             }
         }
     }
 
+    /// Speed changes come with the frames, so everyone changes speed on the same frame
+    fn follow_speed(&mut self, frames: &[MergedInputs]) {
+        let Some(speed) = speed_change(frames) else {
+            return;
+        };
+        log::info!("{}: speed changed to {:?}", self.name, speed);
+        self.speed = speed;
+        self.step.period = speed.period(self.base_period);
+    }
+
+    /// Our data as registered by the server, known once authenticated
+    pub fn player(&self) -> Option<&PlayerData> {
+        self.player.as_ref()
+    }
+
+    /// The speed decided by the server
+    pub fn speed(&self) -> GameSpeed {
+        self.speed
+    }
+
+    pub fn speed_vote(&self) -> Option<SpeedVoteStatus> {
+        self.speed_vote
+    }
+
+    pub fn is_spectator(&self) -> bool {
+        self.spectator
+    }
+
+    /// Asks the server to change the speed, which may need the vote of the other players
+    pub fn request_speed(&mut self, speed: GameSpeed) {
+        if self.spectator {
+            return;
+        }
+        self.net
+            .send_tcp(encode(&ClientReliablePacket::RequestSpeed(speed)));
+    }
+
     pub fn describe(&self) -> String {
         match self.state {
             ClientState::Connecting => "Connecting...".to_string(),
@@ -391,6 +500,9 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
             ClientState::Playing {
                 buffer: ref buf, ..
             } => {
+                if self.spectator {
+                    return format!("Spectating! Buffer advance: {}", buf.advance());
+                }
                 format!("Playing! Buffer advance: {}", buf.advance())
             }
             ClientState::Disconnected { ref reason } => reason.clone(),
@@ -0,0 +1,197 @@
+use crate::hash_str;
+use common::saveload::{Encoder, JSONPretty, JSON};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+
+/// A secret generated once by each client and sent on every connection.
+/// The first client to connect with a name owns it, and must send the same token to use it again.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct PlayerToken(String);
+
+impl PlayerToken {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 16];
+        getrandom::getrandom(&mut bytes).expect("could not generate a player token");
+        Self(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    pub fn new(token: String) -> Self {
+        Self(token)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Only the hash is kept by the server, so a leaked player file cannot be used to log in
+    fn hash(&self) -> TokenHash {
+        TokenHash(blake3::hash(self.0.as_bytes()).to_hex().to_string())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+struct TokenHash(String);
+
+impl TokenHash {
+    /// Compares in constant time so the hash cannot be guessed from the response time
+    fn matches(&self, token: &PlayerToken) -> bool {
+        common::constant_time_eq(self.0.as_bytes(), token.hash().0.as_bytes())
+    }
+}
+
+impl Debug for PlayerToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PlayerToken(..)")
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Permissions {
+    /// Players that cannot play can still watch, but their inputs are ignored
+    pub can_play: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self { can_play: true }
+    }
+}
+
+/// Data attached to a player name that survives reconnects
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlayerData {
+    /// Chat color as 0xRRGGBB
+    pub color: u32,
+    pub permissions: Permissions,
+}
+
+impl PlayerData {
+    fn new(name: &str) -> Self {
+        Self {
+            color: default_color(name),
+            permissions: Permissions::default(),
+        }
+    }
+}
+
+fn default_color(name: &str) -> u32 {
+    const COLORS: [u32; 8] = [
+        0xe6194b, 0x3cb44b, 0xffe119, 0x4363d8, 0xf58231, 0x911eb4, 0x46f0f0, 0xf032e6,
+    ];
+    COLORS[hash_str(name) as usize % COLORS.len()]
+}
+
+#[derive(Serialize, Deserialize)]
+struct RegisteredPlayer {
+    token_hash: TokenHash,
+    data: PlayerData,
+}
+
+/// The players that ever connected to the server, meant to be saved alongside the world
+#[derive(Default, Serialize, Deserialize)]
+pub struct PlayerRegistry {
+    players: BTreeMap<String, RegisteredPlayer>,
+}
+
+const PLAYERS_SAVE_NAME: &str = "players";
+
+impl PlayerRegistry {
+    pub fn load_from_disk() -> Self {
+        JSON::load(PLAYERS_SAVE_NAME).unwrap_or_default()
+    }
+
+    pub fn save_to_disk(&self) {
+        JSONPretty::save_silent(self, PLAYERS_SAVE_NAME);
+    }
+
+    /// Returns the data of the player, registering the name on its first connection
+    pub(crate) fn authenticate(
+        &mut self,
+        name: &str,
+        token: PlayerToken,
+    ) -> Result<PlayerData, String> {
+        if token.is_empty() {
+            return Err("missing player token".to_string());
+        }
+        let p = self
+            .players
+            .entry(name.to_string())
+            .or_insert_with(|| RegisteredPlayer {
+                token_hash: token.hash(),
+                data: PlayerData::new(name),
+            });
+        if !p.token_hash.matches(&token) {
+            return Err(format!("name is registered by another player: {name}"));
+        }
+        Ok(p.data)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlayerData> {
+        self.players.get(name).map(|p| &p.data)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PlayerData> {
+        self.players.get_mut(name).map(|p| &mut p.data)
+    }
+
+    /// Forgets the player so anyone can use the name again, for example when its token was lost
+    pub fn forget(&mut self, name: &str) -> bool {
+        self.players.remove(name).is_some()
+    }
+
+    pub fn can_play(&self, name: &str) -> bool {
+        self.get(name).map_or(true, |p| p.permissions.can_play)
+    }
+
+    /// The chat color of the player, players that never connected get the default one
+    pub fn color(&self, name: &str) -> u32 {
+        self.get(name)
+            .map_or_else(|| default_color(name), |p| p.color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let mut reg = PlayerRegistry::default();
+        let tok = PlayerToken::generate();
+
+        let data = reg.authenticate("bob", tok.clone()).unwrap();
+        assert_eq!(reg.authenticate("bob", tok.clone()), Ok(data));
+        assert!(reg.authenticate("bob", PlayerToken::generate()).is_err());
+        assert!(reg
+            .authenticate("alice", PlayerToken::new(String::new()))
+            .is_err());
+
+        reg.get_mut("bob").unwrap().permissions.can_play = false;
+        assert!(!reg.can_play("bob"));
+        assert!(reg.can_play("alice"));
+
+        assert!(reg.forget("bob"));
+        assert!(reg.authenticate("bob", PlayerToken::generate()).is_ok());
+    }
+
+    #[test]
+    fn tokens_are_not_saved() {
+        let mut reg = PlayerRegistry::default();
+        let tok = PlayerToken::generate();
+        reg.authenticate("bob", tok.clone()).unwrap();
+
+        let saved = JSON::encode(&reg).unwrap();
+        assert!(!String::from_utf8_lossy(&saved).contains(tok.as_str()));
+
+        let mut reg: PlayerRegistry = JSON::decode(&saved).unwrap();
+        assert!(reg.authenticate("bob", tok).is_ok());
+        assert!(reg.authenticate("bob", PlayerToken::generate()).is_err());
+    }
+}
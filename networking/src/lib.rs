@@ -14,14 +14,18 @@ mod catchup;
 mod client;
 mod connection_client;
 mod connections;
+mod identity;
 mod packets;
 mod ring;
 mod server;
+mod speed;
 mod worldsend;
 
 use crate::client::FrameInputs;
 pub use client::{Client, ConnectConf, PollResult, ServerInput};
-pub use server::{Server, ServerConfiguration, ServerPollResult, VirtualClientConf};
+pub use identity::{Permissions, PlayerData, PlayerRegistry, PlayerToken};
+pub use server::{PlayerInfo, Server, ServerConfiguration, ServerPollResult, VirtualClientConf};
+pub use speed::{GameSpeed, SpeedVoteStatus};
 
 pub(crate) const MAX_WORLDSEND_PACKET_SIZE: usize = 262144; //32 ko at least 1.3Mo per s at 50FPS
 pub(crate) const DEFAULT_PORT: u16 = 23019;
@@ -104,6 +108,9 @@ fn decode_merged<I: DeserializeOwned>(
         inputs: x
             .into_iter()
             .flat_map(|(id, x)| {
+                if id == AuthentID::SPEED_ID {
+                    return None;
+                }
                 Some(ServerInput {
                     sent_by_me: id == me,
                     inp: decode(&x.0)?,
@@ -112,3 +119,37 @@ fn decode_merged<I: DeserializeOwned>(
             .collect(),
     }
 }
+
+/// The last speed change carried by the frames, if any
+fn speed_change(frames: &[MergedInputs]) -> Option<GameSpeed> {
+    frames.iter().rev().find_map(|x| {
+        let (_, speed) = x.iter().rev().find(|(id, _)| *id == AuthentID::SPEED_ID)?;
+        decode(&speed.0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speed_changes_are_not_inputs() {
+        let speed = GameSpeed {
+            paused: true,
+            multiplier: 2.0,
+        };
+        let frames = vec![
+            vec![(AuthentID(1), PlayerInput(encode(&5u32)))],
+            vec![
+                (AuthentID::SPEED_ID, PlayerInput(encode(&speed))),
+                (AuthentID(1), PlayerInput(encode(&6u32))),
+            ],
+        ];
+        assert_eq!(speed_change(&frames[..1]), None);
+        assert_eq!(speed_change(&frames), Some(speed));
+
+        let inputs = decode_merged::<u32>(AuthentID(1), frames[1].clone(), Frame(2));
+        assert_eq!(inputs.inputs.len(), 1);
+        assert_eq!(inputs.inputs[0].inp, 6);
+    }
+}
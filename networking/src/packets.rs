@@ -1,4 +1,7 @@
 use crate::authent::AuthentID;
+use crate::identity::{PlayerData, PlayerToken};
+use crate::speed::{GameSpeed, SpeedVoteStatus};
+use crate::worldsend::BlobHash;
 use crate::{Frame, MergedInputs, PlayerInput};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -20,32 +23,77 @@ pub(crate) enum ServerReliablePacket {
     CatchUp {
         inputs: Vec<MergedInputs>,
     },
+    /// The blobs making up the world, sent once authenticated
+    WorldManifest {
+        frame: Frame,
+        blobs: Vec<BlobInfo>,
+    },
     WorldSend(WorldDataFragment),
+    /// None when the vote is over
+    SpeedVote(Option<SpeedVoteStatus>),
 }
 
 #[derive(Serialize, Deserialize)]
 pub(crate) enum ClientUnreliablePacket {
     Connection(AuthentID),
-    Input { input: Vec<(Frame, PlayerInput)> },
+    Input {
+        input: Vec<(Frame, PlayerInput)>,
+    },
+    /// Sent by spectators instead of inputs, so the server knows which frames to resend
+    Ack(Frame),
 }
 
 #[derive(Serialize, Deserialize)]
 pub(crate) enum ClientReliablePacket {
-    Connect { name: String, version: String },
+    Connect {
+        name: String,
+        token: PlayerToken,
+        password: Option<String>,
+        /// Spectators follow the game but never send inputs
+        spectator: bool,
+        version: String,
+        mods: Vec<String>,
+    },
     BeginCatchUp,
     CatchUpAck,
+    /// The blobs of the manifest that are not in the cache, possibly partially downloaded
+    WorldRequest(Vec<BlobRequest>),
     WorldAck,
+    /// Changes the speed right away, or votes for it if the server asks for a majority
+    RequestSpeed(GameSpeed),
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub(crate) enum AuthentResponse {
-    Accepted { id: AuthentID, period: Duration },
-    Refused { reason: String },
+    Accepted {
+        id: AuthentID,
+        /// The period at normal speed
+        period: Duration,
+        speed: GameSpeed,
+        player: PlayerData,
+    },
+    Refused {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BlobInfo {
+    pub name: String,
+    pub hash: BlobHash,
+    pub size: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct BlobRequest {
+    pub hash: BlobHash,
+    /// The number of bytes the client already has
+    pub offset: usize,
 }
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct WorldDataFragment {
-    pub is_over: Option<Frame>,
-    pub data_size: usize,
+    pub hash: BlobHash,
+    pub offset: usize,
     pub data: Vec<u8>,
 }
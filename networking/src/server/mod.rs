@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::time::Duration;
 
 use serde::Serialize;
@@ -6,19 +7,34 @@ use crate::authent::{Authent, AuthentID, ClientGameState};
 use crate::catchup::CatchUp;
 use crate::client::FrameInputs;
 use crate::connections::{Connections, ConnectionsError};
+use crate::identity::PlayerRegistry;
 use crate::packets::{
     AuthentResponse, ClientReliablePacket, ClientUnreliablePacket, ServerReliablePacket,
     ServerUnreliablePacket,
 };
 use crate::server::server_playout::ServerPlayoutBuffer;
+use crate::speed::{GameSpeed, SpeedVote};
 use crate::worldsend::WorldSend;
 use crate::{decode, decode_merged, encode, Frame, PhantomSendSync, PlayerInput, DEFAULT_PORT};
+use common::saveload::Blobs;
 use common::timestep::Timestep;
+use common::StampSender;
 use serde::de::DeserializeOwned;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
 mod server_playout;
 
+/// Overwrites the identity the input claims with the one of the player that sent it
+fn stamp_input<INPUT: Serialize + DeserializeOwned + StampSender>(
+    input: &PlayerInput,
+    name: &str,
+    color: u32,
+) -> Option<PlayerInput> {
+    let mut inp: INPUT = decode(&input.0)?;
+    inp.stamp_sender(name, color);
+    Some(PlayerInput(encode(&inp)))
+}
+
 pub struct ServerConfiguration {
     pub start_frame: Frame,
     pub period: Duration,
@@ -26,14 +42,34 @@ pub struct ServerConfiguration {
     pub virtual_client: Option<VirtualClientConf>,
     /// Checks if client has same version or refuses authent otherwise
     pub version: String,
+    /// Refuses clients that do not send this password, if any
+    pub password: Option<String>,
+    /// The players that already connected, usually loaded from the last save
+    pub players: PlayerRegistry,
+    /// Checks if client has the same mods, in the same order, or refuses authent otherwise
+    pub mods: Vec<String>,
     /// Always run, even when everyone is disconnected
     pub always_run: bool,
+    /// Speed changes asked by the players need the agreement of most of them
+    /// instead of being applied right away. Pausing always needs it
+    pub speed_vote: bool,
 }
 
 pub struct VirtualClientConf {
     pub name: String,
 }
 
+/// A connected player, as shown to the server admin
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerInfo {
+    pub name: String,
+    pub addr: SocketAddr,
+    pub state: String,
+    pub spectator: bool,
+    /// Number of frames the player is behind the server, if it is playing
+    pub lag: Option<u64>,
+}
+
 pub enum ServerPollResult<I> {
     Wait(Option<I>),
     Input(Vec<FrameInputs<I>>),
@@ -55,16 +91,26 @@ pub struct Server<WORLD: Serialize, INPUT> {
 
     step: Timestep,
     always_run: bool,
+    base_period: Duration,
+    speed: GameSpeed,
+    /// Sent along the next frame, which is the first one played at that speed
+    next_speed: Option<GameSpeed>,
+    speed_vote: bool,
+    vote: Option<SpeedVote>,
+    /// Inputs sent by the server itself, one is played per frame
+    server_inputs: VecDeque<PlayerInput>,
 
     _phantom: PhantomSendSync<(WORLD, INPUT)>,
 }
 
-impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WORLD, INPUT> {
+impl<WORLD: 'static + Serialize + Blobs, INPUT: Serialize + DeserializeOwned + StampSender>
+    Server<WORLD, INPUT>
+{
     pub fn start(conf: ServerConfiguration) -> Result<Self, ConnectionsError> {
         let port = conf.port.unwrap_or(DEFAULT_PORT);
         let net = Connections::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port))?;
 
-        let mut authent = Authent::new(conf.version);
+        let mut authent = Authent::new(conf.version, conf.mods, conf.password, conf.players);
         let v_client = conf.virtual_client.map(|c| VirtualClient { name: c.name });
         if let Some(ref v_client) = v_client {
             authent.register(v_client.name.clone());
@@ -80,6 +126,12 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             worldsend: Default::default(),
             _phantom: Default::default(),
             always_run: conf.always_run,
+            base_period: conf.period,
+            speed: GameSpeed::default(),
+            next_speed: None,
+            speed_vote: conf.speed_vote,
+            vote: None,
+            server_inputs: VecDeque::new(),
             next_inputs: vec![],
         })
     }
@@ -120,12 +172,14 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             }
         }
 
+        self.update_vote();
         self.send_merged_inputs();
         self.send_long_running();
 
         if !self.next_inputs.is_empty() {
-            if self.v_client.is_some() {
-                if let Some(inp) = local_inputs {
+            if let Some(ref v_client) = self.v_client {
+                if let Some(mut inp) = local_inputs {
+                    inp.stamp_sender(&v_client.name, self.authent.registry.color(&v_client.name));
                     self.buffer.insert_input(
                         AuthentID::VIRTUAL_ID,
                         self.buffer.consumed_frame.incred(),
@@ -139,15 +193,35 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
     }
 
     fn send_merged_inputs(&mut self) {
-        let n_playing = self.authent.iter_playing().count() + self.v_client.is_some() as usize;
+        // spectators are not enough to keep the game running
+        let n_playing = self.authent.iter_players().count() + self.v_client.is_some() as usize;
 
-        if n_playing == 0 && !self.always_run {
+        let paused = self.speed.paused && self.next_speed.is_none();
+        if paused || (n_playing == 0 && !self.always_run) {
             return;
         }
 
         self.step.prepare_frame(1);
 
         while self.step.tick() {
+            if let Some(input) = self.server_inputs.pop_front() {
+                self.buffer.insert_input(
+                    AuthentID::SERVER_ID,
+                    self.buffer.consumed_frame.incred(),
+                    input,
+                );
+            }
+
+            if let Some(speed) = self.next_speed.take() {
+                self.buffer.insert_input(
+                    AuthentID::SPEED_ID,
+                    self.buffer.consumed_frame.incred(),
+                    PlayerInput(encode(&speed)),
+                );
+                self.speed = speed;
+                self.step.period = speed.period(self.base_period);
+            }
+
             let buffer = &self.buffer;
             let to_disconnect = self
                 .authent
@@ -165,7 +239,12 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
                 self.disconnect(tcp_addr);
             }
 
-            let clients_playing = self.authent.iter_playing();
+            // spectators ack the frames they got like players do, so they get the same resends
+            // and are disconnected the same way when too late
+            let clients_playing = self
+                .authent
+                .iter_players()
+                .chain(self.authent.iter_spectators());
 
             let (consumed_inputs, inputs) =
                 self.buffer.consume(clients_playing.clone().map(|c| c.ack));
@@ -185,6 +264,10 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
 
             self.catchup
                 .add_merged_inputs(self.buffer.consumed_frame, consumed_inputs);
+
+            if self.speed.paused {
+                break;
+            }
         }
     }
 
@@ -209,15 +292,29 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
     ) -> Option<()> {
         match packet {
             ClientUnreliablePacket::Input { input } => {
+                let c = self.authent.get_client(addr)?;
+                let can_play = !c.spectator && self.authent.registry.can_play(&c.name);
+                let color = self.authent.registry.color(&c.name);
                 let client = self.authent.get_client_mut(addr)?;
 
                 //log::info!("{}: received inputs {:?}", client.name, ack_frame);
 
                 for (frame, input) in input {
                     client.ack = client.ack.max(frame);
+                    if !can_play {
+                        continue;
+                    }
+                    let Some(input) = stamp_input::<INPUT>(&input, &client.name, color) else {
+                        log::warn!("{} sent an invalid input", client.name);
+                        continue;
+                    };
                     self.buffer.insert_input(client.id, frame, input);
                 }
             }
+            ClientUnreliablePacket::Ack(frame) => {
+                let client = self.authent.get_client_mut(addr)?;
+                client.ack = client.ack.max(frame);
+            }
             ClientUnreliablePacket::Connection(id) => {
                 self.authent.udp_connect(addr, id, &self.net);
             }
@@ -233,14 +330,26 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
         w_frame: Frame,
     ) -> Option<()> {
         match packet {
-            ClientReliablePacket::Connect { name, version } => {
+            ClientReliablePacket::Connect {
+                name,
+                token,
+                password,
+                spectator,
+                version,
+                mods,
+            } => {
                 log::info!("received tcp game handshake: {} {}", name, version);
                 let auth_r = self.authent.tcp_client_auth(
                     addr,
                     self.buffer.consumed_frame,
                     name,
+                    token,
+                    password,
+                    spectator,
                     version,
-                    self.step.period,
+                    mods,
+                    self.base_period,
+                    self.speed,
                 )?;
 
                 self.net.send_tcp(
@@ -250,9 +359,18 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
 
                 match auth_r {
                     AuthentResponse::Accepted { .. } => {
+                        let blobs = match w.to_blobs() {
+                            Ok(blobs) => blobs,
+                            Err(e) => {
+                                log::error!("could not split the world to send it: {}", e);
+                                self.disconnect(addr);
+                                self.net.remove_tcp(addr);
+                                return None;
+                            }
+                        };
                         let c = self.authent.get_client(addr)?;
                         assert_eq!(self.buffer.consumed_frame, w_frame);
-                        self.worldsend.begin_send(c, encode(&w), w_frame);
+                        self.worldsend.begin_send(c, blobs, w_frame, &self.net);
                         self.catchup
                             .begin_remembering(self.buffer.consumed_frame, c);
 
@@ -275,17 +393,49 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
                 log::info!("client {} ack", c.name);
                 self.catchup.ack(c);
             }
+            ClientReliablePacket::WorldRequest(requests) => {
+                let c = self.authent.get_client(addr)?;
+                self.worldsend.request(c, requests);
+            }
             ClientReliablePacket::WorldAck => {
                 let c = self.authent.get_client(addr)?;
                 log::info!("client {} world rcv acked", c.name);
                 self.worldsend.ack(c);
             }
+            ClientReliablePacket::RequestSpeed(speed) => {
+                let c = self.authent.get_client(addr)?;
+                if c.spectator || !self.authent.registry.can_play(&c.name) {
+                    return None;
+                }
+                let id = c.id;
+                let speed = speed.clamped();
+                if !self.speed_vote && !speed.paused {
+                    log::info!("{} changed the speed to {:?}", c.name, speed);
+                    self.set_speed(speed);
+                    return Some(());
+                }
+                match self.vote {
+                    Some(ref vote) if vote.speed != speed => {
+                        log::info!(
+                            "{} asked for speed {:?} while a vote is open for {:?}",
+                            c.name,
+                            speed,
+                            vote.speed
+                        );
+                        return None;
+                    }
+                    Some(_) => {}
+                    None => self.vote = Some(SpeedVote::new(speed)),
+                }
+                self.vote.as_mut()?.voters.insert(id);
+                self.update_vote();
+            }
         }
         Some(())
     }
 
     fn tcp_connected(&mut self, addr: SocketAddr) {
-        self.authent.tcp_connected(addr, &self.net)
+        self.authent.tcp_connected(addr, &self.net);
     }
 
     fn tcp_disconnected(&mut self, tcp_addr: SocketAddr) {
@@ -304,12 +454,197 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
         s
     }
 
+    pub fn players(&self) -> Vec<PlayerInfo> {
+        let mut players: Vec<_> = self
+            .authent
+            .iter()
+            .map(|c| PlayerInfo {
+                name: c.name.clone(),
+                addr: c.tcp_addr,
+                state: c.state.to_string(),
+                spectator: c.spectator,
+                lag: (c.state == ClientGameState::Playing)
+                    .then(|| self.buffer.lag(c.ack))
+                    .flatten(),
+            })
+            .collect();
+        players.sort_by(|a, b| a.name.cmp(&b.name));
+        players
+    }
+
+    /// The players that ever connected, to be saved with the world
+    pub fn registry(&self) -> &PlayerRegistry {
+        &self.authent.registry
+    }
+
+    /// Changes to the permissions apply to the connected players right away
+    pub fn registry_mut(&mut self) -> &mut PlayerRegistry {
+        &mut self.authent.registry
+    }
+
+    /// Disconnects the player with the given name, returns false if there is none
+    pub fn kick(&mut self, name: &str) -> bool {
+        let Some(tcp_addr) = self
+            .authent
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.tcp_addr)
+        else {
+            return false;
+        };
+        self.disconnect(tcp_addr);
+        self.net.remove_tcp(tcp_addr);
+        true
+    }
+
+    /// The speed of the next frames
+    pub fn speed(&self) -> GameSpeed {
+        self.next_speed.unwrap_or(self.speed)
+    }
+
+    /// Changes the speed for everyone from the next frame on, cancelling the ongoing vote if any
+    pub fn set_speed(&mut self, speed: GameSpeed) {
+        self.next_speed = Some(speed.clamped());
+        if self.vote.take().is_some() {
+            self.broadcast(&ServerReliablePacket::SpeedVote(None));
+        }
+    }
+
+    /// Applies the vote once most of the players agree, and keeps them informed of its progress
+    fn update_vote(&mut self) {
+        let n_playing = self.authent.iter_players().count();
+        let Some(ref mut vote) = self.vote else {
+            return;
+        };
+        if vote.expired() {
+            log::info!("vote for speed {:?} expired", vote.speed);
+            self.vote = None;
+            self.broadcast(&ServerReliablePacket::SpeedVote(None));
+            return;
+        }
+        let status = vote.status(n_playing);
+        if status.votes >= status.needed {
+            log::info!("vote passed, changing the speed to {:?}", vote.speed);
+            let speed = vote.speed;
+            self.set_speed(speed);
+            return;
+        }
+        if vote.sent != Some(status) {
+            vote.sent = Some(status);
+            self.broadcast(&ServerReliablePacket::SpeedVote(Some(status)));
+        }
+    }
+
+    fn broadcast(&self, packet: &ServerReliablePacket) {
+        let data = encode(packet);
+        for c in self.authent.iter() {
+            self.net.send_tcp(c.tcp_addr, data.clone());
+        }
+    }
+
+    /// Sends an input from the server itself to every player, as part of a next frame
+    pub fn send_input(&mut self, input: INPUT) {
+        self.server_inputs.push_back(PlayerInput(encode(&input)));
+    }
+
     fn disconnect(&mut self, tcp_addr: SocketAddr) {
         if let Some(c) = self.authent.disconnected(tcp_addr) {
             log::info!("player {} disconnected", c.name);
             self.buffer.disconnected(c.id);
             self.catchup.disconnected(c.id);
             self.worldsend.disconnected(c.id);
+            if let Some(ref mut vote) = self.vote {
+                vote.voters.remove(&c.id);
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::PlayerToken;
+    use serde::Deserialize;
+
+    #[derive(Serialize)]
+    struct TestWorld;
+
+    impl Blobs for TestWorld {
+        fn to_blobs(&self) -> std::io::Result<Vec<(String, Vec<u8>)>> {
+            Ok(vec![])
+        }
+
+        fn from_blobs(_: Vec<(String, Vec<u8>)>) -> Option<Self> {
+            Some(TestWorld)
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct TestInput(u32);
+
+    impl StampSender for TestInput {
+        fn stamp_sender(&mut self, _: &str, _: u32) {}
+    }
+
+    fn test_server() -> Server<TestWorld, TestInput> {
+        Server::start(ServerConfiguration {
+            start_frame: Frame(0),
+            period: Duration::from_millis(20),
+            port: Some(0),
+            virtual_client: None,
+            version: "test".to_string(),
+            password: None,
+            players: PlayerRegistry::default(),
+            mods: vec![],
+            always_run: false,
+            speed_vote: false,
+        })
+        .unwrap()
+    }
+
+    /// Goes through the handshake without a client on the other side
+    fn connect(
+        server: &mut Server<TestWorld, TestInput>,
+        name: &str,
+        port: u16,
+        spectator: bool,
+    ) -> SocketAddr {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+        let id = server.authent.tcp_connected(addr, &server.net);
+        server.authent.udp_connect(addr, id, &server.net);
+        let frame = server.buffer.consumed_frame;
+        let connect = ClientReliablePacket::Connect {
+            name: name.to_string(),
+            token: PlayerToken::generate(),
+            password: None,
+            spectator,
+            version: "test".to_string(),
+            mods: vec![],
+        };
+        server
+            .message_reliable(addr, connect, &TestWorld, frame)
+            .unwrap();
+        addr
+    }
+
+    #[test]
+    fn spectator_inputs_are_ignored() {
+        let mut server = test_server();
+        let player = connect(&mut server, "player", 40001, false);
+        let spectator = connect(&mut server, "spectator", 40002, true);
+
+        for addr in [player, spectator] {
+            let input = vec![(Frame(1), PlayerInput(encode(&TestInput(7))))];
+            server.message_unreliable(addr, ClientUnreliablePacket::Input { input });
+        }
+
+        let (merged, _) = server.buffer.consume(std::iter::empty());
+        let player_id = server.authent.get_client(player).unwrap().id;
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].0, player_id);
+
+        // the spectator still acked the frame
+        let spectator = server.authent.get_client(spectator).unwrap();
+        assert_eq!(spectator.ack, Frame(1));
+    }
+}
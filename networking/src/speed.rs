@@ -0,0 +1,86 @@
+use crate::authent::AuthentID;
+use common::FastSet;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How fast the server produces frames, every client follows it
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameSpeed {
+    pub paused: bool,
+    /// Frames are produced this many times faster than the configured period
+    pub multiplier: f64,
+}
+
+impl GameSpeed {
+    pub const MIN_MULTIPLIER: f64 = 0.1;
+    pub const MAX_MULTIPLIER: f64 = 16.0;
+
+    pub fn clamped(self) -> Self {
+        let multiplier = if self.multiplier.is_finite() {
+            self.multiplier
+                .clamp(Self::MIN_MULTIPLIER, Self::MAX_MULTIPLIER)
+        } else {
+            1.0
+        };
+        Self {
+            paused: self.paused,
+            multiplier,
+        }
+    }
+
+    /// Time between two frames
+    pub fn period(&self, base_period: Duration) -> Duration {
+        base_period.div_f64(self.multiplier)
+    }
+}
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            multiplier: 1.0,
+        }
+    }
+}
+
+/// An ongoing vote for a speed change, as shown to the players
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeedVoteStatus {
+    pub speed: GameSpeed,
+    pub votes: u32,
+    pub needed: u32,
+}
+
+pub(crate) struct SpeedVote {
+    pub speed: GameSpeed,
+    pub voters: FastSet<AuthentID>,
+    /// The last status sent to the players
+    pub sent: Option<SpeedVoteStatus>,
+    started: Instant,
+}
+
+impl SpeedVote {
+    pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new(speed: GameSpeed) -> Self {
+        Self {
+            speed,
+            voters: Default::default(),
+            sent: None,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.started.elapsed() > Self::TIMEOUT
+    }
+
+    /// A strict majority of the players is needed
+    pub fn status(&self, n_players: usize) -> SpeedVoteStatus {
+        SpeedVoteStatus {
+            speed: self.speed,
+            votes: self.voters.len() as u32,
+            needed: n_players as u32 / 2 + 1,
+        }
+    }
+}
@@ -1,23 +1,60 @@
 use crate::authent::{Client, ClientGameState};
 use crate::connection_client::ConnectionClient;
 use crate::connections::Connections;
-use crate::packets::{ClientReliablePacket, ServerReliablePacket, WorldDataFragment};
-use crate::{decode, encode, AuthentID, Frame, MAX_WORLDSEND_PACKET_SIZE};
-use common::FastMap;
-use serde::de::DeserializeOwned;
+use crate::packets::{
+    BlobInfo, BlobRequest, ClientReliablePacket, ServerReliablePacket, WorldDataFragment,
+};
+use crate::{encode, AuthentID, Frame, MAX_WORLDSEND_PACKET_SIZE};
+use common::saveload::{compress, decompress, Blobs};
+use common::{FastMap, FastSet};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The blake3 hash of a compressed blob, a cached blob is only reused when it matches
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) struct BlobHash([u8; 32]);
+
+impl BlobHash {
+    pub fn of(data: &[u8]) -> Self {
+        Self(*blake3::hash(data).as_bytes())
+    }
+}
+
+impl Display for BlobHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&blake3::Hash::from(self.0).to_hex())
+    }
+}
+
+impl Debug for BlobHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
 
 #[derive(Eq, PartialEq)]
 enum WorldSendStatus {
-    ReadyToSend,
+    WaitingForRequest,
+    Sending,
     WaitingForFinalAck,
     Over,
 }
 
-struct WorldSendState {
+struct Blob {
+    info: BlobInfo,
+    /// Compressed, this is what is hashed, sent and cached by the client
     data: Vec<u8>,
-    sent: usize,
+}
+
+struct WorldSendState {
+    blobs: Vec<Blob>,
+    /// The blobs the client does not have, each sent from the offset it asked for
+    queue: VecDeque<BlobRequest>,
     status: WorldSendStatus,
-    frame: Frame,
 }
 
 #[derive(Default)]
@@ -26,18 +63,82 @@ pub(crate) struct WorldSend {
 }
 
 impl WorldSend {
-    pub fn begin_send(&mut self, c: &Client, data: Vec<u8>, frame: Frame) {
+    /// Sends the manifest of the world, the client then asks for the blobs it is missing
+    pub fn begin_send(
+        &mut self,
+        c: &Client,
+        blobs: Vec<(String, Vec<u8>)>,
+        frame: Frame,
+        net: &Connections,
+    ) {
+        let blobs: Vec<Blob> = blobs
+            .into_iter()
+            .map(|(name, data)| {
+                let data = compress(&data);
+                Blob {
+                    info: BlobInfo {
+                        name,
+                        hash: BlobHash::of(&data),
+                        size: data.len(),
+                    },
+                    data,
+                }
+            })
+            .collect();
+
+        log::info!(
+            "sending world manifest of {} blobs to {}",
+            blobs.len(),
+            c.name
+        );
+        net.send_tcp(
+            c.tcp_addr,
+            encode(&ServerReliablePacket::WorldManifest {
+                frame,
+                blobs: blobs.iter().map(|b| b.info.clone()).collect(),
+            }),
+        );
+
         self.send_state.insert(
             c.id,
             WorldSendState {
-                data,
-                sent: 0,
-                status: WorldSendStatus::ReadyToSend,
-                frame,
+                blobs,
+                queue: VecDeque::new(),
+                status: WorldSendStatus::WaitingForRequest,
             },
         );
     }
 
+    pub fn request(&mut self, c: &Client, requests: Vec<BlobRequest>) {
+        let Some(state) = self.send_state.get_mut(&c.id) else {
+            log::warn!("{} requested blobs but no world is being sent", c.name);
+            return;
+        };
+        if state.status != WorldSendStatus::WaitingForRequest {
+            log::warn!("{} requested blobs twice", c.name);
+            return;
+        }
+        let size: usize = requests
+            .iter()
+            .filter_map(|r| {
+                let b = state.blobs.iter().find(|b| b.info.hash == r.hash)?;
+                Some(b.data.len().saturating_sub(r.offset))
+            })
+            .sum();
+        log::info!(
+            "{} asked for {} blobs, {} bytes to send",
+            c.name,
+            requests.len(),
+            size
+        );
+        state.queue = requests.into();
+        state.status = if state.queue.is_empty() {
+            WorldSendStatus::WaitingForFinalAck
+        } else {
+            WorldSendStatus::Sending
+        };
+    }
+
     pub fn ack(&mut self, c: &Client) {
         if let Some(state) = self.send_state.get_mut(&c.id) {
             if state.status == WorldSendStatus::WaitingForFinalAck {
@@ -57,30 +158,43 @@ impl WorldSend {
                 c.state = ClientGameState::CatchingUp;
                 return;
             }
-            if state.status != WorldSendStatus::ReadyToSend {
+            if state.status != WorldSendStatus::Sending {
                 return;
             }
 
-            let to_send = MAX_WORLDSEND_PACKET_SIZE.min(state.data.len() - state.sent);
-            let is_over = (to_send < MAX_WORLDSEND_PACKET_SIZE).then_some(state.frame);
+            let Some(req) = state.queue.front_mut() else {
+                state.status = WorldSendStatus::WaitingForFinalAck;
+                return;
+            };
+            let Some(blob) = state.blobs.iter().find(|b| b.info.hash == req.hash) else {
+                log::warn!("{} requested an unknown blob {}", c.name, req.hash);
+                state.queue.pop_front();
+                return;
+            };
+
+            let offset = req.offset.min(blob.data.len());
+            let to_send = MAX_WORLDSEND_PACKET_SIZE.min(blob.data.len() - offset);
 
             net.send_tcp(
                 c.tcp_addr,
                 encode(&ServerReliablePacket::WorldSend(WorldDataFragment {
-                    is_over,
-                    data_size: state.data.len(),
-                    data: Vec::from(&state.data[state.sent..state.sent + to_send]),
+                    hash: req.hash,
+                    offset,
+                    data: Vec::from(&blob.data[offset..offset + to_send]),
                 })),
             );
 
-            if is_over.is_some() {
+            req.offset = offset + to_send;
+            if req.offset >= blob.data.len() {
+                state.queue.pop_front();
+            }
+
+            if state.queue.is_empty() {
                 log::info!("sending final world fragment to {}", c.name);
                 state.status = WorldSendStatus::WaitingForFinalAck;
             } else {
                 log::info!("sending world fragment to {}", c.name);
             }
-
-            state.sent += to_send;
         } else {
             log::error!("updating a non existing world send");
         }
@@ -91,11 +205,96 @@ impl WorldSend {
     }
 }
 
+/// The blobs received from previous sessions, named by their hash.
+/// Blobs being downloaded are appended to a `.part` file so an interrupted download can resume.
+pub(crate) struct BlobCache {
+    dir: PathBuf,
+}
+
+impl BlobCache {
+    pub fn new(dir: PathBuf) -> Option<Self> {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::error!("could not create the world cache {:?}: {}", dir, e);
+            return None;
+        }
+        Some(Self { dir })
+    }
+
+    fn path(&self, hash: BlobHash, ext: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", hash, ext))
+    }
+
+    pub fn get(&self, info: &BlobInfo) -> Option<Vec<u8>> {
+        let data = std::fs::read(self.path(info.hash, "blob")).ok()?;
+        (data.len() == info.size && BlobHash::of(&data) == info.hash).then_some(data)
+    }
+
+    /// The start of the blob downloaded by a previous session
+    pub fn partial(&self, info: &BlobInfo) -> Vec<u8> {
+        let path = self.path(info.hash, "part");
+        let data = std::fs::read(&path).unwrap_or_default();
+        if data.len() >= info.size {
+            // a complete part should have been renamed, it must be corrupted
+            let _ = std::fs::remove_file(path);
+            return vec![];
+        }
+        data
+    }
+
+    pub fn append_partial(&self, hash: BlobHash, data: &[u8]) {
+        let r = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(hash, "part"))
+            .and_then(|mut f| f.write_all(data));
+        if let Err(e) = r {
+            log::error!("could not write blob {} to the world cache: {}", hash, e);
+        }
+    }
+
+    pub fn complete(&self, hash: BlobHash) {
+        if let Err(e) = std::fs::rename(self.path(hash, "part"), self.path(hash, "blob")) {
+            log::error!("could not complete blob {} in the world cache: {}", hash, e);
+        }
+    }
+
+    pub fn remove_partial(&self, hash: BlobHash) {
+        let _ = std::fs::remove_file(self.path(hash, "part"));
+    }
+
+    /// Removes the blobs that are not part of the last received world
+    pub fn prune(&self, keep: &FastSet<BlobHash>) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let keep: FastSet<String> = keep.iter().map(ToString::to_string).collect();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+                continue;
+            };
+            if ext != "blob" && ext != "part" {
+                continue;
+            }
+            let hash = path.file_stem().and_then(|s| s.to_str());
+            if hash.map_or(true, |h| !keep.contains(h)) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) enum WorldReceive<W> {
+    WaitingForManifest,
     Downloading {
-        datasize: usize,
-        data_so_far: Vec<u8>,
+        frame: Frame,
+        blobs: Vec<BlobInfo>,
+        /// Identical blobs share the same hash and are downloaded once
+        data: FastMap<BlobHash, Vec<u8>>,
+        /// Bytes still to download
+        missing: usize,
+        total: usize,
     },
     Finished {
         frame: Frame,
@@ -107,10 +306,7 @@ pub(crate) enum WorldReceive<W> {
 impl<W> WorldReceive<W> {
     pub fn progress(&self) -> Option<(usize, usize)> {
         match self {
-            WorldReceive::Downloading {
-                datasize,
-                data_so_far,
-            } => Some((data_so_far.len(), *datasize)),
+            WorldReceive::Downloading { missing, total, .. } => Some((total - missing, *total)),
             _ => None,
         }
     }
@@ -118,42 +314,186 @@ impl<W> WorldReceive<W> {
 
 impl<W> Default for WorldReceive<W> {
     fn default() -> Self {
-        Self::Downloading {
-            datasize: 0,
-            data_so_far: vec![],
-        }
+        Self::WaitingForManifest
     }
 }
 
-impl<W: DeserializeOwned> WorldReceive<W> {
-    pub fn handle(&mut self, fragment: WorldDataFragment, net: &ConnectionClient) {
-        if let WorldReceive::Downloading {
-            ref mut datasize,
-            ref mut data_so_far,
-        } = self
-        {
-            *datasize = fragment.data_size;
-            if data_so_far.capacity() == 0 {
-                data_so_far.reserve(fragment.data_size)
+impl<W: Blobs> WorldReceive<W> {
+    /// Takes what it can from the cache and asks the server for the rest
+    pub fn handle_manifest(
+        &mut self,
+        frame: Frame,
+        blobs: Vec<BlobInfo>,
+        cache: Option<&BlobCache>,
+        net: &ConnectionClient,
+    ) {
+        if !matches!(self, WorldReceive::WaitingForManifest) {
+            log::warn!("received a world manifest but was not waiting for one");
+            return;
+        }
+
+        let mut data = FastMap::default();
+        let mut requests = vec![];
+        let mut missing = 0;
+        for b in &blobs {
+            if data.contains_key(&b.hash) {
+                continue;
             }
-            data_so_far.extend(fragment.data);
-            if let Some(frame) = fragment.is_over {
-                log::info!("received last fragment at {:?}", frame);
-                net.send_tcp(encode(&ClientReliablePacket::WorldAck));
+            if let Some(d) = cache.and_then(|c| c.get(b)) {
+                data.insert(b.hash, d);
+                continue;
+            }
+            let partial = cache.map(|c| c.partial(b)).unwrap_or_default();
+            missing += b.size - partial.len();
+            requests.push(BlobRequest {
+                hash: b.hash,
+                offset: partial.len(),
+            });
+            data.insert(b.hash, partial);
+        }
 
-                let d = decode(data_so_far);
+        log::info!(
+            "received world manifest of {} blobs, {} to download ({} bytes)",
+            blobs.len(),
+            requests.len(),
+            missing
+        );
+        net.send_tcp(encode(&ClientReliablePacket::WorldRequest(requests)));
 
-                if let Some(w) = d {
-                    *self = WorldReceive::Finished { frame, world: w }
-                } else {
-                    *self = WorldReceive::Errored;
-                }
-            }
-        } else {
+        *self = WorldReceive::Downloading {
+            frame,
+            blobs,
+            data,
+            missing,
+            total: missing,
+        };
+        if missing == 0 {
+            self.finish(cache, net);
+        }
+    }
+
+    pub fn handle(
+        &mut self,
+        fragment: WorldDataFragment,
+        cache: Option<&BlobCache>,
+        net: &ConnectionClient,
+    ) {
+        let WorldReceive::Downloading {
+            ref blobs,
+            ref mut data,
+            ref mut missing,
+            ..
+        } = self
+        else {
             log::warn!(
                 "received fragment but was not downloading (errored: {:?})",
                 matches!(self, WorldReceive::Errored)
             );
+            return;
+        };
+
+        let (Some(blob), Some(info)) = (
+            data.get_mut(&fragment.hash),
+            blobs.iter().find(|b| b.hash == fragment.hash),
+        ) else {
+            log::warn!("received a fragment of an unknown blob {}", fragment.hash);
+            return;
+        };
+        if fragment.offset != blob.len() || blob.len() + fragment.data.len() > info.size {
+            log::error!("received a fragment at the wrong offset");
+            *self = WorldReceive::Errored;
+            return;
+        }
+
+        blob.extend_from_slice(&fragment.data);
+        *missing -= fragment.data.len();
+        if let Some(cache) = cache {
+            cache.append_partial(fragment.hash, &fragment.data);
+        }
+
+        if blob.len() == info.size {
+            if BlobHash::of(blob) != info.hash {
+                log::error!("blob {} does not match its hash", info.name);
+                if let Some(cache) = cache {
+                    cache.remove_partial(fragment.hash);
+                }
+                *self = WorldReceive::Errored;
+                return;
+            }
+            if let Some(cache) = cache {
+                cache.complete(fragment.hash);
+            }
+        }
+
+        if *missing == 0 {
+            self.finish(cache, net);
         }
     }
+
+    fn finish(&mut self, cache: Option<&BlobCache>, net: &ConnectionClient) {
+        let WorldReceive::Downloading {
+            frame,
+            ref blobs,
+            ref data,
+            ..
+        } = *self
+        else {
+            return;
+        };
+
+        log::info!("received the whole world at {:?}", frame);
+
+        let mut decoded = Vec::with_capacity(blobs.len());
+        for b in blobs {
+            let Ok(d) = decompress(&data[&b.hash]) else {
+                log::error!("could not decompress blob {}", b.name);
+                *self = WorldReceive::Errored;
+                return;
+            };
+            decoded.push((b.name.clone(), d));
+        }
+
+        if let Some(cache) = cache {
+            cache.prune(&blobs.iter().map(|b| b.hash).collect());
+        }
+
+        *self = match W::from_blobs(decoded) {
+            Some(world) => {
+                net.send_tcp(encode(&ClientReliablePacket::WorldAck));
+                WorldReceive::Finished { frame, world }
+            }
+            None => WorldReceive::Errored,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_cache() {
+        let dir = std::env::temp_dir().join(format!("blob_cache_test_{}", std::process::id()));
+        let cache = BlobCache::new(dir.clone()).unwrap();
+
+        let data = compress(b"some blob that is downloaded in two fragments");
+        let info = BlobInfo {
+            name: "blob".to_string(),
+            hash: BlobHash::of(&data),
+            size: data.len(),
+        };
+
+        assert!(cache.get(&info).is_none());
+        cache.append_partial(info.hash, &data[..10]);
+        assert_eq!(cache.partial(&info), &data[..10]);
+
+        cache.append_partial(info.hash, &data[10..]);
+        cache.complete(info.hash);
+        assert_eq!(cache.get(&info), Some(data));
+
+        cache.prune(&FastSet::default());
+        assert!(cache.get(&info).is_none());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
 }
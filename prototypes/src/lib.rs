@@ -9,13 +9,17 @@ use std::hash::Hash;
 mod macros;
 
 mod load;
+mod mods;
 mod prototypes;
+mod schema;
 mod tests;
 mod types;
 mod validation;
 
 pub use load::*;
+pub use mods::*;
 pub use prototypes::*;
+pub use schema::*;
 pub use types::*;
 
 /// A prototype is a collection of data that is dynamically loaded with Lua and defines a type of object
@@ -29,6 +33,9 @@ pub trait Prototype: 'static + Sized {
     /// The name of the prototype used to parse the prototype from Lua's data table
     const NAME: &'static str;
 
+    /// The fields read by from_lua, without the ones of the parent. Used to generate the JSON schema
+    const FIELDS: &'static [Field];
+
     /// Parse the prototype from a Lua table
     fn from_lua(table: &Table) -> mlua::Result<Self>;
 
@@ -70,6 +77,7 @@ impl Prototype for NoParent {
     type Parent = NoParent;
     type ID = ();
     const NAME: &'static str = "no-parent";
+    const FIELDS: &'static [Field] = &[];
 
     fn from_lua(_table: &Table) -> mlua::Result<Self> {
         unreachable!()
@@ -1,33 +1,112 @@
+use crate::mods::{
+    cached_modules, discover_mods, load_mod, read_script, set_active_mods, sort_mods, ModInfo,
+    ModScript,
+};
 use crate::validation::ValidationError;
-use crate::{validation, Prototypes, PROTOTYPES};
+use crate::{try_prototypes, validation, Prototypes, PROTOTYPES};
 use common::error::MultiError;
-use mlua::{Lua, Table};
+use mlua::{Lua, LuaOptions, StdLib, Table};
 use std::io;
+use std::path::Path;
 use thiserror::Error;
 
 pub fn test_prototypes(lua: &str) {
-    let l = Lua::new();
+    let l = prototypes_lua().unwrap();
 
     unsafe { load_prototypes_str(l, lua).unwrap() };
 }
 
-/// Loads the prototypes from the data.lua file
+/// Loads the prototypes from the data.lua files of the base mod and of the mods in the mods/ directory
 /// # Safety
 /// This function is not thread safe, and should only be called once at the start of the program.
 pub unsafe fn load_prototypes(base: &str) -> Result<(), PrototypeLoadError> {
     log::info!("loading prototypes from {}", base);
-    let l = Lua::new();
+    let (l, mods, scripts) = run_mods(base)?;
 
-    let base = base.to_string();
+    install(parse_prototypes(l)?)?;
+    set_active_mods(mods, scripts);
+    Ok(())
+}
+
+/// Loads and validates the mods without installing the prototypes, for modders to check their work.
+/// Unlike when starting the game, the assets the prototypes refer to are checked too.
+/// Returns the mods that were checked, in load order.
+pub fn validate_mods(base: &str) -> Result<Vec<ModInfo>, PrototypeLoadError> {
+    let (l, mods, _) = run_mods(base)?;
+    let p = parse_prototypes(l)?;
+    validation::validate(&p, Some(Path::new(base)))?;
+    Ok(mods)
+}
+
+/// The --validate-mods command line option: prints the result of [`validate_mods`] and exits,
+/// with a non-zero code if there are errors
+pub fn validate_mods_and_exit(base: &str) -> ! {
+    match validate_mods(base) {
+        Ok(mods) => {
+            for m in mods {
+                println!("{}: ok", m);
+            }
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// What changed when reloading the prototypes
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    /// The prototypes that are not defined anymore. They are kept until the game restarts,
+    /// since the world might still use them.
+    pub removed: Vec<String>,
+}
+
+/// Reloads the prototypes from the mods, for example after a data.lua file was edited.
+/// If parsing or validation fails, the current prototypes are kept.
+/// The previous prototypes are leaked, so that references to them stay valid.
+/// # Safety
+/// This function is not thread safe, nothing must access the prototypes while it runs.
+pub unsafe fn reload_prototypes(base: &str) -> Result<ReloadReport, PrototypeLoadError> {
+    log::info!("reloading prototypes from {}", base);
+    let (l, mods, scripts) = run_mods(base)?;
+
+    let mut p = parse_prototypes(l)?;
+    let mut report = ReloadReport::default();
+    if let Some(old) = try_prototypes() {
+        report.removed = p.keep_removed(old);
+    }
+    for removed in &report.removed {
+        log::warn!("{} was removed, keeping it until restart", removed);
+    }
+
+    install(p)?;
+    set_active_mods(mods, scripts);
+    Ok(report)
+}
 
-    l.globals()
-        .get::<_, Table>("package")?
-        .set("path", base.clone() + "base_mod/?.lua")?;
+/// Executes the data.lua file of each mod in dependency order, and reads their scripts
+pub(crate) fn run_mods(
+    base: &str,
+) -> Result<(Lua, Vec<ModInfo>, Vec<ModScript>), PrototypeLoadError> {
+    let mods = sort_mods(discover_mods(base)?)?;
 
-    load_prototypes_str(
-        l,
-        &common::saveload::load_string(base + "base_mod/data.lua")?,
-    )
+    let l = prototypes_lua()?;
+    l.load(include_str!("prototype_init.lua")).exec()?;
+
+    let std_modules = cached_modules(&l)?;
+    let mut scripts = Vec::new();
+    for m in &mods {
+        load_mod(&l, m, &std_modules)?;
+        scripts.extend(read_script(m)?);
+    }
+    Ok((l, mods.into_iter().map(|m| m.info).collect(), scripts))
+}
+
+/// The Lua VM running the data.lua files. The debug library tells where each prototype is defined.
+pub(crate) fn prototypes_lua() -> mlua::Result<Lua> {
+    Lua::new_with(StdLib::ALL_SAFE | StdLib::DEBUG, LuaOptions::default())
 }
 
 unsafe fn load_prototypes_str(l: Lua, main: &str) -> Result<(), PrototypeLoadError> {
@@ -35,6 +114,10 @@ unsafe fn load_prototypes_str(l: Lua, main: &str) -> Result<(), PrototypeLoadErr
 
     l.load(main).exec()?;
 
+    install(parse_prototypes(l)?)
+}
+
+pub(crate) fn parse_prototypes(l: Lua) -> Result<Box<Prototypes>, PrototypeLoadError> {
     let mut p = Box::<Prototypes>::default();
 
     let mut errors = Vec::new();
@@ -53,7 +136,13 @@ unsafe fn load_prototypes_str(l: Lua, main: &str) -> Result<(), PrototypeLoadErr
         return Err(PrototypeLoadError::MultiError(MultiError(errors)));
     }
 
-    validation::validate(&p)?;
+    Ok(p)
+}
+
+/// Validates the prototypes then makes them the current ones.
+/// Assets are only checked by [`validate_mods`], a missing asset is not worth refusing to start.
+unsafe fn install(mut p: Box<Prototypes>) -> Result<(), PrototypeLoadError> {
+    validation::validate(&p, None)?;
 
     p.compute_orderings();
     p.print_stats();
@@ -71,8 +160,10 @@ pub enum PrototypeLoadError {
     LoadingDataLua(#[from] io::Error),
     #[error("lua error: {0}")]
     LuaError(#[from] mlua::Error),
-    #[error("lua error for {0} {1}: {2}")]
-    PrototypeLuaError(String, String, mlua::Error),
+    #[error("lua error for {0} {1} ({2}): {3}")]
+    PrototypeLuaError(String, String, String, mlua::Error),
+    #[error("mod error: {0}")]
+    ModError(String),
     #[error("multiple errors: {0}")]
     MultiError(MultiError<PrototypeLoadError>),
     #[error("validation errors: {0}")]
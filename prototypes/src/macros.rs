@@ -79,6 +79,21 @@ macro_rules! gen_prototypes {
                 )+
             }
 
+            /// Copies the prototypes of `old` that are missing, so that their ids stay valid.
+            /// Returns a description of each of them.
+            pub(crate) fn keep_removed(&mut self, old: &Prototypes) -> Vec<String> {
+                let mut removed = Vec::new();
+                $(
+                    for (id, proto) in old.$name.iter() {
+                        if !self.$name.contains_key(id) {
+                            removed.push(format!("{} {}", <$t as $crate::Prototype>::NAME, proto.name));
+                            self.$name.insert(*id, proto.clone());
+                        }
+                    }
+                )+
+                removed
+            }
+
             pub(crate) fn compute_orderings(&mut self) {
                 self.orderings = Orderings {
                     $(
@@ -94,6 +109,30 @@ macro_rules! gen_prototypes {
                 }
             }
 
+            /// The JSON schema of each prototype type, by name
+            pub fn schemas() -> std::collections::BTreeMap<&'static str, serde_json::Value> {
+                let mut schemas = std::collections::BTreeMap::new();
+                $(
+                    schemas.insert(<$t as $crate::Prototype>::NAME, $crate::prototype_schema::<$t>());
+                )+
+                schemas
+            }
+
+            /// The prototypes as JSON, by type then by name.
+            /// Prototypes with a parent are also listed under the types of their parents.
+            pub fn dump(&self) -> serde_json::Result<serde_json::Value> {
+                let mut dump = serde_json::Map::new();
+                $(
+                    let mut protos = serde_json::Map::new();
+                    for id in &self.orderings.$name {
+                        let proto = &self.$name[id];
+                        protos.insert(proto.name.clone(), $crate::schema::dump_prototype(proto)?);
+                    }
+                    dump.insert(<$t as $crate::Prototype>::NAME.to_string(), serde_json::Value::Object(protos));
+                )+
+                Ok(serde_json::Value::Object(dump))
+            }
+
             pub(crate) fn parse_prototype(&mut self, table: mlua::Table) -> Result<(), $crate::PrototypeLoadError> {
                 let _type = table.get::<_, String>("type")?;
                 let _type_str = _type.as_str();
@@ -101,7 +140,12 @@ macro_rules! gen_prototypes {
                     $(
                         <$t as $crate::Prototype>::NAME => {
                             let proto: $t = $crate::Prototype::from_lua(&table).map_err(|e| {
-                                  $crate::PrototypeLoadError::PrototypeLuaError(_type_str.to_string(), table.get::<_, String>("name").unwrap(), e)
+                                  $crate::PrototypeLoadError::PrototypeLuaError(
+                                      _type_str.to_string(),
+                                      table.get::<_, String>("name").unwrap(),
+                                      table.get::<_, Option<String>>("_source").ok().flatten().unwrap_or_default(),
+                                      e,
+                                  )
                             })?;
 
                             <$t as $crate::ConcretePrototype>::insert_parents(&proto, self);
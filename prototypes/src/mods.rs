@@ -0,0 +1,203 @@
+use crate::PrototypeLoadError;
+use mlua::{Lua, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The name of the mod every other mod implicitly depends on
+pub const BASE_MOD: &str = "base";
+
+/// The base mod ships with the game so it always has the game's version
+const GAME_VERSION: &str = include_str!("../../VERSION");
+
+/// Identifies a loaded mod, recorded in saves and checked when joining a multiplayer game
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModInfo {
+    pub name: String,
+    pub version: String,
+}
+
+impl std::fmt::Display for ModInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// A mod found on disk, described by the manifest.lua file at the root of its directory:
+/// ```lua
+/// return {
+///     name = "my-mod",
+///     version = "0.1.0",
+///     dependencies = { "base" },
+/// }
+/// ```
+/// The data.lua file of the mod is executed once all of its dependencies were loaded.
+#[derive(Debug, Clone)]
+pub struct ModManifest {
+    pub info: ModInfo,
+    pub dependencies: Vec<String>,
+    pub path: PathBuf,
+}
+
+/// The control.lua file of a mod, run by the simulation to react to gameplay events
+#[derive(Debug, Clone)]
+pub struct ModScript {
+    pub mod_name: String,
+    pub source: String,
+}
+
+static mut ACTIVE_MODS: Vec<ModInfo> = Vec::new();
+static mut MOD_SCRIPTS: Vec<ModScript> = Vec::new();
+
+/// The mods the prototypes were loaded from, in load order
+pub fn active_mods() -> &'static [ModInfo] {
+    unsafe { &*std::ptr::addr_of!(ACTIVE_MODS) }
+}
+
+/// The scripts of the active mods, in load order
+pub fn mod_scripts() -> &'static [ModScript] {
+    unsafe { &*std::ptr::addr_of!(MOD_SCRIPTS) }
+}
+
+pub(crate) unsafe fn set_active_mods(mods: Vec<ModInfo>, scripts: Vec<ModScript>) {
+    ACTIVE_MODS = mods;
+    MOD_SCRIPTS = scripts;
+}
+
+/// Reads the control.lua file of the mod, if it has one
+pub(crate) fn read_script(m: &ModManifest) -> Result<Option<ModScript>, PrototypeLoadError> {
+    let path = m.path.join("control.lua");
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(ModScript {
+        mod_name: m.info.name.clone(),
+        source: common::saveload::load_string(path)?,
+    }))
+}
+
+fn read_manifest(l: &Lua, path: &Path) -> Result<ModManifest, PrototypeLoadError> {
+    let src = common::saveload::load_string(path.join("manifest.lua"))?;
+    let t: Table = l.load(&src).eval()?;
+    let name: String = t.get("name")?;
+    let mut dependencies: Vec<String> = t
+        .get::<_, Option<Vec<String>>>("dependencies")?
+        .unwrap_or_default();
+    if name != BASE_MOD && !dependencies.iter().any(|d| d == BASE_MOD) {
+        dependencies.push(BASE_MOD.to_string());
+    }
+    let version = if name == BASE_MOD {
+        GAME_VERSION.trim().to_string()
+    } else {
+        t.get("version")?
+    };
+    Ok(ModManifest {
+        info: ModInfo { name, version },
+        dependencies,
+        path: path.to_path_buf(),
+    })
+}
+
+/// Finds the base mod and the mods in the mods/ directory
+pub fn discover_mods(base: &str) -> Result<Vec<ModManifest>, PrototypeLoadError> {
+    let l = Lua::new();
+    let mut mods = vec![read_manifest(&l, &Path::new(base).join("base_mod"))?];
+
+    let Ok(entries) = std::fs::read_dir(Path::new(base).join("mods")) else {
+        return Ok(mods);
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if !path.join("manifest.lua").exists() {
+            continue;
+        }
+        mods.push(read_manifest(&l, &path)?);
+    }
+    Ok(mods)
+}
+
+/// Sorts the mods so that each mod comes after its dependencies.
+/// Independent mods are loaded in alphabetical order so that the load order is the same everywhere.
+pub fn sort_mods(mods: Vec<ModManifest>) -> Result<Vec<ModManifest>, PrototypeLoadError> {
+    let mut remaining: BTreeMap<String, ModManifest> = BTreeMap::new();
+    for m in mods {
+        let name = m.info.name.clone();
+        if remaining.insert(name.clone(), m).is_some() {
+            return Err(PrototypeLoadError::ModError(format!(
+                "two mods are named {}",
+                name
+            )));
+        }
+    }
+
+    for m in remaining.values() {
+        if let Some(dep) = m.dependencies.iter().find(|d| !remaining.contains_key(*d)) {
+            return Err(PrototypeLoadError::ModError(format!(
+                "mod {} depends on {} which is not installed",
+                m.info.name, dep
+            )));
+        }
+    }
+
+    let mut sorted: Vec<ModManifest> = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let Some(next) = remaining
+            .values()
+            .find(|m| {
+                m.dependencies
+                    .iter()
+                    .all(|d| sorted.iter().any(|s| s.info.name == *d))
+            })
+            .map(|m| m.info.name.clone())
+        else {
+            let cycle: Vec<_> = remaining.keys().cloned().collect();
+            return Err(PrototypeLoadError::ModError(format!(
+                "dependency cycle between mods: {}",
+                cycle.join(", ")
+            )));
+        };
+        sorted.push(remaining.remove(&next).unwrap());
+    }
+    Ok(sorted)
+}
+
+/// The names of the modules cached by `require`
+pub(crate) fn cached_modules(l: &Lua) -> mlua::Result<Vec<String>> {
+    let package = l.globals().get::<_, Table>("package")?;
+    package
+        .get::<_, Table>("loaded")?
+        .pairs::<String, mlua::Value>()
+        .map(|kv| kv.map(|(k, _)| k))
+        .collect()
+}
+
+/// Executes the data.lua file of the mod, with `require` looking for files in the mod directory.
+/// `std_modules` are the modules cached before any mod was loaded.
+pub(crate) fn load_mod(
+    l: &Lua,
+    m: &ModManifest,
+    std_modules: &[String],
+) -> Result<(), PrototypeLoadError> {
+    log::info!("loading mod {} from {}", m.info, m.path.display());
+    let package = l.globals().get::<_, Table>("package")?;
+    package.set("path", format!("{}/?.lua", m.path.display()))?;
+
+    // modules are cached by name, forget the ones of the previous mods
+    // so that two mods can have files with the same name
+    let loaded = package.get::<_, Table>("loaded")?;
+    for name in cached_modules(l)? {
+        if !std_modules.contains(&name) {
+            loaded.set(name, mlua::Value::Nil)?;
+        }
+    }
+
+    l.load(&common::saveload::load_string(m.path.join("data.lua"))?)
+        .set_name(format!("{}/data.lua", m.info.name))
+        .exec()?;
+    Ok(())
+}
+
+/// The active mods as "name@version", in load order
+pub fn active_mod_ids() -> Vec<String> {
+    active_mods().iter().map(|m| m.to_string()).collect()
+}
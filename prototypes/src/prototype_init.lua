@@ -1,17 +1,58 @@
 data = {}
 
+-- position of each prototype in data, by type and name
+local index = {}
+
+-- "file:line" of the code calling data:extend, so that errors can point to it
+local function caller_location()
+    if debug == nil or debug.info == nil then
+        return nil
+    end
+    local source, line = debug.info(3, "sl")
+    if source == nil then
+        return nil
+    end
+    source = string.gsub(source, "^[@=]", "")
+    return source .. ":" .. tostring(line)
+end
+
+local function add(self, t, source)
+    if type(t) == "table" then
+        rawset(t, "_source", source)
+    end
+    local key = tostring(t.type) .. "/" .. tostring(t.name)
+    local i = index[key]
+    if i ~= nil then -- a later mod overrides the prototype
+        rawset(self, i, t)
+        return
+    end
+    i = rawlen(self)+1
+    index[key] = i
+    rawset(self, i, t)
+end
+
 function data:extend (t)
+    local source = caller_location()
     if t.type ~= nil then -- we're extending a single prototype
-        rawset(self, rawlen(self)+1, t)
+        add(self, t, source)
         return
     end
 
     for _, v in ipairs(t) do
-        rawset(self, rawlen(self)+1, v)
+        add(self, v, source)
+    end
+end
+
+-- returns the prototype with the given type and name so that mods can patch it
+function data:get (type, name)
+    local i = index[tostring(type) .. "/" .. tostring(name)]
+    if i == nil then
+        return nil
     end
+    return rawget(self, i)
 end
 
 setmetatable(data, {
     __index = data,
     __newindex = function (t, k, v) end,
-})
\ No newline at end of file
+})
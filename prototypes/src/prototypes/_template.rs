@@ -1,6 +1,6 @@
 // Prototype template. remplace $proto with the root name e.g Item
 
-use crate::{NoParent, Prototype, PrototypeBase, get_lua};
+use crate::{NoParent, Prototype, PrototypeBase, get_lua, Field, FieldType};
 use mlua::Table;
 use std::ops::Deref;
 
@@ -17,6 +17,7 @@ impl Prototype for $protoPrototype {
     type Parent = $parent;
     type ID = $protoPrototypeID;
     const NAME: &'static str = ;
+    const FIELDS: &'static [Field] = &[];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = $parent::from_lua(table)?;
@@ -1,21 +1,31 @@
-#[derive(Debug, Clone, egui_inspect::Inspect)]
+#[derive(Debug, Clone, egui_inspect::Inspect, serde::Serialize)]
 pub struct PrototypeBase {
     pub name: String,
     pub order: String,
     pub label: String,
+    /// Where the prototype was defined, as "file:line" of the data:extend call.
+    /// Not dumped so that dumps of different versions can be diffed.
+    #[serde(skip)]
+    pub source: String,
 }
 
 impl crate::Prototype for PrototypeBase {
     type Parent = crate::NoParent;
     type ID = ();
     const NAME: &'static str = "base";
+    const FIELDS: &'static [crate::Field] = &[
+        crate::Field::required("name", crate::FieldType::String),
+        crate::Field::required("label", crate::FieldType::String),
+        crate::Field::optional("order", crate::FieldType::String),
+    ];
 
     fn from_lua(table: &mlua::Table) -> mlua::Result<Self> {
-        use crate::get_lua;
+        use crate::{get_lua, get_lua_opt};
         Ok(Self {
             name: get_lua(table, "name")?,
             order: get_lua(table, "order").unwrap_or(String::new()),
             label: get_lua(table, "label")?,
+            source: get_lua_opt(table, "_source")?.unwrap_or_default(),
         })
     }
 
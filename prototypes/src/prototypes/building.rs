@@ -1,5 +1,6 @@
 use crate::{
-    get_lua, get_v2, Money, NoParent, Power, Prototype, PrototypeBase, RenderAsset, Size2D,
+    get_lua, get_v2, Field, FieldType, Money, NoParent, Power, Prototype, PrototypeBase,
+    RenderAsset, Size2D,
 };
 use egui_inspect::debug_inspect_impl;
 use geom::Vec2;
@@ -23,9 +24,11 @@ pub enum BuildingGen {
 debug_inspect_impl!(BuildingGen);
 
 /// BuildingPrototype is a building
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BuildingPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: BuildingPrototypeID,
     pub size: Size2D,
     pub bgen: BuildingGen,
@@ -39,6 +42,14 @@ impl Prototype for BuildingPrototype {
     type Parent = NoParent;
     type ID = BuildingPrototypeID;
     const NAME: &'static str = "building";
+    const FIELDS: &'static [Field] = &[
+        Field::required("bgen", FieldType::BuildingGen),
+        Field::required("size", FieldType::Size),
+        Field::required("asset", FieldType::Asset),
+        Field::required("price", FieldType::Money),
+        Field::optional("power_consumption", FieldType::Power),
+        Field::optional("power_production", FieldType::Power),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = PrototypeBase::from_lua(table)?;
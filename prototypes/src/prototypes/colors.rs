@@ -1,14 +1,17 @@
-use crate::{get_color, NoParent, Prototype, PrototypeBase};
+use crate::{get_color, Field, FieldType, NoParent, Prototype, PrototypeBase};
 use geom::Color;
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
 /// ColorsPrototype is the prototype to hold data about colors
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct ColorsPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: ColorsPrototypeID,
 
     pub sand_col: Color,
@@ -36,6 +39,23 @@ impl Prototype for ColorsPrototype {
     type Parent = NoParent;
     type ID = ColorsPrototypeID;
     const NAME: &'static str = "colors";
+    const FIELDS: &'static [Field] = &[
+        Field::required("sand_col", FieldType::Color),
+        Field::required("sea_col", FieldType::Color),
+        Field::required("roof_col", FieldType::Color),
+        Field::required("house_col", FieldType::Color),
+        Field::required("gui_success", FieldType::Color),
+        Field::required("gui_danger", FieldType::Color),
+        Field::required("gui_primary", FieldType::Color),
+        Field::required("gui_disabled", FieldType::Color),
+        Field::required("road_low_col", FieldType::Color),
+        Field::required("road_mid_col", FieldType::Color),
+        Field::required("road_hig_col", FieldType::Color),
+        Field::required("road_line_col", FieldType::Color),
+        Field::required("road_pylon_col", FieldType::Color),
+        Field::required("lot_unassigned_col", FieldType::Color),
+        Field::required("lot_residential_col", FieldType::Color),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = PrototypeBase::from_lua(table)?;
@@ -1,13 +1,18 @@
-use crate::{get_lua, Money, NoParent, Prototype, PrototypeBase, RenderAsset, Size2D};
+use crate::{
+    get_lua, Field, FieldType, Money, NoParent, Prototype, PrototypeBase, RenderAsset, Size2D,
+};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
 /// FreightStationPrototype is a freight station
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct FreightStationPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: FreightStationPrototypeID,
     pub asset: RenderAsset,
     pub price: Money,
@@ -18,6 +23,11 @@ impl Prototype for FreightStationPrototype {
     type Parent = NoParent;
     type ID = FreightStationPrototypeID;
     const NAME: &'static str = "freight-station";
+    const FIELDS: &'static [Field] = &[
+        Field::required("asset", FieldType::Asset),
+        Field::required("price", FieldType::Money),
+        Field::required("size", FieldType::Size),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = PrototypeBase::from_lua(table)?;
@@ -5,7 +5,10 @@ use serde::{Deserialize, Serialize};
 
 use egui_inspect::Inspect;
 
-use crate::{get_lua, get_lua_opt, BuildingPrototype, GoodsCompanyID, Prototype, Recipe, Zone};
+use crate::{
+    get_lua, get_lua_opt, BuildingPrototype, Field, FieldType, GoodsCompanyID, Prototype, Recipe,
+    Zone,
+};
 
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Inspect)]
 pub enum CompanyKind {
@@ -15,9 +18,11 @@ pub enum CompanyKind {
     Factory,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct GoodsCompanyPrototype {
+    #[serde(flatten)]
     pub base: BuildingPrototype,
+    #[serde(skip)]
     pub id: GoodsCompanyID,
     pub kind: CompanyKind,
     pub recipe: Option<Recipe>,
@@ -30,6 +35,13 @@ impl Prototype for GoodsCompanyPrototype {
     type Parent = BuildingPrototype;
     type ID = GoodsCompanyID;
     const NAME: &'static str = "goods-company";
+    const FIELDS: &'static [Field] = &[
+        Field::required("kind", FieldType::CompanyKind),
+        Field::optional("recipe", FieldType::Recipe),
+        Field::optional("n_trucks", FieldType::Integer),
+        Field::optional("n_workers", FieldType::Integer),
+        Field::optional("zone", FieldType::Zone),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = BuildingPrototype::from_lua(table)?;
@@ -1,12 +1,15 @@
 use crate::prototypes::PrototypeBase;
-use crate::{get_lua, ItemID, NoParent, Prototype};
+use crate::{get_lua, Field, FieldType, ItemID, NoParent, Prototype};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 /// Item is the runtime representation of an item, such as meat, wood, etc.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct ItemPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: ItemID,
     pub optout_exttrade: bool,
 }
@@ -15,6 +18,7 @@ impl Prototype for ItemPrototype {
     type Parent = NoParent;
     type ID = ItemID;
     const NAME: &'static str = "item";
+    const FIELDS: &'static [Field] = &[Field::optional("optout_exttrade", FieldType::Bool)];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = PrototypeBase::from_lua(table)?;
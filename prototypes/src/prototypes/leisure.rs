@@ -1,15 +1,21 @@
-use crate::{get_lua, Money, Prototype, RecTimeInterval};
+use crate::{get_lua, Field, FieldType, Money, Prototype, RecTimeInterval};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
 /// LeisurePrototype is a building where people can go to relax
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct LeisurePrototype {
+    #[serde(flatten)]
     pub base: BuildingPrototype,
+    #[serde(skip)]
     pub id: LeisurePrototypeID,
     pub opening_hours: RecTimeInterval,
+    /// Why opening_hours could not be parsed, reported by the validation. The building is then never open.
+    #[serde(skip)]
+    pub opening_hours_error: Option<String>,
     pub capacity: u32,
     pub entry_fee: Money,
 }
@@ -18,13 +24,24 @@ impl Prototype for LeisurePrototype {
     type Parent = BuildingPrototype;
     type ID = LeisurePrototypeID;
     const NAME: &'static str = "leisure";
+    const FIELDS: &'static [Field] = &[
+        Field::required("opening_hours", FieldType::TimeInterval),
+        Field::required("capacity", FieldType::Integer),
+        Field::required("entry_fee", FieldType::Money),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = BuildingPrototype::from_lua(table)?;
+        let (opening_hours, opening_hours_error) =
+            match get_lua::<RecTimeInterval>(table, "opening_hours") {
+                Ok(v) => (v, None),
+                Err(e) => (RecTimeInterval::never(), Some(e.to_string())),
+            };
         Ok(Self {
             id: Self::ID::new(&base.name),
             base,
-            opening_hours: get_lua(table, "opening_hours")?,
+            opening_hours,
+            opening_hours_error,
             capacity: get_lua(table, "capacity")?,
             entry_fee: get_lua(table, "entry_fee")?,
         })
@@ -5,6 +5,7 @@ crate::gen_prototypes!(
     mod goods_company: GoodsCompanyID      = GoodsCompanyPrototype => BuildingPrototypeID,
     mod leisure:       LeisurePrototypeID  = LeisurePrototype => BuildingPrototypeID,
     mod solar:         SolarPanelID        = SolarPanelPrototype => GoodsCompanyID,
+    mod warehouse:     WarehouseID         = WarehousePrototype => GoodsCompanyID,
 
     mod vehicle:       VehiclePrototypeID = VehiclePrototype,
     mod road_vehicle:  RoadVehicleID      = RoadVehiclePrototype => VehiclePrototypeID,
@@ -1,35 +1,58 @@
-use crate::{get_lua, Prototype};
+use crate::{get_lua, get_lua_opt, Field, FieldType, Prototype};
+use serde::{Deserialize, Serialize};
 
-use mlua::Table;
+use mlua::{FromLua, Lua, Table, Value};
 use std::ops::Deref;
 
 use super::*;
 
-#[derive(Clone, Debug)]
+/// What the vehicle is used for in the simulation
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum RoadVehicleKind {
+    Car,
+    Truck,
+    Bus,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct RoadVehiclePrototype {
+    #[serde(flatten)]
     pub base: VehiclePrototype,
+    #[serde(skip)]
     pub id: RoadVehicleID,
+    pub kind: RoadVehicleKind,
     /// m/s
     pub max_speed: f32,
     /// m.s^2
     pub acceleration: f32,
     /// m.s^2
     pub deceleration: f32,
+    /// Quantity of goods that can be carried, 0 for vehicles that don't carry goods
+    pub capacity: u32,
 }
 
 impl Prototype for RoadVehiclePrototype {
     type Parent = VehiclePrototype;
     type ID = RoadVehicleID;
     const NAME: &'static str = "road-vehicle";
+    const FIELDS: &'static [Field] = &[
+        Field::required("kind", FieldType::RoadVehicleKind),
+        Field::required("max_speed", FieldType::Number),
+        Field::required("acceleration", FieldType::Number),
+        Field::required("deceleration", FieldType::Number),
+        Field::optional("capacity", FieldType::Integer),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = VehiclePrototype::from_lua(table)?;
         Ok(Self {
             id: Self::ID::new(&base.name),
             base,
+            kind: get_lua(table, "kind")?,
             max_speed: get_lua::<f32>(table, "max_speed")?,
             acceleration: get_lua::<f32>(table, "acceleration")?,
             deceleration: get_lua::<f32>(table, "deceleration")?,
+            capacity: get_lua_opt(table, "capacity")?.unwrap_or(0),
         })
     }
     fn id(&self) -> Self::ID {
@@ -46,3 +69,18 @@ impl Deref for RoadVehiclePrototype {
         &self.base
     }
 }
+
+impl<'a> FromLua<'a> for RoadVehicleKind {
+    fn from_lua(value: Value<'a>, lua: &'a Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "car" => Ok(Self::Car),
+            "truck" => Ok(Self::Truck),
+            "bus" => Ok(Self::Bus),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown road vehicle kind: {}",
+                s
+            ))),
+        }
+    }
+}
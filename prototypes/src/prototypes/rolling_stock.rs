@@ -1,12 +1,15 @@
-use crate::{get_lua, Prototype};
+use crate::{get_lua, get_lua_opt, Field, FieldType, Prototype};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct RollingStockPrototype {
+    #[serde(flatten)]
     pub base: VehiclePrototype,
+    #[serde(skip)]
     pub id: RollingStockID,
     /// meter
     pub length: f32,
@@ -18,12 +21,22 @@ pub struct RollingStockPrototype {
     pub acc_force: f32,
     /// kN
     pub dec_force: f32,
+    /// Quantity of goods a freight wagon can carry
+    pub capacity: u32,
 }
 
 impl Prototype for RollingStockPrototype {
     type Parent = VehiclePrototype;
     type ID = RollingStockID;
     const NAME: &'static str = "rolling-stock";
+    const FIELDS: &'static [Field] = &[
+        Field::required("length", FieldType::Number),
+        Field::required("mass", FieldType::Integer),
+        Field::required("max_speed", FieldType::Number),
+        Field::required("acc_force", FieldType::Number),
+        Field::required("dec_force", FieldType::Number),
+        Field::optional("capacity", FieldType::Integer),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = VehiclePrototype::from_lua(table)?;
@@ -35,6 +48,7 @@ impl Prototype for RollingStockPrototype {
             max_speed: get_lua::<f32>(table, "max_speed")?,
             acc_force: get_lua::<f32>(table, "acc_force")?,
             dec_force: get_lua::<f32>(table, "dec_force")?,
+            capacity: get_lua_opt(table, "capacity")?.unwrap_or(0),
         })
     }
     fn id(&self) -> Self::ID {
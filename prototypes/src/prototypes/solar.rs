@@ -1,9 +1,12 @@
-use crate::{GoodsCompanyPrototype, Prototype, SolarPanelID};
+use crate::{Field, GoodsCompanyPrototype, Prototype, SolarPanelID};
+use serde::Serialize;
 use std::ops::Deref;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SolarPanelPrototype {
+    #[serde(flatten)]
     pub base: GoodsCompanyPrototype,
+    #[serde(skip)]
     pub id: SolarPanelID,
 }
 
@@ -11,6 +14,7 @@ impl Prototype for SolarPanelPrototype {
     type Parent = GoodsCompanyPrototype;
     type ID = SolarPanelID;
     const NAME: &'static str = "solar-panel";
+    const FIELDS: &'static [Field] = &[];
 
     fn from_lua(table: &mlua::Table) -> mlua::Result<Self> {
         let base = GoodsCompanyPrototype::from_lua(table)?;
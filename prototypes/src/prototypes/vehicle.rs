@@ -1,12 +1,15 @@
-use crate::{get_lua, Money, NoParent, Prototype, PrototypeBase, RenderAsset};
+use crate::{get_lua, Field, FieldType, Money, NoParent, Prototype, PrototypeBase, RenderAsset};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct VehiclePrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: VehiclePrototypeID,
     pub asset: RenderAsset,
     pub price: Money,
@@ -16,6 +19,10 @@ impl Prototype for VehiclePrototype {
     type Parent = NoParent;
     type ID = VehiclePrototypeID;
     const NAME: &'static str = "vehicle";
+    const FIELDS: &'static [Field] = &[
+        Field::required("asset", FieldType::Asset),
+        Field::required("price", FieldType::Money),
+    ];
 
     fn from_lua(table: &Table) -> mlua::Result<Self> {
         let base = PrototypeBase::from_lua(table)?;
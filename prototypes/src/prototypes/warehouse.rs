@@ -0,0 +1,47 @@
+use crate::{get_lua, Field, FieldType, GoodsCompanyPrototype, Prototype, RecipeItem, WarehouseID};
+use serde::Serialize;
+use std::ops::Deref;
+
+/// A warehouse is a company that stores goods instead of transforming them.
+/// It buys the surplus of producers and sells it back when there is a shortage.
+#[derive(Debug, Clone, Serialize)]
+pub struct WarehousePrototype {
+    #[serde(flatten)]
+    pub base: GoodsCompanyPrototype,
+    #[serde(skip)]
+    pub id: WarehouseID,
+    /// Maximum quantity stored per item
+    pub capacity: Vec<RecipeItem>,
+}
+
+impl Prototype for WarehousePrototype {
+    type Parent = GoodsCompanyPrototype;
+    type ID = WarehouseID;
+    const NAME: &'static str = "warehouse";
+    const FIELDS: &'static [Field] = &[Field::required("capacity", FieldType::RecipeItems)];
+
+    fn from_lua(table: &mlua::Table) -> mlua::Result<Self> {
+        let base = GoodsCompanyPrototype::from_lua(table)?;
+        Ok(Self {
+            id: WarehouseID::new(&base.name),
+            base,
+            capacity: get_lua(table, "capacity")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &self.base
+    }
+}
+
+impl Deref for WarehousePrototype {
+    type Target = GoodsCompanyPrototype;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
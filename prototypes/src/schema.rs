@@ -0,0 +1,253 @@
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{prototypes, NoParent, Prototype, PrototypeBase, Prototypes};
+
+/// A field read by [`Prototype::from_lua`], used to generate the JSON schema of the prototype
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+    pub required: bool,
+}
+
+impl Field {
+    pub const fn required(name: &'static str, ty: FieldType) -> Self {
+        Self {
+            name,
+            ty,
+            required: true,
+        }
+    }
+
+    pub const fn optional(name: &'static str, ty: FieldType) -> Self {
+        Self {
+            name,
+            ty,
+            required: false,
+        }
+    }
+}
+
+/// The values accepted by the FromLua implementations of the field types
+#[derive(Debug, Clone, Copy)]
+pub enum FieldType {
+    String,
+    Number,
+    Integer,
+    Bool,
+    Money,
+    Power,
+    Duration,
+    TimeInterval,
+    Size,
+    Vec2,
+    Color,
+    Asset,
+    BuildingGen,
+    CompanyKind,
+    RoadVehicleKind,
+    Recipe,
+    RecipeItems,
+    Zone,
+}
+
+impl FieldType {
+    fn schema(self) -> Value {
+        match self {
+            FieldType::String => json!({ "type": "string" }),
+            FieldType::Number => json!({ "type": "number" }),
+            FieldType::Integer => json!({ "type": "integer" }),
+            FieldType::Bool => json!({ "type": "boolean" }),
+            FieldType::Money => json!({
+                "type": ["number", "string"],
+                "description": "an amount of money in $, or a string like \"10$\"",
+            }),
+            FieldType::Power => json!({
+                "type": ["number", "string"],
+                "description": "a power in W, or a string like \"200W\", \"10kW\" or \"1MW\"",
+            }),
+            FieldType::Duration => json!({
+                "type": ["number", "string"],
+                "description": "a number of ticks, or a string like \"100s\", \"5m\", \"2h\" or \"1d\"",
+            }),
+            FieldType::TimeInterval => json!({
+                "oneOf": [
+                    {
+                        "type": "string",
+                        "description": "\"always\", \"never\" or an interval like \"18h -> 1h\"",
+                    },
+                    {
+                        "type": "object",
+                        "properties": { "start": { "type": "string" }, "end": { "type": "string" } },
+                        "required": ["start", "end"],
+                    },
+                ]
+            }),
+            FieldType::Size => json!({
+                "oneOf": [
+                    { "type": "number", "description": "the side of a square" },
+                    { "type": "array", "items": { "type": "number" }, "minItems": 2, "maxItems": 2 },
+                    {
+                        "type": "object",
+                        "properties": { "w": { "type": "number" }, "h": { "type": "number" } },
+                        "required": ["w", "h"],
+                    },
+                ]
+            }),
+            FieldType::Vec2 => json!({
+                "oneOf": [
+                    { "type": "array", "items": { "type": "number" }, "minItems": 2, "maxItems": 2 },
+                    {
+                        "type": "object",
+                        "properties": { "x": { "type": "number" }, "y": { "type": "number" } },
+                        "required": ["x", "y"],
+                    },
+                ]
+            }),
+            FieldType::Color => json!({
+                "oneOf": [
+                    { "type": "array", "items": { "type": "number" }, "minItems": 3, "maxItems": 4 },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "r": { "type": "number" },
+                            "g": { "type": "number" },
+                            "b": { "type": "number" },
+                            "a": { "type": "number" },
+                        },
+                        "required": ["r", "g", "b"],
+                    },
+                ]
+            }),
+            FieldType::Asset => json!({
+                "type": "string",
+                "pattern": "\\.(glb|png|jpg)$",
+                "description": "a .glb model in assets/models, or the path of a .png or .jpg sprite",
+            }),
+            FieldType::BuildingGen => json!({
+                "oneOf": [
+                    { "enum": ["house", "farm"] },
+                    {
+                        "type": "object",
+                        "properties": {
+                            "kind": { "enum": ["house", "farm", "centered_door", "no_walkway"] },
+                            "vertical_factor": { "type": "number" },
+                            "door_pos": FieldType::Vec2.schema(),
+                        },
+                        "required": ["kind"],
+                    },
+                ]
+            }),
+            FieldType::CompanyKind => json!({ "enum": ["store", "factory"] }),
+            FieldType::RoadVehicleKind => json!({ "enum": ["car", "truck", "bus"] }),
+            FieldType::Recipe => json!({
+                "type": "object",
+                "properties": {
+                    "consumption": FieldType::RecipeItems.schema(),
+                    "production": FieldType::RecipeItems.schema(),
+                    "duration": FieldType::Duration.schema(),
+                    "storage_multiplier": { "type": "integer" },
+                },
+                "required": ["consumption", "production", "duration", "storage_multiplier"],
+            }),
+            FieldType::RecipeItems => json!({
+                "type": "array",
+                "items": {
+                    "oneOf": [
+                        {
+                            "type": "array",
+                            "description": "the item name and the amount",
+                            "prefixItems": [{ "type": "string" }, { "type": "integer" }],
+                        },
+                        {
+                            "type": "object",
+                            "properties": { "id": { "type": "string" }, "amount": { "type": "integer" } },
+                            "required": ["id", "amount"],
+                        },
+                    ]
+                }
+            }),
+            FieldType::Zone => json!({
+                "type": "object",
+                "properties": {
+                    "floor": { "type": "string" },
+                    "filler": { "type": "string" },
+                    "price_per_area": FieldType::Money.schema(),
+                    "randomize_filler": { "type": "boolean" },
+                },
+                "required": ["floor", "filler"],
+            }),
+        }
+    }
+}
+
+/// The names of the parents of the prototype, closest first
+pub fn parent_chain<T: Prototype>() -> Vec<&'static str> {
+    if T::Parent::NAME == NoParent::NAME {
+        return vec![];
+    }
+    let mut chain = vec![T::Parent::NAME];
+    chain.extend(parent_chain::<T::Parent>());
+    chain
+}
+
+/// The fields of the prototype and of its parents
+fn all_fields<T: Prototype>() -> Vec<Field> {
+    let mut fields = T::FIELDS.to_vec();
+    if T::Parent::NAME == NoParent::NAME {
+        fields.extend_from_slice(PrototypeBase::FIELDS);
+    } else {
+        fields.extend(all_fields::<T::Parent>());
+    }
+    fields
+}
+
+/// The JSON Schema of the Lua table describing a prototype, as passed to data:extend
+pub fn prototype_schema<T: Prototype>() -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![json!("type")];
+    properties.insert("type".to_string(), json!({ "const": T::NAME }));
+
+    for field in all_fields::<T>() {
+        properties.insert(field.name.to_string(), field.ty.schema());
+        if field.required {
+            required.push(json!(field.name));
+        }
+    }
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": T::NAME,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// A loaded prototype as JSON, with the names of its parents
+pub(crate) fn dump_prototype<T: Prototype + Serialize>(proto: &T) -> serde_json::Result<Value> {
+    let mut v = serde_json::to_value(proto)?;
+    if let Value::Object(ref mut m) = v {
+        m.insert("parents".to_string(), json!(parent_chain::<T>()));
+    }
+    Ok(v)
+}
+
+/// Writes the schema of each prototype type to `dir/<type>.schema.json`
+pub fn write_schemas(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (name, schema) in Prototypes::schemas() {
+        let json = serde_json::to_string_pretty(&schema)?;
+        std::fs::write(dir.join(format!("{}.schema.json", name)), json)?;
+    }
+    Ok(())
+}
+
+/// Writes the loaded prototypes to `path` as JSON
+pub fn write_dump(path: &Path) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(&prototypes().dump()?)?;
+    std::fs::write(path, json)
+}
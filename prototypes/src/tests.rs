@@ -1,7 +1,11 @@
 #![cfg(test)]
 
-use crate::load::load_prototypes;
-use crate::{try_prototype, GoodsCompanyID, ItemID, SolarPanelID};
+use crate::load::{load_prototypes, parse_prototypes, prototypes_lua, run_mods};
+use crate::mods::{sort_mods, ModInfo, ModManifest};
+use crate::validation::{validate, ValidationError};
+use crate::{try_prototype, GoodsCompanyID, ItemID, Prototypes, SolarPanelID};
+use mlua::Table;
+use std::collections::BTreeSet;
 
 #[test]
 fn test_base() {
@@ -27,3 +31,187 @@ fn test_base() {
         println!("{:?}", try_prototype(SolarPanelID::new("solar-panel")));
     }
 }
+
+fn manifest(name: &str, deps: &[&str]) -> ModManifest {
+    ModManifest {
+        info: ModInfo {
+            name: name.to_string(),
+            version: "0.1.0".to_string(),
+        },
+        dependencies: deps.iter().map(|d| d.to_string()).collect(),
+        path: Default::default(),
+    }
+}
+
+#[test]
+fn test_mod_order() {
+    let sorted = sort_mods(vec![
+        manifest("trams", &["base", "rails"]),
+        manifest("rails", &["base"]),
+        manifest("base", &[]),
+        manifest("houses", &["base"]),
+    ])
+    .unwrap();
+    let names: Vec<_> = sorted.iter().map(|m| m.info.name.as_str()).collect();
+    assert_eq!(names, ["base", "houses", "rails", "trams"]);
+
+    assert!(sort_mods(vec![manifest("a", &["b"]), manifest("b", &["a"])]).is_err());
+    assert!(sort_mods(vec![manifest("a", &["missing"])]).is_err());
+}
+
+#[test]
+fn test_validation() {
+    let l = prototypes_lua().unwrap();
+    l.load(include_str!("prototype_init.lua")).exec().unwrap();
+    l.load(
+        r#"
+        data:extend {
+          {
+            type = "item",
+            name = "worker",
+            label = "Worker",
+            optout_exttrade = true,
+          },
+          {
+            type = "goods-company",
+            name = "office",
+            label = "Office",
+            bgen = { kind = "centered_door", vertical_factor = 1.0 },
+            kind = "store",
+            recipe = {
+                consumption = {{"worker", 1}},
+                production = {},
+                duration = "10s",
+                storage_multiplier = 1,
+            },
+            size = 10.0,
+            asset = "office.glb",
+            price = 100,
+          },
+          {
+            type = "rolling-stock",
+            name = "wagon",
+            label = "Wagon",
+            length = 0.0,
+            mass = 40,
+            max_speed = 200.0,
+            acc_force = 0.0,
+            dec_force = 240.0,
+            asset = "missing-wagon.glb",
+            price = 100,
+          },
+          {
+            type = "leisure",
+            name = "park",
+            label = "Park",
+            bgen = { kind = "centered_door", vertical_factor = 1.0 },
+            size = 0.0,
+            asset = "park.glb",
+            price = 100,
+            opening_hours = "noon",
+            capacity = 10,
+            entry_fee = 0,
+          },
+        }
+        "#,
+    )
+    .set_name("test.lua")
+    .exec()
+    .unwrap();
+
+    let p = parse_prototypes(l).unwrap();
+    let errors = validate(&p, None).unwrap_err().0;
+    assert!(!errors
+        .iter()
+        .any(|e| matches!(e, ValidationError::AssetNotFound(..))));
+
+    assert!(errors
+        .iter()
+        .any(|e| matches!(e, ValidationError::ItemNotProduced(name, item) if name.starts_with("office (") && name.contains("test.lua:") && item == "worker")));
+    assert!(errors
+        .iter()
+        .any(|e| matches!(e, ValidationError::InvalidField(name, "opening_hours", _) if name.starts_with("park"))));
+    assert!(errors.iter().any(
+        |e| matches!(e, ValidationError::InvalidField(name, "size", _) if name.starts_with("park"))
+    ));
+    // a train made only of wagons is refused when spawned, the wagon itself is fine
+    assert!(errors.iter().any(
+        |e| matches!(e, ValidationError::InvalidField(name, "length", _) if name.starts_with("wagon"))
+    ));
+    assert!(!errors.iter().any(
+        |e| matches!(e, ValidationError::InvalidField(name, "acc_force", _) if name.starts_with("wagon"))
+    ));
+
+    let errors = validate(&p, Some(std::path::Path::new("../")))
+        .unwrap_err()
+        .0;
+    assert!(errors.iter().any(
+        |e| matches!(e, ValidationError::AssetNotFound(name, "asset", path) if name.starts_with("wagon") && path == "missing-wagon.glb")
+    ));
+}
+
+#[test]
+fn test_schema() {
+    let schemas = Prototypes::schemas();
+    let solar = &schemas["solar-panel"];
+    assert_eq!(solar["properties"]["type"]["const"], "solar-panel");
+
+    // fields of the parents are included
+    let required = solar["required"].as_array().unwrap();
+    assert!(required.iter().any(|f| f == "kind"));
+    assert!(required.iter().any(|f| f == "price"));
+    assert!(required.iter().any(|f| f == "label"));
+    assert!(!required.iter().any(|f| f == "recipe"));
+    assert!(solar["properties"]["zone"].is_object());
+}
+
+/// Every field read by from_lua must be in FIELDS, otherwise the schema doesn't know about it
+#[test]
+fn test_fields_are_declared() {
+    let (l, _, _) = run_mods("../").unwrap();
+    let schemas = Prototypes::schemas();
+    let mut p = Prototypes::default();
+    let mut checked = BTreeSet::new();
+
+    // records the fields read from the prototype table
+    let spy = l
+        .load(
+            r#"
+            local t, read = ...
+            return setmetatable({}, { __index = function(_, k) read[k] = true; return t[k] end })
+            "#,
+        )
+        .into_function()
+        .unwrap();
+
+    let data: Table = l.globals().get("data").unwrap();
+    for t in data.sequence_values::<Table>() {
+        let t = t.unwrap();
+        let ty: String = t.get("type").unwrap();
+        let read = l.create_table().unwrap();
+        let proxy: Table = spy.call((t, read.clone())).unwrap();
+        p.parse_prototype(proxy).unwrap();
+
+        let properties = &schemas[ty.as_str()]["properties"];
+        for field in read.pairs::<String, bool>() {
+            let (field, _) = field.unwrap();
+            assert!(
+                field == "_source" || properties.get(&field).is_some(),
+                "{}.{} is read but not declared in FIELDS",
+                ty,
+                field
+            );
+        }
+        checked.insert(ty);
+    }
+
+    for ty in [
+        "item",
+        "goods-company",
+        "rolling-stock",
+        "road-vehicle",
+        "leisure",
+    ] {
+        assert!(checked.contains(ty), "{} was not checked", ty);
+    }
+}
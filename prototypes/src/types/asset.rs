@@ -1,7 +1,9 @@
 use mlua::{FromLua, Value};
+use serde::Serialize;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
 pub enum RenderAsset {
     Mesh { path: PathBuf },
     Sprite { path: PathBuf },
@@ -1,9 +1,11 @@
-use crate::{get_lua, GameDuration, ItemID};
+use crate::{get_lua, try_prototype_preload, GameDuration, ItemID};
 use egui_inspect::Inspect;
 use mlua::{FromLua, Lua, Table, Value};
+use serde::{Serialize, Serializer};
 
-#[derive(Debug, Clone, Inspect)]
+#[derive(Debug, Clone, Inspect, Serialize)]
 pub struct RecipeItem {
+    #[serde(serialize_with = "serialize_item_name")]
     pub id: ItemID,
     pub amount: i32,
 }
@@ -32,7 +34,15 @@ impl<'lua> FromLua<'lua> for RecipeItem {
     }
 }
 
-#[derive(Debug, Clone, Inspect)]
+/// Items are written by name in the prototype dumps, the id is only a hash of it
+fn serialize_item_name<S: Serializer>(id: &ItemID, s: S) -> Result<S::Ok, S::Error> {
+    match try_prototype_preload(*id) {
+        Some(item) => s.serialize_str(&item.name),
+        None => id.serialize(s),
+    }
+}
+
+#[derive(Debug, Clone, Inspect, Serialize)]
 pub struct Recipe {
     pub consumption: Vec<RecipeItem>,
     pub production: Vec<RecipeItem>,
@@ -1,7 +1,8 @@
 use crate::{get_lua, LuaVec2};
 use mlua::{FromLua, Lua, Value};
+use serde::Serialize;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
 pub struct Size2D {
     pub w: f32,
     pub h: f32,
@@ -17,6 +17,7 @@ pub const TICKS_PER_REALTIME_SECOND: u64 = 50;
 pub const TICKS_PER_SECOND: u64 = TICKS_PER_REALTIME_SECOND / SECONDS_PER_REALTIME_SECOND as u64;
 pub const TICKS_PER_MINUTE: u64 = TICKS_PER_SECOND * SECONDS_PER_MINUTE as u64;
 pub const TICKS_PER_HOUR: u64 = TICKS_PER_SECOND * SECONDS_PER_HOUR as u64;
+pub const TICKS_PER_DAY: u64 = TICKS_PER_HOUR * HOURS_PER_DAY as u64;
 pub const DELTA_F64: f64 = 1.0 / TICKS_PER_REALTIME_SECOND as f64;
 pub const DELTA: f32 = DELTA_F64 as f32;
 
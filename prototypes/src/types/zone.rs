@@ -1,7 +1,8 @@
 use crate::{get_lua, Money};
 use mlua::{FromLua, Lua, Table, Value};
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Zone {
     pub floor: String,
     pub filler: String,
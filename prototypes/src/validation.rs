@@ -1,11 +1,18 @@
 #![allow(clippy::iter_over_hash_type)]
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
 use thiserror::Error;
 
 use common::error::MultiError;
 
-use crate::{CompanyKind, Prototypes};
+use crate::{
+    CompanyKind, GoodsCompanyID, GoodsCompanyPrototype, ItemID, PrototypeBase, Prototypes,
+    RenderAsset, Size2D,
+};
 
+/// The first field of each error is the prototype name followed by where it was defined
 #[derive(Debug, Error)]
 pub enum ValidationError {
     #[error("{0}: only factories can have trucks")]
@@ -14,17 +21,35 @@ pub enum ValidationError {
     ZeroTrucks(String),
     #[error("{0}.{1}: referenced prototype not found")]
     ReferencedProtoNotFound(String, &'static str),
-
     #[error("{0}.{1}: {2}")]
     InvalidField(String, &'static str, String),
+    #[error("{0}.{1}: asset {2} not found")]
+    AssetNotFound(String, &'static str, String),
+    #[error("{0}: no company produces {1} and it cannot be imported")]
+    ItemNotProduced(String, String),
+    #[error("{0}: {1} can never be produced, its producers all depend on each other")]
+    CannotBootstrap(String, String),
+}
+
+/// The name of the prototype and the file:line where it was defined
+fn at(p: &PrototypeBase) -> String {
+    if p.source.is_empty() {
+        return p.name.clone();
+    }
+    format!("{} ({})", p.name, p.source)
 }
 
-pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationError>> {
+/// Checks that the prototypes are consistent.
+/// Assets are looked up relative to `base` if given.
+pub(crate) fn validate(
+    proto: &Prototypes,
+    base: Option<&Path>,
+) -> Result<(), MultiError<ValidationError>> {
     let mut errors = vec![];
 
     for comp in proto.goods_company.values() {
         if comp.n_trucks > 0 && comp.kind != CompanyKind::Factory {
-            errors.push(ValidationError::WrongTrucks(comp.name.clone()));
+            errors.push(ValidationError::WrongTrucks(at(comp)));
         }
 
         if comp.n_trucks == 0
@@ -35,14 +60,14 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
                 .map(|r| !r.production.is_empty())
                 .unwrap_or(false)
         {
-            errors.push(ValidationError::ZeroTrucks(comp.name.clone()));
+            errors.push(ValidationError::ZeroTrucks(at(comp)));
         }
 
         if let Some(ref r) = comp.recipe {
             for item in &r.consumption {
                 if !proto.item.contains_key(&item.id) {
                     errors.push(ValidationError::ReferencedProtoNotFound(
-                        comp.name.clone(),
+                        at(comp),
                         "consumption",
                     ));
                 }
@@ -51,7 +76,7 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
             for item in &r.production {
                 if !proto.item.contains_key(&item.id) {
                     errors.push(ValidationError::ReferencedProtoNotFound(
-                        comp.name.clone(),
+                        at(comp),
                         "production",
                     ));
                 }
@@ -60,7 +85,7 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
 
         if comp.power_consumption.map_or(false, |v| v.0 < 0) {
             errors.push(ValidationError::InvalidField(
-                comp.name.clone(),
+                at(comp),
                 "power_consumption",
                 "must not be negative".to_string(),
             ));
@@ -68,11 +93,139 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
 
         if comp.power_production.map_or(false, |v| v.0 < 0) {
             errors.push(ValidationError::InvalidField(
-                comp.name.clone(),
+                at(comp),
                 "power_production",
                 "must not be negative".to_string(),
             ));
         }
+
+        if let (Some(zone), Some(base)) = (&comp.zone, base) {
+            for (field, path) in [("zone.floor", &zone.floor), ("zone.filler", &zone.filler)] {
+                if !asset_path(base, path).exists() {
+                    errors.push(ValidationError::AssetNotFound(
+                        at(comp),
+                        field,
+                        path.clone(),
+                    ));
+                }
+            }
+        }
+    }
+
+    validate_recipes(proto, &mut errors);
+
+    for building in proto.building.values() {
+        validate_size(&mut errors, at(building), building.size);
+        // zoned buildings are paid by area
+        let zoned = proto
+            .goods_company
+            .get(&GoodsCompanyID::from(&building.name))
+            .map_or(false, |c| c.zone.is_some());
+        if building.price.0 < 0 || (building.price.0 == 0 && !zoned) {
+            errors.push(ValidationError::InvalidField(
+                at(building),
+                "price",
+                "must be positive".to_string(),
+            ));
+        }
+        if let Some(base) = base {
+            validate_asset(&mut errors, base, at(building), &building.asset);
+        }
+    }
+
+    for leisure in proto.leisure.values() {
+        if let Some(ref e) = leisure.opening_hours_error {
+            errors.push(ValidationError::InvalidField(
+                at(leisure),
+                "opening_hours",
+                e.clone(),
+            ));
+        }
+        if leisure.capacity == 0 {
+            errors.push(ValidationError::InvalidField(
+                at(leisure),
+                "capacity",
+                "must be positive".to_string(),
+            ));
+        }
+    }
+
+    for station in proto.freightstation.values() {
+        validate_size(&mut errors, at(station), station.size);
+        if station.price.0 <= 0 {
+            errors.push(ValidationError::InvalidField(
+                at(station),
+                "price",
+                "must be positive".to_string(),
+            ));
+        }
+        if let Some(base) = base {
+            validate_asset(&mut errors, base, at(station), &station.asset);
+        }
+    }
+
+    // rolling stock and road vehicles are in there too
+    for vehicle in proto.vehicle.values() {
+        if vehicle.price.0 <= 0 {
+            errors.push(ValidationError::InvalidField(
+                at(vehicle),
+                "price",
+                "must be positive".to_string(),
+            ));
+        }
+        if let Some(base) = base {
+            validate_asset(&mut errors, base, at(vehicle), &vehicle.asset);
+        }
+    }
+
+    // wagons without acc_force are fine, trains are checked for a locomotive when spawned
+    for rs in proto.rolling_stock.values() {
+        if rs.acc_force < 0.0 {
+            errors.push(ValidationError::InvalidField(
+                at(rs),
+                "acc_force",
+                "must not be negative".to_string(),
+            ));
+        }
+        for (field, v) in [
+            ("length", rs.length),
+            ("max_speed", rs.max_speed),
+            ("dec_force", rs.dec_force),
+        ] {
+            if v <= 0.0 {
+                errors.push(ValidationError::InvalidField(
+                    at(rs),
+                    field,
+                    "must be positive".to_string(),
+                ));
+            }
+        }
+    }
+
+    for warehouse in proto.warehouse.values() {
+        if warehouse.capacity.is_empty() {
+            errors.push(ValidationError::InvalidField(
+                at(warehouse),
+                "capacity",
+                "must store at least one item".to_string(),
+            ));
+        }
+
+        for item in &warehouse.capacity {
+            if !proto.item.contains_key(&item.id) {
+                errors.push(ValidationError::ReferencedProtoNotFound(
+                    at(warehouse),
+                    "capacity",
+                ));
+            }
+            if item.amount <= 0 {
+                errors.push(ValidationError::InvalidField(
+                    at(warehouse),
+                    "capacity",
+                    "must be positive".to_string(),
+                ));
+            }
+        }
     }
 
     if !errors.is_empty() {
@@ -80,3 +233,93 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
     }
     Ok(())
 }
+
+/// Checks that every consumed item can eventually be obtained, either by importing it or
+/// from a company whose own inputs can be obtained.
+fn validate_recipes(proto: &Prototypes, errors: &mut Vec<ValidationError>) {
+    let mut producers: BTreeMap<ItemID, Vec<&GoodsCompanyPrototype>> = BTreeMap::new();
+    for comp in proto.goods_company.values() {
+        for item in comp.recipe.iter().flat_map(|r| &r.production) {
+            producers.entry(item.id).or_default().push(comp);
+        }
+    }
+
+    let mut available: BTreeSet<ItemID> = proto
+        .item
+        .values()
+        .filter(|item| !item.optout_exttrade)
+        .map(|item| item.id)
+        .collect();
+    loop {
+        let before = available.len();
+        for comp in proto.goods_company.values() {
+            let Some(ref r) = comp.recipe else {
+                continue;
+            };
+            if r.consumption.iter().all(|i| available.contains(&i.id)) {
+                available.extend(r.production.iter().map(|i| i.id));
+            }
+        }
+        if available.len() == before {
+            break;
+        }
+    }
+
+    for comp in proto.goods_company.values() {
+        for item in comp.recipe.iter().flat_map(|r| &r.consumption) {
+            let Some(item_proto) = proto.item.get(&item.id) else {
+                continue; // already reported
+            };
+            if available.contains(&item.id) {
+                continue;
+            }
+            if producers.contains_key(&item.id) {
+                errors.push(ValidationError::CannotBootstrap(
+                    at(comp),
+                    item_proto.name.clone(),
+                ));
+            } else {
+                errors.push(ValidationError::ItemNotProduced(
+                    at(comp),
+                    item_proto.name.clone(),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_size(errors: &mut Vec<ValidationError>, name: String, size: Size2D) {
+    if size.w <= 0.0 || size.h <= 0.0 {
+        errors.push(ValidationError::InvalidField(
+            name,
+            "size",
+            "must be positive".to_string(),
+        ));
+    }
+}
+
+fn validate_asset(
+    errors: &mut Vec<ValidationError>,
+    base: &Path,
+    name: String,
+    asset: &RenderAsset,
+) {
+    let path = match asset {
+        RenderAsset::Mesh { path } | RenderAsset::Sprite { path } => path.to_string_lossy(),
+    };
+    if !asset_path(base, &path).exists() {
+        errors.push(ValidationError::AssetNotFound(
+            name,
+            "asset",
+            path.to_string(),
+        ));
+    }
+}
+
+/// Meshes are in assets/models, other assets are relative to the game directory
+fn asset_path(base: &Path, path: &str) -> std::path::PathBuf {
+    if path.ends_with(".glb") {
+        return base.join("assets/models").join(path);
+    }
+    base.join(path)
+}
@@ -5,8 +5,7 @@ use serde_big_array::BigArray;
 
 use prototypes::{prototypes_iter, ItemPrototype, Money};
 
-use crate::economy::{ItemID, Trade};
-use crate::SoulID;
+use crate::economy::{ItemID, Trade, TradeKind};
 
 pub const HISTORY_SIZE: usize = 128;
 /// Tick to wait before the new bin
@@ -44,11 +43,74 @@ pub struct ItemHistories {
     cursors: [usize; LEVEL_FREQS.len()],
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct MoneyHistoryLevel {
+    #[serde(with = "BigArray")]
+    pub past_ring: [Money; HISTORY_SIZE],
+}
+
+impl Default for MoneyHistoryLevel {
+    fn default() -> Self {
+        Self {
+            past_ring: [Money::ZERO; HISTORY_SIZE],
+        }
+    }
+}
+
+/// History of a single amount of money, like the government's debt
+#[derive(Default, Serialize, Deserialize)]
+pub struct MoneyHistory {
+    levels: [MoneyHistoryLevel; LEVEL_FREQS.len()],
+    cursors: [usize; LEVEL_FREQS.len()],
+}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct EcoStats {
     pub exports: ItemHistories,
     pub imports: ItemHistories,
     pub internal_trade: ItemHistories,
+    /// Outstanding loans at the end of each bin
+    pub debt: MoneyHistory,
+    /// Interests paid during each bin
+    pub interest: MoneyHistory,
+}
+
+impl MoneyHistory {
+    pub fn cursors(&self) -> &[usize] {
+        &self.cursors
+    }
+
+    pub fn level(&self, level: usize) -> Option<&MoneyHistoryLevel> {
+        self.levels.get(level)
+    }
+
+    /// Adds to the current bin, for flows of money
+    pub fn add(&mut self, amount: Money) {
+        for (level, cursor) in self.levels.iter_mut().zip(&self.cursors) {
+            level.past_ring[*cursor] += amount;
+        }
+    }
+
+    /// Overwrites the current bin, for stocks of money
+    pub fn set(&mut self, amount: Money) {
+        for (level, cursor) in self.levels.iter_mut().zip(&self.cursors) {
+            level.past_ring[*cursor] = amount;
+        }
+    }
+
+    pub fn advance(&mut self, tick: u64) {
+        for ((level, c), freq) in self
+            .levels
+            .iter_mut()
+            .zip(self.cursors.iter_mut())
+            .zip(&LEVEL_FREQS)
+        {
+            if tick % *freq == 0 {
+                *c = (*c + 1) % HISTORY_SIZE;
+                level.past_ring[*c] = Money::ZERO;
+            }
+        }
+    }
 }
 
 impl Default for ItemHistories {
@@ -76,6 +138,14 @@ impl ItemHistories {
             .filter_map(move |(id, history)| Some((*id, history.levels.get(level)?)))
     }
 
+    /// Quantity of the item traded over the history of the given level
+    pub fn total(&self, item: ItemID, level: usize) -> i64 {
+        self.m
+            .get(&item)
+            .and_then(|h| h.levels.get(level))
+            .map_or(0, |l| l.past_ring_items.iter().sum())
+    }
+
     pub fn handle_trade(&mut self, trade: &Trade) {
         if trade.qty <= 0 {
             return;
@@ -110,17 +180,15 @@ impl EcoStats {
         self.exports.advance(tick);
         self.imports.advance(tick);
         self.internal_trade.advance(tick);
+        self.debt.advance(tick);
+        self.interest.advance(tick);
 
         for trade in trades {
-            if matches!(trade.buyer.0, SoulID::FreightStation(_)) {
-                self.exports.handle_trade(trade);
-                continue;
-            }
-            if matches!(trade.seller.0, SoulID::FreightStation(_)) {
-                self.imports.handle_trade(trade);
-                continue;
+            match trade.trade_kind {
+                TradeKind::Export => self.exports.handle_trade(trade),
+                TradeKind::Import => self.imports.handle_trade(trade),
+                TradeKind::Internal | TradeKind::Freight => self.internal_trade.handle_trade(trade),
             }
-            self.internal_trade.handle_trade(trade);
         }
     }
 }
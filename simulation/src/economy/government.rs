@@ -1,28 +1,117 @@
-use crate::map::{LanePattern, MapProject, MAX_ZONE_AREA};
-use crate::world_command::WorldCommand;
+use crate::map::{LanePattern, MapHistory, MapProject, MAX_ZONE_AREA};
+use crate::transportation::depot::{recompose_cost, wagons_cost};
+use crate::world_command::{CommandSender, WorldCommand};
 use crate::{BuildingKind, Simulation};
 use prototypes::Money;
 use serde::{Deserialize, Serialize};
 
+/// Maximum total debt the government can have from loans
+pub const CREDIT_LIMIT: Money = Money::new_bucks(1_000_000);
+/// Daily interest on loans, in basis points of the remaining principal
+pub const LOAN_DAILY_INTEREST_BP: i64 = 5;
+/// Daily interest on a negative balance, in basis points
+pub const OVERDRAFT_DAILY_INTEREST_BP: i64 = 20;
+/// Number of consecutive days with a negative balance before going bankrupt
+pub const BANKRUPTCY_DAYS: u32 = 30;
+
+/// A loan is repaid in equal daily parts of the principal plus the interest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Loan {
+    pub principal: Money,
+    pub remaining: Money,
+    pub days_left: u32,
+}
+
 /// The government represents the player.
 #[derive(Serialize, Deserialize)]
 pub struct Government {
     pub money: Money,
+    pub loans: Vec<Loan>,
+    /// Number of consecutive days the balance was negative
+    pub insolvent_days: u32,
+    /// Game over: nothing can be built anymore
+    pub bankrupt: bool,
 }
 
 impl Default for Government {
     fn default() -> Self {
         Self {
             money: Money::new_bucks(150_000),
+            loans: Vec::new(),
+            insolvent_days: 0,
+            bankrupt: false,
         }
     }
 }
 
 impl Government {
+    pub fn debt(&self) -> Money {
+        self.loans.iter().map(|l| l.remaining).sum()
+    }
+
+    pub fn available_credit(&self) -> Money {
+        (CREDIT_LIMIT - self.debt()).max(Money::ZERO)
+    }
+
+    /// Insolvent governments cannot pay for anything until they are back to a positive balance
+    pub fn is_insolvent(&self) -> bool {
+        self.money < Money::ZERO
+    }
+
+    pub fn take_loan(&mut self, principal: Money, term_days: u32) {
+        self.money += principal;
+        self.loans.push(Loan {
+            principal,
+            remaining: principal,
+            days_left: term_days.max(1),
+        });
+    }
+
+    /// Pays the daily loan installments and the interests, returns the interests paid
+    pub fn daily_update(&mut self) -> Money {
+        let mut interest = Money::ZERO;
+
+        for loan in &mut self.loans {
+            let repaid = loan.remaining / loan.days_left as i64;
+            interest += loan.remaining * LOAN_DAILY_INTEREST_BP / 10_000;
+            self.money -= repaid;
+            loan.remaining -= repaid;
+            loan.days_left -= 1;
+        }
+        self.loans.retain(|l| l.days_left > 0);
+
+        if self.is_insolvent() {
+            interest += -self.money * OVERDRAFT_DAILY_INTEREST_BP / 10_000;
+        }
+        self.money -= interest;
+
+        if self.is_insolvent() {
+            self.insolvent_days += 1;
+            if self.insolvent_days >= BANKRUPTCY_DAYS && !self.bankrupt {
+                log::warn!("the government went bankrupt");
+                self.bankrupt = true;
+            }
+        } else {
+            self.insolvent_days = 0;
+        }
+
+        interest
+    }
+
     pub fn action_cost(action: &WorldCommand, sim: &Simulation) -> Money {
         Money::new_bucks(match action {
             WorldCommand::MapBuildHouse(_) => 100,
             WorldCommand::AddTrain { n_wagons, .. } => 1000 + 100 * (*n_wagons as i64),
+            // unknown rolling stock is rejected by the validation
+            WorldCommand::SpawnTrain { wagons, .. } => {
+                return wagons_cost(wagons).unwrap_or(Money::ZERO)
+            }
+            WorldCommand::RecomposeTrain { train, wagons } => {
+                let Some(t) = sim.world().trains.get(*train) else {
+                    return Money::ZERO;
+                };
+                return recompose_cost(&t.consist, wagons).unwrap_or(Money::ZERO);
+            }
             WorldCommand::MapMakeConnection { from, to, pat, .. } => {
                 Self::connection_cost(from, to, pat)
             }
@@ -56,21 +145,70 @@ impl Government {
                 }
                 total
             }
-            WorldCommand::MapBuildSpecialBuilding { kind: x, .. } => match x {
-                BuildingKind::GoodsCompany(x) => {
-                    let descr = x.prototype();
-                    let mut price = descr.price;
-                    if let Some(ref z) = descr.zone {
-                        price += z.price_per_area * descr.size.area() as i64 / MAX_ZONE_AREA as i64;
-                    }
-                    return price;
+            WorldCommand::MapUpdateRoadPattern { road, pattern } => {
+                let m = sim.map();
+                let Some(r) = m.roads().get(*road) else {
+                    return Money::ZERO;
+                };
+                // only the added lanes are paid for
+                let added = (pattern.lanes_forward.len() + pattern.lanes_backward.len())
+                    .saturating_sub(r.n_lanes());
+                ((0.03 * r.length()) as i64).max(1) * added as i64
+            }
+            WorldCommand::MapBuildSpecialBuilding { kind, .. } => {
+                return Self::building_cost(kind);
+            }
+            // reverting an edit refunds it, redoing it pays again
+            WorldCommand::MapUndo => {
+                let sender = sim.read::<CommandSender>();
+                return sim
+                    .read::<MapHistory>()
+                    .undo_cost(&sender.name)
+                    .unwrap_or(Money::ZERO);
+            }
+            WorldCommand::MapRedo => {
+                let sender = sim.read::<CommandSender>();
+                return sim
+                    .read::<MapHistory>()
+                    .redo_cost(&sender.name)
+                    .unwrap_or(Money::ZERO);
+            }
+            WorldCommand::MapBuildBlueprint(paste) => {
+                let mut total = Money::ZERO;
+                for (from, to, _, pat) in paste.links.iter() {
+                    total += Money::new_bucks(Self::connection_cost(
+                        &paste.projects[*from],
+                        &paste.projects[*to],
+                        pat,
+                    ));
                 }
-                BuildingKind::RailFreightStation(x) => {
-                    return x.prototype().price;
+                for b in &paste.buildings {
+                    total += Self::building_cost(&b.kind);
                 }
-                BuildingKind::TrainStation => 1000,
-                _ => 0,
-            },
+                return total;
+            }
+            _ => 0,
+        })
+    }
+
+    fn building_cost(kind: &BuildingKind) -> Money {
+        Money::new_bucks(match kind {
+            BuildingKind::GoodsCompany(x) => {
+                let descr = x.prototype();
+                let mut price = descr.price;
+                if let Some(ref z) = descr.zone {
+                    price += z.price_per_area * descr.size.area() as i64 / MAX_ZONE_AREA as i64;
+                }
+                return price;
+            }
+            BuildingKind::RailFreightStation(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::Leisure(x) => {
+                return x.prototype().price;
+            }
+            BuildingKind::TrainStation => 1000,
+            BuildingKind::TrainDepot => 2000,
             _ => 0,
         })
     }
@@ -81,3 +219,39 @@ impl Government {
             * (pat.lanes_forward.len() + pat.lanes_backward.len()) as i64
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Government, BANKRUPTCY_DAYS};
+    use prototypes::Money;
+
+    #[test]
+    fn loan_is_repaid_with_interest() {
+        let mut gvt = Government::default();
+        let start = gvt.money;
+
+        gvt.take_loan(Money::new_bucks(10_000), 10);
+        assert_eq!(gvt.debt(), Money::new_bucks(10_000));
+
+        let mut interest = Money::ZERO;
+        for _ in 0..10 {
+            interest += gvt.daily_update();
+        }
+
+        assert!(gvt.loans.is_empty());
+        assert!(interest > Money::ZERO);
+        assert_eq!(gvt.money, start - interest);
+    }
+
+    #[test]
+    fn insolvency_leads_to_bankruptcy() {
+        let mut gvt = Government {
+            money: Money::new_bucks(-1),
+            ..Default::default()
+        };
+        for _ in 0..BANKRUPTCY_DAYS {
+            gvt.daily_update();
+        }
+        assert!(gvt.bankrupt);
+    }
+}
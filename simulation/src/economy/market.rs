@@ -26,12 +26,27 @@ pub struct BuyOrder {
     pub qty: u32,
 }
 
+/// A soul storing goods, like a warehouse
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct StorageOrder {
+    pub pos: Vec2,
+    pub capacity: u32,
+}
+
+/// Markup of the external prices over the cost of the raw materials and the work
+const PRICE_MULTIPLIER: f32 = 1.25;
+
+/// Storages buy the surplus at a discount, in percent of the external price
+pub const STORAGE_DISCOUNT_PERCENT: i64 = 20;
+
 #[derive(Serialize, Deserialize)]
 pub struct SingleMarket {
     // todo: change i32 to Quantity
     capital: BTreeMap<SoulID, i32>,
     buy_orders: BTreeMap<SoulID, BuyOrder>,
     sell_orders: BTreeMap<SoulID, SellOrder>,
+    /// Storages take the surplus that would be exported and serve the orders that would be imported
+    storages: BTreeMap<SoulID, StorageOrder>,
     pub ext_value: Money,
     optout_exttrade: bool,
 }
@@ -42,6 +57,7 @@ impl SingleMarket {
             capital: Default::default(),
             buy_orders: Default::default(),
             sell_orders: Default::default(),
+            storages: Default::default(),
             ext_value,
             optout_exttrade,
         }
@@ -56,6 +72,9 @@ impl SingleMarket {
     pub fn sell_order(&self, soul: SoulID) -> Option<&SellOrder> {
         self.sell_orders.get(&soul)
     }
+    pub fn storage(&self, soul: SoulID) -> Option<&StorageOrder> {
+        self.storages.get(&soul)
+    }
 
     pub fn capital_map(&self) -> &BTreeMap<SoulID, i32> {
         &self.capital
@@ -63,8 +82,9 @@ impl SingleMarket {
 }
 
 /// Market handles good exchanging between souls themselves and the external market.
-/// When goods are exchanges between souls, money is not involved.
-/// When goods are exchanged with the external market, money is involved.
+/// When goods are exchanges between souls, the government's money is not involved,
+/// but trades are valued at the external price so that companies can be paid.
+/// When goods are exchanged with the external market, the government's money is involved.
 #[derive(Serialize, Deserialize)]
 pub struct Market {
     markets: BTreeMap<ItemID, SingleMarket>,
@@ -81,6 +101,22 @@ pub struct TradeTarget(pub SoulID);
 
 debug_inspect_impl!(TradeTarget);
 
+/// Where the goods of a trade go
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeKind {
+    /// Between two souls of the map
+    #[default]
+    Internal,
+    /// Bought from the external market, brought by a freight train
+    Import,
+    /// Sold to the external market, taken away by a freight train
+    Export,
+    /// Sold to a freight station whose freight routes carry the goods to another station of the map
+    Freight,
+}
+
+debug_inspect_impl!(TradeKind);
+
 #[derive(Inspect, Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Trade {
     pub buyer: TradeTarget,
@@ -88,6 +124,9 @@ pub struct Trade {
     pub qty: i32,
     pub kind: ItemID,
     pub money_delta: Money, // money delta from the govt point of view, positive means we gained money
+    /// Value of the goods at the external price, paid by the buyer to the seller
+    pub value: Money,
+    pub trade_kind: TradeKind,
 }
 
 pub fn find_trade_place(target: TradeTarget, binfos: &BuildingInfos) -> Option<BuildingID> {
@@ -96,7 +135,7 @@ pub fn find_trade_place(target: TradeTarget, binfos: &BuildingInfos) -> Option<B
 
 impl Default for Market {
     fn default() -> Self {
-        let prices = calculate_prices(1.25);
+        let prices = calculate_prices(PRICE_MULTIPLIER);
         Self {
             markets: prototypes_iter::<ItemPrototype>()
                 .map(|v| (v.id, SingleMarket::new(prices[&v.id], v.optout_exttrade)))
@@ -116,6 +155,11 @@ impl Market {
         self.markets.iter()
     }
 
+    /// Price of one unit of the item
+    pub fn ext_value(&self, kind: ItemID) -> Money {
+        self.markets.get(&kind).map_or(Money::ZERO, |m| m.ext_value)
+    }
+
     /// Called when an agent tells the world it wants to sell something
     /// If an order is already placed, it will be updated.
     /// Beware that you need capital to sell anything, using produce.
@@ -144,10 +188,36 @@ impl Market {
         for market in self.markets.values_mut() {
             market.sell_orders.remove(&soul);
             market.buy_orders.remove(&soul);
+            market.storages.remove(&soul);
             market.capital.remove(&soul);
         }
     }
 
+    /// Cancels all the buy orders of the agent
+    pub fn cancel_buys(&mut self, soul: SoulID) {
+        for market in self.markets.values_mut() {
+            market.buy_orders.remove(&soul);
+        }
+    }
+
+    /// Adds the markets of new items and updates the external prices after the prototypes were reloaded.
+    /// The markets of removed items are kept since they are still used until the game restarts.
+    pub fn refresh_prototypes(&mut self) {
+        let prices = calculate_prices(PRICE_MULTIPLIER);
+        for item in prototypes_iter::<ItemPrototype>() {
+            match self.markets.entry(item.id) {
+                Entry::Occupied(mut e) => {
+                    let m = e.get_mut();
+                    m.ext_value = prices[&item.id];
+                    m.optout_exttrade = item.optout_exttrade;
+                }
+                Entry::Vacant(e) => {
+                    e.insert(SingleMarket::new(prices[&item.id], item.optout_exttrade));
+                }
+            }
+        }
+    }
+
     /// Called when an agent tells the world it wants to buy something
     /// If an order is already placed, it will be updated.
     pub fn buy(&mut self, soul: SoulID, near: Vec2, kind: ItemID, qty: u32) {
@@ -166,6 +236,21 @@ impl Market {
         self.buy(soul, near, kind, qty - c as u32);
     }
 
+    /// Called when an agent wants to store an item, up to the given capacity.
+    /// Its stock will be used to fulfill orders that would otherwise be imported,
+    /// and it will take the surplus that would otherwise be exported.
+    pub fn store(&mut self, soul: SoulID, near: Vec2, kind: ItemID, capacity: u32) {
+        let m = self.m(kind);
+        m.capital.entry(soul).or_default();
+        m.storages.insert(
+            soul,
+            StorageOrder {
+                pos: near,
+                capacity,
+            },
+        );
+    }
+
     /// Get the capital that this agent owns
     pub fn capital(&self, soul: SoulID, kind: ItemID) -> i32 {
         self.markets.get(&kind).unwrap().capital(soul).unwrap_or(0)
@@ -190,7 +275,12 @@ impl Market {
     /// A trade updates the buy and sell orders from the market, and the capital of the buyers and sellers.
     /// A trade can only be completed if the seller has enough capital.
     /// Please do not keep the trades around much, it needs to be destroyed by the next time you call this function.
-    pub fn make_trades(&mut self, find_external: impl Fn(Vec2) -> Option<SoulID>) -> &[Trade] {
+    /// The surplus sold to an external soul for which `takes_freight` is true stays on the map, it is not exported.
+    pub fn make_trades(
+        &mut self,
+        find_external: impl Fn(Vec2) -> Option<SoulID>,
+        takes_freight: impl Fn(SoulID, ItemID) -> bool,
+    ) -> &[Trade] {
         self.all_trades.clear();
 
         for (&kind, market) in &mut self.markets {
@@ -224,6 +314,8 @@ impl Market {
                             qty: qty_buy,
                             kind,
                             money_delta: Money::ZERO,
+                            value: market.ext_value * qty_buy as i64,
+                            trade_kind: TradeKind::Internal,
                         },
                         score,
                     ))
@@ -235,9 +327,9 @@ impl Market {
                 buy_orders,
                 sell_orders,
                 capital,
+                storages,
                 optout_exttrade,
                 ext_value,
-                ..
             } = market;
 
             self.all_trades
@@ -281,6 +373,76 @@ impl Market {
                     Some(trade)
                 }));
 
+            // Storages serve the buyers left before they are served externally
+            if !storages.is_empty() {
+                let mut served = Vec::new();
+                for (&buyer, order) in buy_orders.iter() {
+                    let qty = order.qty as i32;
+                    let Some(storage) = storages
+                        .iter()
+                        .filter(|&(&s, _)| {
+                            s != buyer && capital.get(&s).copied().unwrap_or(0) >= qty
+                        })
+                        .min_by_key(|(_, st)| OrderedFloat(st.pos.distance2(order.pos)))
+                        .map(|(&s, _)| s)
+                    else {
+                        continue;
+                    };
+
+                    *capital.entry(storage).or_default() -= qty;
+                    *capital.entry(buyer).or_default() += qty;
+                    served.push(buyer);
+
+                    self.all_trades.push(Trade {
+                        buyer: TradeTarget(buyer),
+                        seller: TradeTarget(storage),
+                        qty,
+                        kind,
+                        money_delta: Money::ZERO,
+                        value: *ext_value * qty as i64,
+                        trade_kind: TradeKind::Internal,
+                    });
+                }
+                for buyer in served {
+                    buy_orders.remove(&buyer);
+                }
+
+                // Storages take the surplus before it is exported
+                for (&seller, order) in sell_orders.iter_mut() {
+                    let qty = order.qty as i32 - order.stock as i32;
+                    if qty <= 0 || storages.contains_key(&seller) {
+                        continue;
+                    }
+                    if capital.get(&seller).copied().unwrap_or(0) < qty {
+                        continue;
+                    }
+                    let Some(storage) = storages
+                        .iter()
+                        .filter(|&(&s, st)| {
+                            capital.get(&s).copied().unwrap_or(0) + qty <= st.capacity as i32
+                        })
+                        .min_by_key(|(_, st)| OrderedFloat(st.pos.distance2(order.pos)))
+                        .map(|(&s, _)| s)
+                    else {
+                        continue;
+                    };
+
+                    *capital.entry(seller).or_default() -= qty;
+                    *capital.entry(storage).or_default() += qty;
+                    order.qty -= qty as u32;
+
+                    self.all_trades.push(Trade {
+                        buyer: TradeTarget(storage),
+                        seller: TradeTarget(seller),
+                        qty,
+                        kind,
+                        money_delta: Money::ZERO,
+                        value: *ext_value * qty as i64 * (100 - STORAGE_DISCOUNT_PERCENT) / 100,
+                        trade_kind: TradeKind::Internal,
+                    });
+                }
+            }
+
             // External trading
             if !*optout_exttrade {
                 // All buyers can fullfil since they can buy externally
@@ -300,6 +462,8 @@ impl Market {
                         qty: qty_buy,
                         kind,
                         money_delta: -(*ext_value * qty_buy as i64), // we buy from external so we pay
+                        value: *ext_value * qty_buy as i64,
+                        trade_kind: TradeKind::Import,
                     });
                 }
 
@@ -321,12 +485,21 @@ impl Market {
                         continue;
                     };
 
+                    let value = *ext_value * qty_sell as i64;
+                    let (trade_kind, money_delta) = if takes_freight(ext, kind) {
+                        (TradeKind::Freight, Money::ZERO)
+                    } else {
+                        (TradeKind::Export, value)
+                    };
+
                     self.all_trades.push(Trade {
                         buyer: TradeTarget(ext),
                         seller: TradeTarget(seller),
                         qty: qty_sell,
                         kind,
-                        money_delta: *ext_value * qty_sell as i64,
+                        money_delta,
+                        value,
+                        trade_kind,
                     });
                 }
             }
@@ -406,13 +579,13 @@ fn calculate_prices(price_multiplier: f32) -> BTreeMap<ItemID, Money> {
 mod tests {
     use geom::{vec2, Vec2};
     use prototypes::test_prototypes;
-    use prototypes::ItemID;
+    use prototypes::{ItemID, Money};
 
     use crate::economy::WORKER_CONSUMPTION_PER_MINUTE;
     use crate::world::CompanyID;
     use crate::{FreightStationID, SoulID};
 
-    use super::Market;
+    use super::{Market, TradeKind};
 
     fn mk_ent(id: u64) -> CompanyID {
         CompanyID::from(slotmapd::KeyData::from_ffi(id))
@@ -455,7 +628,7 @@ mod tests {
         m.sell(seller, Vec2::X, cereal, 3, 5);
         m.sell(seller_far, vec2(10.0, 10.0), cereal, 3, 5);
 
-        let trades = m.make_trades(|_| Some(freight));
+        let trades = m.make_trades(|_| Some(freight), |_, _| false);
 
         assert_eq!(trades.len(), 1);
         let t0 = trades[0];
@@ -464,6 +637,56 @@ mod tests {
         assert_eq!(t0.qty, 2);
     }
 
+    #[test]
+    fn storage_takes_surplus_and_serves_shortages() {
+        let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let storage = SoulID::GoodsCompany(mk_ent((1 << 32) | 2));
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 3));
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 4,
+        )));
+
+        test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+        let cereal = ItemID::new("cereal");
+
+        m.store(storage, Vec2::ZERO, cereal, 4);
+
+        // surplus above the seller's stock goes to the storage, up to its capacity
+        m.produce(seller, cereal, 5);
+        m.sell_all(seller, Vec2::X, cereal, 2);
+
+        let trades = m.make_trades(|_| Some(freight), |_, _| false);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].seller.0, seller);
+        assert_eq!(trades[0].buyer.0, storage);
+        assert_eq!(trades[0].qty, 3);
+        assert_eq!(m.capital(storage, cereal), 3);
+
+        // the buyer gets the seller's stock first, then the storage's instead of importing
+        m.buy(buyer, Vec2::Y, cereal, 2);
+        let trades = m.make_trades(|_| Some(freight), |_, _| false);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].seller.0, seller);
+
+        m.buy(buyer, Vec2::Y, cereal, 3);
+        let trades = m.make_trades(|_| Some(freight), |_, _| false);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].seller.0, storage);
+        assert_eq!(m.capital(storage, cereal), 0);
+    }
+
     #[test]
     fn calculate_prices() {
         test_prototypes(
@@ -539,4 +762,43 @@ mod tests {
             (price_cereal * 2 + 5 * WORKER_CONSUMPTION_PER_MINUTE * 10) / 2
         );
     }
+
+    #[test]
+    fn surplus_carried_by_freight_routes_is_not_exported() {
+        let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let freight = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 4,
+        )));
+
+        test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+        let cereal = ItemID::new("cereal");
+        let value = m.ext_value(cereal) * 5;
+
+        m.produce(seller, cereal, 5);
+        m.sell_all(seller, Vec2::X, cereal, 0);
+        let trades = m.make_trades(|_| Some(freight), |_, _| true);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].trade_kind, TradeKind::Freight);
+        assert_eq!(trades[0].money_delta, Money::ZERO);
+        assert_eq!(trades[0].value, value);
+
+        m.produce(seller, cereal, 5);
+        m.sell_all(seller, Vec2::X, cereal, 0);
+        let trades = m.make_trades(|_| Some(freight), |_, _| false);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].trade_kind, TradeKind::Export);
+        assert_eq!(trades[0].money_delta, value);
+    }
 }
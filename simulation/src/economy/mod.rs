@@ -19,13 +19,17 @@ use std::fmt::Debug;
 mod ecostats;
 mod government;
 mod market;
+mod wallet;
 
 use crate::map::Map;
+use crate::scripting::{ScriptEvent, Scripts};
+use crate::transportation::freight_route::FreightRoutes;
 use crate::world::HumanID;
 pub use ecostats::*;
 pub use government::*;
 pub use market::*;
-use prototypes::{GameTime, ItemID, Money, TICKS_PER_MINUTE};
+use prototypes::{GameTime, ItemID, Money, TICKS_PER_DAY, TICKS_PER_MINUTE};
+pub use wallet::*;
 
 const WORKER_CONSUMPTION_PER_MINUTE: Money = Money::new_cents(10);
 
@@ -54,23 +58,51 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
     let freights = &world.freight_stations;
 
     let map = resources.read::<Map>();
-    let trades = m.make_trades(|pos| {
-        freights
-            .iter()
-            .min_by_key(|(_, b)| {
-                let Some(b) = map.buildings.get(b.f.building) else {
-                    return OrderedFloat(f32::INFINITY);
-                };
-                OrderedFloat(b.door_pos.xy().distance2(pos))
-            })
-            .map(|(id, _)| SoulID::FreightStation(id))
-    });
-
-    resources.write::<EcoStats>().advance(tick.0, trades);
+    let routes = resources.read::<FreightRoutes>();
+    let trades = m.make_trades(
+        |pos| {
+            freights
+                .iter()
+                .min_by_key(|(_, b)| {
+                    let Some(b) = map.buildings.get(b.f.building) else {
+                        return OrderedFloat(f32::INFINITY);
+                    };
+                    OrderedFloat(b.door_pos.xy().distance2(pos))
+                })
+                .map(|(id, _)| SoulID::FreightStation(id))
+        },
+        |ext, item| {
+            let SoulID::FreightStation(id) = ext else {
+                return false;
+            };
+            freights
+                .get(id)
+                .map_or(false, |f| routes.takes(f.f.building, item))
+        },
+    );
+
+    let mut ecostats = resources.write::<EcoStats>();
+    ecostats.advance(tick.0, trades);
+    if tick.0 % TICKS_PER_DAY == 0 {
+        let interest = gvt.daily_update();
+        ecostats.interest.add(interest);
+    }
+    ecostats.debt.set(gvt.debt());
+    drop(ecostats);
+
+    let mut scripts = resources.write::<Scripts>();
 
     for &trade in trades.iter() {
         log::debug!("A trade was made! {:?}", trade);
 
+        scripts.push(ScriptEvent::Trade {
+            buyer: trade.buyer.0,
+            seller: trade.seller.0,
+            item: trade.kind,
+            qty: trade.qty,
+            money_delta: trade.money_delta,
+        });
+
         if trade.kind == job_opening {
             if let SoulID::GoodsCompany(id) = trade.seller.0 {
                 let comp = world.companies.get_mut(id).unwrap();
@@ -79,20 +111,36 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
         }
         gvt.money += trade.money_delta;
 
+        // the city runs the freight stations, it buys the goods carried by the freight routes
+        // and sells them at the other end
+        match (trade.buyer.0, trade.seller.0) {
+            (SoulID::FreightStation(_), _) if trade.trade_kind == TradeKind::Freight => {
+                gvt.money -= trade.value;
+            }
+            (_, SoulID::FreightStation(_)) if trade.trade_kind == TradeKind::Internal => {
+                gvt.money += trade.value;
+            }
+            _ => {}
+        }
+
         if let SoulID::GoodsCompany(id) = trade.seller.0 {
             if trade.kind != job_opening {
-                world.companies.get_mut(id).unwrap().sold.0.push(trade);
+                let c = world.companies.get_mut(id).unwrap();
+                c.comp.earn(trade.value);
+                c.sold.0.push(trade);
             }
         }
 
         match trade.buyer.0 {
             SoulID::Human(id) => {
                 if let Some(h) = world.humans.get_mut(id) {
+                    h.wallet.spend(trade.value);
                     h.bought.0.entry(trade.kind).or_default().push(trade);
                 }
             }
             SoulID::GoodsCompany(id) => {
                 if let Some(c) = world.companies.get_mut(id) {
+                    c.comp.earn(-trade.value);
                     c.bought.0.entry(trade.kind).or_default().push(trade)
                 }
             }
@@ -0,0 +1,108 @@
+use egui_inspect::Inspect;
+use prototypes::Money;
+use serde::{Deserialize, Serialize};
+
+/// Savings a household starts with when moving in
+pub const HOUSEHOLD_STARTING_SAVINGS: Money = Money::new_bucks(300);
+/// Rent charged every day to the head of the household
+pub const RENT_PER_DAY: Money = Money::new_bucks(40);
+/// Number of consecutive days unemployed and in debt before moving out
+pub const MOVE_OUT_DAYS: u32 = 5;
+/// Days of rent a household keeps aside before spending on leisure
+pub const LEISURE_RESERVE_DAYS: i64 = 3;
+
+/// The budget of a household
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Wallet {
+    pub money: Money,
+    /// Income made since the start of the day
+    pub income: Money,
+    pub last_day_income: Money,
+    /// Number of consecutive days without a job and with a negative balance
+    pub broke_days: u32,
+}
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Self {
+            money: HOUSEHOLD_STARTING_SAVINGS,
+            income: Money::ZERO,
+            last_day_income: Money::ZERO,
+            broke_days: 0,
+        }
+    }
+}
+
+impl Wallet {
+    pub fn earn(&mut self, amount: Money) {
+        self.money += amount;
+        self.income += amount;
+    }
+
+    pub fn spend(&mut self, amount: Money) {
+        self.money -= amount;
+    }
+
+    pub fn can_afford(&self, price: Money) -> bool {
+        self.money >= price
+    }
+
+    /// What can be spent on leisure, once the next days of rent are set aside
+    pub fn leisure_budget(&self) -> Money {
+        self.money - RENT_PER_DAY * LEISURE_RESERVE_DAYS
+    }
+
+    pub fn can_afford_leisure(&self, price: Money) -> bool {
+        self.leisure_budget() >= price
+    }
+
+    /// Pays the rent and closes the books for the day.
+    /// Returns whether the household should move out.
+    pub fn end_day(&mut self, rent: Money, employed: bool) -> bool {
+        self.spend(rent);
+        self.last_day_income = std::mem::replace(&mut self.income, Money::ZERO);
+
+        if !employed && self.money < Money::ZERO {
+            self.broke_days += 1;
+        } else {
+            self.broke_days = 0;
+        }
+
+        self.broke_days >= MOVE_OUT_DAYS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unemployed_households_move_out() {
+        let mut w = Wallet::default();
+        let mut days = 0;
+        while !w.end_day(RENT_PER_DAY, false) {
+            days += 1;
+            assert!(days < 100);
+        }
+        assert!(w.money < Money::ZERO);
+
+        let mut w = Wallet::default();
+        for _ in 0..100 {
+            w.earn(RENT_PER_DAY);
+            assert!(!w.end_day(RENT_PER_DAY, true));
+        }
+        assert_eq!(w.money, HOUSEHOLD_STARTING_SAVINGS);
+    }
+
+    #[test]
+    fn leisure_keeps_the_rent_aside() {
+        let mut w = Wallet::default();
+        w.money = RENT_PER_DAY * LEISURE_RESERVE_DAYS;
+        assert!(w.can_afford(Money::new_bucks(10)));
+        assert!(!w.can_afford_leisure(Money::new_bucks(10)));
+
+        w.earn(Money::new_bucks(10));
+        assert!(w.can_afford_leisure(Money::new_bucks(10)));
+        assert!(!w.can_afford_leisure(Money::new_bucks(11)));
+    }
+}
@@ -6,17 +6,22 @@ use common::saveload::{Bincode, Encoder, JSONPretty, JSON};
 use prototypes::{GameTime, Tick};
 
 use crate::economy::{market_update, EcoStats, Government, Market};
-use crate::map::Map;
+use crate::map::{Map, MapHistory};
 use crate::map_dynamic::{
     dispatch_system, electricity_flow_system, itinerary_update, routing_changed_system,
-    routing_update_system, BuildingInfos, Dispatcher, ElectricityFlow, ParkingManagement,
+    routing_update_system, BuildingInfos, Dispatcher, ElectricityFlow, ModalShare,
+    ParkingManagement,
 };
 use crate::multiplayer::MultiplayerState;
+use crate::scripting::{scripts_system, Scripts};
+use crate::souls::demographics::{demographics_system, Demographics};
 use crate::souls::freight_station::freight_station_system;
 use crate::souls::goods_company::company_system;
-use crate::souls::human::update_decision_system;
+use crate::souls::human::{household_system, update_decision_system};
+use crate::transportation::freight_route::{freight_route_system, FreightRoutes};
 use crate::transportation::pedestrian_decision_system;
 use crate::transportation::road::{vehicle_decision_system, vehicle_state_update_system};
+use crate::transportation::signals::RailSignals;
 use crate::transportation::testing_vehicles::{random_vehicles_update, RandomVehicles};
 use crate::transportation::train::{
     locomotive_system, train_reservations_update, TrainReservations,
@@ -24,6 +29,7 @@ use crate::transportation::train::{
 use crate::transportation::{transport_grid_synchronize, TransportGrid};
 use crate::utils::resources::Resources;
 use crate::world::{CompanyEnt, FreightStationEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt};
+use crate::world_command::{CommandSender, RejectedCommands};
 use crate::World;
 use crate::{
     add_souls_to_empty_buildings, utils, ParCommandBuffer, RandProvider, Replay, RunnableSystem,
@@ -53,6 +59,8 @@ pub fn init() {
     register_system("dispatch_system", dispatch_system);
     register_system("update_decision_system", update_decision_system);
     register_system("company_system", company_system);
+    register_system("household_system", household_system);
+    register_system("demographics_system", demographics_system);
     register_system("pedestrian_decision_system", pedestrian_decision_system);
     register_system("transport_grid_synchronize", transport_grid_synchronize);
     register_system("locomotive_system", locomotive_system);
@@ -64,8 +72,10 @@ pub fn init() {
     register_system("market_update", market_update);
     register_system("train_reservations_update", train_reservations_update);
     register_system("freight_station", freight_station_system);
+    register_system("freight_route", freight_route_system);
     register_system("random_vehicles", random_vehicles_update);
     register_system("update_map", |_, res| res.write::<Map>().update());
+    register_system("scripts", scripts_system);
 
     register_system_sim("add_souls_to_empty_buildings", add_souls_to_empty_buildings);
 
@@ -75,6 +85,8 @@ pub fn init() {
     register_resource_noserialize::<ParCommandBuffer<WagonEnt>>();
     register_resource_noserialize::<ParCommandBuffer<FreightStationEnt>>();
     register_resource_noserialize::<ParCommandBuffer<CompanyEnt>>();
+    register_resource_noserialize::<RejectedCommands>();
+    register_resource_noserialize::<CommandSender>();
     register_resource_noinit::<SimulationOptions, Bincode>("simoptions");
 
     register_resource_default::<ElectricityFlow, Bincode>("electricity_flow");
@@ -83,14 +95,20 @@ pub fn init() {
     register_resource_default::<MultiplayerState, Bincode>("multiplayer_state");
     register_resource_default::<RandomVehicles, Bincode>("random_vehicles");
     register_resource_default::<Map, Bincode>("map");
+    register_resource_default::<MapHistory, Bincode>("map_history");
     register_resource_default::<TrainReservations, Bincode>("train_reservations");
+    register_resource_default::<RailSignals, Bincode>("rail_signals");
     register_resource_default::<Government, Bincode>("government");
     register_resource_default::<ParkingManagement, Bincode>("pmanagement");
+    register_resource_default::<ModalShare, Bincode>("modal_share");
     register_resource_default::<BuildingInfos, Bincode>("binfos");
+    register_resource_default::<Demographics, Bincode>("demographics");
     register_resource::<GameTime, Bincode>("game_time", || GameTime::new(Tick(1)));
     register_resource::<TransportGrid, Bincode>("transport_grid", || TransportGrid::new(100));
     register_resource::<RandProvider, Bincode>("randprovider", || RandProvider::new(RNG_SEED));
     register_resource_default::<Dispatcher, Bincode>("dispatcher");
+    register_resource_default::<FreightRoutes, Bincode>("freight_routes");
+    register_resource_default::<Scripts, Bincode>("scripts");
     register_resource_default::<Replay, JSON>("replay");
 }
 
@@ -100,7 +118,7 @@ pub struct InitFunc {
 
 pub(crate) struct SaveLoadFunc {
     pub name: &'static str,
-    pub save: Box<dyn Fn(&Simulation) -> Vec<u8> + 'static>,
+    pub save: Box<dyn Fn(&Simulation) -> std::io::Result<Vec<u8>> + 'static>,
     pub load: Box<dyn Fn(&mut Simulation, Vec<u8>) + 'static>,
 }
 
@@ -176,7 +194,7 @@ fn register_resource_noinit<T: 'static + Send + Sync + Serialize + DeserializeOw
     unsafe {
         SAVELOAD_FUNCS.push(SaveLoadFunc {
             name,
-            save: Box::new(move |uiworld| E::encode(&*uiworld.read::<T>()).unwrap()),
+            save: Box::new(move |uiworld| E::encode(&*uiworld.read::<T>())),
             load: Box::new(move |uiworld, data| match E::decode::<T>(&data) {
                 Ok(res) => {
                     uiworld.insert(res);
@@ -2,19 +2,26 @@
 #![allow(clippy::type_complexity)]
 #![warn(clippy::iter_over_hash_type)]
 
+use crate::economy::Market;
 use crate::init::{GSYSTEMS, INIT_FUNCS, SAVELOAD_FUNCS};
 use crate::map::{BuildingKind, Map};
 use crate::map_dynamic::{Itinerary, ItineraryLeader};
+use crate::scripting::{apply_script_commands, Scripts};
 use crate::souls::add_souls_to_empty_buildings;
+use crate::souls::goods_company::refresh_companies;
 use crate::utils::resources::{Ref, RefMut, Resources};
 use crate::utils::scheduler::RunnableSystem;
-use crate::world_command::WorldCommand;
 use crate::world_command::WorldCommand::Init;
-use common::saveload::Encoder;
+use crate::world_command::{CommandSender, RejectedCommands, WorldCommand};
+use common::saveload::{Bincode, Blobs, Encoder};
 use common::FastMap;
 use derive_more::{From, TryInto};
 use geom::Vec3;
-use prototypes::{prototype, ColorsPrototype, ColorsPrototypeID, GameTime, Tick};
+use prototypes::{
+    prototype, ColorsPrototype, ColorsPrototypeID, GameTime, ModInfo, PrototypeLoadError,
+    ReloadReport, Tick,
+};
+use serde::de::{self, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
 use std::collections::BTreeMap;
@@ -44,6 +51,7 @@ pub mod map;
 pub mod map_dynamic;
 pub mod multiplayer;
 mod rerun;
+pub mod scripting;
 pub mod souls;
 #[cfg(test)]
 mod tests;
@@ -147,7 +155,23 @@ impl Simulation {
         })
     }
 
-    pub fn from_replay(replay: Replay) -> (Simulation, SimulationReplayLoader) {
+    /// Returns None if the replay starts from a save that cannot be loaded
+    pub fn from_replay(replay: Replay) -> Option<(Simulation, SimulationReplayLoader)> {
+        if let Some(ref base_save) = replay.base_save {
+            let sim = Self::load_from_disk(base_save)?;
+            let pastt = sim.read::<GameTime>().tick;
+            return Some((
+                sim,
+                SimulationReplayLoader {
+                    replay,
+                    pastt,
+                    idx: 0,
+                    speed: 1,
+                    advance_n_ticks: 0,
+                },
+            ));
+        }
+
         let mut sim = Simulation {
             world: Default::default(),
             resources: Default::default(),
@@ -161,7 +185,7 @@ impl Simulation {
             }
         }
 
-        (
+        Some((
             sim,
             SimulationReplayLoader {
                 replay,
@@ -170,7 +194,7 @@ impl Simulation {
                 speed: 1,
                 advance_n_ticks: 0,
             },
-        )
+        ))
     }
 
     pub fn new_with_options(opts: SimulationOptions) -> Simulation {
@@ -188,13 +212,17 @@ impl Simulation {
             }
         }
 
-        Init(Box::new(opts)).apply(&mut sim);
+        if let Err(e) = Init(Box::new(opts)).apply(&mut sim) {
+            log::error!("could not init simulation: {}", e);
+        }
 
         let start_commands: Vec<(u32, WorldCommand)> =
             common::saveload::JSON::decode(START_COMMANDS.as_bytes()).unwrap();
 
         for (_, command) in start_commands {
-            command.apply(&mut sim);
+            if let Err(e) = command.apply(&mut sim) {
+                log::warn!("start command {:?} was rejected: {}", command, e);
+            }
         }
 
         sim
@@ -218,8 +246,9 @@ impl Simulation {
 
         unsafe {
             for l in &*addr_of!(SAVELOAD_FUNCS) {
-                let a = (l.save)(self);
-                let b = (l.save)(other);
+                let (Ok(a), Ok(b)) = ((l.save)(self), (l.save)(other)) else {
+                    return false;
+                };
 
                 if a != b {
                     std::fs::write(format!("{}_a.json", l.name), &*String::from_utf8_lossy(&a))
@@ -245,9 +274,16 @@ impl Simulation {
         // so that instant commands work on single player but the game is still deterministic
         {
             profiling::scope!("applying commands");
-            for command in commands {
-                command.apply(self);
+            let mut rejected = vec![];
+            self.write::<CommandSender>().name.clear();
+            for (i, command) in commands.into_iter().enumerate() {
+                if let Err(e) = command.apply(self) {
+                    rejected.push((i, e));
+                }
             }
+            self.write::<RejectedCommands>().rejected = rejected;
+
+            apply_script_commands(self);
         }
 
         {
@@ -274,8 +310,9 @@ impl Simulation {
 
         unsafe {
             for l in &*addr_of!(SAVELOAD_FUNCS) {
-                let v = (l.save)(self);
-                hashes.insert(l.name.to_string(), common::hash_u64(&*v));
+                if let Ok(v) = (l.save)(self) {
+                    hashes.insert(l.name.to_string(), common::hash_u64(&*v));
+                }
             }
         }
 
@@ -288,8 +325,24 @@ impl Simulation {
         Some(replay)
     }
 
+    /// Saves that cannot be read, like the ones made before the save format was versioned,
+    /// are moved to `<save_name>_unreadable` instead of being overwritten by the next autosave.
     pub fn load_from_disk(save_name: &str) -> Option<Self> {
-        let sim: Simulation = common::saveload::CompressedBincode::load(save_name).ok()?;
+        use common::saveload::CompressedBincode;
+        let sim: Simulation = match CompressedBincode::load(save_name) {
+            Ok(sim) => sim,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                log::error!("could not load {}: {}", save_name, e);
+                // keep the unreadable save around, the next autosave would overwrite it
+                let path = CompressedBincode::filename(save_name);
+                let backup = CompressedBincode::filename(&format!("{save_name}_unreadable"));
+                if std::fs::rename(&path, &backup).is_ok() {
+                    log::warn!("moved {} to {}", path, backup);
+                }
+                return None;
+            }
+        };
         if sim.resources.try_read::<Map>().ok()?.environment.size().0 == 0 {
             return None;
         }
@@ -304,6 +357,24 @@ impl Simulation {
         }
     }
 
+    /// Reloads the prototypes from the mods and refreshes the state that depends on them.
+    /// Only use it in singleplayer, other players would not reload at the same tick.
+    /// # Safety
+    /// Nothing must access the prototypes on other threads while it runs.
+    pub unsafe fn reload_prototypes(
+        &mut self,
+        base: &str,
+    ) -> Result<ReloadReport, PrototypeLoadError> {
+        let report = prototypes::reload_prototypes(base)?;
+
+        let (world, res) = self.world_res();
+        let mut market = res.write::<Market>();
+        market.refresh_prototypes();
+        refresh_companies(world, &mut market, &res.read::<Map>());
+        res.write::<Scripts>().restart();
+        Ok(report)
+    }
+
     pub fn pos<E: WorldTransform>(&self, id: E) -> Option<Vec3> {
         self.world.pos(id)
     }
@@ -360,7 +431,7 @@ impl Serialize for Simulation {
 
         unsafe {
             for l in &*addr_of!(SAVELOAD_FUNCS) {
-                let v: Vec<u8> = (l.save)(self);
+                let v: Vec<u8> = (l.save)(self).map_err(serde::ser::Error::custom)?;
                 m.insert(l.name.to_string(), v);
             }
         }
@@ -368,8 +439,11 @@ impl Serialize for Simulation {
         log::info!("took {}s to serialize resources", t.elapsed().as_secs_f32());
 
         let v = SimulationSer {
-            world: &self.world,
+            magic: SAVE_MAGIC,
+            format: SAVE_FORMAT,
             version: VERSION.to_string(),
+            mods: prototypes::active_mods(),
+            world: &self.world,
             res: m,
         }
         .serialize(serializer);
@@ -378,49 +452,174 @@ impl Serialize for Simulation {
     }
 }
 
+/// Used to send the world to the clients. The entities and the map are split further, so the
+/// parts that did not change since their last session are not downloaded again.
+impl Blobs for Simulation {
+    fn to_blobs(&self) -> std::io::Result<Vec<(String, Vec<u8>)>> {
+        let header = (SAVE_MAGIC, SAVE_FORMAT, VERSION, prototypes::active_mods());
+        let mut blobs = vec![("header".to_string(), Bincode::encode(&header)?)];
+        self.world.to_blobs(&mut blobs)?;
+        unsafe {
+            for l in &*addr_of!(SAVELOAD_FUNCS) {
+                if l.name == "map" {
+                    self.map().to_blobs(&mut blobs)?;
+                    continue;
+                }
+                blobs.push((format!("res/{}", l.name), (l.save)(self)?));
+            }
+        }
+        Ok(blobs)
+    }
+
+    fn from_blobs(blobs: Vec<(String, Vec<u8>)>) -> Option<Self> {
+        let mut blobs: FastMap<String, Vec<u8>> = blobs.into_iter().collect();
+
+        let (magic, format, version, mods): ([u8; 8], u32, String, Vec<ModInfo>) =
+            Bincode::decode(&blobs.remove("header")?).ok()?;
+        if magic != SAVE_MAGIC {
+            log::error!("the received world is not a simulation");
+            return None;
+        }
+        if let Err(e) = check_save_format(format) {
+            log::error!("could not load the received world: {}", e);
+            return None;
+        }
+        check_save_compat(&version, &mods);
+
+        let mut sim = Self {
+            world: World::default(),
+            resources: Resources::default(),
+        };
+
+        unsafe {
+            for s in &*addr_of!(INIT_FUNCS) {
+                (s.f)(&mut sim);
+            }
+        }
+
+        sim.world = World::from_blobs(&mut blobs)?;
+        sim.insert(Map::from_blobs(&mut blobs)?);
+
+        unsafe {
+            for l in &*addr_of!(SAVELOAD_FUNCS) {
+                if let Some(data) = blobs.remove(&format!("res/{}", l.name)) {
+                    (l.load)(&mut sim, data);
+                }
+            }
+        }
+
+        Some(sim)
+    }
+}
+
+/// Written at the start of every save so that saves made before the format was versioned
+/// are rejected instead of being decoded as garbage. They cannot be loaded anymore: the
+/// entities they contain changed layout too, see [`load_from_disk`](Simulation::load_from_disk).
+const SAVE_MAGIC: [u8; 8] = *b"EGRSAVE\0";
+/// Bumped whenever the layout of the world or of a resource changes.
+/// Only this format is read for now, converting older ones is left to the version that bumps it.
+const SAVE_FORMAT: u32 = 1;
+
 #[derive(Serialize)]
 struct SimulationSer<'a> {
-    world: &'a World,
+    magic: [u8; 8],
+    format: u32,
     version: String,
+    mods: &'a [ModInfo],
+    world: &'a World,
     res: FastMap<String, Vec<u8>>,
 }
 
-#[derive(Deserialize)]
-struct SimulationDeser {
-    world: World,
-    version: String,
-    res: FastMap<String, Vec<u8>>,
+const SIMULATION_FIELDS: &[&str] = &["magic", "format", "version", "mods", "world", "res"];
+
+/// Refuses the formats this version cannot read
+fn check_save_format(format: u32) -> Result<(), String> {
+    match format {
+        SAVE_FORMAT => Ok(()),
+        _ => Err(format!(
+            "unknown save format {} (this game reads up to {}), the save was made by a newer version",
+            format, SAVE_FORMAT
+        )),
+    }
 }
 
-impl<'de> Deserialize<'de> for Simulation {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
+/// Warns when the save was made by another version of the game or with other mods
+fn check_save_compat(version: &str, mods: &[ModInfo]) {
+    let cur_version_parts = VERSION.split('.').collect::<Vec<_>>();
+    let deser_parts = version.split('.').collect::<Vec<_>>();
+
+    if cur_version_parts[0] != deser_parts[0]
+        || (cur_version_parts[0] == "0" && Some(&cur_version_parts[1]) != deser_parts.get(1))
     {
-        log::info!("deserializing sim state");
+        log::warn!(
+            "incompatible version, save might be corrupted! save is: {} - game is: {}",
+            version,
+            VERSION
+        );
+    }
+
+    if mods != prototypes::active_mods() {
+        let names = |mods: &[ModInfo]| {
+            mods.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        log::warn!(
+            "save was made with different mods, some prototypes might be missing! save has: [{}] - game has: [{}]",
+            names(mods),
+            names(prototypes::active_mods())
+        );
+    }
+}
+
+struct SimulationVisitor;
+
+impl<'de> Visitor<'de> for SimulationVisitor {
+    type Value = Simulation;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a simulation save")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Simulation, A::Error> {
         let t = Instant::now();
 
-        let mut simdeser = <SimulationDeser as Deserialize>::deserialize(deserializer)?;
+        let magic: [u8; 8] = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        if magic != SAVE_MAGIC {
+            return Err(de::Error::custom(
+                "the save was made before the save format was versioned and cannot be loaded",
+            ));
+        }
+
+        let format: u32 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        check_save_format(format).map_err(de::Error::custom)?;
+
+        let version: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        let mods: Vec<ModInfo> = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+        check_save_compat(&version, &mods);
+
+        let world: World = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(4, &self))?;
+        let mut res: FastMap<String, Vec<u8>> = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(5, &self))?;
 
         log::info!(
             "took {}s to deserialize base deser",
             t.elapsed().as_secs_f32()
         );
 
-        let cur_version_parts = VERSION.split('.').collect::<Vec<_>>();
-        let deser_parts = simdeser.version.split('.').collect::<Vec<_>>();
-
-        if cur_version_parts[0] != deser_parts[0]
-            || (cur_version_parts[0] == "0" && cur_version_parts[1] != deser_parts[1])
-        {
-            log::warn!(
-                "incompatible version, save might be corrupted! save is: {} - game is: {}",
-                simdeser.version,
-                VERSION
-            );
-        }
-
-        let mut sim = Self {
+        let mut sim = Simulation {
             world: World::default(),
             resources: Resources::default(),
         };
@@ -431,11 +630,11 @@ impl<'de> Deserialize<'de> for Simulation {
             }
         }
 
-        sim.world = simdeser.world;
+        sim.world = world;
 
         unsafe {
             for l in &*addr_of!(SAVELOAD_FUNCS) {
-                if let Some(data) = simdeser.res.remove(l.name) {
+                if let Some(data) = res.remove(l.name) {
                     (l.load)(&mut sim, data);
                 }
             }
@@ -450,6 +649,16 @@ impl<'de> Deserialize<'de> for Simulation {
     }
 }
 
+impl<'de> Deserialize<'de> for Simulation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        log::info!("deserializing sim state");
+        deserializer.deserialize_struct("SimulationSer", SIMULATION_FIELDS, SimulationVisitor)
+    }
+}
+
 const START_COMMANDS: &str = r#"
 [
   [
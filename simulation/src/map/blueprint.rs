@@ -0,0 +1,264 @@
+use crate::map::{
+    BuildingKind, IntersectionID, LanePattern, Map, MapProject, ProjectFilter, ProjectKind, Zone,
+};
+use geom::{vec2, PolyLine3, Polygon, Vec2, AABB, OBB};
+use prototypes::BuildingGen;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A reusable road layout captured from the map.
+/// Everything is stored relative to the center of the captured area.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub name: String,
+    pub nodes: Vec<BlueprintNode>,
+    pub roads: Vec<BlueprintRoad>,
+    pub buildings: Vec<BlueprintBuilding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueprintNode {
+    pub pos: Vec2,
+    /// height above the terrain, so that bridges stay bridges
+    pub elevation: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueprintRoad {
+    /// index into the nodes
+    pub src: usize,
+    pub dst: usize,
+    pub elbow: Option<Vec2>,
+    pub pattern: LanePattern,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueprintBuilding {
+    pub obb: OBB,
+    pub kind: BuildingKind,
+    pub gen: BuildingGen,
+    pub zone: Option<Zone>,
+    /// index into the roads
+    pub road: Option<usize>,
+}
+
+/// Where and how to paste a blueprint
+#[derive(Debug, Copy, Clone)]
+pub struct BlueprintTransform {
+    pub pos: Vec2,
+    /// cossin of the rotation
+    pub rotation: Vec2,
+    /// mirror along the local x axis before rotating
+    pub mirror: bool,
+}
+
+impl BlueprintTransform {
+    pub fn point(&self, p: Vec2) -> Vec2 {
+        self.dir(p) + self.pos
+    }
+
+    pub fn dir(&self, d: Vec2) -> Vec2 {
+        let d = if self.mirror { vec2(d.x, -d.y) } else { d };
+        d.rotated_by(self.rotation)
+    }
+
+    pub fn obb(&self, obb: &OBB) -> OBB {
+        let [a, b, c, d] = obb.corners.map(|p| self.point(p));
+        if self.mirror {
+            // keep the first segment (where the door is) first and the orientation counter-clockwise
+            return OBB::new_corners([b, a, d, c]);
+        }
+        OBB::new_corners([a, b, c, d])
+    }
+
+    pub fn zone(&self, zone: &Zone) -> Zone {
+        let mut points: Vec<Vec2> = zone.poly.iter().map(|&p| self.point(p)).collect();
+        if self.mirror {
+            points.reverse();
+        }
+        Zone::new(Polygon(points), self.dir(zone.filldir))
+    }
+}
+
+impl Blueprint {
+    /// Captures the roads that are entirely inside the area and the buildings that have their center inside it.
+    /// Returns None if nothing was captured.
+    pub fn capture(map: &Map, name: String, area: AABB) -> Option<Blueprint> {
+        let center = area.center();
+
+        let mut node_ids: BTreeMap<IntersectionID, usize> = BTreeMap::new();
+        let mut nodes = vec![];
+        let mut mk_node = |id: IntersectionID| {
+            *node_ids.entry(id).or_insert_with(|| {
+                let inter = &map.intersections()[id];
+                let height = map
+                    .environment
+                    .height(inter.pos.xy())
+                    .unwrap_or(inter.pos.z);
+                nodes.push(BlueprintNode {
+                    pos: inter.pos.xy() - center,
+                    elevation: inter.pos.z - height,
+                });
+                nodes.len() - 1
+            })
+        };
+
+        let mut road_ids = BTreeMap::new();
+        let mut roads = vec![];
+        for road in map.roads().values() {
+            let (Some(src), Some(dst)) = (
+                map.intersections().get(road.src),
+                map.intersections().get(road.dst),
+            ) else {
+                continue;
+            };
+            if !area.contains(src.pos.xy()) || !area.contains(dst.pos.xy()) {
+                continue;
+            }
+
+            road_ids.insert(road.id, roads.len());
+            roads.push(BlueprintRoad {
+                src: mk_node(road.src),
+                dst: mk_node(road.dst),
+                elbow: elbow_of(&road.points).map(|x| x - center),
+                pattern: road.pattern(map.lanes()),
+            });
+        }
+
+        let mut buildings = vec![];
+        for b in map.buildings().values() {
+            if !area.contains(b.obb.center()) {
+                continue;
+            }
+            let Some(gen) = building_gen(b.kind) else {
+                continue;
+            };
+            let mut obb = b.obb;
+            for c in &mut obb.corners {
+                *c -= center;
+            }
+            buildings.push(BlueprintBuilding {
+                obb,
+                kind: b.kind,
+                gen,
+                zone: b.zone.as_ref().map(|z| {
+                    let mut poly = z.poly.clone();
+                    poly.translate(-center);
+                    Zone::new(poly, z.filldir)
+                }),
+                road: b.connected_road.and_then(|r| road_ids.get(&r).copied()),
+            });
+        }
+
+        if roads.is_empty() && buildings.is_empty() {
+            return None;
+        }
+
+        Some(Blueprint {
+            name,
+            nodes,
+            roads,
+            buildings,
+        })
+    }
+
+    /// Where the nodes of the blueprint would end up on the map.
+    /// Nodes that fall on existing intersections or roads are connected to them.
+    pub fn projects(&self, map: &Map, transform: &BlueprintTransform) -> Vec<MapProject> {
+        self.nodes
+            .iter()
+            .map(|node| {
+                let pos = transform.point(node.pos);
+                let height = map.environment.height(pos).unwrap_or(0.0);
+                let pos = pos.z(height + node.elevation);
+
+                let proj = map.project(pos, 1.0, ProjectFilter::INTER | ProjectFilter::ROAD);
+                match proj.kind {
+                    ProjectKind::Intersection(_) | ProjectKind::Road(_) => proj,
+                    _ => MapProject::ground(pos),
+                }
+            })
+            .collect()
+    }
+
+    /// Builds the command pasting the blueprint at the given transform
+    pub fn paste(&self, map: &Map, transform: &BlueprintTransform) -> BlueprintPaste {
+        BlueprintPaste {
+            projects: self.projects(map, transform),
+            links: self
+                .roads
+                .iter()
+                .map(|r| {
+                    (
+                        r.src,
+                        r.dst,
+                        r.elbow.map(|e| transform.point(e)),
+                        r.pattern.clone(),
+                    )
+                })
+                .collect(),
+            buildings: self
+                .buildings
+                .iter()
+                .map(|b| BlueprintBuilding {
+                    obb: transform.obb(&b.obb),
+                    kind: b.kind,
+                    gen: match b.gen {
+                        // door_pos is relative to the building and mirroring flips its x axis
+                        BuildingGen::NoWalkway { door_pos } if transform.mirror => {
+                            BuildingGen::NoWalkway {
+                                door_pos: vec2(-door_pos.x, door_pos.y),
+                            }
+                        }
+                        x => x,
+                    },
+                    zone: b.zone.as_ref().map(|z| transform.zone(z)),
+                    road: b.road,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The arguments of [`crate::world_command::WorldCommand::MapBuildBlueprint`], in map coordinates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlueprintPaste {
+    pub projects: Vec<MapProject>,
+    pub links: Vec<(usize, usize, Option<Vec2>, LanePattern)>,
+    pub buildings: Vec<BlueprintBuilding>,
+}
+
+/// Houses and external trading are not part of blueprints as they are not built by the player
+fn building_gen(kind: BuildingKind) -> Option<BuildingGen> {
+    match kind {
+        BuildingKind::GoodsCompany(id) => Some(id.prototype().bgen),
+        BuildingKind::Leisure(id) => Some(id.prototype().bgen),
+        BuildingKind::RailFreightStation(_)
+        | BuildingKind::TrainStation
+        | BuildingKind::TrainDepot => Some(BuildingGen::NoWalkway {
+            door_pos: Vec2::ZERO,
+        }),
+        BuildingKind::House | BuildingKind::ExternalTrading => None,
+    }
+}
+
+/// Approximates a road polyline by the elbow that would generate it, see [`crate::map::RoadSegmentKind::from_elbow`]
+fn elbow_of(points: &PolyLine3) -> Option<Vec2> {
+    if points.n_points() <= 2 {
+        return None;
+    }
+    let from = points.first().xy();
+    let to = points.last().xy();
+    let d0 = points.first_dir()?.xy();
+    let d1 = points.last_dir()?.xy();
+
+    let denom = d0.perp_dot(d1);
+    if denom.abs() < 0.01 {
+        return None;
+    }
+    let t = (to - from).perp_dot(d1) / denom;
+    if t <= 0.0 {
+        return None;
+    }
+    Some(from + d0 * t)
+}
@@ -0,0 +1,434 @@
+use crate::map::{
+    Building, BuildingID, Chunk, IntersectionID, LanePattern, LightPolicy, Map, RoadID,
+    TerrainChunkID, TurnPolicy, Zone,
+};
+use geom::{PolyLine3, Vec3};
+use prototypes::{Money, Tick};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Maximum number of edits kept in the undo history of each player, older edits are forgotten
+pub const MAX_HISTORY: usize = 64;
+
+/// Terraforming is sent every frame while the tool is held, so the terraforms closer than this
+/// are merged into a single edit
+const TERRAFORM_STROKE_TICKS: u64 = 10;
+
+/// What is needed to recreate an intersection at the end of a removed road
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IntersectionSnapshot {
+    pub id: IntersectionID,
+    pub pos: Vec3,
+    pub turn_policy: TurnPolicy,
+    pub light_policy: LightPolicy,
+}
+
+/// What is needed to recreate a removed road exactly as it was
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoadSnapshot {
+    pub id: RoadID,
+    pub src: IntersectionSnapshot,
+    pub dst: IntersectionSnapshot,
+    pub points: PolyLine3,
+    pub pattern: LanePattern,
+    pub connected_buildings: Vec<BuildingID>,
+}
+
+/// Everything a single command changed on the map, so that it can be reverted.
+/// It is filled by the map itself while a journal is open, see [`Map::open_journal`].
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MapEdit {
+    pub(crate) created_roads: Vec<RoadID>,
+    pub(crate) removed_roads: Vec<RoadSnapshot>,
+    pub(crate) created_buildings: Vec<BuildingID>,
+    pub(crate) removed_buildings: Vec<Building>,
+    pub(crate) policies: Vec<(IntersectionID, TurnPolicy, LightPolicy)>,
+    pub(crate) zones: Vec<(BuildingID, Zone)>,
+    pub(crate) patterns: Vec<(RoadID, LanePattern)>,
+    /// The terrain chunks as they were before being terraformed
+    pub(crate) terrain: Vec<(TerrainChunkID, Chunk)>,
+    pub(crate) terrain_tick: Tick,
+    /// What the government paid for the edit, reverting it refunds it
+    pub(crate) cost: Money,
+}
+
+impl MapEdit {
+    pub fn is_empty(&self) -> bool {
+        self.terrain.is_empty() && self.is_empty_except_terrain()
+    }
+
+    fn is_empty_except_terrain(&self) -> bool {
+        self.created_roads.is_empty()
+            && self.removed_roads.is_empty()
+            && self.created_buildings.is_empty()
+            && self.removed_buildings.is_empty()
+            && self.policies.is_empty()
+            && self.zones.is_empty()
+            && self.patterns.is_empty()
+    }
+
+    fn is_terrain_only(&self) -> bool {
+        !self.terrain.is_empty() && self.is_empty_except_terrain()
+    }
+
+    /// Only the first state of the chunk is kept, it is what undoing restores
+    pub(crate) fn terrain_changed(&mut self, id: TerrainChunkID, chunk: Chunk) {
+        if self.terrain.iter().any(|(x, _)| *x == id) {
+            return;
+        }
+        self.terrain.push((id, chunk));
+    }
+
+    pub(crate) fn road_created(&mut self, id: RoadID) {
+        self.created_roads.push(id);
+    }
+
+    /// Roads created and removed during the same edit (e.g when splitting then merging) cancel out
+    pub(crate) fn road_removed(
+        &mut self,
+        id: RoadID,
+        snapshot: impl FnOnce() -> Option<RoadSnapshot>,
+    ) {
+        if let Some(i) = self.created_roads.iter().position(|x| *x == id) {
+            self.created_roads.remove(i);
+            return;
+        }
+        self.removed_roads.extend(snapshot());
+    }
+
+    pub(crate) fn building_created(&mut self, id: BuildingID) {
+        self.created_buildings.push(id);
+    }
+
+    pub(crate) fn building_removed(&mut self, b: &Building) {
+        if let Some(i) = self.created_buildings.iter().position(|x| *x == b.id) {
+            self.created_buildings.remove(i);
+            return;
+        }
+        self.removed_buildings.push(b.clone());
+    }
+
+    fn remap(&mut self, remap: &IdRemap) {
+        for r in &mut self.created_roads {
+            *r = remap.road(*r);
+        }
+        for snap in &mut self.removed_roads {
+            snap.src.id = remap.intersection(snap.src.id);
+            snap.dst.id = remap.intersection(snap.dst.id);
+            for b in &mut snap.connected_buildings {
+                *b = remap.building(*b);
+            }
+        }
+        for b in &mut self.created_buildings {
+            *b = remap.building(*b);
+        }
+        for b in &mut self.removed_buildings {
+            b.connected_road = b.connected_road.map(|r| remap.road(r));
+        }
+        for (i, _, _) in &mut self.policies {
+            *i = remap.intersection(*i);
+        }
+        for (b, _) in &mut self.zones {
+            *b = remap.building(*b);
+        }
+        for (r, _) in &mut self.patterns {
+            *r = remap.road(*r);
+        }
+    }
+}
+
+/// Recreated objects get new ids, this keeps track of old id -> new id
+/// so that the rest of the history can be kept pointing at the right objects.
+#[derive(Default)]
+pub(crate) struct IdRemap {
+    pub(crate) roads: BTreeMap<RoadID, RoadID>,
+    pub(crate) intersections: BTreeMap<IntersectionID, IntersectionID>,
+    pub(crate) buildings: BTreeMap<BuildingID, BuildingID>,
+}
+
+impl IdRemap {
+    pub(crate) fn road(&self, id: RoadID) -> RoadID {
+        self.roads.get(&id).copied().unwrap_or(id)
+    }
+
+    pub(crate) fn intersection(&self, id: IntersectionID) -> IntersectionID {
+        self.intersections.get(&id).copied().unwrap_or(id)
+    }
+
+    pub(crate) fn building(&self, id: BuildingID) -> BuildingID {
+        self.buildings.get(&id).copied().unwrap_or(id)
+    }
+}
+
+/// Undo/redo history of the map editing commands, kept per player so that players can only
+/// revert their own edits. Players are known by the name the server stamps their commands with,
+/// see [`crate::world_command::WorldCommand::SentBy`].
+/// It is part of the simulation state so that undoing is deterministic in multiplayer.
+#[derive(Default, Serialize, Deserialize)]
+pub struct MapHistory {
+    players: BTreeMap<String, PlayerHistory>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct PlayerHistory {
+    undo: VecDeque<MapEdit>,
+    redo: Vec<MapEdit>,
+}
+
+impl MapHistory {
+    pub fn can_undo(&self, player: &str) -> bool {
+        self.undo_cost(player).is_some()
+    }
+
+    pub fn can_redo(&self, player: &str) -> bool {
+        self.redo_cost(player).is_some()
+    }
+
+    /// What undoing the last edit of the player costs, negative as it is a refund
+    pub fn undo_cost(&self, player: &str) -> Option<Money> {
+        Some(-self.players.get(player)?.undo.back()?.cost)
+    }
+
+    /// What redoing the last undone edit of the player costs
+    pub fn redo_cost(&self, player: &str) -> Option<Money> {
+        Some(-self.players.get(player)?.redo.last()?.cost)
+    }
+
+    /// Records a new edit, which invalidates the redo stack of the player
+    pub fn push(&mut self, player: &str, edit: MapEdit) {
+        if edit.is_empty() {
+            return;
+        }
+        let h = self.players.entry(player.to_string()).or_default();
+        h.redo.clear();
+        if let Some(last) = h.undo.back_mut() {
+            if edit.is_terrain_only()
+                && last.is_terrain_only()
+                && edit.terrain_tick.0 <= last.terrain_tick.0 + TERRAFORM_STROKE_TICKS
+            {
+                for (id, chunk) in edit.terrain {
+                    last.terrain_changed(id, chunk);
+                }
+                last.terrain_tick = edit.terrain_tick;
+                last.cost += edit.cost;
+                return;
+            }
+        }
+        h.undo.push_back(edit);
+        if h.undo.len() > MAX_HISTORY {
+            h.undo.pop_front();
+        }
+    }
+
+    /// Reverts the last edit of the player. Returns the buildings that were recreated.
+    pub fn undo(&mut self, player: &str, map: &mut Map) -> Vec<BuildingID> {
+        let Some(edit) = self.players.get_mut(player).and_then(|h| h.undo.pop_back()) else {
+            return vec![];
+        };
+        let (inverse, recreated) = self.revert(map, &edit);
+        if !inverse.is_empty() {
+            self.players
+                .entry(player.to_string())
+                .or_default()
+                .redo
+                .push(inverse);
+        }
+        recreated
+    }
+
+    /// Reapplies the last edit the player undid. Returns the buildings that were recreated.
+    pub fn redo(&mut self, player: &str, map: &mut Map) -> Vec<BuildingID> {
+        let Some(edit) = self.players.get_mut(player).and_then(|h| h.redo.pop()) else {
+            return vec![];
+        };
+        let (inverse, recreated) = self.revert(map, &edit);
+        if !inverse.is_empty() {
+            self.players
+                .entry(player.to_string())
+                .or_default()
+                .undo
+                .push_back(inverse);
+        }
+        recreated
+    }
+
+    /// The inverse edit costs what reverting cost, so that reverting it back pays it back
+    fn revert(&mut self, map: &mut Map, edit: &MapEdit) -> (MapEdit, Vec<BuildingID>) {
+        let (mut inverse, remap) = map.revert(edit);
+        inverse.cost = -edit.cost;
+        self.remap(&remap);
+        let recreated = inverse.created_buildings.clone();
+        (inverse, recreated)
+    }
+
+    /// The ids are remapped in the edits of every player as they share the map
+    fn remap(&mut self, remap: &IdRemap) {
+        for h in self.players.values_mut() {
+            for edit in h.undo.iter_mut().chain(h.redo.iter_mut()) {
+                edit.remap(remap);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec3, Vec2};
+
+    use crate::economy::Government;
+    use crate::map::{LanePatternBuilder, MapHistory, MapProject, TerraformKind};
+    use crate::tests::TestCtx;
+    use crate::world_command::CommandError;
+    use crate::WorldCommand;
+
+    #[test]
+    fn undo_redo_road() {
+        let mut test = TestCtx::new();
+
+        let counts = |test: &TestCtx| {
+            let map = test.g.map();
+            (map.roads().len(), map.intersections().len())
+        };
+        let (n_roads, n_inters) = counts(&test);
+
+        test.apply(&[WorldCommand::MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::new().build(),
+        }]);
+        assert_eq!(counts(&test), (n_roads + 1, n_inters + 2));
+
+        let road = test
+            .g
+            .map()
+            .roads()
+            .values()
+            .find(|r| r.points.first().xy().distance(Vec2::ZERO) < 1.0)
+            .unwrap()
+            .id;
+
+        test.apply(&[WorldCommand::MapRemoveRoad(road)]);
+        assert_eq!(counts(&test), (n_roads, n_inters));
+
+        test.apply(&[WorldCommand::MapUndo]);
+        assert_eq!(counts(&test), (n_roads + 1, n_inters + 2));
+
+        test.apply(&[WorldCommand::MapUndo]);
+        assert_eq!(counts(&test), (n_roads, n_inters));
+        assert!(!test.g.read::<MapHistory>().can_undo(""));
+
+        test.apply(&[WorldCommand::MapRedo]);
+        assert_eq!(counts(&test), (n_roads + 1, n_inters + 2));
+
+        test.apply(&[WorldCommand::MapRedo]);
+        assert_eq!(counts(&test), (n_roads, n_inters));
+        assert!(!test.g.read::<MapHistory>().can_redo(""));
+
+        test.tick();
+    }
+
+    #[test]
+    fn players_undo_their_own_edits() {
+        let mut test = TestCtx::new();
+        let money = |test: &TestCtx| test.g.read::<Government>().money;
+        let n_roads = |test: &TestCtx| test.g.map().roads().len();
+        let (before, n_before) = (money(&test), n_roads(&test));
+
+        test.apply(&[
+            WorldCommand::SentBy("alice".to_string()),
+            WorldCommand::MapMakeConnection {
+                from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+                to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+                inter: None,
+                pat: LanePatternBuilder::new().build(),
+            },
+        ]);
+        let built = money(&test);
+        assert!(built < before);
+
+        test.apply(&[WorldCommand::SentBy("bob".to_string())]);
+        assert_eq!(
+            WorldCommand::MapUndo.validate(&test.g),
+            Err(CommandError::NothingToRevert)
+        );
+
+        // undoing refunds the edit and redoing pays for it again
+        test.apply(&[
+            WorldCommand::SentBy("alice".to_string()),
+            WorldCommand::MapUndo,
+        ]);
+        assert_eq!(n_roads(&test), n_before);
+        assert_eq!(money(&test), before);
+
+        test.apply(&[WorldCommand::MapRedo]);
+        assert_eq!(n_roads(&test), n_before + 1);
+        assert_eq!(money(&test), built);
+        assert!(test.g.read::<MapHistory>().can_undo("alice"));
+        assert!(!test.g.read::<MapHistory>().can_undo("bob"));
+    }
+
+    #[test]
+    fn undo_terraform() {
+        let mut test = TestCtx::new();
+
+        let height = |test: &TestCtx| test.g.map().environment.height(Vec2::splat(256.0));
+        let before = height(&test).unwrap();
+
+        let terraform = WorldCommand::Terraform {
+            kind: TerraformKind::Elevation,
+            amount: 1000.0,
+            center: Vec2::splat(256.0),
+            radius: 100.0,
+            level: 0.0,
+            slope: None,
+        };
+        test.apply(&[terraform.clone()]);
+        test.apply(&[terraform]);
+        assert!(height(&test).unwrap() > before);
+
+        // both terraforms are part of the same stroke
+        test.apply(&[WorldCommand::MapUndo]);
+        assert_eq!(height(&test), Some(before));
+        assert!(!test.g.read::<MapHistory>().can_undo(""));
+
+        test.apply(&[WorldCommand::MapRedo]);
+        assert!(height(&test).unwrap() > before);
+    }
+
+    #[test]
+    fn undo_road_pattern() {
+        let mut test = TestCtx::new();
+
+        test.apply(&[WorldCommand::MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::new().build(),
+        }]);
+
+        let n_lanes = |test: &TestCtx| {
+            let map = test.g.map();
+            let r = map
+                .roads()
+                .values()
+                .find(|r| r.points.first().xy().distance(Vec2::ZERO) < 1.0)
+                .unwrap();
+            (r.id, r.n_lanes())
+        };
+        let (road, before) = n_lanes(&test);
+
+        test.apply(&[WorldCommand::MapUpdateRoadPattern {
+            road,
+            pattern: LanePatternBuilder::new().n_lanes(2).build(),
+        }]);
+        let (road2, after) = n_lanes(&test);
+        assert_eq!(road, road2);
+        assert_eq!(after, before + 2);
+
+        test.apply(&[WorldCommand::MapUndo]);
+        assert_eq!(n_lanes(&test), (road, before));
+
+        test.tick();
+    }
+}
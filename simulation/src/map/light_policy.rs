@@ -38,6 +38,11 @@ impl LightPolicy {
             }
         }
 
+        // Ramps and merges are grade-separated, vehicles merge instead of stopping
+        if inter.turn_policy.junction.is_grade_separated() {
+            return;
+        }
+
         match self {
             LightPolicy::NoLights => {}
             LightPolicy::StopSigns => {
@@ -2,10 +2,11 @@ use crate::map::electricity_cache::ElectricityCache;
 use crate::map::height_override::find_overrides;
 use crate::map::serializing::SerializedMap;
 use crate::map::{
-    Building, BuildingID, BuildingKind, Environment, Intersection, IntersectionID, Lane, LaneID,
-    LaneKind, LanePattern, Lot, LotID, LotKind, MapSubscriber, MapSubscribers, ParkingSpotID,
-    ParkingSpots, ProjectFilter, ProjectKind, Road, RoadID, RoadSegmentKind, SpatialMap,
-    SubscriberChunkID, TerraformKind, UpdateType, Zone, ROAD_Z_OFFSET,
+    Building, BuildingID, BuildingKind, Environment, IdRemap, Intersection, IntersectionID,
+    IntersectionSnapshot, Lane, LaneID, LaneKind, LanePattern, Lot, LotID, LotKind, MapEdit,
+    MapSubscriber, MapSubscribers, ParkingSpotID, ParkingSpots, ProjectFilter, ProjectKind, Road,
+    RoadID, RoadSegmentKind, RoadSnapshot, SpatialMap, SubscriberChunkID, TerraformKind,
+    UpdateType, Zone, ROAD_Z_OFFSET,
 };
 use geom::OBB;
 use geom::{Vec2, Vec3};
@@ -40,6 +41,8 @@ pub struct Map {
     pub parking: ParkingSpots,
     pub subscribers: MapSubscribers,
     pub(crate) override_subscriber: MapSubscriber,
+    /// Records the changes made while applying a command so that it can be undone
+    pub(crate) journal: Option<MapEdit>,
 }
 
 defer_serialize!(Map, SerializedMap);
@@ -67,6 +70,7 @@ impl Map {
             electricity: Default::default(),
             override_subscriber: subscribers.subscribe(UpdateType::Road | UpdateType::Building),
             subscribers,
+            journal: None,
         }
     }
 
@@ -84,6 +88,9 @@ impl Map {
         let Some(inter) = self.intersections.get_mut(id) else {
             return;
         };
+        if let Some(ref mut j) = self.journal {
+            j.policies.push((id, inter.turn_policy, inter.light_policy));
+        }
         f(inter);
         self.invalidate(id);
 
@@ -131,6 +138,10 @@ impl Map {
         let b = self.buildings.remove(b)?;
         self.subscribers.dispatch(UpdateType::Building, &b);
 
+        if let Some(ref mut j) = self.journal {
+            j.building_removed(&b);
+        }
+
         if b.kind == BuildingKind::ExternalTrading {
             self.external_train_stations.retain(|id| *id != b.id);
         }
@@ -223,6 +234,9 @@ impl Map {
         let Some(ref mut z) = b.zone else {
             return;
         };
+        if let Some(ref mut j) = self.journal {
+            j.zones.push((id, z.clone()));
+        }
         f(z);
 
         self.environment.remove_trees_near(&z.poly, |tree_chunk| {
@@ -293,6 +307,10 @@ impl Map {
             self.external_train_stations.push(id);
         }
 
+        if let Some(ref mut j) = self.journal {
+            j.building_created(id);
+        }
+
         self.check_invariants();
         Some(id)
     }
@@ -324,6 +342,10 @@ impl Map {
             .dispatch(UpdateType::Building, &self.buildings[id]);
         self.electricity.add_object(id);
 
+        if let Some(ref mut j) = self.journal {
+            j.building_created(id);
+        }
+
         self.check_invariants();
         Some(id)
     }
@@ -336,6 +358,56 @@ impl Map {
         v
     }
 
+    /// Rebuilds the lanes of the road with the given pattern.
+    /// The road keeps its id, its lots and its connected buildings.
+    /// Returns the lanes that were removed so that the vehicles using them can be rerouted.
+    pub fn update_road_pattern(
+        &mut self,
+        id: RoadID,
+        pattern: &LanePattern,
+    ) -> Option<Vec<LaneID>> {
+        info!("update_road_pattern {:?} {:?}", id, pattern);
+
+        let road = self.roads.get_mut(id)?;
+        let old_pattern = road.pattern(&self.lanes);
+        if old_pattern == *pattern {
+            return None;
+        }
+
+        // the geometry of rails and roads is generated differently
+        let is_rail = |p: &LanePattern| p.lanes().any(|(k, _, _)| k.is_rail());
+        if is_rail(&old_pattern) != is_rail(pattern) {
+            log::warn!("cannot change {:?} between rail and road", id);
+            return None;
+        }
+
+        if let Some(ref mut j) = self.journal {
+            j.patterns.push((id, old_pattern));
+        }
+
+        self.subscribers.dispatch(UpdateType::Road, road);
+
+        let old_lanes = road.lanes_iter().map(|(lane, _)| lane).collect();
+        road.set_pattern(
+            pattern,
+            &mut self.lanes,
+            &mut self.parking,
+            &self.environment,
+        );
+        self.spatial_map.update(road);
+
+        let (src, dst) = (road.src, road.dst);
+
+        Lot::remove_intersecting_lots(self, id);
+
+        self.invalidate(src);
+        self.invalidate(dst);
+
+        self.check_invariants();
+
+        Some(old_lanes)
+    }
+
     pub fn subscribe(&self, filter: UpdateType) -> MapSubscriber {
         self.subscribers.subscribe(filter)
     }
@@ -402,17 +474,223 @@ impl Map {
         level: f32,
         slope: Option<(Vec3, Vec3)>,
     ) {
+        let before = if self.journal.is_some() {
+            self.environment.terraform_snapshot(center, radius)
+        } else {
+            vec![]
+        };
+
         let modified = self
             .environment
             .terraform(tick, kind, center, radius, amount, level, slope);
 
+        if let Some(ref mut j) = self.journal {
+            j.terrain_tick = tick;
+            for (id, chunk) in before {
+                if modified.contains(&id) {
+                    j.terrain_changed(id, chunk);
+                }
+            }
+        }
+
         for id in modified {
             self.subscribers.dispatch_chunk(UpdateType::Terrain, id);
         }
     }
 
+    /// Starts recording the changes made to the map, see [`MapEdit`]
+    pub(crate) fn open_journal(&mut self) {
+        self.journal = Some(MapEdit::default());
+    }
+
+    /// Stops recording changes and returns what was recorded since [`Map::open_journal`]
+    pub(crate) fn close_journal(&mut self) -> MapEdit {
+        self.journal.take().unwrap_or_default()
+    }
+
+    /// Reverts the changes recorded in the given edit.
+    /// Returns the edit that would revert the revert (for redo) and the ids of the recreated objects.
+    pub(crate) fn revert(&mut self, edit: &MapEdit) -> (MapEdit, IdRemap) {
+        info!(
+            "revert edit: {} roads created, {} roads removed, {} buildings created, {} buildings removed",
+            edit.created_roads.len(),
+            edit.removed_roads.len(),
+            edit.created_buildings.len(),
+            edit.removed_buildings.len()
+        );
+        self.open_journal();
+        let mut remap = IdRemap::default();
+
+        for (id, chunk) in &edit.terrain {
+            if let Some(cur) = self.environment.get_chunk(*id) {
+                let cur = cur.clone();
+                if let Some(ref mut j) = self.journal {
+                    j.terrain_changed(*id, cur);
+                }
+            }
+            self.environment.set_chunk(*id, chunk.clone());
+            self.subscribers.dispatch_chunk(UpdateType::Terrain, *id);
+        }
+
+        for (id, zone) in edit.zones.iter().rev() {
+            self.update_zone(*id, |z| *z = zone.clone());
+        }
+
+        for (id, pattern) in edit.patterns.iter().rev() {
+            self.update_road_pattern(*id, pattern);
+        }
+
+        for &(id, turn, light) in edit.policies.iter().rev() {
+            self.update_intersection(id, |i| {
+                i.turn_policy = turn;
+                i.light_policy = light;
+            });
+        }
+
+        for &id in &edit.created_buildings {
+            let Some(b) = self.remove_building(id) else {
+                continue;
+            };
+            // houses are built on lots, give them back
+            if let (BuildingKind::House, Some(r)) = (b.kind, b.connected_road) {
+                Lot::generate_along_road(self, r);
+            }
+        }
+
+        for &id in &edit.created_roads {
+            self.remove_road_inner(id);
+        }
+
+        for snapshot in &edit.removed_roads {
+            self.restore_road(snapshot, &mut remap);
+        }
+
+        for b in &edit.removed_buildings {
+            self.restore_building(b, &mut remap);
+        }
+
+        self.check_invariants();
+
+        (self.close_journal(), remap)
+    }
+
     // Private mutating
 
+    fn road_snapshot(&self, id: RoadID) -> Option<RoadSnapshot> {
+        let road = self.roads.get(id)?;
+        let inter_snapshot = |id: IntersectionID| {
+            let inter = self.intersections.get(id)?;
+            Some(IntersectionSnapshot {
+                id,
+                pos: inter.pos,
+                turn_policy: inter.turn_policy,
+                light_policy: inter.light_policy,
+            })
+        };
+
+        Some(RoadSnapshot {
+            id,
+            src: inter_snapshot(road.src)?,
+            dst: inter_snapshot(road.dst)?,
+            points: road.points.clone(),
+            pattern: road.pattern(&self.lanes),
+            connected_buildings: road.connected_buildings.clone(),
+        })
+    }
+
+    fn restore_intersection(
+        &mut self,
+        snapshot: &IntersectionSnapshot,
+        remap: &mut IdRemap,
+    ) -> IntersectionID {
+        let id = remap.intersection(snapshot.id);
+        if self.intersections.contains_key(id) {
+            return id;
+        }
+
+        let id = self.add_intersection(snapshot.pos);
+        #[allow(clippy::indexing_slicing)]
+        let inter = &mut self.intersections[id];
+        inter.turn_policy = snapshot.turn_policy;
+        inter.light_policy = snapshot.light_policy;
+
+        remap.intersections.insert(snapshot.id, id);
+        id
+    }
+
+    fn restore_road(&mut self, snapshot: &RoadSnapshot, remap: &mut IdRemap) -> Option<RoadID> {
+        let src = self.restore_intersection(&snapshot.src, remap);
+        let dst = self.restore_intersection(&snapshot.dst, remap);
+
+        let r = self.connect(
+            src,
+            dst,
+            &snapshot.pattern,
+            RoadSegmentKind::Arbitrary(snapshot.points.clone()),
+        )?;
+        remap.roads.insert(snapshot.id, r);
+
+        Lot::generate_along_road(self, r);
+
+        for &b in &snapshot.connected_buildings {
+            let b = remap.building(b);
+            let building = unwrap_cont!(self.buildings.get_mut(b));
+            if building.connected_road.is_some() {
+                continue;
+            }
+            building.connected_road = Some(r);
+            self.roads.get_mut(r)?.connected_buildings.push(b);
+            self.electricity.add_edge(b, r);
+        }
+
+        self.invalidate(src);
+        self.invalidate(dst);
+
+        Some(r)
+    }
+
+    fn restore_building(&mut self, b: &Building, remap: &mut IdRemap) -> Option<BuildingID> {
+        if self.building_overlaps(b.obb) {
+            log::warn!("could not restore {:?}: building overlaps", b.kind);
+            return None;
+        }
+
+        self.clean_lots_inner(self.spatial_map.query(&b.obb, ProjectFilter::LOT).collect());
+
+        let connected_road = b
+            .connected_road
+            .map(|r| remap.road(r))
+            .filter(|r| self.roads.contains_key(*r));
+
+        let id = self.buildings.insert_with_key(|id| Building {
+            id,
+            connected_road,
+            ..b.clone()
+        });
+        remap.buildings.insert(b.id, id);
+
+        self.electricity.add_object(id);
+        if let Some(r) = connected_road {
+            self.electricity.add_edge(id, r);
+            self.roads.get_mut(r)?.connected_buildings.push(id);
+        }
+
+        if b.kind == BuildingKind::ExternalTrading {
+            self.external_train_stations.push(id);
+        }
+
+        #[allow(clippy::indexing_slicing)]
+        let building = &self.buildings[id];
+        self.spatial_map.insert(building);
+        self.subscribers.dispatch(UpdateType::Building, building);
+
+        if let Some(ref mut j) = self.journal {
+            j.building_created(id);
+        }
+
+        Some(id)
+    }
+
     pub(crate) fn add_intersection(&mut self, pos: Vec3) -> IntersectionID {
         let id = Intersection::make(&mut self.intersections, &mut self.spatial_map, pos);
         self.subscribers
@@ -464,6 +742,11 @@ impl Map {
     /// Only removes road from Roads and spatial map but keeps lots, buildings connection
     /// and potentially empty intersections.
     fn remove_raw_road(&mut self, road_id: RoadID) -> Option<Road> {
+        if let Some(mut j) = self.journal.take() {
+            j.road_removed(road_id, || self.road_snapshot(road_id));
+            self.journal = Some(j);
+        }
+
         let road = self.roads.remove(road_id)?;
 
         self.spatial_map.remove(road_id);
@@ -708,6 +991,10 @@ impl Map {
         self.electricity.add_edge(src_id, rid);
         self.electricity.add_edge(dst_id, rid);
 
+        if let Some(ref mut j) = self.journal {
+            j.road_created(rid);
+        }
+
         #[allow(clippy::indexing_slicing)]
         let r = &self.roads[rid];
 
@@ -27,9 +27,11 @@ pub mod procgen {
     pub use presets::*;
 }
 
+mod blueprint;
 mod change_detection;
 mod electricity_cache;
 mod height_override;
+mod history;
 mod light_policy;
 #[allow(clippy::module_inception)]
 mod map;
@@ -43,8 +45,10 @@ mod turn_policy;
 
 // Use self or else it would be ambiguous with "pathfinding" crate
 pub use self::pathfinding::*;
+pub use blueprint::*;
 pub use change_detection::*;
 pub use electricity_cache::*;
+pub use history::*;
 pub use light_policy::*;
 pub use map::*;
 pub use spatial_map::*;
@@ -4,7 +4,7 @@ use crate::map::{
 };
 use egui_inspect::debug_inspect_impl;
 use geom::{Color, Polygon, Vec2, Vec3, OBB};
-use prototypes::{BuildingGen, FreightStationPrototypeID, GoodsCompanyID};
+use prototypes::{BuildingGen, FreightStationPrototypeID, GoodsCompanyID, LeisurePrototypeID};
 use serde::{Deserialize, Serialize};
 use slotmapd::new_key_type;
 
@@ -20,7 +20,10 @@ pub enum BuildingKind {
     GoodsCompany(GoodsCompanyID),
     RailFreightStation(FreightStationPrototypeID),
     TrainStation,
+    /// Where trains are recalled to, to be taken out of service and re-composed
+    TrainDepot,
     ExternalTrading,
+    Leisure(LeisurePrototypeID),
 }
 
 impl BuildingKind {
@@ -282,6 +282,16 @@ impl Intersection {
         })
     }
 
+    /// Whether traffic coming from this lane has to merge with traffic from other lanes,
+    /// which only happens at ramps and merges.
+    pub fn merges_from(&self, lane: LaneID) -> bool {
+        if !self.turn_policy.junction.is_grade_separated() {
+            return false;
+        }
+        self.turns_from(lane)
+            .any(|(turn, _)| self.turns_to(turn.dst).nth(1).is_some())
+    }
+
     pub fn find_turn(&self, needle: TurnID) -> Option<&Turn> {
         self.turns.get(&needle)
     }
@@ -25,6 +25,12 @@ impl LaneKind {
         matches!(self, LaneKind::Driving | LaneKind::Biking | LaneKind::Bus)
     }
 
+    /// Lanes that cars, trucks and buses can use
+    #[inline]
+    pub fn cars(self) -> bool {
+        matches!(self, LaneKind::Driving | LaneKind::Bus)
+    }
+
     #[inline]
     pub fn needs_light(self) -> bool {
         matches!(self, LaneKind::Driving | LaneKind::Biking | LaneKind::Bus)
@@ -46,8 +52,9 @@ impl LaneKind {
     #[inline]
     pub const fn width(self) -> f32 {
         match self {
-            LaneKind::Driving | LaneKind::Biking | LaneKind::Bus => 4.0,
+            LaneKind::Driving | LaneKind::Bus => 4.0,
             LaneKind::Parking => 2.5,
+            LaneKind::Biking => 2.0,
             LaneKind::Walking => 3.0,
             LaneKind::Rail => 5.3,
         }
@@ -112,6 +119,7 @@ pub struct LanePatternBuilder {
     pub parking: bool,
     pub one_way: bool,
     pub rail: bool,
+    pub bike_lanes: bool,
 }
 impl Eq for LanePatternBuilder {}
 
@@ -130,6 +138,7 @@ impl LanePatternBuilder {
             parking: true,
             one_way: false,
             rail: false,
+            bike_lanes: false,
         }
     }
 
@@ -163,6 +172,11 @@ impl LanePatternBuilder {
         self
     }
 
+    pub const fn bike_lanes(mut self, bike_lanes: bool) -> Self {
+        self.bike_lanes = bike_lanes;
+        self
+    }
+
     pub fn width(self) -> f32 {
         if self.rail {
             let wayf = if self.one_way { 1.0 } else { 2.0 };
@@ -177,6 +191,9 @@ impl LanePatternBuilder {
         if self.parking {
             w += LaneKind::Parking.width() * wayf;
         }
+        if self.bike_lanes {
+            w += LaneKind::Biking.width() * wayf;
+        }
         w += self.n_lanes as f32 * wayf * LaneKind::Driving.width();
         w + 0.5
     }
@@ -202,6 +219,14 @@ impl LanePatternBuilder {
             forward.push(LaneKind::Parking);
         }
 
+        // bike lanes are between the parking and the sidewalk so that cyclists don't cross the cars
+        if self.bike_lanes {
+            if !self.one_way {
+                backward.push(LaneKind::Biking);
+            }
+            forward.push(LaneKind::Biking);
+        }
+
         if self.sidewalks {
             backward.push(LaneKind::Walking);
             forward.push(LaneKind::Walking);
@@ -106,22 +106,46 @@ impl Road {
         #[allow(clippy::indexing_slicing)]
         let road = &mut roads[id];
 
+        road.set_pattern(lane_pattern, lanes, parking, env);
+
+        spatial.insert(road);
+        road.id
+    }
+
+    /// Replaces the lanes of the road by the ones of the pattern, the road itself is kept as is.
+    /// Parking spots are reused where the new parking lanes are at the same place.
+    /// Intersections at both ends must be invalidated afterward as turns refer to the old lanes.
+    pub fn set_pattern(
+        &mut self,
+        lane_pattern: &LanePattern,
+        lanes: &mut Lanes,
+        parking: &mut ParkingSpots,
+        env: &Environment,
+    ) {
+        for (id, _) in self
+            .lanes_forward
+            .drain(..)
+            .chain(self.lanes_backward.drain(..))
+        {
+            parking.remove_to_reuse(id);
+            lanes.remove(id);
+        }
+
+        self.width = lane_pattern.width();
+
         let mut dist_from_bottom = 0.0;
         for (lane_k, dir, limit) in lane_pattern.lanes() {
-            let id = Lane::make(road, lanes, lane_k, limit, dir, dist_from_bottom);
+            let id = Lane::make(self, lanes, lane_k, limit, dir, dist_from_bottom);
 
             match dir {
-                LaneDirection::Forward => road.lanes_forward.insert(0, (id, lane_k)),
-                LaneDirection::Backward => road.lanes_backward.push((id, lane_k)),
+                LaneDirection::Forward => self.lanes_forward.insert(0, (id, lane_k)),
+                LaneDirection::Backward => self.lanes_backward.push((id, lane_k)),
             }
 
             dist_from_bottom += lane_k.width();
         }
 
-        road.update_lanes(lanes, parking, env);
-
-        spatial.insert(road);
-        road.id
+        self.update_lanes(lanes, parking, env);
     }
 
     pub fn is_one_way(&self) -> bool {
@@ -30,6 +30,7 @@ pub enum TurnKind {
     Crosswalk,
     WalkingCorner,
     Driving,
+    Biking,
     Rail,
 }
 
@@ -107,8 +108,10 @@ impl Turn {
         let src_dir = -src_lane.orientation_from(self.id.parent);
         let dst_dir = dst_lane.orientation_from(self.id.parent);
 
-        if matches!(self.kind, TurnKind::Driving | TurnKind::WalkingCorner)
-            && parent.is_roundabout()
+        if matches!(
+            self.kind,
+            TurnKind::Driving | TurnKind::Biking | TurnKind::WalkingCorner
+        ) && parent.is_roundabout()
         {
             if let Some(rp) = parent.turn_policy.roundabout {
                 let center = parent.pos.xy();
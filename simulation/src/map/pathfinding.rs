@@ -25,6 +25,7 @@ pub trait Pathfinder {
 pub enum PathKind {
     Pedestrian,
     Vehicle,
+    Bike,
     Rail,
 }
 
@@ -39,6 +40,7 @@ impl Pathfinder for PathKind {
         match self {
             PathKind::Pedestrian => PedestrianPath.path(map, tick, start, end),
             PathKind::Vehicle => CarPath.path(map, tick, start, end),
+            PathKind::Bike => BikePath.path(map, tick, start, end),
             PathKind::Rail => RailPath.path(map, tick, start, end),
         }
     }
@@ -47,6 +49,7 @@ impl Pathfinder for PathKind {
         match self {
             PathKind::Pedestrian => PedestrianPath.nearest_lane(map, pos),
             PathKind::Vehicle => CarPath.nearest_lane(map, pos),
+            PathKind::Bike => BikePath.nearest_lane(map, pos),
             PathKind::Rail => RailPath.nearest_lane(map, pos),
         }
     }
@@ -55,6 +58,7 @@ impl Pathfinder for PathKind {
         match self {
             PathKind::Pedestrian => PedestrianPath.local_route(map, lane, start, end),
             PathKind::Vehicle => CarPath.local_route(map, lane, start, end),
+            PathKind::Bike => BikePath.local_route(map, lane, start, end),
             PathKind::Rail => RailPath.local_route(map, lane, start, end),
         }
     }
@@ -63,6 +67,7 @@ impl Pathfinder for PathKind {
         match self {
             PathKind::Pedestrian => PedestrianPath.authorized_lane(kind),
             PathKind::Vehicle => CarPath.authorized_lane(kind),
+            PathKind::Bike => BikePath.authorized_lane(kind),
             PathKind::Rail => RailPath.authorized_lane(kind),
         }
     }
@@ -170,7 +175,7 @@ impl Pathfinder for RailPath {
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
-        CarPath.path(map, tick, start, end)
+        lane_path(self, map, tick, start, end)
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
@@ -186,9 +191,9 @@ impl Pathfinder for RailPath {
     }
 }
 
-struct CarPath;
+struct BikePath;
 
-impl Pathfinder for CarPath {
+impl Pathfinder for BikePath {
     fn path(
         &self,
         map: &Map,
@@ -196,76 +201,34 @@ impl Pathfinder for CarPath {
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
-        let inters = &map.intersections;
-        let lanes = &map.lanes;
-
-        let start_lane = start.destination_lane();
-
-        let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
-
-        let dummy = LaneID::null();
-
-        const HEURISTIC_SPEED: f32 = LanePatternBuilder::new().speed_limit;
-
-        let heuristic = |&p: &LaneID| {
-            let pos = unwrap_ret!(
-                inters.get(unwrap_ret!(lanes.get(p), OrderedFloat(f32::INFINITY)).dst),
-                OrderedFloat(f32::INFINITY)
-            )
-            .pos;
-            OrderedFloat(pos.distance(end_pos) * 1.2 / HEURISTIC_SPEED) // Inexact but (much) faster
-        };
-
-        let base_random = hash_u64((start_lane.data().as_ffi(), tick.0)) as u32;
-
-        let successors = move |&p: &LaneID| {
-            let l;
-            let p = if p == dummy {
-                l = lanes.get(start_lane);
-                start_lane
-            } else {
-                l = lanes.get(p);
-                p
-            };
-            l.and_then(move |x| inters.get(x.dst))
-                .into_iter()
-                .flat_map(move |inter| {
-                    inter.turns_from(p).map(move |(x, _)| {
-                        let mut cost = f32::INFINITY;
-
-                        if let Some(l) = lanes.get(x.dst) {
-                            cost = l.points.length() / l.speed_limit;
-                            cost += common::rand::randu(l.dist_from_bottom.to_bits() ^ base_random);
-                        }
-
-                        (x.dst, OrderedFloat(cost))
-                    })
-                })
-        };
+        lane_path(self, map, tick, start, end)
+    }
 
-        let (v, _) =
-            pathfinding::directed::astar::astar(&dummy, successors, heuristic, |p| *p == end)?;
+    fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
+        map.nearest_lane(pos, LaneKind::Biking, Some(20.0))
+            .or_else(|| map.nearest_lane(pos, LaneKind::Driving, None))
+    }
 
-        let mut path = Vec::with_capacity(v.len() * 2);
-        path.push(start);
+    fn local_route(&self, map: &Map, lane: LaneID, start: Vec3, end: Vec3) -> Option<PolyLine3> {
+        CarPath.local_route(map, lane, start, end)
+    }
 
-        let mut last_id = start_lane;
+    fn authorized_lane(&self, kind: LaneKind) -> bool {
+        matches!(kind, LaneKind::Biking | LaneKind::Driving)
+    }
+}
 
-        for lane in v.into_iter().skip(1) {
-            let inter_end = &inters.get(lanes.get(lane)?.src)?;
-            let id = TurnID::new(inter_end.id, last_id, lane, false);
-            path.push(Traversable::new(
-                TraverseKind::Turn(id),
-                TraverseDirection::Forward,
-            ));
-            path.push(Traversable::new(
-                TraverseKind::Lane(lane),
-                TraverseDirection::Forward,
-            ));
+struct CarPath;
 
-            last_id = lane;
-        }
-        Some(path)
+impl Pathfinder for CarPath {
+    fn path(
+        &self,
+        map: &Map,
+        tick: Tick,
+        start: Traversable,
+        end: LaneID,
+    ) -> Option<Vec<Traversable>> {
+        lane_path(self, map, tick, start, end)
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
@@ -297,3 +260,83 @@ impl Pathfinder for CarPath {
         matches!(kind, LaneKind::Driving | LaneKind::Bus)
     }
 }
+
+/// A* over the lanes and turns, restricted to the lanes the pathfinder is authorized on
+fn lane_path(
+    pathfinder: &impl Pathfinder,
+    map: &Map,
+    tick: Tick,
+    start: Traversable,
+    end: LaneID,
+) -> Option<Vec<Traversable>> {
+    let inters = &map.intersections;
+    let lanes = &map.lanes;
+
+    let start_lane = start.destination_lane();
+
+    let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
+
+    let dummy = LaneID::null();
+
+    const HEURISTIC_SPEED: f32 = LanePatternBuilder::new().speed_limit;
+
+    let heuristic = |&p: &LaneID| {
+        let pos = unwrap_ret!(
+            inters.get(unwrap_ret!(lanes.get(p), OrderedFloat(f32::INFINITY)).dst),
+            OrderedFloat(f32::INFINITY)
+        )
+        .pos;
+        OrderedFloat(pos.distance(end_pos) * 1.2 / HEURISTIC_SPEED) // Inexact but (much) faster
+    };
+
+    let base_random = hash_u64((start_lane.data().as_ffi(), tick.0)) as u32;
+
+    let successors = move |&p: &LaneID| {
+        let l;
+        let p = if p == dummy {
+            l = lanes.get(start_lane);
+            start_lane
+        } else {
+            l = lanes.get(p);
+            p
+        };
+        l.and_then(move |x| inters.get(x.dst))
+            .into_iter()
+            .flat_map(move |inter| {
+                inter.turns_from(p).filter_map(move |(x, _)| {
+                    let l = lanes.get(x.dst)?;
+                    if !pathfinder.authorized_lane(l.kind) {
+                        return None;
+                    }
+
+                    let mut cost = l.points.length() / l.speed_limit;
+                    cost += common::rand::randu(l.dist_from_bottom.to_bits() ^ base_random);
+
+                    Some((x.dst, OrderedFloat(cost)))
+                })
+            })
+    };
+
+    let (v, _) = pathfinding::directed::astar::astar(&dummy, successors, heuristic, |p| *p == end)?;
+
+    let mut path = Vec::with_capacity(v.len() * 2);
+    path.push(start);
+
+    let mut last_id = start_lane;
+
+    for lane in v.into_iter().skip(1) {
+        let inter_end = &inters.get(lanes.get(lane)?.src)?;
+        let id = TurnID::new(inter_end.id, last_id, lane, false);
+        path.push(Traversable::new(
+            TraverseKind::Turn(id),
+            TraverseDirection::Forward,
+        ));
+        path.push(Traversable::new(
+            TraverseKind::Lane(lane),
+            TraverseDirection::Forward,
+        ));
+
+        last_id = lane;
+    }
+    Some(path)
+}
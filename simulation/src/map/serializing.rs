@@ -1,3 +1,5 @@
+use common::saveload::{Bincode, Encoder};
+use common::FastMap;
 use serde::{Deserialize, Serialize};
 
 use crate::map::{
@@ -52,6 +54,60 @@ impl From<SerializedMap> for Map {
     }
 }
 
+/// The map without its environment, which is split in blobs of its own
+#[derive(Serialize)]
+struct SerializedMapRef<'a> {
+    roads: &'a Roads,
+    intersections: &'a Intersections,
+    buildings: &'a Buildings,
+    lanes: &'a Lanes,
+    parking: &'a ParkingSpots,
+    lots: &'a Lots,
+    external_train_stations: &'a Vec<BuildingID>,
+}
+
+#[derive(Deserialize)]
+struct SerializedMapNoEnv {
+    roads: Roads,
+    intersections: Intersections,
+    buildings: Buildings,
+    lanes: Lanes,
+    parking: ParkingSpots,
+    lots: Lots,
+    external_train_stations: Vec<BuildingID>,
+}
+
+impl Map {
+    pub(crate) fn to_blobs(&self, blobs: &mut Vec<(String, Vec<u8>)>) -> std::io::Result<()> {
+        let m = SerializedMapRef {
+            roads: &self.roads,
+            intersections: &self.intersections,
+            buildings: &self.buildings,
+            lanes: &self.lanes,
+            parking: &self.parking,
+            lots: &self.lots,
+            external_train_stations: &self.external_train_stations,
+        };
+        blobs.push(("map".to_string(), Bincode::encode(&m)?));
+        self.environment.to_blobs(blobs)
+    }
+
+    pub(crate) fn from_blobs(blobs: &mut FastMap<String, Vec<u8>>) -> Option<Self> {
+        let m: SerializedMapNoEnv = Bincode::decode(&blobs.remove("map")?).ok()?;
+        let environment = Environment::from_blobs(blobs)?;
+        Some(Map::from(SerializedMap {
+            roads: m.roads,
+            intersections: m.intersections,
+            buildings: m.buildings,
+            lanes: m.lanes,
+            parking: m.parking,
+            lots: m.lots,
+            environment,
+            external_train_stations: m.external_train_stations,
+        }))
+    }
+}
+
 fn mk_spatial_map(m: &SerializedMap) -> SpatialMap {
     let mut sm = SpatialMap::default();
     for b in m.buildings.values() {
@@ -5,7 +5,8 @@ use flat_spatial::Grid;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 
-use common::FastSet;
+use common::saveload::{Bincode, Encoder};
+use common::{FastMap, FastSet};
 use egui_inspect::egui::ahash::HashSetExt;
 use geom::{lerp, pack_height, vec2, Intersect, Radians, Ray3, Vec2, Vec3, AABB};
 use prototypes::{Tick, DELTA};
@@ -118,6 +119,26 @@ impl Environment {
         self.heightmap.get_chunk((id.0 as u16, id.1 as u16))
     }
 
+    pub(crate) fn set_chunk(&mut self, id: TerrainChunkID, chunk: Chunk) {
+        self.heightmap.set_chunk((id.0 as u16, id.1 as u16), chunk);
+    }
+
+    /// The chunks a terraform around this position may modify, smoothing reads the neighboring cells
+    pub(crate) fn terraform_snapshot(
+        &self,
+        center: Vec2,
+        radius: f32,
+    ) -> Vec<(TerrainChunkID, Chunk)> {
+        let bbox = AABB::centered(center, Vec2::splat(radius * 2.0 + CELL_SIZE * 2.0));
+        self.heightmap
+            .covered_chunks(bbox)
+            .filter_map(|(x, y)| {
+                let chunk = self.heightmap.get_chunk((x, y))?;
+                Some((TerrainChunkID::new_i16(x as i16, y as i16), chunk.clone()))
+            })
+            .collect()
+    }
+
     pub fn bounds(&self) -> AABB {
         self.heightmap.bounds()
     }
@@ -375,16 +396,21 @@ impl From<SerializedEnvironment> for Environment {
 
 impl From<&Environment> for SerializedEnvironment {
     fn from(ter: &Environment) -> Self {
-        let mut t = SerializedEnvironment {
+        SerializedEnvironment {
             h: ter.heightmap.clone(),
-            trees: Vec::new(),
-        };
+            trees: ter.serialized_trees(),
+        }
+    }
+}
 
-        let tree_cells = &ter.trees.storage().cells;
+impl Environment {
+    fn serialized_trees(&self) -> Vec<(CellIdx, Vec<SmolTree>)> {
+        let tree_cells = &self.trees.storage().cells;
 
         let mut keys = tree_cells.keys().copied().collect::<Vec<_>>();
         keys.sort_unstable();
 
+        let mut trees = Vec::with_capacity(keys.len());
         for cell_id in keys {
             let chunk = &tree_cells[&cell_id];
             let mut smoltrees = Vec::with_capacity(chunk.objs.len());
@@ -392,9 +418,36 @@ impl From<&Environment> for SerializedEnvironment {
                 let smol = encode_pos(*tree_pos, cell_id);
                 smoltrees.push(smol);
             }
-            t.trees.push((cell_id, smoltrees));
+            trees.push((cell_id, smoltrees));
+        }
+        trees
+    }
+
+    /// Each heightmap chunk is its own blob, most of them are never terraformed
+    pub(crate) fn to_blobs(&self, blobs: &mut Vec<(String, Vec<u8>)>) -> std::io::Result<()> {
+        let ser = SerializedEnvironment {
+            h: Heightmap::new(0, 0),
+            trees: self.serialized_trees(),
+        };
+        let (w, h) = (self.heightmap.w, self.heightmap.h);
+        blobs.push(("environment".to_string(), Bincode::encode(&(w, h, ser))?));
+        for ((x, y), chunk) in self.heightmap.chunks() {
+            blobs.push((format!("terrain/{}/{}", x, y), Bincode::encode(chunk)?));
         }
+        Ok(())
+    }
 
-        t
+    pub(crate) fn from_blobs(blobs: &mut FastMap<String, Vec<u8>>) -> Option<Self> {
+        let (w, h, mut ser): (u16, u16, SerializedEnvironment) =
+            Bincode::decode(&blobs.remove("environment")?).ok()?;
+        ser.h = Heightmap::new(w, h);
+        for y in 0..h {
+            for x in 0..w {
+                let chunk: Chunk =
+                    Bincode::decode(&blobs.remove(&format!("terrain/{}/{}", x, y))?).ok()?;
+                ser.h.set_chunk((x, y), chunk);
+            }
+        }
+        Some(ser.into())
     }
 }
@@ -1,5 +1,5 @@
 use crate::map::{Intersection, IntersectionID, LaneID, LaneKind, Lanes, Roads, TurnID, TurnKind};
-use egui_inspect::{Inspect, OptionDefault};
+use egui_inspect::{egui, egui::Ui, Inspect, InspectArgs, OptionDefault};
 use geom::{vec2, Vec2};
 use serde::{Deserialize, Serialize};
 use std::iter::{Extend, Iterator};
@@ -16,6 +16,25 @@ impl Default for RoundaboutPolicy {
     }
 }
 
+/// How the roads meet at an intersection.
+/// Ramps and merges are grade-separated: lanes are connected one to one,
+/// with lanes being dropped or added on the right, and no traffic control.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JunctionKind {
+    #[default]
+    AtGrade,
+    /// One road diverges into several (off-ramp)
+    Ramp,
+    /// Several roads merge into one (on-ramp)
+    Merge,
+}
+
+impl JunctionKind {
+    pub fn is_grade_separated(self) -> bool {
+        !matches!(self, JunctionKind::AtGrade)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Inspect)]
 pub struct TurnPolicy {
     pub back_turns: bool,
@@ -23,6 +42,8 @@ pub struct TurnPolicy {
     pub crosswalks: bool,
     #[inspect(proxy_type = "OptionDefault")]
     pub roundabout: Option<RoundaboutPolicy>,
+    #[serde(default)]
+    pub junction: JunctionKind,
 }
 
 impl Default for TurnPolicy {
@@ -32,18 +53,36 @@ impl Default for TurnPolicy {
             left_turns: true,
             crosswalks: true,
             roundabout: None,
+            junction: JunctionKind::AtGrade,
         }
     }
 }
 
-fn filter_vehicles(x: &[(LaneID, LaneKind)]) -> Vec<LaneID> {
+fn filter_cars(x: &[(LaneID, LaneKind)]) -> Vec<LaneID> {
     x.iter()
-        .filter(|(_, kind)| kind.vehicles())
+        .filter(|(_, kind)| kind.cars())
         .map(|(id, _)| id)
         .copied()
         .collect::<Vec<_>>()
 }
 
+/// Bikes use the bike lanes when there are some, and share the outermost driving lane otherwise
+fn filter_bikes(x: &[(LaneID, LaneKind)]) -> Vec<(LaneID, LaneKind)> {
+    let bikes: Vec<_> = x
+        .iter()
+        .filter(|(_, kind)| matches!(kind, LaneKind::Biking))
+        .copied()
+        .collect();
+    if !bikes.is_empty() {
+        return bikes;
+    }
+    x.iter()
+        .rfind(|(_, kind)| matches!(kind, LaneKind::Driving))
+        .copied()
+        .into_iter()
+        .collect()
+}
+
 fn filter_rail(x: &[(LaneID, LaneKind)]) -> Vec<LaneID> {
     x.iter()
         .filter(|(_, kind)| kind.is_rail())
@@ -97,6 +136,69 @@ impl TurnPolicy {
         }
     }
 
+    /// Connects the lanes left-aligned: extra incoming lanes are dropped into the rightmost
+    /// outgoing lane, and extra outgoing lanes are fed by the rightmost incoming lane.
+    fn lane_wise(
+        inter_id: IntersectionID,
+        incoming: &[LaneID],
+        outgoing: &[LaneID],
+        turns: &mut Vec<(TurnID, TurnKind)>,
+    ) {
+        let (Some(&last_in), Some(&last_out)) = (incoming.last(), outgoing.last()) else {
+            return;
+        };
+        for i in 0..incoming.len().max(outgoing.len()) {
+            let src = incoming.get(i).copied().unwrap_or(last_in);
+            let dst = outgoing.get(i).copied().unwrap_or(last_out);
+            turns.push((TurnID::new(inter_id, src, dst, false), TurnKind::Driving));
+        }
+    }
+
+    /// Ramps connect each incoming road to the outgoing lanes of all the other roads,
+    /// ordered left to right as seen by the driver.
+    /// Merges connect the incoming lanes of all the other roads to each outgoing road.
+    fn generate_junction_turns(
+        self,
+        inter: &Intersection,
+        roads: &Roads,
+        turns: &mut Vec<(TurnID, TurnKind)>,
+    ) {
+        let n_roads = inter.roads.len();
+
+        for (i, road) in inter.roads.iter().enumerate() {
+            let r = unwrap_cont!(roads.get(*road));
+
+            // roads are sorted counter-clockwise, so going clockwise from the road we arrive on
+            // goes from left to right, and going counter-clockwise from the road we leave on
+            // also goes from left to right.
+            let others = (1..n_roads).flat_map(|k| {
+                let j = match self.junction {
+                    JunctionKind::Merge => (i + k) % n_roads,
+                    _ => (i + n_roads - k) % n_roads,
+                };
+                inter.roads.get(j).and_then(|&o| roads.get(o))
+            });
+
+            match self.junction {
+                JunctionKind::Ramp => {
+                    let incoming = filter_cars(r.incoming_lanes_to(inter.id));
+                    let outgoing: Vec<_> = others
+                        .flat_map(|o| filter_cars(o.outgoing_lanes_from(inter.id)))
+                        .collect();
+                    Self::lane_wise(inter.id, &incoming, &outgoing, turns);
+                }
+                JunctionKind::Merge => {
+                    let incoming: Vec<_> = others
+                        .flat_map(|o| filter_cars(o.incoming_lanes_to(inter.id)))
+                        .collect();
+                    let outgoing = filter_cars(r.outgoing_lanes_from(inter.id));
+                    Self::lane_wise(inter.id, &incoming, &outgoing, turns);
+                }
+                JunctionKind::AtGrade => {}
+            }
+        }
+    }
+
     pub fn generate_vehicle_turns(
         self,
         inter: &Intersection,
@@ -104,13 +206,18 @@ impl TurnPolicy {
         roads: &Roads,
         turns: &mut Vec<(TurnID, TurnKind)>,
     ) {
+        if self.junction.is_grade_separated() && inter.roads.len() > 1 {
+            self.generate_junction_turns(inter, roads, turns);
+            return;
+        }
+
         match inter.roads.as_slice() {
             [road_id] => {
                 let road = unwrap_ret!(roads.get(*road_id));
                 turns.extend(Self::zip_on_same_length(
                     inter.id,
-                    &filter_vehicles(road.incoming_lanes_to(inter.id)),
-                    &filter_vehicles(road.outgoing_lanes_from(inter.id)),
+                    &filter_cars(road.incoming_lanes_to(inter.id)),
+                    &filter_cars(road.outgoing_lanes_from(inter.id)),
                     TurnKind::Driving,
                 ));
                 return;
@@ -119,11 +226,11 @@ impl TurnPolicy {
                 let road1 = unwrap_ret!(roads.get(*road1));
                 let road2 = unwrap_ret!(roads.get(*road2));
 
-                let incoming_road1 = filter_vehicles(road1.incoming_lanes_to(inter.id));
-                let incoming_road2 = filter_vehicles(road2.incoming_lanes_to(inter.id));
+                let incoming_road1 = filter_cars(road1.incoming_lanes_to(inter.id));
+                let incoming_road2 = filter_cars(road2.incoming_lanes_to(inter.id));
 
-                let outgoing_road1 = filter_vehicles(road1.outgoing_lanes_from(inter.id));
-                let outgoing_road2 = filter_vehicles(road2.outgoing_lanes_from(inter.id));
+                let outgoing_road1 = filter_cars(road1.outgoing_lanes_from(inter.id));
+                let outgoing_road2 = filter_cars(road2.outgoing_lanes_from(inter.id));
 
                 turns.extend(Self::zip_on_same_length(
                     inter.id,
@@ -172,7 +279,7 @@ impl TurnPolicy {
                 let r2 = unwrap_cont!(roads.get(*road2));
                 for (incoming, incoming_kind) in r1.incoming_lanes_to(inter.id) {
                     for (outgoing, outgoing_kind) in r2.outgoing_lanes_from(inter.id) {
-                        if !incoming_kind.vehicles() || !outgoing_kind.vehicles() {
+                        if !incoming_kind.cars() || !outgoing_kind.cars() {
                             continue;
                         }
 
@@ -197,6 +304,41 @@ impl TurnPolicy {
         }
     }
 
+    /// Turns between two driving lanes are already generated for cars,
+    /// so only the turns going to or from a bike lane are generated here.
+    pub fn generate_bike_turns(
+        self,
+        inter: &Intersection,
+        roads: &Roads,
+        turns: &mut Vec<(TurnID, TurnKind)>,
+    ) {
+        let dead_end = inter.roads.len() == 1;
+
+        for road1 in &inter.roads {
+            for road2 in &inter.roads {
+                if road1 == road2 && !self.back_turns && !dead_end {
+                    continue;
+                }
+
+                let r1 = unwrap_cont!(roads.get(*road1));
+                let r2 = unwrap_cont!(roads.get(*road2));
+
+                let incoming = filter_bikes(r1.incoming_lanes_to(inter.id));
+                let outgoing = filter_bikes(r2.outgoing_lanes_from(inter.id));
+
+                for &(incoming, incoming_kind) in &incoming {
+                    for &(outgoing, outgoing_kind) in &outgoing {
+                        if incoming_kind != LaneKind::Biking && outgoing_kind != LaneKind::Biking {
+                            continue;
+                        }
+                        let id = TurnID::new(inter.id, incoming, outgoing, false);
+                        turns.push((id, TurnKind::Biking));
+                    }
+                }
+            }
+        }
+    }
+
     pub fn generate_walking_turns(
         self,
         inter: &Intersection,
@@ -221,7 +363,7 @@ impl TurnPolicy {
                     ));
                 }
 
-                if self.crosswalks && n_roads > 2 {
+                if self.crosswalks && n_roads > 2 && !self.junction.is_grade_separated() {
                     if let (Some(incoming), Some(outgoing_in)) = (a.incoming, a.outgoing) {
                         turns.push((
                             TurnID::new(inter.id, incoming, outgoing_in, true),
@@ -323,6 +465,7 @@ impl TurnPolicy {
         let mut turns = vec![];
 
         self.generate_vehicle_turns(inter, lanes, roads, &mut turns);
+        self.generate_bike_turns(inter, roads, &mut turns);
         self.generate_rail_turns(inter, lanes, roads, &mut turns);
 
         self.generate_walking_turns(inter, roads, &mut turns);
@@ -330,3 +473,38 @@ impl TurnPolicy {
         turns
     }
 }
+
+impl Inspect<JunctionKind> for JunctionKind {
+    fn render(data: &JunctionKind, label: &'static str, ui: &mut Ui, _: &InspectArgs) {
+        ui.label(format!("{data:?} {label}"));
+    }
+
+    fn render_mut(
+        data: &mut JunctionKind,
+        label: &'static str,
+        ui: &mut Ui,
+        _: &InspectArgs,
+    ) -> bool {
+        let mut id = match data {
+            JunctionKind::AtGrade => 0,
+            JunctionKind::Ramp => 1,
+            JunctionKind::Merge => 2,
+        };
+
+        let get = |i| match i {
+            0 => JunctionKind::AtGrade,
+            1 => JunctionKind::Ramp,
+            2 => JunctionKind::Merge,
+            _ => unreachable!(),
+        };
+
+        let changed = egui::ComboBox::from_label(label)
+            .show_index(ui, &mut id, 3, |i| format!("{:?}", get(i)))
+            .changed();
+        if changed {
+            *data = get(id);
+        }
+
+        changed
+    }
+}
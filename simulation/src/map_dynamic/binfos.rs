@@ -8,6 +8,8 @@ use std::collections::BTreeMap;
 pub struct BuildingInfo {
     pub owner: Option<SoulID>,
     pub inside: Vec<SoulID>,
+    /// The previous owner closed down, the building waits for demand to be reused
+    pub vacant: bool,
 }
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
@@ -35,11 +37,28 @@ impl BuildingInfos {
 
     pub fn set_owner(&mut self, building: BuildingID, soul: SoulID) {
         if let Some(x) = self.get_mut(building) {
-            x.owner = Some(soul)
+            x.owner = Some(soul);
+            x.vacant = false;
         }
         self.owners.insert(soul, building);
     }
 
+    pub fn remove_owner(&mut self, building: BuildingID) {
+        let Some(x) = self.get_mut(building) else {
+            return;
+        };
+        if let Some(owner) = x.owner.take() {
+            self.owners.remove(&owner);
+        }
+    }
+
+    pub fn set_vacant(&mut self, building: BuildingID) {
+        self.remove_owner(building);
+        if let Some(x) = self.get_mut(building) {
+            x.vacant = true;
+        }
+    }
+
     pub fn owner(&self, building: BuildingID) -> Option<SoulID> {
         self.assignment.get(building).and_then(|x| x.owner)
     }
@@ -1,9 +1,14 @@
-use crate::map::{LaneID, LaneKind, TraverseDirection};
+use crate::economy::find_trade_place;
+use crate::map::{BuildingID, LaneID, LaneKind, TraverseDirection};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::desire::WorkKind;
+use crate::transportation::VehicleKind;
 use crate::utils::resources::Resources;
 use crate::world::{TrainID, VehicleID};
 use crate::{Map, World};
 use derive_more::From;
-use geom::Vec3;
+use geom::{Vec2, Vec3};
+use prototypes::ItemID;
 use serde::{Deserialize, Serialize};
 use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet};
@@ -21,6 +26,7 @@ const PRECISION_RADIUS_2: f32 = PRECISION_RADIUS * PRECISION_RADIUS;
 #[derive(Default, Serialize, Deserialize)]
 pub struct Dispatcher {
     dispatches: BTreeMap<DispatchKind, DispatchOne>,
+    pub fleet: FleetStats,
 }
 
 #[derive(Debug, Copy, Clone, PartialOrd, Ord, Eq, PartialEq, Serialize, Deserialize, From)]
@@ -114,6 +120,17 @@ impl Dispatcher {
         disp.reserved_by.remove(&ent);
     }
 
+    /// Reserves a specific entity so that it is not returned by queries until it is freed
+    pub fn reserve(&mut self, ent: impl Into<DispatchID>) {
+        let ent: DispatchID = ent.into();
+        let kind: DispatchKind = ent.into();
+        self.dispatches
+            .entry(kind)
+            .or_insert_with(|| DispatchOne::new(kind.lane_kind()))
+            .reserved_by
+            .insert(ent);
+    }
+
     pub fn unregister(&mut self, id: DispatchID) {
         let kind = id.into();
         let Some(disp) = self.dispatches.get_mut(&kind) else {
@@ -288,12 +305,273 @@ impl DispatchOne {
     }
 }
 
+/// A stop of a delivery tour
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TourStop {
+    pub building: BuildingID,
+    pub item: ItemID,
+    pub qty: u32,
+    pub delivered: bool,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TourState {
+    /// The truck is waiting at the depot
+    #[default]
+    Pending,
+    /// The truck is on the road
+    Started,
+    /// The truck is back at the depot
+    Finished,
+}
+
+/// A delivery tour of a truck, leaving from and coming back to its depot
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Tour {
+    pub stops: Vec<TourStop>,
+    pub state: TourState,
+}
+
+impl Tour {
+    /// Whether the truck can be given a new tour
+    pub fn is_idle(&self) -> bool {
+        match self.state {
+            TourState::Pending => self.stops.is_empty(),
+            TourState::Started => false,
+            TourState::Finished => true,
+        }
+    }
+
+    pub fn load(&self) -> u32 {
+        self.stops.iter().map(|s| s.qty).sum()
+    }
+
+    pub fn n_delivered(&self) -> usize {
+        self.stops.iter().filter(|s| s.delivered).count()
+    }
+
+    /// Returns the item and quantity delivered at the building, if it was a stop of the tour
+    pub fn mark_delivered(&mut self, building: BuildingID) -> Option<(ItemID, u32)> {
+        let stop = self
+            .stops
+            .iter_mut()
+            .find(|s| s.building == building && !s.delivered)?;
+        stop.delivered = true;
+        Some((stop.item, stop.qty))
+    }
+}
+
+/// Statistics about the delivery trucks
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FleetStats {
+    pub tours: u64,
+    pub stops: u64,
+    pub delivered_stops: u64,
+    /// Quantity of goods loaded in the trucks
+    pub loaded: u64,
+    /// Quantity of goods the trucks could have loaded
+    pub capacity: u64,
+}
+
+impl FleetStats {
+    pub fn start_tour(&mut self, tour: &Tour, capacity: u32) {
+        self.tours += 1;
+        self.stops += tour.stops.len() as u64;
+        self.loaded += tour.load() as u64;
+        self.capacity += capacity.max(tour.load()) as u64;
+    }
+
+    pub fn finish_tour(&mut self, tour: &Tour) {
+        self.delivered_stops += tour.n_delivered() as u64;
+    }
+
+    /// Proportion of the trucks capacity that was used
+    pub fn load_factor(&self) -> f32 {
+        self.loaded as f32 / self.capacity.max(1) as f32
+    }
+
+    pub fn stops_per_tour(&self) -> f32 {
+        self.stops as f32 / self.tours.max(1) as f32
+    }
+}
+
+/// Plans a delivery tour leaving from and coming back to the depot, given the position
+/// and quantity of the pending deliveries, oldest first.
+/// The oldest delivery is always taken, then the nearest ones are added while the truck has room.
+/// The stops are ordered by nearest neighbour, then improved with 2-opt.
+/// Returns the indices of the chosen deliveries in visiting order.
+pub fn plan_tour(depot: Vec2, deliveries: &[(Vec2, u32)], capacity: u32) -> Vec<usize> {
+    let Some(&(first_pos, first_qty)) = deliveries.first() else {
+        return vec![];
+    };
+
+    // choose the deliveries
+    let mut chosen = vec![0];
+    let mut load = first_qty;
+    let mut last = first_pos;
+    let mut remaining: Vec<usize> = (1..deliveries.len()).collect();
+    loop {
+        let nearest = remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, &d)| load + deliveries[d].1 <= capacity)
+            .min_by(|(_, &a), (_, &b)| {
+                let da = deliveries[a].0.distance2(last);
+                let db = deliveries[b].0.distance2(last);
+                da.total_cmp(&db)
+            })
+            .map(|(i, &d)| (i, d));
+        let Some((i, next)) = nearest else {
+            break;
+        };
+        remaining.swap_remove(i);
+        chosen.push(next);
+        load += deliveries[next].1;
+        last = deliveries[next].0;
+    }
+
+    // order them by nearest neighbour
+    let mut order = Vec::with_capacity(chosen.len());
+    let mut cur = depot;
+    loop {
+        let nearest = chosen
+            .iter()
+            .enumerate()
+            .min_by(|(_, &a), (_, &b)| {
+                let da = deliveries[a].0.distance2(cur);
+                let db = deliveries[b].0.distance2(cur);
+                da.total_cmp(&db)
+            })
+            .map(|(i, _)| i);
+        let Some(i) = nearest else {
+            break;
+        };
+        let d = chosen.swap_remove(i);
+        cur = deliveries[d].0;
+        order.push(d);
+    }
+
+    // improve with 2-opt
+    let pos = |k: usize, order: &[usize]| {
+        if k == 0 || k == order.len() + 1 {
+            depot
+        } else {
+            deliveries[order[k - 1]].0
+        }
+    };
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for i in 1..order.len() {
+            for j in i + 1..=order.len() {
+                let before = pos(i - 1, &order).distance(pos(i, &order))
+                    + pos(j, &order).distance(pos(j + 1, &order));
+                let after = pos(i - 1, &order).distance(pos(j, &order))
+                    + pos(i, &order).distance(pos(j + 1, &order));
+                if after + 0.01 < before {
+                    order[i - 1..j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+
+    order
+}
+
+impl Dispatcher {
+    /// Gives a new delivery tour to the drivers whose truck is back at the depot.
+    /// The goods sold by their company are batched by [`plan_tour`],
+    /// what doesn't fit in the truck waits for the next tour.
+    fn dispatch_tours(&mut self, map: &Map, binfos: &BuildingInfos, world: &mut World) {
+        let capacity = VehicleKind::Truck
+            .prototype()
+            .map_or(1, |p| p.capacity.max(1));
+
+        for (_, c) in world.companies.iter_mut() {
+            let Some(driver) = c.comp.driver else {
+                continue;
+            };
+            let Some(depot) = map.buildings.get(c.comp.building).map(|b| b.door_pos.xy()) else {
+                continue;
+            };
+            let Some(w) = world.humans.get_mut(driver).and_then(|h| h.work.as_mut()) else {
+                continue;
+            };
+            let WorkKind::Driver { ref mut tour, .. } = w.kind else {
+                continue;
+            };
+            if !tour.is_idle() {
+                continue;
+            }
+            if tour.state == TourState::Finished {
+                self.fleet.finish_tour(tour);
+            } else if c.sold.0.is_empty() {
+                continue;
+            }
+
+            // deliveries oldest first
+            let mut deliveries = Vec::with_capacity(c.sold.0.len());
+            let mut positions = Vec::with_capacity(c.sold.0.len());
+            for trade in c.sold.0.drain(..) {
+                let Some(owner_build) = find_trade_place(trade.buyer, binfos) else {
+                    log::warn!("driver can't find the place to deliver for {:?}", &trade);
+                    continue;
+                };
+                let Some(pos) = map.buildings.get(owner_build).map(|b| b.door_pos.xy()) else {
+                    continue;
+                };
+                deliveries.push((trade, owner_build));
+                positions.push((pos, trade.qty.max(0) as u32));
+            }
+
+            let order = plan_tour(depot, &positions, capacity);
+
+            let mut next_tour = Tour::default();
+            for &i in &order {
+                let (trade, building) = deliveries[i];
+                match next_tour.stops.last_mut() {
+                    Some(stop) if stop.building == building && stop.item == trade.kind => {
+                        stop.qty += positions[i].1
+                    }
+                    _ => next_tour.stops.push(TourStop {
+                        building,
+                        item: trade.kind,
+                        qty: positions[i].1,
+                        delivered: false,
+                    }),
+                }
+            }
+
+            // put back what didn't fit in the truck
+            let mut taken = vec![false; deliveries.len()];
+            for &i in &order {
+                taken[i] = true;
+            }
+            c.sold.0.extend(
+                deliveries
+                    .iter()
+                    .zip(taken)
+                    .filter(|(_, taken)| !taken)
+                    .map(|((trade, _), _)| *trade),
+            );
+
+            if !next_tour.stops.is_empty() {
+                self.fleet.start_tour(&next_tour, capacity);
+            }
+            *tour = next_tour;
+        }
+    }
+}
+
 pub fn dispatch_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::dispatch");
 
     let mut dispatcher = resources.write::<Dispatcher>();
     let map = resources.read::<Map>();
+    let binfos = resources.read::<BuildingInfos>();
     dispatcher.update(&map, world);
+    dispatcher.dispatch_tours(&map, &binfos, world);
 }
 
 #[cfg(test)]
@@ -306,6 +584,21 @@ mod tests {
         DispatchID::FreightTrain(TrainID::from(KeyData::from_ffi(id)))
     }
 
+    #[test]
+    fn plan_tour_respects_capacity() {
+        let deliveries = [
+            (Vec2::new(100.0, 0.0), 5),
+            (Vec2::new(10.0, 0.0), 5),
+            (Vec2::new(50.0, 0.0), 5),
+            (Vec2::new(-100.0, 0.0), 5),
+        ];
+
+        // the oldest delivery is always taken, then its neighbours while there is room
+        assert_eq!(plan_tour(Vec2::ZERO, &deliveries, 15), vec![1, 2, 0]);
+        assert_eq!(plan_tour(Vec2::ZERO, &deliveries, 1), vec![0]);
+        assert!(plan_tour(Vec2::ZERO, &[], 10).is_empty());
+    }
+
     #[test]
     fn dispatch_one_register_one_works() {
         let mut disp = DispatchOne::new(LaneKind::Rail);
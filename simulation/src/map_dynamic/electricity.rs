@@ -1,5 +1,6 @@
 use crate::map::{BuildingKind, ElectricityNetworkID, Map};
 use crate::map_dynamic::BuildingInfos;
+use crate::scripting::{ScriptEvent, Scripts};
 use crate::utils::resources::Resources;
 use crate::{SoulID, World};
 use prototypes::Power;
@@ -46,8 +47,9 @@ pub fn electricity_flow_system(world: &mut World, resources: &mut Resources) {
     let map = resources.read::<Map>();
     let binfos = resources.read::<BuildingInfos>();
     let mut flow = resources.write::<ElectricityFlow>();
+    let mut scripts = resources.write::<Scripts>();
 
-    flow.flowmap.clear();
+    let previous = std::mem::take(&mut flow.flowmap);
 
     for network in map.electricity.networks.values() {
         let mut consumed_power: Power = Power::ZERO;
@@ -75,18 +77,31 @@ pub fn electricity_flow_system(world: &mut World, resources: &mut Resources) {
                     consumed_power += proto.power_consumption.unwrap_or(Power::ZERO) * productivity;
                     produced_power += proto.power_production.unwrap_or(Power::ZERO) * productivity;
                 }
+                BuildingKind::Leisure(id) => {
+                    let proto = id.prototype();
+                    consumed_power += proto.power_consumption.unwrap_or(Power::ZERO);
+                    produced_power += proto.power_production.unwrap_or(Power::ZERO);
+                }
                 BuildingKind::RailFreightStation(_) => {}
                 BuildingKind::TrainStation => {}
+                BuildingKind::TrainDepot => {}
                 BuildingKind::ExternalTrading => {}
             }
         }
 
+        let blackout = consumed_power > produced_power;
+        if blackout && !previous.get(&network.id).map_or(false, |f| f.blackout) {
+            scripts.push(ScriptEvent::Blackout {
+                network: network.id,
+            });
+        }
+
         flow.flowmap.insert(
             network.id,
             NetworkFlow {
                 consumed_power,
                 produced_power,
-                blackout: consumed_power > produced_power,
+                blackout,
             },
         );
     }
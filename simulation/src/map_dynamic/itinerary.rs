@@ -1,4 +1,4 @@
-use crate::map::{Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::map::{LaneID, Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
 use crate::utils::resources::Resources;
 use crate::world::TrainID;
 use crate::World;
@@ -92,6 +92,22 @@ impl Itinerary {
         }
     }
 
+    /// Forces the route to be recomputed if it goes through one of the given lanes,
+    /// for example because they were rebuilt
+    pub fn reroute_if_uses(&mut self, lanes: &[LaneID]) {
+        let ItineraryKind::Route(ref r, kind) = self.kind else {
+            return;
+        };
+        let uses = |t: &Traversable| match t.kind {
+            TraverseKind::Lane(id) => lanes.contains(&id),
+            TraverseKind::Turn(id) => lanes.contains(&id.src) || lanes.contains(&id.dst),
+        };
+        if !uses(&r.cur) && !r.reversed_route.iter().any(uses) {
+            return;
+        }
+        *self = Self::wait_for_reroute(kind, r.end_pos);
+    }
+
     pub fn route(
         tick: Tick,
         start: Vec3,
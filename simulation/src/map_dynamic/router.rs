@@ -1,7 +1,10 @@
 use crate::map::{BuildingID, Map, PathKind};
 use crate::map_dynamic::{Itinerary, ParkingManagement, ParkingReserveError, SpotReservation};
 use crate::transportation::TransportGrid;
-use crate::transportation::{put_pedestrian_in_transport_grid, unpark, Location, VehicleState};
+use crate::transportation::{
+    put_pedestrian_in_transport_grid, unpark, Location, VehicleState, MAX_CYCLING_DIST,
+    MIN_CYCLING_DIST,
+};
 use crate::utils::resources::Resources;
 use crate::world::{HumanEnt, HumanID, VehicleEnt, VehicleID};
 use crate::{ParCommandBuffer, World};
@@ -41,6 +44,7 @@ debug_inspect_impl!(Destination);
 #[derive(Debug, Serialize, Deserialize)]
 pub enum RoutingStep {
     WalkTo(Vec3),
+    RideTo(Vec3),
     DriveTo(VehicleID, Vec3),
     Park(VehicleID, Option<SpotReservation>),
     Unpark(VehicleID),
@@ -52,14 +56,56 @@ pub enum RoutingStep {
 
 debug_inspect_impl!(RoutingStep);
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransportMode {
+    Walk,
+    Bike,
+    Car,
+}
+
+/// Number of trips started with each transport mode since the start of the game
+#[derive(Default, Serialize, Deserialize)]
+pub struct ModalShare {
+    pub walk: u64,
+    pub bike: u64,
+    pub car: u64,
+}
+
+impl ModalShare {
+    pub fn record(&mut self, mode: TransportMode) {
+        match mode {
+            TransportMode::Walk => self.walk += 1,
+            TransportMode::Bike => self.bike += 1,
+            TransportMode::Car => self.car += 1,
+        }
+    }
+
+    /// Proportion of the trips made with the given mode, between 0 and 1
+    pub fn share(&self, mode: TransportMode) -> f32 {
+        let total = self.walk + self.bike + self.car;
+        if total == 0 {
+            return 0.0;
+        }
+        let n = match mode {
+            TransportMode::Walk => self.walk,
+            TransportMode::Bike => self.bike,
+            TransportMode::Car => self.car,
+        };
+        n as f32 / total as f32
+    }
+}
+
 pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::routing_changed_system");
     let map: &Map = &resources.read();
     let parking: &mut ParkingManagement = &mut resources.write();
+    let modal_share: &mut ModalShare = &mut resources.write();
 
     world.humans.values_mut().for_each(|h| {
         let router = &mut h.router;
         let loc = &h.location;
+        let pos = h.trans.pos;
+        let has_bicycle = h.bicycle.is_some();
         if router.cur_dest == router.target_dest {
             return;
         }
@@ -67,8 +113,16 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
 
         router.clear_steps(parking);
         match dest {
-            Destination::Outside(pos) => {
-                router.steps = match router.steps_to(pos, parking, map, loc, &world.vehicles) {
+            Destination::Outside(obj_pos) => {
+                router.steps = match router.steps_to(
+                    pos,
+                    obj_pos,
+                    has_bicycle,
+                    parking,
+                    map,
+                    loc,
+                    &world.vehicles,
+                ) {
                     Ok(x) => x,
                     Err(e) => {
                         router.last_error = Some(e);
@@ -92,7 +146,15 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
                     }
                 };
                 let door_pos = bobj.door_pos;
-                router.steps = match router.steps_to(door_pos, parking, map, loc, &world.vehicles) {
+                router.steps = match router.steps_to(
+                    pos,
+                    door_pos,
+                    has_bicycle,
+                    parking,
+                    map,
+                    loc,
+                    &world.vehicles,
+                ) {
                     Ok(x) => x,
                     Err(e) => {
                         router.last_error = Some(e);
@@ -105,6 +167,8 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
 
         router.cur_dest = router.target_dest;
 
+        modal_share.record(router.mode());
+
         router.steps.reverse();
     });
 }
@@ -141,7 +205,7 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
 
         if let Some(ref step) = h.router.cur_step {
             cur_step_over = match *step {
-                RoutingStep::WalkTo(_) => itin.has_ended(0.0),
+                RoutingStep::WalkTo(_) | RoutingStep::RideTo(_) => itin.has_ended(0.0),
                 RoutingStep::DriveTo(vehicle, _) => world
                     .vehicles
                     .get(vehicle)
@@ -166,6 +230,7 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
         if let Some(step) = h.router.steps.last() {
             next_step_ready = match *step {
                 RoutingStep::WalkTo(_) => true,
+                RoutingStep::RideTo(_) => true,
                 RoutingStep::DriveTo(_, _) => true,
                 RoutingStep::Park(_, _) => true,
                 RoutingStep::Unpark(_) => true,
@@ -190,11 +255,23 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
 
         h.router.cur_step = h.router.steps.pop();
 
+        if let Some(ref mut bicycle) = h.bicycle {
+            let riding = matches!(h.router.cur_step, Some(RoutingStep::RideTo(_)));
+            if bicycle.riding && !riding {
+                // get off the bike
+                h.speed.0 = h.speed.0.min(h.pedestrian.walking_speed);
+            }
+            bicycle.riding = riding;
+        }
+
         if let Some(ref mut next_step) = h.router.cur_step {
             match *next_step {
                 RoutingStep::WalkTo(obj) => {
                     h.it = Itinerary::wait_for_reroute(PathKind::Pedestrian, obj);
                 }
+                RoutingStep::RideTo(obj) => {
+                    h.it = Itinerary::wait_for_reroute(PathKind::Bike, obj);
+                }
                 RoutingStep::DriveTo(vehicle, obj) => {
                     let route = Itinerary::wait_for_reroute(PathKind::Vehicle, obj);
                     if let Some(x) = world.vehicles.get_mut(vehicle) {
@@ -332,9 +409,27 @@ impl Router {
         false
     }
 
+    /// The transport mode of the current trip
+    fn mode(&self) -> TransportMode {
+        let mut mode = TransportMode::Walk;
+        for step in self.steps.iter().chain(&self.cur_step) {
+            match step {
+                RoutingStep::DriveTo(..) => return TransportMode::Car,
+                RoutingStep::RideTo(_) => mode = TransportMode::Bike,
+                _ => {}
+            }
+        }
+        mode
+    }
+
+    /// Cycling is chosen for medium distances, or for long distances when there is no car.
+    /// Work vehicles are always driven.
+    #[allow(clippy::too_many_arguments)]
     fn steps_to(
         &mut self,
+        from: Vec3,
         obj: Vec3,
+        has_bicycle: bool,
         parking: &mut ParkingManagement,
         map: &Map,
         loc: &Location,
@@ -345,7 +440,16 @@ impl Router {
             steps.push(RoutingStep::GetOutBuilding(*cur_build));
         }
 
-        if let Some(car) = self.vehicle {
+        let dist = from.distance(obj);
+        let ride = has_bicycle
+            && !matches!(loc, Location::Vehicle(_))
+            && self.vehicle == self.personal_car
+            && dist >= MIN_CYCLING_DIST
+            && (dist <= MAX_CYCLING_DIST || self.vehicle.is_none());
+
+        if ride {
+            steps.push(RoutingStep::RideTo(obj));
+        } else if let Some(car) = self.vehicle {
             let spot_resa = parking
                 .reserve_near(obj, map)
                 .map_err(RouterError::ReservingParkingSpot)?;
@@ -0,0 +1,773 @@
+//! Mods can have a control.lua script reacting to gameplay events by issuing world commands:
+//! ```lua
+//! script.on("new_day", function(e)
+//!     storage.days = (storage.days or 0) + 1
+//!     if storage.days == 30 then
+//!         game.command({ TakeLoan = { principal = 10000, term_days = 60 } })
+//!     end
+//! end)
+//! ```
+//! Scripts run in a sandboxed Luau VM without access to files, the clock or randomness so that
+//! they stay deterministic in multiplayer. The `storage` table of each mod is the only state kept
+//! across ticks: the scripts are run again from their storage before each tick with events, as
+//! when the game is loaded or joined, so the locals they declare start from their initial value
+//! every tick. The tables reachable from the globals of a script are frozen once it ran, handlers
+//! cannot assign globals (`storage` itself included, change its fields instead) nor register
+//! handlers, and commands can only be issued from handlers.
+//! Do not rely on the iteration order of `pairs` on `storage`, it can change after loading.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use mlua::{Function, Lua, LuaOptions, LuaSerdeExt, StdLib, Table, Value, VmState};
+use serde::{Deserialize, Serialize, Serializer};
+
+use common::FastSet;
+use prototypes::{mod_scripts, GameTime, ItemID, Money, TICKS_PER_DAY};
+
+use crate::economy::Government;
+use crate::map::{BuildingID, BuildingKind, ElectricityNetworkID};
+use crate::utils::resources::Resources;
+use crate::world_command::{CommandSender, WorldCommand};
+use crate::{Simulation, SoulID, World};
+
+/// Number of interrupts a handler can trigger (at calls and loop iterations) before being stopped,
+/// so that a script stuck in a loop doesn't freeze the game
+const INTERRUPT_BUDGET: u32 = 1_000_000;
+
+/// Nested tables deeper than this are not saved
+const MAX_STORAGE_DEPTH: usize = 32;
+
+/// The gameplay events scripts can react to with `script.on(name, handler)`.
+/// The handler receives the fields of the event as a table.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "name", rename_all = "snake_case")]
+pub enum ScriptEvent {
+    /// The first tick of a new day
+    NewDay { day: i32 },
+    BuildingBuilt {
+        building: BuildingID,
+        kind: BuildingKind,
+    },
+    /// An electricity network started to consume more power than it produces
+    Blackout { network: ElectricityNetworkID },
+    Trade {
+        buyer: SoulID,
+        seller: SoulID,
+        /// Scripts get the name of the item
+        #[serde(serialize_with = "item_name")]
+        item: ItemID,
+        qty: i32,
+        /// Money gained by the government, for trades with the outside
+        money_delta: Money,
+    },
+}
+
+fn item_name<S: Serializer>(item: &ItemID, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&item.prototype().name)
+}
+
+impl ScriptEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptEvent::NewDay { .. } => "new_day",
+            ScriptEvent::BuildingBuilt { .. } => "building_built",
+            ScriptEvent::Blackout { .. } => "blackout",
+            ScriptEvent::Trade { .. } => "trade",
+        }
+    }
+}
+
+/// A Lua value that can be saved, tables are stored with their keys sorted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScriptValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Table(Vec<(ScriptValue, ScriptValue)>),
+}
+
+impl ScriptValue {
+    fn from_lua(v: Value, depth: usize) -> mlua::Result<Self> {
+        Ok(match v {
+            Value::Nil => ScriptValue::Nil,
+            Value::Boolean(b) => ScriptValue::Bool(b),
+            Value::Integer(i) => ScriptValue::Number(i as f64),
+            Value::Number(n) => ScriptValue::Number(n),
+            Value::String(s) => ScriptValue::String(s.to_str()?.to_string()),
+            Value::Table(t) if depth < MAX_STORAGE_DEPTH => {
+                let mut entries = Vec::new();
+                for kv in t.pairs::<Value, Value>() {
+                    let (k, v) = kv?;
+                    let k = Self::from_lua(k, depth + 1)?;
+                    let v = Self::from_lua(v, depth + 1)?;
+                    if k == ScriptValue::Nil || v == ScriptValue::Nil {
+                        // functions and the like can't be saved
+                        continue;
+                    }
+                    entries.push((k, v));
+                }
+                entries.sort_by(|(a, _), (b, _)| a.key_order(b));
+                ScriptValue::Table(entries)
+            }
+            _ => ScriptValue::Nil,
+        })
+    }
+
+    fn to_lua(&self, lua: &Lua) -> mlua::Result<Value> {
+        Ok(match self {
+            ScriptValue::Nil => Value::Nil,
+            ScriptValue::Bool(b) => Value::Boolean(*b),
+            ScriptValue::Number(n) => Value::Number(*n),
+            ScriptValue::String(s) => Value::String(lua.create_string(s)?),
+            ScriptValue::Table(entries) => {
+                let t = lua.create_table()?;
+                for (k, v) in entries {
+                    t.set(k.to_lua(lua)?, v.to_lua(lua)?)?;
+                }
+                Value::Table(t)
+            }
+        })
+    }
+
+    fn key_order(&self, other: &Self) -> std::cmp::Ordering {
+        let rank = |v: &ScriptValue| match v {
+            ScriptValue::Nil => 0,
+            ScriptValue::Bool(_) => 1,
+            ScriptValue::Number(_) => 2,
+            ScriptValue::String(_) => 3,
+            ScriptValue::Table(_) => 4,
+        };
+        match (self, other) {
+            (ScriptValue::Bool(a), ScriptValue::Bool(b)) => a.cmp(b),
+            (ScriptValue::Number(a), ScriptValue::Number(b)) => a.total_cmp(b),
+            (ScriptValue::String(a), ScriptValue::String(b)) => a.cmp(b),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+/// What the `game` functions can read and write while handlers run
+#[derive(Default)]
+struct ScriptContext {
+    tick: u64,
+    day: i32,
+    money: Money,
+    /// Commands issued while loading would be issued again each time the game is loaded
+    in_handler: bool,
+    commands: Vec<WorldCommand>,
+}
+
+/// The environment and the handlers of a mod are kept in the registry of the VM
+struct ModRuntime {
+    name: String,
+    source: String,
+}
+
+impl ModRuntime {
+    fn env_key(&self) -> String {
+        format!("env/{}", self.name)
+    }
+
+    fn handlers_key(name: &str) -> String {
+        format!("handlers/{}", name)
+    }
+}
+
+/// The Lua VM running the scripts, recreated from the scripts and the storages after loading
+struct ScriptRuntime {
+    lua: Lua,
+    mods: Vec<ModRuntime>,
+    ctx: Arc<Mutex<ScriptContext>>,
+    interrupts: Arc<AtomicU32>,
+}
+
+impl ScriptRuntime {
+    fn new(storages: &BTreeMap<String, ScriptValue>) -> mlua::Result<Self> {
+        let mut me = Self::sandboxed()?;
+
+        for script in mod_scripts() {
+            if let Err(e) = me.load_mod(&script.mod_name, &script.source, storages) {
+                log::error!("could not run the script of {}: {}", script.mod_name, e);
+            }
+        }
+
+        Ok(me)
+    }
+
+    /// The VM without any mod loaded
+    fn sandboxed() -> mlua::Result<Self> {
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8,
+            LuaOptions::new(),
+        )?;
+
+        // randomness would make the clients diverge
+        let math: Table = lua.globals().get("math")?;
+        math.set("random", Value::Nil)?;
+        math.set("randomseed", Value::Nil)?;
+
+        let interrupts = Arc::new(AtomicU32::new(0));
+        let counter = interrupts.clone();
+        lua.set_interrupt(move |_| {
+            if counter.fetch_add(1, Ordering::Relaxed) > INTERRUPT_BUDGET {
+                return Err(mlua::Error::RuntimeError("script ran for too long".into()));
+            }
+            Ok(VmState::Continue)
+        });
+
+        let ctx = Arc::new(Mutex::new(ScriptContext::default()));
+        let game = Self::game_api(&lua, &ctx)?;
+        lua.globals().set("game", game)?;
+        lua.sandbox(true)?;
+
+        Ok(Self {
+            lua,
+            mods: Vec::new(),
+            ctx,
+            interrupts,
+        })
+    }
+
+    fn game_api<'lua>(
+        lua: &'lua Lua,
+        ctx: &Arc<Mutex<ScriptContext>>,
+    ) -> mlua::Result<Table<'lua>> {
+        let game = lua.create_table()?;
+
+        let c = ctx.clone();
+        game.set(
+            "tick",
+            lua.create_function(move |_, ()| Ok(c.lock().unwrap().tick))?,
+        )?;
+        let c = ctx.clone();
+        game.set(
+            "day",
+            lua.create_function(move |_, ()| Ok(c.lock().unwrap().day))?,
+        )?;
+        let c = ctx.clone();
+        game.set(
+            "money",
+            lua.create_function(move |_, ()| Ok(c.lock().unwrap().money.bucks()))?,
+        )?;
+        let c = ctx.clone();
+        game.set(
+            "command",
+            lua.create_function(move |lua, v: Value| {
+                let command: WorldCommand = lua.from_value(v)?;
+                if matches!(command, WorldCommand::Init(_)) {
+                    return Err(mlua::Error::RuntimeError(
+                        "scripts cannot reset the world".into(),
+                    ));
+                }
+                let mut c = c.lock().unwrap();
+                if !c.in_handler {
+                    return Err(mlua::Error::RuntimeError(
+                        "commands can only be issued from event handlers".into(),
+                    ));
+                }
+                c.commands.push(command);
+                Ok(())
+            })?,
+        )?;
+        game.set(
+            "log",
+            lua.create_function(|_, msg: String| {
+                log::info!("script: {}", msg);
+                Ok(())
+            })?,
+        )?;
+
+        Ok(game)
+    }
+
+    fn load_mod(
+        &mut self,
+        name: &str,
+        source: &str,
+        storages: &BTreeMap<String, ScriptValue>,
+    ) -> mlua::Result<()> {
+        let m = ModRuntime {
+            name: name.to_string(),
+            source: source.to_string(),
+        };
+        self.run_mod(&m, storages)?;
+        self.mods.push(m);
+        Ok(())
+    }
+
+    /// Runs the scripts again from their storage, so the state they keep outside of it starts
+    /// from the same values on every machine, however long it has been playing
+    fn refresh(&self) {
+        let storages = self.storages();
+        for m in &self.mods {
+            if let Err(e) = self.run_mod(m, &storages) {
+                log::error!("could not run the script of {}: {}", m.name, e);
+            }
+        }
+    }
+
+    fn run_mod(
+        &self,
+        m: &ModRuntime,
+        storages: &BTreeMap<String, ScriptValue>,
+    ) -> mlua::Result<()> {
+        let lua = &self.lua;
+        let name = &m.name;
+
+        let storage = match storages.get(name) {
+            Some(v @ ScriptValue::Table(_)) => v.to_lua(lua)?,
+            _ => Value::Table(lua.create_table()?),
+        };
+        let handlers_key = ModRuntime::handlers_key(name);
+        lua.set_named_registry_value(&handlers_key, lua.create_table()?)?;
+
+        let script = lua.create_table()?;
+        let ctx = self.ctx.clone();
+        script.set(
+            "on",
+            lua.create_function(move |lua, (event, f): (String, Function)| {
+                if ctx.lock().unwrap().in_handler {
+                    return Err(mlua::Error::RuntimeError(
+                        "handlers can only be registered while loading".into(),
+                    ));
+                }
+                let handlers: Table = lua.named_registry_value(&handlers_key)?;
+                let list: Table = match handlers.get::<_, Option<Table>>(event.as_str())? {
+                    Some(list) => list,
+                    None => {
+                        let list = lua.create_table()?;
+                        handlers.set(event.as_str(), list.clone())?;
+                        list
+                    }
+                };
+                list.push(f)
+            })?,
+        )?;
+
+        let env = lua.create_table()?;
+        env.set("storage", storage.clone())?;
+        env.set("script", script)?;
+        let meta = lua.create_table()?;
+        meta.set("__index", lua.globals())?;
+        env.set_metatable(Some(meta));
+        // registered first so the storage is kept even if the script fails
+        lua.set_named_registry_value(&m.env_key(), env.clone())?;
+
+        self.interrupts.store(0, Ordering::Relaxed);
+        let ran = lua
+            .load(&m.source)
+            .set_name(format!("{}/control.lua", name))
+            .set_environment(env.clone())
+            .exec();
+
+        // what the script defined while running is defined again on the next run,
+        // anything the handlers would change there would be lost so they cannot
+        let writable = reachable_tables(storage)?
+            .iter()
+            .map(Table::to_pointer)
+            .collect::<FastSet<_>>();
+        for t in reachable_tables(Value::Table(env))? {
+            if !writable.contains(&t.to_pointer()) {
+                t.set_readonly(true);
+            }
+        }
+
+        ran
+    }
+
+    /// Calls the handlers of each mod for the event, in load order
+    fn dispatch(&self, event: &ScriptEvent) {
+        for m in &self.mods {
+            let Ok(handlers) = self
+                .lua
+                .named_registry_value::<Table>(&ModRuntime::handlers_key(&m.name))
+            else {
+                continue;
+            };
+            let Ok(Some(list)) = handlers.get::<_, Option<Table>>(event.name()) else {
+                continue;
+            };
+            let arg = match self.lua.to_value(event) {
+                Ok(arg) => arg,
+                Err(e) => {
+                    log::error!("could not convert {:?} for scripts: {}", event, e);
+                    return;
+                }
+            };
+            for f in list.sequence_values::<Function>().flatten() {
+                self.interrupts.store(0, Ordering::Relaxed);
+                if let Err(e) = f.call::<_, ()>(arg.clone()) {
+                    log::error!("{} failed on {}: {}", m.name, event.name(), e);
+                }
+            }
+        }
+    }
+
+    fn storages(&self) -> BTreeMap<String, ScriptValue> {
+        let mut storages = BTreeMap::new();
+        for m in &self.mods {
+            let storage = self
+                .lua
+                .named_registry_value::<Table>(&m.env_key())
+                .and_then(|env| env.get::<_, Value>("storage"))
+                .and_then(|v| ScriptValue::from_lua(v, 0));
+            match storage {
+                Ok(v) => {
+                    storages.insert(m.name.clone(), v);
+                }
+                Err(e) => log::error!("could not save the storage of {}: {}", m.name, e),
+            }
+        }
+        storages
+    }
+}
+
+/// The tables reachable from the value through keys, values and metatables
+fn reachable_tables(root: Value) -> mlua::Result<Vec<Table>> {
+    let mut seen = FastSet::default();
+    let mut tables = Vec::new();
+    let mut stack = vec![root];
+    while let Some(v) = stack.pop() {
+        let Value::Table(t) = v else {
+            continue;
+        };
+        if !seen.insert(t.to_pointer()) {
+            continue;
+        }
+        for kv in t.clone().pairs::<Value, Value>() {
+            let (k, v) = kv?;
+            stack.push(k);
+            stack.push(v);
+        }
+        if let Some(meta) = t.get_metatable() {
+            stack.push(Value::Table(meta));
+        }
+        tables.push(t);
+    }
+    Ok(tables)
+}
+
+/// The scripts of the mods, with the events of the tick and the commands they issued
+#[derive(Default)]
+pub struct Scripts {
+    runtime: Mutex<Option<ScriptRuntime>>,
+    /// The saved storage of each mod, used until the runtime is started
+    storages: BTreeMap<String, ScriptValue>,
+    events: Vec<ScriptEvent>,
+    /// Applied at the start of the next tick
+    commands: Vec<WorldCommand>,
+}
+
+impl Scripts {
+    pub fn push(&mut self, event: ScriptEvent) {
+        self.events.push(event);
+    }
+
+    /// Stops the scripts while keeping their storage, they are started again at the next tick.
+    /// Used when the mods are reloaded.
+    pub fn restart(&mut self) {
+        if let Some(runtime) = self.runtime.get_mut().unwrap().take() {
+            self.storages = runtime.storages();
+        }
+    }
+
+    pub(crate) fn take_commands(&mut self) -> Vec<WorldCommand> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn storages(&self) -> BTreeMap<String, ScriptValue> {
+        match *self.runtime.lock().unwrap() {
+            Some(ref runtime) => runtime.storages(),
+            None => self.storages.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ScriptsSave {
+    storages: BTreeMap<String, ScriptValue>,
+    commands: Vec<WorldCommand>,
+}
+
+impl From<&Scripts> for ScriptsSave {
+    fn from(s: &Scripts) -> Self {
+        Self {
+            storages: s.storages(),
+            commands: s.commands.clone(),
+        }
+    }
+}
+
+impl From<ScriptsSave> for Scripts {
+    fn from(s: ScriptsSave) -> Self {
+        Self {
+            runtime: Mutex::new(None),
+            storages: s.storages,
+            events: Vec::new(),
+            commands: s.commands,
+        }
+    }
+}
+
+defer_serialize!(Scripts, ScriptsSave);
+
+/// Applies the commands issued by the scripts during the previous tick
+pub(crate) fn apply_script_commands(sim: &mut Simulation) {
+    let commands = sim.write::<Scripts>().take_commands();
+    for command in commands {
+        // scripts do not act on behalf of a player
+        sim.write::<CommandSender>().name.clear();
+        if let Err(e) = command.execute(sim) {
+            log::info!("command from a script was rejected: {}", e);
+        }
+    }
+}
+
+pub fn scripts_system(_: &mut World, resources: &mut Resources) {
+    profiling::scope!("scripting::scripts_system");
+    let time = *resources.read::<GameTime>();
+    let money = resources.read::<Government>().money;
+    let mut scripts = resources.write::<Scripts>();
+    let scripts = &mut *scripts;
+
+    if time.tick.0 % TICKS_PER_DAY == 0 {
+        scripts.events.push(ScriptEvent::NewDay {
+            day: time.daytime.day,
+        });
+    }
+
+    if mod_scripts().is_empty() {
+        scripts.events.clear();
+        return;
+    }
+
+    let mut runtime = scripts.runtime.lock().unwrap();
+    if runtime.is_none() {
+        match ScriptRuntime::new(&scripts.storages) {
+            Ok(r) => *runtime = Some(r),
+            Err(e) => {
+                log::error!("could not start the scripts: {}", e);
+                scripts.events.clear();
+                return;
+            }
+        }
+    }
+    let runtime = runtime.as_mut().unwrap();
+
+    if !scripts.events.is_empty() {
+        runtime.refresh();
+    }
+
+    {
+        let mut ctx = runtime.ctx.lock().unwrap();
+        ctx.tick = time.tick.0;
+        ctx.day = time.daytime.day;
+        ctx.money = money;
+        ctx.in_handler = true;
+    }
+
+    for event in scripts.events.drain(..) {
+        runtime.dispatch(&event);
+    }
+    runtime.ctx.lock().unwrap().in_handler = false;
+
+    scripts
+        .commands
+        .append(&mut runtime.ctx.lock().unwrap().commands);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(src: &str) -> ScriptRuntime {
+        let mut runtime = ScriptRuntime::sandboxed().unwrap();
+        runtime.load_mod("test", src, &BTreeMap::new()).unwrap();
+        runtime
+    }
+
+    fn run_day(runtime: &ScriptRuntime) -> Vec<WorldCommand> {
+        runtime.refresh();
+        runtime.ctx.lock().unwrap().in_handler = true;
+        runtime.dispatch(&ScriptEvent::NewDay { day: 1 });
+        let mut ctx = runtime.ctx.lock().unwrap();
+        ctx.in_handler = false;
+        std::mem::take(&mut ctx.commands)
+    }
+
+    fn storage(runtime: &ScriptRuntime) -> ScriptValue {
+        runtime.storages().remove("test").unwrap()
+    }
+
+    #[test]
+    fn script_value_roundtrip() {
+        let lua = Lua::new();
+        let v: Value = lua
+            .load(
+                r#"return { b = true, n = 1.5, s = "s", [3] = "three", t = { 1, 2, { x = 1 } }, f = print }"#,
+            )
+            .eval()
+            .unwrap();
+
+        let sv = ScriptValue::from_lua(v, 0).unwrap();
+        let ScriptValue::Table(ref entries) = sv else {
+            panic!("expected a table, got {:?}", sv);
+        };
+        assert_eq!(entries.len(), 5, "functions are not saved");
+
+        let back = ScriptValue::from_lua(sv.to_lua(&lua).unwrap(), 0).unwrap();
+        assert_eq!(sv, back);
+
+        let ser = common::saveload::Bincode::encode(&sv).unwrap();
+        let deser: ScriptValue = common::saveload::Bincode::decode(&ser).unwrap();
+        assert_eq!(sv, deser);
+    }
+
+    #[test]
+    fn storage_survives_reload() {
+        let src = r#"script.on("new_day", function(e) storage.days = (storage.days or 0) + 1 end)"#;
+        let runtime = load(src);
+        run_day(&runtime);
+        run_day(&runtime);
+
+        let mut storages = BTreeMap::new();
+        storages.insert("test".to_string(), storage(&runtime));
+
+        let mut reloaded = ScriptRuntime::sandboxed().unwrap();
+        reloaded.load_mod("test", src, &storages).unwrap();
+        run_day(&reloaded);
+        assert_eq!(
+            storage(&reloaded),
+            ScriptValue::Table(vec![(
+                ScriptValue::String("days".to_string()),
+                ScriptValue::Number(3.0)
+            )])
+        );
+    }
+
+    #[test]
+    fn sandbox_has_no_io() {
+        for src in [
+            r#"io.open("file")"#,
+            "os.time()",
+            "os.exit()",
+            "math.random()",
+            r#"require("x")"#,
+        ] {
+            let mut runtime = ScriptRuntime::sandboxed().unwrap();
+            assert!(
+                runtime.load_mod("test", src, &BTreeMap::new()).is_err(),
+                "{} should fail",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn scripts_are_interrupted() {
+        let mut runtime = ScriptRuntime::sandboxed().unwrap();
+        assert!(runtime
+            .load_mod("test", "while true do end", &BTreeMap::new())
+            .is_err());
+
+        let runtime = load(
+            r#"script.on("new_day", function(e)
+                storage.started = true
+                while true do end
+            end)"#,
+        );
+        run_day(&runtime);
+        // the handler was stopped but the next ones still run
+        run_day(&runtime);
+        assert_eq!(
+            storage(&runtime),
+            ScriptValue::Table(vec![(
+                ScriptValue::String("started".to_string()),
+                ScriptValue::Bool(true)
+            )])
+        );
+    }
+
+    #[test]
+    fn storage_is_the_only_state() {
+        let runtime = load(
+            r#"
+            count = 0
+            script.on("new_day", function(e)
+                storage.before = true
+                count = count + 1
+                storage.after = true
+            end)
+            script.on("new_day", function(e)
+                storage = {}
+            end)
+            script.on("new_day", function(e)
+                script.on("blackout", function(e) end)
+            end)"#,
+        );
+        run_day(&runtime);
+        assert_eq!(
+            storage(&runtime),
+            ScriptValue::Table(vec![(
+                ScriptValue::String("before".to_string()),
+                ScriptValue::Bool(true)
+            )])
+        );
+    }
+
+    #[test]
+    fn locals_and_tables_do_not_outlive_the_tick() {
+        let src = r#"
+            local n = 0
+            cfg = { x = 0 }
+            script.on("new_day", function(e)
+                n = n + 1
+                storage.n = n
+                storage.days = (storage.days or 0) + 1
+                storage.frozen = not pcall(function() cfg.x = 1 end)
+            end)"#;
+        let runtime = load(src);
+        run_day(&runtime);
+        run_day(&runtime);
+
+        let mut storages = BTreeMap::new();
+        storages.insert("test".to_string(), storage(&runtime));
+        let mut reloaded = ScriptRuntime::sandboxed().unwrap();
+        reloaded.load_mod("test", src, &storages).unwrap();
+
+        run_day(&runtime);
+        run_day(&reloaded);
+        let expected = ScriptValue::from_lua(
+            Lua::new()
+                .load("return { n = 1, days = 3, frozen = true }")
+                .eval()
+                .unwrap(),
+            0,
+        )
+        .unwrap();
+        assert_eq!(storage(&runtime), expected);
+        assert_eq!(storage(&reloaded), expected);
+    }
+
+    #[test]
+    fn commands_only_come_from_handlers() {
+        let mut runtime = ScriptRuntime::sandboxed().unwrap();
+        let issue = r#"game.command({ TakeLoan = { principal = 10000, term_days = 60 } })"#;
+        assert!(runtime.load_mod("test", issue, &BTreeMap::new()).is_err());
+
+        let runtime = load(&format!(
+            r#"script.on("new_day", function(e) {} end)
+            script.on("new_day", function(e) game.command({{ Init = {{ terrain_size = 1, save_replay = false }} }}) end)"#,
+            issue
+        ));
+        let commands = run_day(&runtime);
+        assert_eq!(commands.len(), 1);
+        assert!(matches!(
+            commands[0],
+            WorldCommand::TakeLoan { term_days: 60, .. }
+        ));
+    }
+}
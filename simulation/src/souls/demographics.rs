@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameTime, ItemID, Money, TICKS_PER_DAY};
+
+use crate::economy::{Market, Wallet};
+use crate::map::{BuildingID, BuildingKind};
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::desire::Home;
+use crate::souls::human::{insert_human, leave_city, Gender, PersonalInfo};
+use crate::transportation::Location;
+use crate::utils::rand_provider::RandProvider;
+use crate::utils::resources::Resources;
+use crate::world::{HumanEnt, HumanID};
+use crate::{ParCommandBuffer, Simulation, SoulID, World};
+
+/// Humans get one year older every day
+pub const YEARS_PER_DAY: u8 = 1;
+/// Age at which children leave their parents to form their own household
+pub const ADULT_AGE: u8 = 18;
+/// Ages at which women can give birth
+pub const FERTILE_AGES: std::ops::Range<u8> = 20..40;
+/// Chance for a woman of fertile age to give birth each year
+pub const BIRTH_RATE: f32 = 0.08;
+/// Number of unemployed people above the open jobs count before immigration stops
+pub const IMMIGRATION_SLACK: u32 = 20;
+
+/// Size of an age bracket of the population pyramid, in years
+pub const AGE_BRACKET: u8 = 5;
+pub const N_AGE_BRACKETS: usize = 20;
+
+/// Chance to die during the year at the given age
+pub fn death_rate(age: u8) -> f32 {
+    if age < 60 {
+        return 0.002;
+    }
+    let x = (age - 60) as f32 / 40.0;
+    (0.002 + x * x * x).min(1.0)
+}
+
+/// Population statistics, updated every day, and counters of the population changes.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Demographics {
+    pub population: u32,
+    /// Number of men and women per age bracket of [`AGE_BRACKET`] years
+    pub pyramid: [[u32; 2]; N_AGE_BRACKETS],
+    /// Adults without a job
+    pub unemployed: u32,
+    pub open_jobs: u32,
+
+    pub births: u32,
+    pub deaths: u32,
+    pub immigrants: u32,
+    pub emigrants: u32,
+}
+
+impl Demographics {
+    /// People only move in the city if they can hope to find a job
+    pub fn immigration_allowed(&self) -> bool {
+        self.unemployed < self.open_jobs + IMMIGRATION_SLACK
+    }
+
+    pub fn record_immigrant(&mut self) {
+        self.immigrants += 1;
+        self.unemployed += 1;
+    }
+
+    fn count(&mut self, world: &World) {
+        self.population = world.humans.len() as u32;
+        self.pyramid = Default::default();
+        self.unemployed = 0;
+
+        for h in world.humans.values() {
+            let info = &h.personal_info;
+            let bracket = (info.age / AGE_BRACKET) as usize;
+            let gender = match info.gender {
+                Gender::M => 0,
+                Gender::F => 1,
+            };
+            self.pyramid[bracket.min(N_AGE_BRACKETS - 1)][gender] += 1;
+
+            if info.age >= ADULT_AGE && h.work.is_none() {
+                self.unemployed += 1;
+            }
+        }
+
+        self.open_jobs = world
+            .companies
+            .values()
+            .map(|c| c.comp.max_workers.saturating_sub(c.workers.0.len() as u32))
+            .sum();
+    }
+}
+
+/// Ages the population once a day, and decides who gives birth, who dies
+/// and who leaves their parents.
+pub fn demographics_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::demographics_system");
+    let time: &GameTime = &resources.read();
+    if time.tick.0 % TICKS_PER_DAY != 0 {
+        return;
+    }
+    let cbuf: &ParCommandBuffer<HumanEnt> = &resources.read();
+    let binfos: &BuildingInfos = &resources.read();
+    let mut rng = resources.write::<RandProvider>();
+
+    for (id, h) in world.humans.iter_mut() {
+        let info = &mut h.personal_info;
+        let became_adult = info.age < ADULT_AGE && info.age + YEARS_PER_DAY >= ADULT_AGE;
+        info.age = info.age.saturating_add(YEARS_PER_DAY);
+
+        // don't remove people from the roads
+        if !matches!(h.location, Location::Building(_)) {
+            continue;
+        }
+
+        if rng.next_f32() < death_rate(info.age) * YEARS_PER_DAY as f32 {
+            cbuf.exec_ent(id, move |sim| {
+                if leave_city(sim, id) {
+                    sim.write::<Demographics>().deaths += 1;
+                }
+            });
+            continue;
+        }
+
+        if matches!(info.gender, Gender::F)
+            && FERTILE_AGES.contains(&info.age)
+            && rng.next_f32() < BIRTH_RATE * YEARS_PER_DAY as f32
+        {
+            let house = h.home.house;
+            cbuf.exec_ent(id, move |sim| give_birth(sim, house));
+        }
+
+        let is_head = binfos.owner(h.home.house) == Some(SoulID::Human(id));
+        if became_adult && !is_head {
+            cbuf.exec_ent(id, move |sim| form_household(sim, id));
+        }
+    }
+
+    drop(rng);
+    resources.write::<Demographics>().count(world);
+}
+
+fn give_birth(sim: &mut Simulation, house: BuildingID) {
+    let info = PersonalInfo::newborn(&mut sim.write::<RandProvider>());
+    let wallet = Wallet {
+        money: Money::ZERO,
+        ..Wallet::default()
+    };
+    if insert_human(sim, house, info, None, wallet).is_some() {
+        sim.write::<Demographics>().births += 1;
+    }
+}
+
+/// A young adult moves out of their parents' home, to an empty house if there is one
+/// or out of the city otherwise.
+fn form_household(sim: &mut Simulation, id: HumanID) {
+    let empty_house = {
+        let map = sim.map();
+        let binfos = sim.read::<BuildingInfos>();
+        map.buildings()
+            .iter()
+            .filter(|(_, b)| matches!(b.kind, BuildingKind::House))
+            .find(|(bid, _)| binfos.get(*bid).map_or(false, |i| i.owner.is_none()))
+            .map(|(bid, b)| (bid, b.door_pos))
+    };
+
+    let Some((house, door_pos)) = empty_house else {
+        if leave_city(sim, id) {
+            sim.write::<Demographics>().emigrants += 1;
+        }
+        return;
+    };
+
+    let Some(h) = sim.world.humans.get_mut(id) else {
+        return;
+    };
+    h.home = Home::new(house);
+
+    let soul = SoulID::Human(id);
+    sim.write::<BuildingInfos>().set_owner(house, soul);
+    sim.write::<Market>()
+        .buy(soul, door_pos.xy(), ItemID::new("job-opening"), 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+    use geom::{vec2, vec3};
+
+    #[test]
+    fn death_rate_increases_with_age() {
+        assert!(death_rate(20) < 0.01);
+        assert!(death_rate(70) < death_rate(80));
+        assert_eq!(death_rate(100), 1.0);
+        assert_eq!(death_rate(u8::MAX), 1.0);
+    }
+
+    #[test]
+    fn leave_city_only_once() {
+        let mut test = TestCtx::new();
+
+        test.build_roads(&[vec3(0., 0., 0.), vec3(100., 0., 0.)]);
+        let house = test.build_house_near(vec2(50.0, 50.0));
+        let human = spawn_human(&mut test.g, house).unwrap();
+        test.tick();
+
+        // death and emigration can happen on the same day
+        assert!(leave_city(&mut test.g, human));
+        assert!(!leave_city(&mut test.g, human));
+        assert!(test.g.read::<BuildingInfos>().owner(house).is_none());
+
+        test.tick();
+        assert!(test.g.world().humans.get(human).is_none());
+        assert!(!leave_city(&mut test.g, human));
+    }
+}
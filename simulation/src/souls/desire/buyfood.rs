@@ -37,7 +37,10 @@ impl BuyFood {
         }
     }
 
-    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought) -> f32 {
+    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought, can_afford: bool) -> f32 {
+        if !can_afford && matches!(self.state, BuyFoodState::Empty) {
+            return 0.0;
+        }
         if matches!(self.state, BuyFoodState::WaitingForTrade)
             && bought
                 .0
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use egui_inspect::Inspect;
+use geom::Vec2;
+use prototypes::{GameInstant, GameTime, Money};
+
+use crate::economy::{Government, Wallet};
+use crate::map::{BuildingID, BuildingKind, Map, ProjectFilter, ProjectKind};
+use crate::map_dynamic::{BuildingInfos, Destination};
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use crate::world::{HumanEnt, HumanID};
+use crate::ParCommandBuffer;
+
+/// How far people are willing to go to have fun
+const LEISURE_RADIUS: f32 = 3000.0;
+/// How long people stay once inside
+const LEISURE_DURATION: i32 = 2 * GameTime::HOUR;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum LeisureState {
+    Empty,
+    GoingTo(BuildingID),
+    Inside(BuildingID, GameInstant),
+}
+
+debug_inspect_impl!(LeisureState);
+
+#[derive(Inspect, Clone, Serialize, Deserialize, Debug)]
+pub struct Leisure {
+    pub last_outing: GameInstant,
+    state: LeisureState,
+    pub last_score: f32,
+}
+
+impl Leisure {
+    pub fn new(start: GameInstant) -> Self {
+        Self {
+            last_outing: start,
+            state: LeisureState::Empty,
+            last_score: 0.0,
+        }
+    }
+
+    pub fn score(&self, time: &GameTime, loc: &Location, wallet: &Wallet) -> f32 {
+        match self.state {
+            LeisureState::Inside(..) => 1.0,
+            LeisureState::GoingTo(b) if loc == &Location::Building(b) => 1.0,
+            LeisureState::Empty if wallet.leisure_budget() <= Money::ZERO => 0.0,
+            _ => self.last_outing.elapsed(time).seconds() as f32 / (2 * GameTime::DAY) as f32 - 1.0,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply(
+        &mut self,
+        cbuf: &ParCommandBuffer<HumanEnt>,
+        binfos: &BuildingInfos,
+        map: &Map,
+        time: &GameTime,
+        id: HumanID,
+        pos: Vec2,
+        loc: &Location,
+        wallet: &mut Wallet,
+    ) -> HumanDecisionKind {
+        use HumanDecisionKind::*;
+        match self.state {
+            LeisureState::Empty => {
+                let Some((b, _)) = find_leisure(binfos, map, time, pos, wallet.leisure_budget())
+                else {
+                    // nothing open or affordable, try again later
+                    self.last_outing = time.instant();
+                    return Yield;
+                };
+                self.state = LeisureState::GoingTo(b);
+                GoTo(Destination::Building(b))
+            }
+            LeisureState::GoingTo(b) => {
+                if loc != &Location::Building(b) {
+                    return GoTo(Destination::Building(b));
+                }
+                let fee = match map.buildings().get(b).map(|b| b.kind) {
+                    Some(BuildingKind::Leisure(proto)) => proto.prototype().entry_fee,
+                    _ => {
+                        self.state = LeisureState::Empty;
+                        return Yield;
+                    }
+                };
+                if !wallet.can_afford_leisure(fee) {
+                    self.state = LeisureState::Empty;
+                    self.last_outing = time.instant();
+                    return Yield;
+                }
+                // the city runs the leisure buildings
+                wallet.spend(fee);
+                cbuf.exec_on(id, move |gvt: &mut Government| gvt.money += fee);
+                self.state = LeisureState::Inside(b, time.instant());
+                Yield
+            }
+            LeisureState::Inside(b, since) => {
+                if loc != &Location::Building(b)
+                    || since.elapsed(time).seconds() >= LEISURE_DURATION as f64
+                {
+                    self.state = LeisureState::Empty;
+                    self.last_outing = time.instant();
+                }
+                Yield
+            }
+        }
+    }
+}
+
+/// The closest open leisure building with some room left whose entry fee fits the budget
+fn find_leisure(
+    binfos: &BuildingInfos,
+    map: &Map,
+    time: &GameTime,
+    pos: Vec2,
+    budget: Money,
+) -> Option<(BuildingID, Money)> {
+    map.spatial_map()
+        .query_around(pos, LEISURE_RADIUS, ProjectFilter::BUILDING)
+        .filter_map(|kind| {
+            let ProjectKind::Building(id) = kind else {
+                return None;
+            };
+            let b = map.buildings().get(id)?;
+            let BuildingKind::Leisure(proto) = b.kind else {
+                return None;
+            };
+            let proto = proto.prototype();
+            let visitors = binfos.get(id).map_or(0, |info| info.inside.len());
+            (proto.opening_hours.is_active(&time.daytime)
+                && proto.entry_fee <= budget
+                && visitors < proto.capacity as usize)
+                .then(|| (id, proto.entry_fee, b.door_pos.xy().distance2(pos)))
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(id, fee, _)| (id, fee))
+}
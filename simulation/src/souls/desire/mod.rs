@@ -1,7 +1,9 @@
 mod buyfood;
 mod home;
+mod leisure;
 mod work;
 
 pub use buyfood::*;
 pub use home::*;
+pub use leisure::*;
 pub use work::*;
@@ -1,5 +1,5 @@
 use crate::map::BuildingID;
-use crate::map_dynamic::{Destination, Router};
+use crate::map_dynamic::{Destination, Router, Tour, TourState};
 use crate::souls::human::HumanDecisionKind;
 use crate::transportation::Location;
 use crate::world::VehicleID;
@@ -7,12 +7,9 @@ use egui_inspect::Inspect;
 use prototypes::{GameTime, RecTimeInterval, MINUTES_PER_HOUR};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WorkKind {
-    Driver {
-        deliver_order: Option<BuildingID>,
-        truck: VehicleID,
-    },
+    Driver { tour: Tour, truck: VehicleID },
     Worker,
 }
 debug_inspect_impl!(WorkKind);
@@ -43,24 +40,35 @@ impl Work {
         match self.kind {
             WorkKind::Worker => GoTo(Destination::Building(self.workplace)),
             WorkKind::Driver {
-                deliver_order,
+                ref mut tour,
                 truck,
             } => {
                 if &Location::Building(self.workplace) != loc {
-                    MultiStack(vec![
+                    return MultiStack(vec![
                         GoTo(Destination::Building(self.workplace)),
                         SetVehicle(router.personal_car),
-                    ])
-                } else if let Some(b) = deliver_order {
-                    MultiStack(vec![
-                        SetVehicle(router.personal_car),
-                        GoTo(Destination::Building(self.workplace)),
-                        DeliverAtBuilding(b),
-                        GoTo(Destination::Building(b)),
-                        SetVehicle(Some(truck)),
-                    ])
-                } else {
-                    Yield
+                    ]);
+                }
+                match tour.state {
+                    // back at the depot
+                    TourState::Started => {
+                        tour.state = TourState::Finished;
+                        Yield
+                    }
+                    TourState::Pending if !tour.stops.is_empty() => {
+                        tour.state = TourState::Started;
+
+                        let mut stack = Vec::with_capacity(3 + tour.stops.len() * 2);
+                        stack.push(SetVehicle(router.personal_car));
+                        stack.push(GoTo(Destination::Building(self.workplace)));
+                        for stop in tour.stops.iter().rev() {
+                            stack.push(DeliverAtBuilding(stop.building));
+                            stack.push(GoTo(Destination::Building(stop.building)));
+                        }
+                        stack.push(SetVehicle(Some(truck)));
+                        MultiStack(stack)
+                    }
+                    _ => Yield,
                 }
             }
         }
@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-use geom::Transform;
-use prototypes::{FreightStationPrototypeID, GameTime};
+use geom::{Transform, Vec3};
+use prototypes::{FreightStationPrototypeID, GameTime, ItemID};
 
 use crate::map::{BuildingID, Map, PathKind};
 use crate::map_dynamic::{
@@ -34,6 +36,13 @@ pub struct FreightStation {
     pub trains: Vec<(TrainID, FreightTrainState)>,
     pub waiting_cargo: u32,
     pub wanted_cargo: u32,
+    /// Goods waiting to be loaded on the trains of the freight routes serving the station
+    pub cargo: BTreeMap<ItemID, u32>,
+}
+
+/// Where the trains stop to load and unload at the station
+pub fn station_dock(trans: &Transform) -> Vec3 {
+    trans.pos + trans.dir * 75.0 - trans.dir.perp_up() * 40.0
 }
 
 pub fn freight_station_soul(
@@ -49,6 +58,7 @@ pub fn freight_station_soul(
         trains: Vec::with_capacity(MAX_TRAINS_PER_STATION),
         waiting_cargo: 0,
         wanted_cargo: 0,
+        cargo: BTreeMap::new(),
     };
     let b = map.buildings.get(building)?;
 
@@ -140,7 +150,7 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
             continue;
         }
 
-        let destination = pos.pos + pos.dir * 75.0 - pos.dir.perp_up() * 40.0;
+        let destination = station_dock(&pos);
 
         let Some(DispatchID::FreightTrain(trainid)) = dispatch.query(
             &map,
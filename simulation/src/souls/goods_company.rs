@@ -3,16 +3,17 @@ use serde::{Deserialize, Serialize};
 use egui_inspect::Inspect;
 use geom::{Transform, Vec2};
 use prototypes::{
-    CompanyKind, GoodsCompanyID, GoodsCompanyPrototype, ItemID, Power, Recipe, DELTA,
+    try_prototype, CompanyKind, GameTime, GoodsCompanyID, GoodsCompanyPrototype, ItemID, Money,
+    Power, Recipe, WarehouseID, WarehousePrototype, DELTA, TICKS_PER_DAY,
 };
 
-use crate::economy::{find_trade_place, Market};
+use crate::economy::{find_trade_place, EcoStats, Market, TradeKind};
 use crate::map::{Building, BuildingID, Map, Zone, MAX_ZONE_AREA};
-use crate::map_dynamic::{BuildingInfos, ElectricityFlow};
+use crate::map_dynamic::{BuildingInfos, ElectricityFlow, Tour};
 use crate::souls::desire::WorkKind;
-use crate::transportation::{spawn_parked_vehicle, VehicleKind};
+use crate::transportation::{spawn_parked_vehicle, Location, VehicleKind};
 use crate::utils::resources::Resources;
-use crate::world::{CompanyEnt, HumanEnt, HumanID, VehicleID};
+use crate::world::{CompanyEnt, CompanyID, HumanEnt, HumanID, VehicleID};
 use crate::{ParCommandBuffer, SoulID, VehicleEnt};
 use crate::{Simulation, World};
 
@@ -57,6 +58,13 @@ pub fn recipe_act(recipe: &Recipe, soul: SoulID, near: Vec2, market: &mut Market
     }
 }
 
+/// Money a company starts with
+pub const COMPANY_STARTING_FUNDS: Money = Money::new_bucks(5_000);
+/// Wage paid to each worker every day
+pub const WORKER_WAGE_PER_DAY: Money = Money::new_bucks(100);
+/// Number of consecutive days with a negative balance before a company closes
+pub const COMPANY_CLOSURE_DAYS: u32 = 7;
+
 #[derive(Clone, Serialize, Deserialize, Inspect)]
 pub struct GoodsCompanyState {
     pub proto: GoodsCompanyID,
@@ -66,6 +74,38 @@ pub struct GoodsCompanyState {
     pub progress: f32,
     pub driver: Option<HumanID>,
     pub trucks: Vec<VehicleID>,
+    pub money: Money,
+    /// Profit made since the start of the day
+    pub profit: Money,
+    pub last_day_profit: Money,
+    /// Number of consecutive days the balance was negative
+    pub deficit_days: u32,
+}
+
+impl GoodsCompanyState {
+    pub fn warehouse(&self) -> Option<&'static WarehousePrototype> {
+        try_prototype(WarehouseID::from(self.proto))
+    }
+
+    /// Sales are positive, purchases are negative
+    pub fn earn(&mut self, amount: Money) {
+        self.money += amount;
+        self.profit += amount;
+    }
+
+    /// Pays the workers and closes the books for the day
+    pub fn end_day(&mut self, n_workers: usize) -> Money {
+        let wages = WORKER_WAGE_PER_DAY * n_workers as i64;
+        self.earn(-wages);
+        self.last_day_profit = std::mem::replace(&mut self.profit, Money::ZERO);
+
+        if self.money < Money::ZERO {
+            self.deficit_days += 1;
+        } else {
+            self.deficit_days = 0;
+        }
+        wages
+    }
 }
 
 impl CompanyEnt {
@@ -139,6 +179,10 @@ pub fn company_soul(
         progress: 0.0,
         driver: None,
         trucks,
+        money: COMPANY_STARTING_FUNDS,
+        profit: Money::ZERO,
+        last_day_profit: Money::ZERO,
+        deficit_days: 0,
     };
 
     let id = sim.world.insert(CompanyEnt {
@@ -163,6 +207,12 @@ pub fn company_soul(
         if let Some(ref r) = proto.recipe {
             recipe_init(r, soul, door_pos.xy(), m);
         }
+
+        if let Some(warehouse) = company.warehouse() {
+            for item in &warehouse.capacity {
+                m.store(soul, door_pos.xy(), item.id, item.amount as u32);
+            }
+        }
     }
 
     sim.write::<BuildingInfos>()
@@ -171,6 +221,90 @@ pub fn company_soul(
     Some(soul)
 }
 
+/// Applies the changes of the company prototypes after they were reloaded:
+/// the job openings follow the new number of workers and the orders follow the new recipe
+pub fn refresh_companies(world: &mut World, market: &mut Market, map: &Map) {
+    let job_opening = ItemID::new("job-opening");
+    for (id, c) in world.companies.iter_mut() {
+        let soul = SoulID::GoodsCompany(id);
+        let proto = c.comp.proto.prototype();
+        let near = map
+            .buildings()
+            .get(c.comp.building)
+            .map_or(c.trans.pos.xy(), |b| b.door_pos.xy());
+
+        if proto.n_workers != c.comp.max_workers {
+            let delta = proto.n_workers as i32 - c.comp.max_workers as i32;
+            c.comp.max_workers = proto.n_workers;
+            let open = market.produce(soul, job_opening, delta);
+            if open < 0 {
+                // more workers than the new maximum, they keep their job but no one is hired
+                market.produce(soul, job_opening, -open);
+            }
+            market.sell_all(soul, near, job_opening, 0);
+        }
+
+        market.cancel_buys(soul);
+        if let Some(ref r) = proto.recipe {
+            recipe_init(r, soul, near, market);
+        }
+        if let Some(warehouse) = c.comp.warehouse() {
+            for item in &warehouse.capacity {
+                market.store(soul, near, item.id, item.amount as u32);
+            }
+        }
+    }
+}
+
+/// Whether a closed company could reopen in this building, because the city
+/// imports what it would produce.
+pub fn company_has_demand(proto: &GoodsCompanyPrototype, ecostats: &EcoStats) -> bool {
+    if let Some(warehouse) = try_prototype(WarehouseID::from(proto.id)) {
+        return warehouse.capacity.iter().any(|item| {
+            ecostats.imports.total(item.id, 0) > 0 || ecostats.exports.total(item.id, 0) > 0
+        });
+    }
+    let Some(ref recipe) = proto.recipe else {
+        return false;
+    };
+    recipe
+        .production
+        .iter()
+        .any(|item| ecostats.imports.total(item.id, 0) > 0)
+}
+
+/// Shuts down a company: its workers lose their job, its trucks are removed
+/// and its building is left vacant.
+pub fn close_company(sim: &mut Simulation, id: CompanyID) {
+    let Some(c) = sim.world.companies.get(id) else {
+        return;
+    };
+    log::info!("company {:?} closed", id);
+
+    let building = c.comp.building;
+    let trucks = c.comp.trucks.clone();
+    let workers = c.workers.0.clone();
+
+    let job_opening = ItemID::new("job-opening");
+    for worker in workers {
+        let Some(h) = sim.world.humans.get_mut(worker) else {
+            continue;
+        };
+        h.work = None;
+        let house = h.home.house;
+        let Some(door_pos) = sim.map().buildings().get(house).map(|b| b.door_pos) else {
+            continue;
+        };
+        sim.write::<Market>()
+            .buy(SoulID::Human(worker), door_pos.xy(), job_opening, 1);
+    }
+
+    sim.write::<ParCommandBuffer<VehicleEnt>>()
+        .kill_all(&trucks);
+    sim.write::<BuildingInfos>().set_vacant(building);
+    sim.write::<ParCommandBuffer<CompanyEnt>>().kill(id);
+}
+
 pub fn company_system(world: &mut World, res: &mut Resources) {
     profiling::scope!("souls::company_system");
     let cbuf: &ParCommandBuffer<CompanyEnt> = &res.read();
@@ -179,6 +313,9 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
     let market: &Market = &res.read();
     let map: &Map = &res.read();
     let elec_flow: &ElectricityFlow = &res.read();
+    let time: &GameTime = &res.read();
+
+    let end_of_day = time.tick.0 % TICKS_PER_DAY == 0;
 
     world.companies.iter_mut().for_each(|(me, c)| {
         let soul = SoulID::GoodsCompany(me);
@@ -189,6 +326,37 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
 
         let proto = c.comp.proto.prototype();
 
+        // power plants and the like don't sell goods, they are not run for profit
+        let sells_goods = c.comp.warehouse().is_some()
+            || proto
+                .recipe
+                .as_ref()
+                .map_or(false, |r| !r.production.is_empty());
+
+        if end_of_day {
+            for &worker in c.workers.0.iter() {
+                if let Some(h) = world.humans.get_mut(worker) {
+                    h.wallet.earn(WORKER_WAGE_PER_DAY);
+                }
+            }
+        }
+
+        if end_of_day && sells_goods {
+            c.comp.end_day(c.workers.0.len());
+
+            // wait for the truck to be back before closing
+            let driving = c
+                .comp
+                .driver
+                .and_then(|d| world.humans.get(d))
+                .map_or(false, |h| matches!(h.location, Location::Vehicle(_)));
+
+            if c.comp.deficit_days >= COMPANY_CLOSURE_DAYS && !driving {
+                cbuf.exec_ent(me, move |sim| close_company(sim, me));
+                return;
+            }
+        }
+
         if let Some(recipe) = &proto.recipe {
             if recipe_should_produce(recipe, soul, market) {
                 let productivity = c.productivity(proto, b.zone.as_ref(), map, elec_flow);
@@ -211,6 +379,10 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
 
         for (_, trades) in c.bought.0.iter_mut() {
             for trade in trades.drain(..) {
+                // only imports need a train, goods brought by a freight route are already there
+                if trade.trade_kind != TradeKind::Import {
+                    continue;
+                }
                 if let Some(owner_build) = find_trade_place(trade.seller, binfos) {
                     cbuf.exec_ent(me, move |sim| {
                         let (world, res) = sim.world_res();
@@ -226,43 +398,6 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
             }
         }
 
-        (|| {
-            let Some(driver) = c.comp.driver else {
-                return;
-            };
-            let Some(w) = world.humans.get(driver).and_then(|h| h.work.as_ref()) else {
-                return;
-            };
-            if !matches!(
-                w.kind,
-                WorkKind::Driver {
-                    deliver_order: None,
-                    ..
-                }
-            ) {
-                return;
-            }
-            let Some(trade) = c.sold.0.pop() else {
-                return;
-            };
-            let Some(owner_build) = find_trade_place(trade.buyer, binfos) else {
-                log::warn!("driver can't find the place to deliver for {:?}", &trade);
-                return;
-            };
-            cbuf.exec_ent(me, move |sim| {
-                let Some(h) = sim.world.humans.get_mut(driver) else {
-                    return;
-                };
-                let Some(w) = h.work.as_mut() else {
-                    return;
-                };
-                let WorkKind::Driver { deliver_order, .. } = &mut w.kind else {
-                    return;
-                };
-                *deliver_order = Some(owner_build)
-            });
-        })();
-
         for &worker in c.workers.0.iter() {
             let Some(w) = world.humans.get(worker) else {
                 continue;
@@ -274,7 +409,7 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
                 if let Some(truck) = c.comp.trucks.first() {
                     if proto.kind == CompanyKind::Factory && c.comp.driver.is_none() {
                         kind = WorkKind::Driver {
-                            deliver_order: None,
+                            tour: Tour::default(),
                             truck: *truck,
                         };
 
@@ -1,22 +1,27 @@
-use crate::economy::{Bought, Market};
+use crate::economy::{Bought, Market, Wallet, RENT_PER_DAY};
 use crate::map::BuildingID;
 use crate::map_dynamic::{BuildingInfos, Destination, Itinerary, Router};
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::demographics::Demographics;
+use crate::souls::desire::{BuyFood, Home, Leisure, Work, WorkKind};
+use crate::transportation::freight_route::FreightRoutes;
 use crate::transportation::Speed;
 use crate::transportation::{
-    random_pedestrian_shirt_color, spawn_parked_vehicle, Location, Pedestrian, VehicleKind,
+    random_pedestrian_shirt_color, spawn_parked_vehicle, Bicycle, Location, Pedestrian, VehicleKind,
 };
 use crate::utils::rand_provider::RandProvider;
 use crate::utils::resources::Resources;
 use crate::world::{FreightStationEnt, HumanEnt, HumanID, VehicleID};
 use crate::World;
-use crate::{BuildingKind, Map, ParCommandBuffer, Simulation, SoulID};
+use crate::{BuildingKind, Map, ParCommandBuffer, Simulation, SoulID, VehicleEnt};
 use egui_inspect::Inspect;
 use geom::Transform;
 use lazy_static::lazy_static;
-use prototypes::{GameTime, ItemID};
+use prototypes::{GameTime, ItemID, Money, TICKS_PER_DAY};
 use serde::{Deserialize, Serialize};
 
+/// Proportion of the population owning a bicycle
+const BICYCLE_OWNERSHIP: f32 = 0.5;
+
 #[derive(Inspect, Serialize, Deserialize, Default)]
 pub struct HumanDecision {
     pub kind: HumanDecisionKind,
@@ -58,6 +63,14 @@ lazy_static! {
 impl PersonalInfo {
     pub fn new(rng: &mut RandProvider) -> Self {
         let age = (rng.next_f32() * 30.0 + 20.0) as u8;
+        Self::with_age(rng, age)
+    }
+
+    pub fn newborn(rng: &mut RandProvider) -> Self {
+        Self::with_age(rng, 0)
+    }
+
+    fn with_age(rng: &mut RandProvider, age: u8) -> Self {
         let gender = match rng.next_u32() % 2 {
             0 => Gender::M,
             1 => Gender::F,
@@ -86,12 +99,13 @@ impl HumanDecisionKind {
         binfos: &BuildingInfos,
         map: &Map,
         cbuf_freight: &ParCommandBuffer<FreightStationEnt>,
+        work: Option<&mut Work>,
     ) -> bool {
         match *self {
             HumanDecisionKind::GoTo(dest) => router.go_to(dest),
             HumanDecisionKind::MultiStack(ref mut decisions) => {
                 if let Some(d) = decisions.last_mut() {
-                    if d.update(router, binfos, map, cbuf_freight) {
+                    if d.update(router, binfos, map, cbuf_freight, work) {
                         decisions.pop();
                     }
                     false
@@ -104,6 +118,10 @@ impl HumanDecisionKind {
                 true
             }
             HumanDecisionKind::DeliverAtBuilding(bid) => {
+                let mut delivered = None;
+                if let Some(WorkKind::Driver { tour, .. }) = work.map(|w| &mut w.kind) {
+                    delivered = tour.mark_delivered(bid);
+                }
                 let Some(b) = map.buildings().get(bid) else {
                     return true;
                 };
@@ -112,8 +130,14 @@ impl HumanDecisionKind {
                         return true;
                     };
                     cbuf_freight.exec_ent(fid, move |e| {
-                        if let Some(f) = e.world.freight_stations.get_mut(fid) {
-                            f.f.waiting_cargo += 1;
+                        let routed = delivered
+                            .filter(|&(item, _)| e.read::<FreightRoutes>().takes(bid, item));
+                        let Some(f) = e.world.freight_stations.get_mut(fid) else {
+                            return;
+                        };
+                        match routed {
+                            Some((item, qty)) => *f.f.cargo.entry(item).or_default() += qty,
+                            None => f.f.waiting_cargo += 1,
                         }
                     });
                 }
@@ -130,6 +154,7 @@ enum NextDesire<'a> {
     Home(&'a mut Home),
     Work(&'a mut Work),
     Food(&'a mut BuyFood),
+    Leisure(&'a mut Leisure),
 }
 
 pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
@@ -139,6 +164,8 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
     let rc = &*resources.read();
     let rd = &*resources.read();
     let re = &*resources.read();
+    let market: &Market = &resources.read();
+    let food_price = market.ext_value(ItemID::new("bread"));
 
     world.humans.iter_mut().for_each(|(ent, h)| {
         update_decision(
@@ -152,8 +179,11 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
             &h.location,
             &mut h.router,
             &mut h.bought,
+            food_price,
+            &mut h.wallet,
             &mut h.decision,
             Some(&mut h.food),
+            Some(&mut h.leisure),
             Some(&mut h.home),
             h.work.as_mut(),
         )
@@ -172,10 +202,13 @@ pub fn update_decision(
     loc: &Location,
     router: &mut Router,
     bought: &mut Bought,
+    food_price: Money,
+    wallet: &mut Wallet,
     decision: &mut HumanDecision,
     food: Option<&mut BuyFood>,
+    leisure: Option<&mut Leisure>,
     home: Option<&mut Home>,
-    work: Option<&mut Work>,
+    mut work: Option<&mut Work>,
 ) {
     if decision.wait != 0 {
         decision.wait -= 1;
@@ -183,7 +216,10 @@ pub fn update_decision(
     }
     let pos = trans.pos;
     decision.wait = (30.0 + common::rand::rand2(pos.x, pos.y) * 50.0) as u8;
-    if !decision.kind.update(router, binfos, map, cbuf_freight) {
+    if !decision
+        .kind
+        .update(router, binfos, map, cbuf_freight, work.as_deref_mut())
+    {
         return;
     }
 
@@ -211,65 +247,193 @@ pub fn update_decision(
     }
 
     if let Some(food) = food {
-        let score = food.score(time, loc, bought);
+        let score = food.score(time, loc, bought, wallet.can_afford(food_price));
         food.last_score = score;
 
-        #[allow(unused_assignments)]
         if score > max_score {
             max_score = score;
             decision_id = NextDesire::Food(food);
         }
     }
 
+    if let Some(leisure) = leisure {
+        let score = leisure.score(time, loc, wallet);
+        leisure.last_score = score;
+
+        #[allow(unused_assignments)]
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::Leisure(leisure);
+        }
+    }
+
     match decision_id {
         NextDesire::Home(home) => decision.kind = home.apply(),
         NextDesire::Work(work) => decision.kind = work.apply(loc, router),
         NextDesire::Food(food) => {
             decision.kind = food.apply(cbuf, binfos, time, me, trans, loc, bought)
         }
+        NextDesire::Leisure(leisure) => {
+            decision.kind = leisure.apply(cbuf, binfos, map, time, me, pos.xy(), loc, wallet)
+        }
         NextDesire::None => {}
     }
 }
 
 pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
     profiling::scope!("spawn_human");
-    let map = sim.map();
-    let housepos = map.buildings().get(house)?.door_pos;
-    drop(map);
+    let housepos = sim.map().buildings().get(house)?.door_pos;
 
-    let _color = random_pedestrian_shirt_color(&mut sim.write::<RandProvider>());
+    let car = spawn_parked_vehicle(sim, VehicleKind::Car, housepos);
+    let personal_info = PersonalInfo::new(&mut sim.write::<RandProvider>());
+
+    let id = insert_human(sim, house, personal_info, car, Wallet::default())?;
+
+    let soul = SoulID::Human(id);
+    let mut m = sim.write::<Market>();
+    m.buy(soul, housepos.xy(), ItemID::new("job-opening"), 1);
 
+    sim.write::<BuildingInfos>().set_owner(house, soul);
+
+    Some(id)
+}
+
+/// Adds a human living in the given house, without looking for a job
+pub fn insert_human(
+    sim: &mut Simulation,
+    house: BuildingID,
+    personal_info: PersonalInfo,
+    car: Option<VehicleID>,
+    wallet: Wallet,
+) -> Option<HumanID> {
     let hpos = sim.map().buildings().get(house)?.door_pos;
-    let p = Pedestrian::new(&mut sim.write::<RandProvider>());
 
-    let time = sim.read::<GameTime>().instant();
+    let _color = random_pedestrian_shirt_color(&mut sim.write::<RandProvider>());
 
-    let car = spawn_parked_vehicle(sim, VehicleKind::Car, housepos);
+    let p = Pedestrian::new(&mut sim.write::<RandProvider>());
 
-    let personal_info = Box::new(PersonalInfo::new(&mut sim.write::<RandProvider>()));
+    let bicycle = {
+        let mut rng = sim.write::<RandProvider>();
+        (rng.next_f32() < BICYCLE_OWNERSHIP).then(|| Bicycle::new(&mut rng))
+    };
+
+    let time = sim.read::<GameTime>().instant();
 
     let id = sim.world.insert(HumanEnt {
         trans: Transform::new(hpos),
         location: Location::Building(house),
         pedestrian: p,
+        bicycle,
         it: Itinerary::NONE,
         speed: Speed::default(),
         decision: HumanDecision::default(),
         home: Home::new(house),
         food: BuyFood::new(time),
+        leisure: Leisure::new(time),
         bought: Bought::default(),
         router: Router::new(car),
         collider: None,
         work: None,
-        personal_info,
+        wallet,
+        personal_info: Box::new(personal_info),
     });
 
+    sim.write::<BuildingInfos>()
+        .get_in(house, SoulID::Human(id));
+
+    Some(id)
+}
+
+/// Pays the rent of every household once a day.
+/// Households without a job that can't pay anymore leave the city.
+pub fn household_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::household_system");
+    let time: &GameTime = &resources.read();
+    if time.tick.0 % TICKS_PER_DAY != 0 {
+        return;
+    }
+    let cbuf: &ParCommandBuffer<HumanEnt> = &resources.read();
+    let binfos: &BuildingInfos = &resources.read();
+
+    for (id, h) in world.humans.iter_mut() {
+        // only the head of the household pays the rent
+        let is_head = binfos.owner(h.home.house) == Some(SoulID::Human(id));
+        let rent = if is_head { RENT_PER_DAY } else { Money::ZERO };
+
+        let move_out = h.wallet.end_day(rent, h.work.is_some());
+        if is_head && move_out && h.location == Location::Building(h.home.house) {
+            cbuf.exec_ent(id, move |sim| {
+                if leave_city(sim, id) {
+                    sim.write::<Demographics>().emigrants += 1;
+                }
+            });
+        }
+    }
+}
+
+/// The human leaves the city, by death or emigration.
+/// Its job is opened again and the home is handed over to the rest of the household,
+/// or freed for someone else.
+/// Returns false if the human already left, so it is only counted once.
+pub fn leave_city(sim: &mut Simulation, id: HumanID) -> bool {
+    let Some(h) = sim.world.humans.get(id) else {
+        return false;
+    };
+    // the human is only removed at the end of the tick, it might be asked to leave twice
+    if sim.read::<ParCommandBuffer<HumanEnt>>().is_killed(id) {
+        return false;
+    }
+
     let soul = SoulID::Human(id);
-    let mut m = sim.write::<Market>();
-    m.buy(soul, housepos.xy(), ItemID::new("job-opening"), 1);
+    let house = h.home.house;
+    let car = h.router.personal_car;
+    let workplace = h.work.as_ref().map(|w| w.workplace);
+    let inside = match h.location {
+        Location::Building(b) => Some(b),
+        _ => None,
+    };
+
+    let heir = sim
+        .world
+        .humans
+        .iter()
+        .filter(|(other, h)| *other != id && h.home.house == house)
+        .max_by_key(|(_, h)| h.personal_info.age)
+        .map(|(other, _)| other);
+
+    {
+        let mut binfos = sim.write::<BuildingInfos>();
+        if let Some(b) = inside {
+            binfos.get_out(b, soul);
+        }
+        if binfos.owner(house) == Some(soul) {
+            match heir {
+                Some(heir) => binfos.set_owner(house, SoulID::Human(heir)),
+                None => binfos.remove_owner(house),
+            }
+        }
+    }
 
-    sim.write::<BuildingInfos>().get_in(house, soul);
-    sim.write::<BuildingInfos>().set_owner(house, soul);
+    if let Some(workplace) = workplace {
+        let employer = sim.read::<BuildingInfos>().owner(workplace);
+        if let Some(SoulID::GoodsCompany(cid)) = employer {
+            if let Some(c) = sim.world.companies.get_mut(cid) {
+                c.workers.0.retain(|w| *w != id);
+                if c.comp.driver == Some(id) {
+                    c.comp.driver = None;
+                }
+                sim.write::<Market>().produce(
+                    SoulID::GoodsCompany(cid),
+                    ItemID::new("job-opening"),
+                    1,
+                );
+            }
+        }
+    }
 
-    Some(id)
+    if let Some(car) = car {
+        sim.write::<ParCommandBuffer<VehicleEnt>>().kill(car);
+    }
+    sim.write::<ParCommandBuffer<HumanEnt>>().kill(id);
+    true
 }
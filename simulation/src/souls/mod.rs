@@ -1,13 +1,16 @@
+use crate::economy::EcoStats;
 use crate::map::BuildingKind;
 use crate::map_dynamic::BuildingInfos;
+use crate::souls::demographics::Demographics;
 use crate::souls::freight_station::freight_station_soul;
-use crate::souls::goods_company::company_soul;
+use crate::souls::goods_company::{company_has_demand, company_soul};
 use crate::souls::human::spawn_human;
 use crate::Simulation;
 
 #[macro_use]
 pub mod desire;
 
+pub mod demographics;
 pub mod freight_station;
 pub mod goods_company;
 pub mod human;
@@ -17,15 +20,26 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
     profiling::scope!("souls::add_souls_to_empty_buildings");
     let map = sim.map();
     let infos = sim.read::<BuildingInfos>();
+    let ecostats = sim.read::<EcoStats>();
     let mut empty_buildings = Vec::with_capacity(16);
 
     for (id, building) in map.buildings() {
-        if unwrap_cont!(infos.get(id)).owner.is_some() {
+        let info = unwrap_cont!(infos.get(id));
+        if info.owner.is_some() {
             continue;
         }
+        if info.vacant {
+            let BuildingKind::GoodsCompany(proto) = building.kind else {
+                continue;
+            };
+            if !company_has_demand(proto.prototype(), &ecostats) {
+                continue;
+            }
+        }
 
         empty_buildings.push((building.kind, id));
     }
+    drop(ecostats);
     drop(infos);
     drop(map);
 
@@ -34,8 +48,13 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
     for (bkind, build_id) in empty_buildings {
         match bkind {
             BuildingKind::House => {
-                spawn_human(sim, build_id);
-                n_souls_added += 1;
+                if !sim.read::<Demographics>().immigration_allowed() {
+                    continue;
+                }
+                if spawn_human(sim, build_id).is_some() {
+                    sim.write::<Demographics>().record_immigrant();
+                    n_souls_added += 1;
+                }
             }
             BuildingKind::GoodsCompany(id) => {
                 company_soul(sim, build_id, id);
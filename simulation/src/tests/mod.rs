@@ -58,7 +58,7 @@ impl TestCtx {
 
     pub(crate) fn apply(&mut self, commands: &[WorldCommand]) {
         for c in commands {
-            c.apply(&mut self.g);
+            c.apply(&mut self.g).unwrap();
         }
     }
 
@@ -1,10 +1,13 @@
+use crate::economy::Government;
 use crate::init::init;
 use crate::map::{LanePatternBuilder, Map, MapProject, ProjectKind};
 use crate::utils::scheduler::SeqSchedule;
+use crate::world_command::WorldCommand;
 use crate::World;
-use crate::{Replay, Simulation};
-use common::saveload::{Bincode, Encoder, JSONPretty};
+use crate::{Replay, Simulation, SimulationOptions};
+use common::saveload::{Bincode, Blobs, CompressedBincode, Encoder, JSONPretty};
 use geom::vec3;
+use prototypes::{Money, Tick};
 use quickcheck::{Arbitrary, Gen, TestResult};
 
 static REPLAY: &[u8] = include_bytes!("world_replay.json");
@@ -253,8 +256,8 @@ fn test_world_survives_serde() {
         if check_size == 0 {
             break;
         }
-        let (mut sim, mut loader) = Simulation::from_replay(replay.clone());
-        let (mut sim2, mut loader2) = Simulation::from_replay(replay.clone());
+        let (mut sim, mut loader) = Simulation::from_replay(replay.clone()).unwrap();
+        let (mut sim2, mut loader2) = Simulation::from_replay(replay.clone()).unwrap();
 
         while !loader.advance_tick(&mut sim, &mut s) {
             loader2.advance_tick(&mut sim2, &mut s);
@@ -304,3 +307,97 @@ fn test_world_survives_serde() {
         break;
     }
 }
+
+#[test]
+fn test_world_survives_blobs() {
+    init();
+
+    let replay: Replay = JSONPretty::decode(REPLAY).unwrap();
+    let mut s = SeqSchedule::default();
+    let (mut sim, mut loader) = Simulation::from_replay(replay).unwrap();
+    for _ in 0..1000 {
+        if loader.advance_tick(&mut sim, &mut s) {
+            break;
+        }
+    }
+
+    let blobs = sim.to_blobs().unwrap();
+    assert!(blobs.iter().any(|(name, _)| name == "world/humans"));
+
+    let back = Simulation::from_blobs(blobs.clone()).unwrap();
+    assert!(check_eq(&back.world, &sim.world));
+    assert!(back.is_equal(&sim));
+
+    let mut headless = blobs.clone();
+    headless.retain(|(name, _)| name != "header");
+    assert!(Simulation::from_blobs(headless).is_none());
+
+    let mut newer = blobs;
+    newer[0].1 = Bincode::encode(&(
+        *b"EGRSAVE\0",
+        u32::MAX,
+        "0.0.0",
+        Vec::<prototypes::ModInfo>::new(),
+    ))
+    .unwrap();
+    assert!(Simulation::from_blobs(newer).is_none());
+}
+
+#[test]
+fn unversioned_saves_are_rejected() {
+    init();
+
+    // the layout used before the saves had a header
+    let old = (
+        World::default(),
+        "0.6.1".to_string(),
+        common::FastMap::<String, Vec<u8>>::default(),
+    );
+    let ser = Bincode::encode(&old).unwrap();
+    assert!(Bincode::decode::<Simulation>(&ser).is_err());
+
+    let sim = Simulation::new_with_options(SimulationOptions {
+        terrain_size: 1,
+        save_replay: false,
+    });
+    let ser = Bincode::encode(&sim).unwrap();
+    let deser: Simulation = Bincode::decode(&ser).unwrap();
+    assert!(deser.is_equal(&sim));
+}
+
+#[test]
+fn replays_start_from_their_base_save() {
+    init();
+
+    let mut schedule = SeqSchedule::default();
+    let mut sim = Simulation::new_with_options(SimulationOptions {
+        terrain_size: 1,
+        save_replay: false,
+    });
+    for _ in 0..10 {
+        sim.tick(&mut schedule, &[]);
+    }
+
+    let base_tick = Tick(sim.get_tick());
+    let base_save = format!("replay_base_test_{}", std::process::id());
+    sim.save_to_disk(&base_save);
+
+    let mut replay = Replay::starting_from(base_save.clone(), base_tick);
+    let loan = WorldCommand::TakeLoan {
+        principal: Money::new_bucks(10000),
+        term_days: 60,
+    };
+    replay.record(Tick(sim.get_tick()), [&loan]);
+    sim.tick(&mut schedule, [&loan]);
+
+    let replayed = Simulation::from_replay(replay);
+    let _ = std::fs::remove_file(CompressedBincode::filename(&base_save));
+    let (mut replayed, mut loader) = replayed.unwrap();
+    assert_eq!(loader.pastt.0, base_tick.0);
+
+    while !loader.advance_tick(&mut replayed, &mut schedule) {}
+
+    assert_eq!(replayed.get_tick(), sim.get_tick());
+    assert_eq!(replayed.read::<Government>().loans.len(), 1);
+    assert!(replayed.is_equal(&sim));
+}
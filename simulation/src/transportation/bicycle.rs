@@ -0,0 +1,27 @@
+use crate::utils::rand_provider::RandProvider;
+use egui_inspect::Inspect;
+use serde::{Deserialize, Serialize};
+
+/// Trips shorter than this are walked
+pub const MIN_CYCLING_DIST: f32 = 400.0;
+/// Trips longer than this are driven when a car is available
+pub const MAX_CYCLING_DIST: f32 = 4000.0;
+
+pub const BICYCLE_ACC: f32 = 1.0;
+
+/// A bicycle owned by a human.
+/// Cyclists ride on the bike lanes, or share the driving lanes when there are none.
+#[derive(Serialize, Deserialize, Inspect)]
+pub struct Bicycle {
+    pub riding_speed: f32,
+    pub riding: bool,
+}
+
+impl Bicycle {
+    pub(crate) fn new(r: &mut RandProvider) -> Self {
+        Self {
+            riding_speed: 4.0 + r.next_f32() * 2.0,
+            riding: false,
+        }
+    }
+}
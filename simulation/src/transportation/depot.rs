@@ -0,0 +1,345 @@
+use ordered_float::OrderedFloat;
+use prototypes::{try_prototype, GameTime, Money, RollingStockID};
+
+use crate::map::{BuildingKind, LaneID, LaneKind, Map, PathKind};
+use crate::map_dynamic::{Dispatcher, Itinerary};
+use crate::transportation::freight_route::FreightRoutes;
+use crate::transportation::train::{spawn_train, RailWagonKind};
+use crate::world::{TrainEnt, TrainID, WagonEnt};
+use crate::{ParCommandBuffer, Simulation, World};
+
+/// Price of the wagons, as bought when spawning or re-composing a train.
+/// None if one of them is not a known rolling stock.
+pub fn wagons_cost(wagons: &[RollingStockID]) -> Option<Money> {
+    wagons
+        .iter()
+        .map(|&id| try_prototype(id).map(|p| p.price))
+        .sum()
+}
+
+/// Price of the wagons of the new consist that are not part of the old one.
+/// None if one of them is not a known rolling stock.
+pub fn recompose_cost(old: &[RollingStockID], new: &[RollingStockID]) -> Option<Money> {
+    let mut reused = old.to_vec();
+    let bought: Vec<_> = new
+        .iter()
+        .copied()
+        .filter(|id| match reused.iter().position(|x| x == id) {
+            Some(i) => {
+                reused.swap_remove(i);
+                false
+            }
+            None => true,
+        })
+        .collect();
+    wagons_cost(&bought)
+}
+
+/// Whether the train was recalled and arrived at its depot
+pub fn in_depot(train: &TrainEnt) -> bool {
+    train.depot.is_some() && train.it.has_ended(0.0)
+}
+
+/// Kills the train and its wagons
+pub fn despawn_train(sim: &mut Simulation, id: TrainID) {
+    let wagons: Vec<_> = sim
+        .world
+        .wagons
+        .iter()
+        .filter(|(_, w)| w.itfollower.leader == id)
+        .map(|(wid, _)| wid)
+        .collect();
+    sim.write::<ParCommandBuffer<WagonEnt>>().kill_all(&wagons);
+    sim.write::<ParCommandBuffer<TrainEnt>>().kill(id);
+}
+
+/// Takes the train out of service, so that freight stations and routes can't use it anymore
+fn take_out_of_service(world: &mut World, routes: &mut FreightRoutes, id: TrainID) {
+    for f in world.freight_stations.values_mut() {
+        f.f.trains.retain(|(t, _)| *t != id);
+    }
+    routes.release_train(id);
+}
+
+/// Sends the train to the nearest depot it can reach. Returns false if there is none.
+pub fn recall_train(sim: &mut Simulation, id: TrainID) -> bool {
+    let tick = sim.read::<GameTime>().tick;
+    let map = sim.map();
+    let Some(train) = sim.world.trains.get(id) else {
+        return false;
+    };
+    let pos = train.trans.pos;
+
+    let mut depots: Vec<_> = map
+        .buildings()
+        .iter()
+        .filter(|(_, b)| matches!(b.kind, BuildingKind::TrainDepot))
+        .map(|(bid, b)| (bid, b.obb.center().z(0.0)))
+        .collect();
+    depots.sort_by_key(|(_, p)| OrderedFloat(p.distance2(pos)));
+
+    let Some((depot, it)) = depots
+        .into_iter()
+        .find_map(|(bid, p)| Some((bid, Itinerary::route(tick, pos, p, &map, PathKind::Rail)?)))
+    else {
+        return false;
+    };
+    drop(map);
+
+    let (world, res) = sim.world_res();
+    take_out_of_service(world, &mut res.write::<FreightRoutes>(), id);
+    res.write::<Dispatcher>().reserve(id);
+
+    let train = sim.world.trains.get_mut(id).unwrap();
+    train.it = it;
+    train.depot = Some(depot);
+    true
+}
+
+/// Puts a recalled train back in service
+pub fn release_train(sim: &mut Simulation, id: TrainID) {
+    let Some(train) = sim.world.trains.get_mut(id) else {
+        return;
+    };
+    if train.depot.take().is_some() {
+        train.it = Itinerary::NONE;
+        sim.write::<Dispatcher>().free(id);
+    }
+}
+
+/// Replaces the train by a new one with the given wagons at the same place.
+/// The new train keeps the depot and the kind of wagons of the old one.
+/// The old train is only removed once the new one is spawned.
+fn replace_train(
+    sim: &mut Simulation,
+    id: TrainID,
+    wagons: &[RollingStockID],
+    lane: LaneID,
+    dist: f32,
+) -> Option<TrainID> {
+    let depot = sim.world.trains.get(id)?.depot;
+    let kind = sim
+        .world
+        .wagons
+        .values()
+        .filter(|w| w.itfollower.leader == id)
+        .map(|w| w.wagon.kind)
+        .find(|kind| !matches!(kind, RailWagonKind::Locomotive))
+        .unwrap_or(RailWagonKind::Freight);
+
+    let new = spawn_train(sim, wagons, kind, lane, dist)?;
+
+    let (world, res) = sim.world_res();
+    take_out_of_service(world, &mut res.write::<FreightRoutes>(), id);
+    despawn_train(sim, id);
+
+    if depot.is_some() {
+        sim.world.trains.get_mut(new).unwrap().depot = depot;
+        sim.write::<Dispatcher>().reserve(new);
+    }
+    Some(new)
+}
+
+/// Where a train waiting in its depot is put back on the rails when re-composed
+pub fn recompose_place(sim: &Simulation, id: TrainID) -> Option<(LaneID, f32)> {
+    let map = sim.map();
+    let train = sim.world.trains.get(id)?;
+    let lane = map.nearest_lane(train.trans.pos, LaneKind::Rail, Some(20.0))?;
+    let l = map.lanes().get(lane)?;
+    let dist = l.points.length_at_proj(l.points.project(train.trans.pos));
+    Some((lane, dist))
+}
+
+/// Changes the wagons of a train waiting in its depot
+pub fn recompose_train(
+    sim: &mut Simulation,
+    id: TrainID,
+    wagons: &[RollingStockID],
+) -> Option<TrainID> {
+    let (lane, dist) = recompose_place(sim, id)?;
+    replace_train(sim, id, wagons, lane, dist)
+}
+
+/// Makes a stopped train go the other way: the wagons are put in reverse order
+/// on the opposite track, starting from the last wagon
+pub fn reverse_train(sim: &mut Simulation, id: TrainID) -> Option<TrainID> {
+    let (wagons, lane, dist) = {
+        let map = sim.map();
+        let train = sim.world.trains.get(id)?;
+        let tail = sim
+            .world
+            .wagons
+            .values()
+            .filter(|w| w.itfollower.leader == id)
+            .map(|w| w.trans.pos)
+            .max_by_key(|p| OrderedFloat(p.distance2(train.trans.pos)))
+            .unwrap_or(train.trans.pos);
+
+        let cur =
+            map.lanes()
+                .get(map.nearest_lane(train.trans.pos, LaneKind::Rail, Some(20.0))?)?;
+        let road = map.roads().get(cur.parent)?;
+        let opposite = road
+            .incoming_lanes_to(cur.src)
+            .iter()
+            .filter(|(_, kind)| kind.is_rail())
+            .filter_map(|&(l, _)| map.lanes().get(l))
+            .min_by_key(|l| OrderedFloat(l.points.project(tail).distance2(tail)))?;
+
+        let dist = opposite
+            .points
+            .length_at_proj(opposite.points.project(tail))
+            .max(train.locomotive.length)
+            .min(opposite.points.length());
+
+        if train.consist.is_empty() {
+            return None;
+        }
+        let mut wagons = train.consist.clone();
+        wagons.reverse();
+        (wagons, opposite.id, dist)
+    };
+    replace_train(sim, id, &wagons, lane, dist)
+}
+
+/// Whether the map has a depot trains can be recalled to
+pub fn has_depot(map: &Map) -> bool {
+    map.buildings()
+        .values()
+        .any(|b| matches!(b.kind, BuildingKind::TrainDepot))
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+    use prototypes::{Money, RollingStockID};
+
+    use super::*;
+    use crate::economy::Government;
+    use crate::map::{BuildingID, LanePatternBuilder, ProjectFilter};
+    use crate::tests::TestCtx;
+    use crate::world_command::CommandError;
+    use crate::WorldCommand;
+
+    #[test]
+    fn recompose_cost_only_counts_new_wagons() {
+        let _test = TestCtx::new();
+
+        let loco = RollingStockID::new("locomotive");
+        let wagon = RollingStockID::new("freight-wagon");
+        let emu = RollingStockID::new("passenger-emu-middle");
+        let price = |id: RollingStockID| RollingStockID::prototype(id).price;
+
+        assert_eq!(
+            recompose_cost(&[loco, wagon], &[wagon, loco]),
+            Some(Money::ZERO)
+        );
+        assert_eq!(
+            recompose_cost(&[loco, wagon], &[loco, wagon, wagon]),
+            Some(price(wagon))
+        );
+        assert_eq!(
+            recompose_cost(&[loco, wagon], &[loco, emu]),
+            Some(price(emu))
+        );
+        assert_eq!(
+            recompose_cost(&[loco], &[loco, RollingStockID::new("unknown-wagon")]),
+            None
+        );
+    }
+
+    #[test]
+    fn recompose_keeps_the_wagon_kind_and_the_depot() {
+        let mut test = TestCtx::new();
+        {
+            let mut m = test.g.map_mut();
+            let a = m.project(vec3(0.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let b = m.project(vec3(300.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let pat = LanePatternBuilder::new().rail(true).one_way(true).build();
+            m.make_connection(a, b, None, &pat);
+        }
+        let lane = test
+            .g
+            .map()
+            .lanes()
+            .iter()
+            .find(|(_, l)| l.kind.is_rail())
+            .unwrap()
+            .0;
+
+        let loco = RollingStockID::new("locomotive");
+        let wagon = RollingStockID::new("passenger-wagon");
+        let old = spawn_train(
+            &mut test.g,
+            &[loco, wagon],
+            RailWagonKind::Passenger,
+            lane,
+            150.0,
+        )
+        .unwrap();
+        let depot = BuildingID::default();
+        test.g
+            .world_mut_unchecked()
+            .trains
+            .get_mut(old)
+            .unwrap()
+            .depot = Some(depot);
+
+        // unknown rolling stock is rejected, it doesn't panic while computing the cost
+        let unknown = WorldCommand::RecomposeTrain {
+            train: old,
+            wagons: vec![loco, RollingStockID::new("unknown-wagon")],
+        };
+        assert_eq!(
+            unknown.validate(&test.g),
+            Err(CommandError::MissingEntity("rolling stock"))
+        );
+        let unknown = WorldCommand::SpawnTrain {
+            wagons: vec![RollingStockID::new("unknown-wagon")],
+            lane,
+            dist: 150.0,
+        };
+        assert_eq!(
+            unknown.validate(&test.g),
+            Err(CommandError::MissingEntity("rolling stock"))
+        );
+
+        // wagons alone cannot move
+        let no_loco = WorldCommand::RecomposeTrain {
+            train: old,
+            wagons: vec![wagon, wagon],
+        };
+        assert_eq!(no_loco.validate(&test.g), Err(CommandError::NoLocomotive));
+        let no_loco = WorldCommand::SpawnTrain {
+            wagons: vec![wagon],
+            lane,
+            dist: 150.0,
+        };
+        assert_eq!(no_loco.validate(&test.g), Err(CommandError::NoLocomotive));
+
+        let before = test.g.read::<Government>().money;
+        let wagons = vec![loco, wagon, wagon];
+        WorldCommand::RecomposeTrain {
+            train: old,
+            wagons: wagons.clone(),
+        }
+        .apply(&mut test.g)
+        .unwrap();
+        assert_eq!(
+            test.g.read::<Government>().money,
+            before - RollingStockID::prototype(wagon).price
+        );
+        test.tick();
+
+        let world = test.g.world();
+        assert!(world.trains.get(old).is_none());
+        let (new, train) = world.trains.iter().next().unwrap();
+        assert_eq!(train.consist, wagons);
+        assert_eq!(train.depot, Some(depot));
+        assert!(world
+            .wagons
+            .values()
+            .filter(|w| w.itfollower.leader == new && w.wagon.rolling_stock == wagon)
+            .all(|w| matches!(w.wagon.kind, RailWagonKind::Passenger)));
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::Vec2;
+use prototypes::{GameDuration, GameInstant, GameTime, ItemID, RollingStockID};
+
+use crate::economy::Market;
+use crate::map::{BuildingID, Map, PathKind};
+use crate::map_dynamic::{
+    BuildingInfos, DispatchID, DispatchKind, DispatchQueryTarget, Dispatcher, Itinerary,
+};
+use crate::souls::freight_station::station_dock;
+use crate::utils::resources::Resources;
+use crate::world::{FreightStationID, TrainID};
+use crate::{SoulID, World};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FreightRouteID(pub u32);
+
+/// When the train of a freight route leaves and how long it stops at each station
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Timetable {
+    /// Time between two departures from the first station
+    pub interval: GameDuration,
+    pub loading_time: GameDuration,
+    pub unloading_time: GameDuration,
+}
+
+impl Default for Timetable {
+    fn default() -> Self {
+        Self {
+            interval: GameDuration::from_minutes(60),
+            loading_time: GameDuration::from_secs(30),
+            unloading_time: GameDuration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FreightRouteState {
+    /// Waiting for a free freight train
+    NoTrain,
+    /// The train is going to the n-th station
+    GoingTo(usize),
+    /// The train is loading and unloading at the n-th station
+    Stopped { stop: usize, until: GameInstant },
+}
+
+/// A freight train going through several freight stations in order, then back to the first one.
+/// At each station, it unloads everything it carries and loads the waiting cargo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreightRoute {
+    /// The freight stations buildings
+    pub stations: Vec<BuildingID>,
+    /// The items carried, any item if empty
+    pub items: Vec<ItemID>,
+    pub timetable: Timetable,
+    pub train: Option<TrainID>,
+    pub state: FreightRouteState,
+    pub next_departure: GameInstant,
+    /// Goods on board
+    pub cargo: BTreeMap<ItemID, u32>,
+    /// Quantity of goods the wagons of the train can carry
+    pub capacity: u32,
+    /// Quantity of goods carried since the route was created
+    pub carried: u64,
+}
+
+impl FreightRoute {
+    pub fn new(stations: Vec<BuildingID>, items: Vec<ItemID>, timetable: Timetable) -> Self {
+        Self {
+            stations,
+            items,
+            timetable,
+            train: None,
+            state: FreightRouteState::NoTrain,
+            next_departure: GameInstant(Default::default()),
+            cargo: BTreeMap::new(),
+            capacity: 0,
+            carried: 0,
+        }
+    }
+
+    pub fn carries(&self, item: ItemID) -> bool {
+        self.items.is_empty() || self.items.contains(&item)
+    }
+
+    pub fn serves(&self, station: BuildingID) -> bool {
+        self.stations.contains(&station)
+    }
+
+    pub fn load(&self) -> u32 {
+        self.cargo.values().sum()
+    }
+
+    /// Loads the goods carried by the route from the station, up to the capacity of the train.
+    /// Returns the quantity loaded.
+    pub fn load_from(&mut self, station_cargo: &mut BTreeMap<ItemID, u32>) -> u32 {
+        let mut room = self.capacity.saturating_sub(self.load());
+        let mut loaded = 0;
+        for (&item, qty) in station_cargo.iter_mut() {
+            if room == 0 {
+                break;
+            }
+            if !self.carries(item) {
+                continue;
+            }
+            let taken = (*qty).min(room);
+            *qty -= taken;
+            room -= taken;
+            loaded += taken;
+            *self.cargo.entry(item).or_default() += taken;
+        }
+        station_cargo.retain(|_, qty| *qty > 0);
+        self.carried += loaded as u64;
+        loaded
+    }
+
+    /// Hands the goods on board over to the station, which sells them on the market.
+    /// They were taken from the sellers' stock when sold to the loading station,
+    /// so they only move from the train to the station here.
+    /// Returns the quantity unloaded.
+    pub fn unload_to(&mut self, market: &mut Market, soul: SoulID, near: Vec2) -> u32 {
+        let mut unloaded = 0;
+        for (item, qty) in std::mem::take(&mut self.cargo) {
+            let stock = market.produce(soul, item, qty as i32);
+            // keep everything on the map, the goods were brought here to be sold here
+            market.sell_all(soul, near, item, stock as u32);
+            unloaded += qty;
+        }
+        unloaded
+    }
+}
+
+/// All the freight routes of the map
+#[derive(Default, Serialize, Deserialize)]
+pub struct FreightRoutes {
+    routes: BTreeMap<FreightRouteID, FreightRoute>,
+    next_id: u32,
+}
+
+impl FreightRoutes {
+    pub fn insert(&mut self, route: FreightRoute) -> FreightRouteID {
+        let id = FreightRouteID(self.next_id);
+        self.next_id += 1;
+        self.routes.insert(id, route);
+        id
+    }
+
+    /// Changes the route, the train keeps its cargo.
+    /// If the stations changed, the train heads to the first one.
+    pub fn update(
+        &mut self,
+        id: FreightRouteID,
+        stations: Vec<BuildingID>,
+        items: Vec<ItemID>,
+        timetable: Timetable,
+    ) {
+        let Some(route) = self.routes.get_mut(&id) else {
+            return;
+        };
+        if route.stations != stations {
+            route.stations = stations;
+            if route.train.is_some() {
+                route.state = FreightRouteState::Stopped {
+                    stop: route.stations.len() - 1,
+                    until: GameInstant(Default::default()),
+                };
+            }
+        }
+        route.items = items;
+        route.timetable = timetable;
+    }
+
+    pub fn remove(&mut self, id: FreightRouteID) -> Option<FreightRoute> {
+        self.routes.remove(&id)
+    }
+
+    pub fn get(&self, id: FreightRouteID) -> Option<&FreightRoute> {
+        self.routes.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (FreightRouteID, &FreightRoute)> {
+        self.routes.iter().map(|(&id, r)| (id, r))
+    }
+
+    /// The train is not used by its route anymore, the route will look for another one
+    pub fn release_train(&mut self, train: TrainID) {
+        for route in self.routes.values_mut() {
+            if route.train == Some(train) {
+                route.train = None;
+                route.state = FreightRouteState::NoTrain;
+                route.cargo.clear();
+            }
+        }
+    }
+
+    /// Whether the goods delivered at the station are taken by a freight route
+    pub fn takes(&self, station: BuildingID, item: ItemID) -> bool {
+        self.routes
+            .values()
+            .any(|r| r.serves(station) && r.carries(item))
+    }
+}
+
+fn station_soul(binfos: &BuildingInfos, building: BuildingID) -> Option<FreightStationID> {
+    let Some(SoulID::FreightStation(id)) = binfos.owner(building) else {
+        return None;
+    };
+    Some(id)
+}
+
+/// Sum of the capacity of the wagons of the train
+pub fn train_capacity(world: &World, train: TrainID) -> u32 {
+    world
+        .wagons
+        .values()
+        .filter(|w| w.itfollower.leader == train)
+        .map(|w| RollingStockID::prototype(w.wagon.rolling_stock).capacity)
+        .sum()
+}
+
+pub fn freight_route_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("transportation::freight_route_system");
+    let mut routes = resources.write::<FreightRoutes>();
+    let mut dispatch = resources.write::<Dispatcher>();
+    let mut market = resources.write::<Market>();
+    let binfos = resources.read::<BuildingInfos>();
+    let map = resources.read::<Map>();
+    let time = resources.read::<GameTime>();
+    let now = time.instant();
+
+    for route in routes.routes.values_mut() {
+        let n_stops = route.stations.len();
+        if n_stops == 0 {
+            continue;
+        }
+
+        if route.train.map_or(true, |t| !world.trains.contains_key(t)) {
+            route.train = None;
+            route.state = FreightRouteState::NoTrain;
+            route.cargo.clear();
+        }
+
+        match route.state {
+            FreightRouteState::NoTrain => {
+                let Some(f) = station_soul(&binfos, route.stations[0])
+                    .and_then(|id| world.freight_stations.get(id))
+                else {
+                    continue;
+                };
+                let dock = station_dock(&f.trans);
+                let Some(DispatchID::FreightTrain(trainid)) = dispatch.query(
+                    &map,
+                    DispatchKind::FreightTrain,
+                    DispatchQueryTarget::Pos(dock),
+                ) else {
+                    continue;
+                };
+                let train = world.trains.get_mut(trainid).unwrap();
+                let Some(it) =
+                    Itinerary::route(time.tick, train.trans.pos, dock, &map, PathKind::Rail)
+                else {
+                    dispatch.free(trainid);
+                    continue;
+                };
+                train.it = it;
+                route.train = Some(trainid);
+                route.capacity = train_capacity(world, trainid);
+                if route.capacity == 0 {
+                    log::warn!(
+                        "freight route got train {:?} which has no wagon to carry goods",
+                        trainid
+                    );
+                }
+                route.state = FreightRouteState::GoingTo(0);
+            }
+            FreightRouteState::GoingTo(stop) => {
+                let train = world.trains.get(route.train.unwrap()).unwrap();
+                if !train.it.has_ended(time.timestamp) {
+                    continue;
+                }
+                let Some(id) = station_soul(&binfos, route.stations[stop]) else {
+                    // the station was destroyed, go to the next one
+                    route.state = FreightRouteState::Stopped { stop, until: now };
+                    continue;
+                };
+                let Some(f) = world.freight_stations.get_mut(id) else {
+                    route.state = FreightRouteState::Stopped { stop, until: now };
+                    continue;
+                };
+                let near = map
+                    .buildings()
+                    .get(f.f.building)
+                    .map_or(f.trans.pos.xy(), |b| b.door_pos.xy());
+                let soul = SoulID::FreightStation(id);
+
+                let mut until = now;
+                if route.unload_to(&mut market, soul, near) > 0 {
+                    until = until + route.timetable.unloading_time;
+                }
+                if route.load_from(&mut f.f.cargo) > 0 {
+                    until = until + route.timetable.loading_time;
+                }
+                if stop == 0 {
+                    until = until.max(route.next_departure);
+                    route.next_departure = until + route.timetable.interval;
+                }
+                route.state = FreightRouteState::Stopped { stop, until };
+            }
+            FreightRouteState::Stopped { stop, until } => {
+                if now < until {
+                    continue;
+                }
+                let next = (stop + 1) % n_stops;
+                let Some(f) = station_soul(&binfos, route.stations[next])
+                    .and_then(|id| world.freight_stations.get(id))
+                else {
+                    route.state = FreightRouteState::GoingTo(next);
+                    continue;
+                };
+                let dock = station_dock(&f.trans);
+                let train = world.trains.get_mut(route.train.unwrap()).unwrap();
+                let Some(it) =
+                    Itinerary::route(time.tick, train.trans.pos, dock, &map, PathKind::Rail)
+                else {
+                    // no path for now, try again later
+                    route.state = FreightRouteState::Stopped {
+                        stop,
+                        until: now + GameDuration::from_secs(10),
+                    };
+                    continue;
+                };
+                train.it = it;
+                route.state = FreightRouteState::GoingTo(next);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::test_prototypes;
+
+    use super::*;
+    use crate::FreightStationID;
+
+    #[test]
+    fn load_respects_capacity_and_items() {
+        let wood = ItemID::new("wood");
+        let iron = ItemID::new("iron");
+
+        let mut route = FreightRoute::new(vec![], vec![wood], Timetable::default());
+        route.capacity = 50;
+
+        let mut station = BTreeMap::from([(wood, 80), (iron, 10)]);
+        assert_eq!(route.load_from(&mut station), 50);
+        assert_eq!(route.cargo[&wood], 50);
+        assert_eq!(station, BTreeMap::from([(wood, 30), (iron, 10)]));
+
+        assert_eq!(route.load_from(&mut station), 0);
+        assert_eq!(route.carried, 50);
+    }
+
+    #[test]
+    fn unload_moves_the_cargo_to_the_station() {
+        test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "wood",
+            label = "Wood"
+          },
+        }
+        "#,
+        );
+        let wood = ItemID::new("wood");
+        let station = SoulID::FreightStation(FreightStationID::from(slotmapd::KeyData::from_ffi(
+            (1 << 32) | 1,
+        )));
+
+        let mut market = Market::default();
+        let mut route = FreightRoute::new(vec![], vec![], Timetable::default());
+        route.capacity = 50;
+
+        let mut loading = BTreeMap::from([(wood, 30)]);
+        assert_eq!(route.load_from(&mut loading), 30);
+        assert!(loading.is_empty());
+
+        assert_eq!(route.unload_to(&mut market, station, Vec2::ZERO), 30);
+        assert_eq!(route.load(), 0);
+        assert_eq!(market.capital(station, wood), 30);
+
+        // nothing left to unload, nothing is created
+        assert_eq!(route.unload_to(&mut market, station, Vec2::ZERO), 0);
+        assert_eq!(market.capital(station, wood), 30);
+
+        // the unloaded goods are sold on the map, not exported
+        let order = market.m(wood).sell_order(station).unwrap();
+        assert_eq!(order.qty, 30);
+        assert_eq!(order.stock, 30);
+    }
+}
@@ -3,6 +3,7 @@ use std::fmt::{Debug, Formatter};
 use flat_spatial::grid::GridHandle;
 use serde::{Deserialize, Serialize};
 
+pub use bicycle::*;
 use egui_inspect::InspectVec2Rotation;
 use geom::{Transform, Vec2};
 pub use pedestrian::*;
@@ -13,8 +14,12 @@ use crate::utils::resources::Resources;
 use crate::world::VehicleID;
 use crate::{Simulation, World};
 
+pub mod bicycle;
+pub mod depot;
+pub mod freight_route;
 pub mod pedestrian;
 pub mod road;
+pub mod signals;
 pub mod testing_vehicles;
 pub mod train;
 mod vehicle;
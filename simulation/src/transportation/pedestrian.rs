@@ -1,6 +1,6 @@
 use crate::map_dynamic::Itinerary;
 use crate::transportation::{
-    Speed, TransportGrid, TransportState, TransportationGroup, Transporter,
+    Bicycle, Speed, TransportGrid, TransportState, TransportationGroup, Transporter, BICYCLE_ACC,
 };
 use crate::utils::rand_provider::RandProvider;
 use crate::utils::resources::Resources;
@@ -70,7 +70,7 @@ pub fn pedestrian_decision_system(world: &mut World, _resources: &mut Resources)
     world.humans
         .values_mut()
         //.par_bridge()
-        .for_each(|human| pedestrian_decision(&mut human.it, &mut human.trans, &mut human.speed, &mut human.pedestrian))
+        .for_each(|human| pedestrian_decision(&mut human.it, &mut human.trans, &mut human.speed, &mut human.pedestrian, human.bicycle.as_ref()))
 }
 
 pub fn pedestrian_decision(
@@ -78,19 +78,34 @@ pub fn pedestrian_decision(
     trans: &mut Transform,
     kin: &mut Speed,
     pedestrian: &mut Pedestrian,
+    bicycle: Option<&Bicycle>,
 ) {
-    let (desired_v, desired_dir) = calc_decision(pedestrian, trans, it);
+    let (mut desired_v, desired_dir) = calc_decision(pedestrian, trans, it);
+
+    if let Some(bicycle) = bicycle.filter(|b| b.riding) {
+        if desired_v > 0.0 {
+            desired_v = bicycle.riding_speed;
+        }
+        physics(kin, trans, desired_v, desired_dir, BICYCLE_ACC);
+        return;
+    }
 
     pedestrian.walk_anim += 7.0 * kin.0 * DELTA / pedestrian.walking_speed;
     pedestrian.walk_anim %= 2.0 * std::f32::consts::PI;
-    physics(kin, trans, desired_v, desired_dir);
+    physics(kin, trans, desired_v, desired_dir, PEDESTRIAN_ACC);
 }
 
 const PEDESTRIAN_ACC: f32 = 1.5;
 
-pub fn physics(kin: &mut Speed, trans: &mut Transform, desired_velocity: f32, desired_dir: Vec3) {
+pub fn physics(
+    kin: &mut Speed,
+    trans: &mut Transform,
+    desired_velocity: f32,
+    desired_dir: Vec3,
+    acc: f32,
+) {
     let diff = desired_velocity - kin.0;
-    let mag = diff.min(DELTA * PEDESTRIAN_ACC);
+    let mag = diff.min(DELTA * acc);
     if mag > 0.0 {
         kin.0 += mag;
     }
@@ -206,7 +206,9 @@ pub fn calc_decision<'a>(
 
     let cutoff = (0.8 + stop_dist).min(1.5);
 
-    let (front_dist, flag) = calc_front_dist(vehicle, trans, self_obj, it, neighs, cutoff);
+    let merging = is_merging(map, it, trans.pos);
+
+    let (front_dist, flag) = calc_front_dist(vehicle, trans, self_obj, it, neighs, cutoff, merging);
 
     let position = trans.pos;
     let dir_to_pos = unwrap_or!(
@@ -296,6 +298,32 @@ pub fn calc_decision<'a>(
     )
 }
 
+/// Distance before the end of a lane at which vehicles start zipper merging
+const MERGE_DIST: f32 = 30.0;
+
+/// Whether the vehicle is approaching or going through a lane merge at a ramp or merge junction
+fn is_merging(map: &Map, it: &Itinerary, position: Vec3) -> bool {
+    let Some(travers) = it.get_travers() else {
+        return false;
+    };
+    match travers.kind {
+        TraverseKind::Turn(id) => map
+            .intersections()
+            .get(id.parent)
+            .map_or(false, |inter| inter.merges_from(id.src)),
+        TraverseKind::Lane(id) => {
+            let Some(l) = map.lanes().get(id) else {
+                return false;
+            };
+            l.control_point().is_close(position, MERGE_DIST)
+                && map
+                    .intersections()
+                    .get(l.dst)
+                    .map_or(false, |inter| inter.merges_from(id))
+        }
+    }
+}
+
 /// Calculates the distance to the closest problematic object in front of the car.
 /// It can be another car or a pedestrian, or it can be a potential collision point from a
 /// car coming perpendicularly.
@@ -306,6 +334,7 @@ fn calc_front_dist<'a>(
     it: &Itinerary,
     neighs: impl Iterator<Item = (Vec2, &'a TransportState)>,
     cutoff: f32,
+    merging: bool,
 ) -> (f32, u64) {
     let position = trans.pos;
     let direction = trans.dir;
@@ -375,6 +404,16 @@ fn calc_front_dist<'a>(
             continue;
         }
 
+        // Zipper merging: vehicles going the same way alongside us go first if they are ahead
+        if merging && cos_direction_angle > 0.7 && dist_to_side < 5.0 {
+            let dist_to_obj = towards_vec.dot(dir2) - my_radius - nei_physics_obj.radius;
+            if dist_to_obj < min_front_dist {
+                min_front_dist = dist_to_obj;
+                flag = nei_physics_obj.flag;
+            }
+            continue;
+        }
+
         // closest win
         let his_ray = Ray {
             from: his_pos - nei_physics_obj.radius * nei_physics_obj.dir,
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::map::{IntersectionID, Intersections, LaneID, Lanes, TraverseKind};
+use crate::world::TrainID;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RailSignalKind {
+    /// Lets a train through if the path until the next signal is free
+    Block,
+    /// Lets a train through only if the path until the next block signal is free,
+    /// so that trains never wait in the middle of a junction
+    Chain,
+}
+
+/// Signals placed at the end of rail lanes, cutting the tracks into blocks.
+/// To pass a signal, a train reserves the lanes and junctions of its path through the block behind it,
+/// so two trains can share a block as long as their paths don't cross, like in a passing loop.
+#[derive(Default, Serialize, Deserialize)]
+pub struct RailSignals {
+    signals: BTreeMap<LaneID, RailSignalKind>,
+    lanes: BTreeMap<LaneID, TrainID>,
+    inters: BTreeMap<IntersectionID, TrainID>,
+}
+
+impl RailSignals {
+    pub fn get(&self, lane: LaneID) -> Option<RailSignalKind> {
+        self.signals.get(&lane).copied()
+    }
+
+    pub fn set(&mut self, lane: LaneID, kind: Option<RailSignalKind>) {
+        match kind {
+            Some(kind) => self.signals.insert(lane, kind),
+            None => self.signals.remove(&lane),
+        };
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LaneID, RailSignalKind)> + '_ {
+        self.signals.iter().map(|(&l, &k)| (l, k))
+    }
+
+    /// The train that reserved the lane or the junction
+    pub fn reserved_by(&self, kind: TraverseKind) -> Option<TrainID> {
+        match kind {
+            TraverseKind::Lane(id) => self.lanes.get(&id).copied(),
+            TraverseKind::Turn(id) => self.inters.get(&id.parent).copied(),
+        }
+    }
+
+    /// Iterates over the signals at the end of the lanes of the path, with the distance to them
+    /// and the rest of the path after them. `start` is the distance along the first traverse of the path.
+    pub fn upcoming<'a>(
+        &'a self,
+        lanes: &'a Lanes,
+        inters: &'a Intersections,
+        path: &'a [TraverseKind],
+        start: f32,
+    ) -> impl Iterator<Item = (f32, RailSignalKind, &'a [TraverseKind])> + 'a {
+        let mut acc = -start;
+        path.iter().enumerate().filter_map(move |(i, kind)| {
+            acc += kind.length(lanes, inters).unwrap_or(0.0);
+            let TraverseKind::Lane(lane) = *kind else {
+                return None;
+            };
+            let signal = self.get(lane)?;
+            Some((acc, signal, &path[i + 1..]))
+        })
+    }
+
+    /// Whether the train reserved the block after a signal, given its path after the signal
+    pub fn passable(&self, me: TrainID, after: &[TraverseKind]) -> bool {
+        let Some(&entry) = after.iter().find(|k| k.is_lane()) else {
+            return true;
+        };
+        self.reserved_by(entry) == Some(me)
+    }
+
+    /// The part of the path to reserve to pass a signal: until the next signal for a block signal,
+    /// or until the next block signal for a chain signal.
+    pub fn block_path(&self, signal: RailSignalKind, after: &[TraverseKind]) -> Vec<TraverseKind> {
+        let mut path = Vec::new();
+        for &kind in after {
+            path.push(kind);
+            let TraverseKind::Lane(lane) = kind else {
+                continue;
+            };
+            match (signal, self.get(lane)) {
+                (_, Some(RailSignalKind::Block)) | (RailSignalKind::Block, Some(_)) => break,
+                _ => {}
+            }
+        }
+        path
+    }
+
+    /// Reserves the whole path for the train, or nothing if a part of it is already reserved.
+    /// Only junctions are reserved at intersections, `others` are the junctions reserved
+    /// by the trains outside of the signalled blocks.
+    pub fn reserve(
+        &mut self,
+        me: TrainID,
+        path: &[TraverseKind],
+        inters: &Intersections,
+        others: &BTreeMap<IntersectionID, TrainID>,
+    ) -> bool {
+        let reservable = |kind: &TraverseKind| match *kind {
+            TraverseKind::Lane(_) => true,
+            TraverseKind::Turn(id) => inters.get(id.parent).map_or(false, |i| i.roads.len() > 2),
+        };
+        let taken = |kind: &TraverseKind| {
+            let other = match *kind {
+                TraverseKind::Lane(_) => None,
+                TraverseKind::Turn(id) => others.get(&id.parent).copied(),
+            };
+            [self.reserved_by(*kind), other]
+                .into_iter()
+                .flatten()
+                .any(|t| t != me)
+        };
+
+        if path.iter().filter(|k| reservable(k)).any(taken) {
+            return false;
+        }
+        for kind in path.iter().filter(|k| reservable(k)) {
+            match *kind {
+                TraverseKind::Lane(id) => self.lanes.insert(id, me),
+                TraverseKind::Turn(id) => self.inters.insert(id.parent, me),
+            };
+        }
+        true
+    }
+
+    /// The train left the lane or the junction
+    pub fn release(&mut self, me: TrainID, kind: TraverseKind) {
+        match kind {
+            TraverseKind::Lane(id) => {
+                if self.lanes.get(&id) == Some(&me) {
+                    self.lanes.remove(&id);
+                }
+            }
+            TraverseKind::Turn(id) => {
+                if self.inters.get(&id.parent) == Some(&me) {
+                    self.inters.remove(&id.parent);
+                }
+            }
+        }
+    }
+
+    /// Removes the signals on removed lanes, and the reservations of the trains that won't use them,
+    /// for example after being rerouted. `uses` tells whether the train is on or will go through
+    /// a traverse matching the predicate.
+    pub fn clean(
+        &mut self,
+        lanes: &Lanes,
+        uses: impl Fn(TrainID, &dyn Fn(&TraverseKind) -> bool) -> bool,
+    ) {
+        self.signals.retain(|&id, _| lanes.contains_key(id));
+        self.lanes
+            .retain(|&id, &mut t| uses(t, &|k| *k == TraverseKind::Lane(id)));
+        self.inters.retain(|&id, &mut t| {
+            uses(
+                t,
+                &|k| matches!(k, TraverseKind::Turn(turn) if turn.parent == id),
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lane(id: u64) -> LaneID {
+        LaneID::from(slotmapd::KeyData::from_ffi(id))
+    }
+
+    #[test]
+    fn chain_signals_reserve_until_block_signal() {
+        let mut signals = RailSignals::default();
+        signals.set(lane(2), Some(RailSignalKind::Chain));
+        signals.set(lane(3), Some(RailSignalKind::Block));
+
+        let after: Vec<_> = (1..=4).map(|i| TraverseKind::Lane(lane(i))).collect();
+
+        let block = signals.block_path(RailSignalKind::Block, &after);
+        assert_eq!(block, after[..2]);
+
+        let chain = signals.block_path(RailSignalKind::Chain, &after);
+        assert_eq!(chain, after[..3]);
+    }
+}
@@ -11,11 +11,15 @@ use prototypes::{RollingStockID, DELTA};
 
 use crate::map::{IntersectionID, LaneID, Map, TraverseKind};
 use crate::map_dynamic::ItineraryFollower;
+use crate::transportation::signals::RailSignals;
 use crate::transportation::Speed;
 use crate::utils::resources::Resources;
 use crate::world::{TrainEnt, TrainID, WagonEnt};
 use crate::{Itinerary, ItineraryLeader, Simulation, World};
 
+/// How far ahead of their stopping distance trains try to reserve the blocks behind signals, in meters
+const SIGNAL_LOOKAHEAD: f32 = 50.0;
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct TrainReservations {
     pub reservations: BTreeMap<IntersectionID, TrainID>,
@@ -161,6 +165,8 @@ pub fn spawn_train(
         leader: ItineraryLeader {
             past: Polyline3Queue::new(points.into_iter(), locopos, train_length + 20.0),
         },
+        consist: wagons.to_vec(),
+        depot: None,
     });
 
     let leader = &world.trains.get(loco).unwrap().leader;
@@ -231,8 +237,21 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
     profiling::scope!("transportation::train_reservations_update");
     let map = &*resources.read::<Map>();
     let reservations = &mut *resources.write::<TrainReservations>();
+    let signals = &mut *resources.write::<RailSignals>();
     let lanes = map.lanes();
     let inters = map.intersections();
+
+    signals.clean(lanes, |t, matches| {
+        let Some(train) = world.trains.get(t) else {
+            return false;
+        };
+        train.res.past_travers.keys().any(matches)
+            || train.it.get_travers().map_or(false, |tr| matches(&tr.kind))
+            || train.it.get_route().map_or(false, |r| {
+                r.reversed_route.iter().any(|tr| matches(&tr.kind))
+            })
+    });
+
     world.trains.iter_mut().for_each(move |(me, train)| {
         // Remember when we've been
         if let Some(travers) = train.it.get_travers() {
@@ -290,7 +309,11 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
                             continue;
                         }
 
-                        if reservations.reservations.get(&id.parent).is_some() {
+                        if reservations.reservations.get(&id.parent).is_some()
+                            || signals
+                                .reserved_by(TraverseKind::Turn(id))
+                                .map_or(false, |t| t != me)
+                        {
                             all_ok = false;
                             break;
                         }
@@ -305,6 +328,24 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
                     }
                 }
             }
+
+            // Reserve the blocks behind the signals we are about to pass, in order
+            let path = train_path(&train.it);
+            let upcoming: Vec<_> = signals
+                .upcoming(lanes, inters, &path, train.res.cur_travers_dist)
+                .take_while(|(dist, _, _)| *dist <= stop_dist + SIGNAL_LOOKAHEAD)
+                .map(|(_, signal, after)| (signal, path.len() - after.len()))
+                .collect();
+            for (signal, i) in upcoming {
+                let after = &path[i..];
+                if signals.passable(me, after) {
+                    continue;
+                }
+                let block = signals.block_path(signal, after);
+                if !signals.reserve(me, &block, inters, &reservations.reservations) {
+                    break;
+                }
+            }
         }
 
         // Clean past_things and unreserve them
@@ -319,6 +360,7 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
                 if let TraverseKind::Turn(id) = id {
                     reservations.reservations.remove(&id.parent);
                 }
+                signals.release(me, id);
                 let l = unwrap_ret!(reservations.localisations.get_mut(&id), false);
                 l.remove(&me);
                 if l.is_empty() {
@@ -335,10 +377,24 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
     });
 }
 
+/// The traverse the train is on, followed by the ones of its route
+fn train_path(it: &Itinerary) -> Vec<TraverseKind> {
+    it.get_travers()
+        .into_iter()
+        .map(|t| t.kind)
+        .chain(
+            it.get_route()
+                .into_iter()
+                .flat_map(|r| r.reversed_route.iter().rev().map(|t| t.kind)),
+        )
+        .collect()
+}
+
 pub fn locomotive_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("transportation::locomotive_system");
     let map: &Map = &resources.read();
     let reservs: &TrainReservations = &resources.read();
+    let signals: &RailSignals = &resources.read();
 
     // asume iter order stays the same
     let mut desired_speeds = Vec::with_capacity(world.trains.len());
@@ -348,6 +404,7 @@ pub fn locomotive_system(world: &mut World, resources: &mut Resources) {
             ent,
             map,
             reservs,
+            signals,
             &world.trains,
             train,
         ));
@@ -389,6 +446,7 @@ pub fn locomotive_desired_speed(
     me: TrainID,
     map: &Map,
     reservs: &TrainReservations,
+    signals: &RailSignals,
     locos: &HopSlotMap<TrainID, TrainEnt>,
     t: &TrainEnt,
 ) -> f32 {
@@ -398,6 +456,22 @@ pub fn locomotive_desired_speed(
 
     let stop_dist = t.speed.0 * t.speed.0 / (2.0 * t.locomotive.dec_force);
 
+    // Stop at the signals whose block we could not reserve
+    let path = train_path(&t.it);
+    for (dist, _, after) in signals.upcoming(
+        map.lanes(),
+        map.intersections(),
+        &path,
+        t.res.cur_travers_dist,
+    ) {
+        if dist > stop_dist + 15.0 {
+            break;
+        }
+        if !signals.passable(me, after) {
+            return 0.0;
+        }
+    }
+
     let mut lastid = None;
     let mydist = t.res.cur_travers_dist;
     if let Some(travers) = t.it.get_travers() {
@@ -6,7 +6,7 @@ use crate::Simulation;
 use egui_inspect::Inspect;
 use geom::Transform;
 use geom::{Color, Spline3, Vec3};
-use prototypes::GameInstant;
+use prototypes::{GameInstant, RoadVehicleKind, RoadVehiclePrototype};
 use serde::{Deserialize, Serialize};
 
 /// The duration for the parking animation.
@@ -58,6 +58,16 @@ pub fn put_vehicle_in_transport_grid(sim: &Simulation, w: f32, trans: Transform)
 }
 
 impl VehicleKind {
+    /// The first road vehicle prototype of this kind, if a mod defines one
+    pub fn prototype(self) -> Option<&'static RoadVehiclePrototype> {
+        let kind = match self {
+            VehicleKind::Car => RoadVehicleKind::Car,
+            VehicleKind::Truck => RoadVehicleKind::Truck,
+            VehicleKind::Bus => RoadVehicleKind::Bus,
+        };
+        RoadVehiclePrototype::iter().find(|p| p.kind == kind)
+    }
+
     pub fn width(self) -> f32 {
         match self {
             VehicleKind::Car => 4.5,
@@ -29,6 +29,11 @@ impl<E: SimDrop> ParCommandBuffer<E> {
         self.to_kill.lock().unwrap().push(e);
     }
 
+    /// Whether the entity is already going to be removed at the end of the tick
+    pub fn is_killed(&self, e: E::ID) -> bool {
+        self.to_kill.lock().unwrap().contains(&e)
+    }
+
     pub fn kill_all(&self, e: &[E::ID]) {
         self.to_kill.lock().unwrap().extend_from_slice(e);
     }
@@ -9,12 +9,33 @@ pub struct Replay {
     pub enabled: bool,
     commands: Vec<(Tick, WorldCommand)>,
     pub last_tick_recorded: Tick,
+    /// The save the replay starts from, it starts from an empty world otherwise
+    #[serde(default)]
+    pub base_save: Option<String>,
 }
 
 impl Replay {
+    /// An enabled replay starting from the given save, which must be at the given tick
+    pub fn starting_from(base_save: String, tick: Tick) -> Self {
+        Self {
+            enabled: true,
+            commands: vec![],
+            last_tick_recorded: tick,
+            base_save: Some(base_save),
+        }
+    }
+
     pub fn push(&mut self, tick: Tick, command: WorldCommand) {
         self.commands.push((tick, command));
     }
+
+    /// Records the commands applied at the start of the given tick
+    pub fn record<'a>(&mut self, tick: Tick, commands: impl IntoIterator<Item = &'a WorldCommand>) {
+        for command in commands {
+            self.push(tick, command.clone());
+        }
+        self.last_tick_recorded = Tick(tick.0 + 1);
+    }
 }
 
 pub struct SimulationReplayLoader {
@@ -80,3 +101,23 @@ impl SimulationReplayLoader {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_marks_the_tick_as_recorded() {
+        let mut replay = Replay::starting_from("world".to_string(), Tick(10));
+        replay.record(Tick(10), []);
+        assert!(replay.commands.is_empty());
+        assert_eq!(replay.last_tick_recorded.0, 11);
+
+        let commands = [WorldCommand::MapUndo, WorldCommand::MapRedo];
+        replay.record(Tick(12), &commands);
+        assert_eq!(replay.last_tick_recorded.0, 13);
+        assert_eq!(replay.commands.len(), 2);
+        assert!(replay.commands.iter().all(|(tick, _)| tick.0 == 12));
+        assert!(matches!(replay.commands[1].1, WorldCommand::MapRedo));
+    }
+}
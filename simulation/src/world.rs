@@ -1,4 +1,5 @@
-use crate::economy::{Bought, Market, Sold, Workers};
+use crate::economy::{Bought, Market, Sold, Wallet, Workers};
+use crate::map::BuildingID;
 use crate::map_dynamic::{
     DispatchID, Dispatcher, Itinerary, ItineraryFollower, ItineraryLeader, ParkingManagement,
     Router,
@@ -9,14 +10,19 @@ use crate::souls::goods_company::GoodsCompanyState;
 use crate::souls::human::{HumanDecision, PersonalInfo};
 use crate::transportation::train::{Locomotive, LocomotiveReservation, RailWagon};
 use crate::transportation::{
-    Location, Pedestrian, Speed, TransportGrid, Transporter, Vehicle, VehicleKind, VehicleState,
+    Bicycle, Location, Pedestrian, Speed, TransportGrid, Transporter, Vehicle, VehicleKind,
+    VehicleState,
 };
 use crate::utils::par_command_buffer::SimDrop;
 use crate::utils::resources::Resources;
 use crate::{impl_entity, impl_trans, SoulID};
 use common::iter::chain;
+use common::saveload::{Bincode, Encoder};
+use common::FastMap;
 use derive_more::{From, TryInto};
 use geom::{Transform, Vec2, Vec3};
+use prototypes::RollingStockID;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use slotmapd::__impl::Serialize;
 use slotmapd::{new_key_type, HopSlotMap};
@@ -89,6 +95,7 @@ pub struct HumanEnt {
     pub speed: Speed,
     pub location: Location,
     pub pedestrian: Pedestrian,
+    pub bicycle: Option<Bicycle>,
     pub collider: Option<Transporter>,
 
     pub router: Router,
@@ -97,8 +104,10 @@ pub struct HumanEnt {
     pub decision: HumanDecision,
     pub home: Home,
     pub food: BuyFood,
+    pub leisure: Leisure,
     pub bought: Bought,
     pub work: Option<Work>,
+    pub wallet: Wallet,
 
     pub personal_info: Box<PersonalInfo>,
 }
@@ -125,6 +134,10 @@ pub struct TrainEnt {
     pub res: LocomotiveReservation,
     #[inspect(skip)]
     pub leader: ItineraryLeader,
+    /// The rolling stock of the wagons, locomotive first
+    pub consist: Vec<RollingStockID>,
+    /// The depot the train was recalled to, it is out of service while set
+    pub depot: Option<BuildingID>,
 }
 
 impl SimDrop for TrainEnt {
@@ -190,6 +203,44 @@ pub struct World {
 }
 
 impl World {
+    /// Each kind of entity is its own blob, the kinds that rarely change are seldom sent again
+    pub(crate) fn to_blobs(&self, blobs: &mut Vec<(String, Vec<u8>)>) -> std::io::Result<()> {
+        blobs.push((
+            "world/vehicles".to_string(),
+            Bincode::encode(&self.vehicles)?,
+        ));
+        blobs.push(("world/humans".to_string(), Bincode::encode(&self.humans)?));
+        blobs.push(("world/trains".to_string(), Bincode::encode(&self.trains)?));
+        blobs.push(("world/wagons".to_string(), Bincode::encode(&self.wagons)?));
+        blobs.push((
+            "world/freight_stations".to_string(),
+            Bincode::encode(&self.freight_stations)?,
+        ));
+        blobs.push((
+            "world/companies".to_string(),
+            Bincode::encode(&self.companies)?,
+        ));
+        Ok(())
+    }
+
+    pub(crate) fn from_blobs(blobs: &mut FastMap<String, Vec<u8>>) -> Option<Self> {
+        fn decode<T: DeserializeOwned>(
+            blobs: &mut FastMap<String, Vec<u8>>,
+            name: &str,
+        ) -> Option<T> {
+            Bincode::decode(&blobs.remove(name)?).ok()
+        }
+
+        Some(Self {
+            vehicles: decode(blobs, "world/vehicles")?,
+            humans: decode(blobs, "world/humans")?,
+            trains: decode(blobs, "world/trains")?,
+            wagons: decode(blobs, "world/wagons")?,
+            freight_stations: decode(blobs, "world/freight_stations")?,
+            companies: decode(blobs, "world/companies")?,
+        })
+    }
+
     pub fn get<E: EntityID>(&self, id: E) -> Option<&E::Entity> {
         <<E as EntityID>::Entity as Entity>::storage(self).get(id)
     }
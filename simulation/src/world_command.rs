@@ -1,7 +1,9 @@
 use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::time::Instant;
 
-use prototypes::RollingStockID;
+use prototypes::{try_prototype, ItemID, Money, RollingStockID};
 use serde::{Deserialize, Serialize};
 
 use geom::{vec3, Vec2, Vec3, OBB};
@@ -12,17 +14,27 @@ use WorldCommand::*;
 use crate::economy::Government;
 use crate::map::procgen::{load_parismap, load_testfield};
 use crate::map::{
-    BuildingID, BuildingKind, Environment, IntersectionID, LaneID, LanePattern, LanePatternBuilder,
-    LightPolicy, LotID, Map, MapProject, ProjectKind, RoadID, TerraformKind, TurnPolicy, Zone,
+    BlueprintPaste, BuildingID, BuildingKind, Environment, IntersectionID, LaneID, LanePattern,
+    LanePatternBuilder, LightPolicy, LotID, Map, MapHistory, MapProject, PointGenerateError,
+    ProjectKind, Road, RoadID, RoadSegmentKind, TerraformKind, TurnPolicy, Zone,
 };
-use crate::map_dynamic::{BuildingInfos, ParkingManagement};
-use crate::multiplayer::chat::Message;
+use crate::map_dynamic::{BuildingInfos, Dispatcher, ParkingManagement};
+use crate::multiplayer::chat::{Message, MessageKind};
 use crate::multiplayer::MultiplayerState;
+use crate::scripting::{ScriptEvent, Scripts};
+use crate::transportation::depot::{
+    despawn_train, has_depot, in_depot, recall_train, recompose_place, recompose_train,
+    release_train, reverse_train,
+};
+use crate::transportation::freight_route::{
+    FreightRoute, FreightRouteID, FreightRoutes, Timetable,
+};
+use crate::transportation::signals::{RailSignalKind, RailSignals};
 use crate::transportation::testing_vehicles::RandomVehicles;
 use crate::transportation::train::{spawn_train, RailWagonKind};
 use crate::transportation::{spawn_parked_vehicle_with_spot, unpark, VehicleKind};
 use crate::utils::rand_provider::RandProvider;
-use crate::{Replay, Simulation, SimulationOptions};
+use crate::{Replay, Simulation, SimulationOptions, TrainID};
 
 #[derive(Clone, Default)]
 pub struct WorldCommands {
@@ -62,6 +74,38 @@ pub enum WorldCommand {
         lane: LaneID,
         dist: f32,
     },
+    /// Removes the train and its wagons
+    DespawnTrain(TrainID),
+    /// Sends the train to the nearest depot, taking it out of service
+    RecallTrain(TrainID),
+    /// Puts a recalled train back in service
+    ReleaseTrain(TrainID),
+    /// Makes a stopped train go the other way on the opposite track
+    ReverseTrain(TrainID),
+    /// Replaces the wagons of a train waiting in its depot, only the new wagons are paid for
+    RecomposeTrain {
+        train: TrainID,
+        wagons: Vec<RollingStockID>,
+    },
+    /// Creates a freight route going through the freight stations in order, see [`FreightRoute`]
+    AddFreightRoute {
+        stations: Vec<BuildingID>,
+        items: Vec<ItemID>,
+        timetable: Timetable,
+    },
+    /// Changes the stations, items and timetable of a freight route, keeping its train
+    UpdateFreightRoute {
+        id: FreightRouteID,
+        stations: Vec<BuildingID>,
+        items: Vec<ItemID>,
+        timetable: Timetable,
+    },
+    RemoveFreightRoute(FreightRouteID),
+    /// Places a signal at the end of a rail lane, or removes it if `kind` is `None`
+    SetRailSignal {
+        lane: LaneID,
+        kind: Option<RailSignalKind>,
+    },
     MapMakeConnection {
         from: MapProject,
         to: MapProject,
@@ -72,11 +116,18 @@ pub enum WorldCommand {
         Vec<MapProject>,
         Vec<(usize, usize, Option<Vec2>, LanePattern)>,
     ),
+    /// Roads are built like [`WorldCommand::MapMakeMultipleConnections`], then the buildings
+    MapBuildBlueprint(BlueprintPaste),
     MapUpdateIntersectionPolicy {
         inter: IntersectionID,
         turn: TurnPolicy,
         light: LightPolicy,
     },
+    /// Rebuilds the lanes of a road in place, keeping its lots and connected buildings
+    MapUpdateRoadPattern {
+        road: RoadID,
+        pattern: LanePattern,
+    },
     MapBuildSpecialBuilding {
         pos: OBB,
         kind: BuildingKind,
@@ -97,6 +148,98 @@ pub enum WorldCommand {
         zone: Zone,
     },
     SetGameTime(GameTime),
+    /// Reverts the last map edit of the player, see [`MapHistory`]
+    MapUndo,
+    /// Reapplies the last map edit the player reverted
+    MapRedo,
+    /// The player that sent the commands following it in the tick, the server puts it in front
+    /// of the commands of each player, see [`CommandSender`]
+    SentBy(String),
+    /// Borrows money, repaid daily over the term with interest, see [`Government::daily_update`]
+    TakeLoan {
+        principal: Money,
+        term_days: u32,
+    },
+}
+
+/// Why a command was rejected by [`WorldCommand::validate`].
+/// Rejected commands are not applied and are not charged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    InsufficientFunds {
+        cost: Money,
+        available: Money,
+    },
+    /// The entity the command refers to does not exist (anymore)
+    MissingEntity(&'static str),
+    /// The new object would overlap an existing one
+    Overlap,
+    /// The road would be too steep
+    InvalidSlope,
+    /// There is no map edit to undo or redo
+    NothingToRevert,
+    /// The loan would go over [`crate::economy::CREDIT_LIMIT`]
+    CreditLimitExceeded {
+        available: Money,
+    },
+    InvalidLoan,
+    /// The government is bankrupt and cannot spend or borrow anymore
+    Bankrupt,
+    /// A freight route needs at least two different stations
+    InvalidFreightRoute,
+    /// The train must be stopped
+    TrainMoving,
+    /// The train must be waiting in its depot
+    TrainNotInDepot,
+    /// None of the wagons of the train can pull it
+    NoLocomotive,
+    /// A road links projects that are not part of the command, or a project to itself
+    InvalidLink,
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::InsufficientFunds { cost, available } => {
+                write!(
+                    f,
+                    "not enough money: costs {cost} but only {available} available"
+                )
+            }
+            CommandError::MissingEntity(what) => write!(f, "{what} does not exist"),
+            CommandError::Overlap => f.pad("overlaps with something else"),
+            CommandError::InvalidSlope => f.pad("too steep"),
+            CommandError::NothingToRevert => f.pad("nothing to undo or redo"),
+            CommandError::CreditLimitExceeded { available } => {
+                write!(f, "credit limit reached: only {available} can be borrowed")
+            }
+            CommandError::InvalidLoan => f.pad("invalid loan"),
+            CommandError::Bankrupt => f.pad("the government is bankrupt"),
+            CommandError::TrainMoving => f.pad("the train must be stopped"),
+            CommandError::TrainNotInDepot => f.pad("the train must be waiting in its depot"),
+            CommandError::NoLocomotive => f.pad("the train needs a locomotive"),
+            CommandError::InvalidLink => f.pad("a road links invalid projects"),
+            CommandError::InvalidFreightRoute => {
+                f.pad("a freight route needs at least two different stations")
+            }
+        }
+    }
+}
+
+impl Error for CommandError {}
+
+/// Commands rejected during the last tick, by index in the commands of the tick.
+/// Used by clients to explain why their action failed.
+#[derive(Default)]
+pub struct RejectedCommands {
+    pub rejected: Vec<(usize, CommandError)>,
+}
+
+/// The player that sent the command being applied, empty in single player and for the commands
+/// of the simulation itself. Reset at the start of every tick.
+#[derive(Default)]
+pub struct CommandSender {
+    pub name: String,
 }
 
 impl AsRef<[WorldCommand]> for WorldCommands {
@@ -194,6 +337,25 @@ impl WorldCommands {
         })
     }
 
+    pub fn map_update_road_pattern(&mut self, road: RoadID, pattern: LanePattern) {
+        self.commands.push(MapUpdateRoadPattern { road, pattern })
+    }
+
+    pub fn take_loan(&mut self, principal: Money, term_days: u32) {
+        self.commands.push(TakeLoan {
+            principal,
+            term_days,
+        })
+    }
+
+    pub fn map_undo(&mut self) {
+        self.commands.push(MapUndo)
+    }
+
+    pub fn map_redo(&mut self) {
+        self.commands.push(MapRedo)
+    }
+
     pub fn map_update_intersection_policy(
         &mut self,
         id: IntersectionID,
@@ -217,13 +379,256 @@ impl WorldCommand {
                 | MapUpdateIntersectionPolicy { .. }
                 | UpdateZone { .. }
                 | SetGameTime(_)
+                | TakeLoan { .. }
+                | AddFreightRoute { .. }
+                | UpdateFreightRoute { .. }
+                | RemoveFreightRoute(_)
+                | SetRailSignal { .. }
+                | RecallTrain(_)
+                | ReleaseTrain(_)
         )
     }
 
-    pub fn apply(&self, sim: &mut Simulation) {
+    /// Returns true if the command edits the map and is recorded in the [`MapHistory`]
+    pub fn is_undoable(&self) -> bool {
+        matches!(
+            self,
+            MapRemoveIntersection(_)
+                | MapRemoveRoad(_)
+                | MapRemoveBuilding(_)
+                | MapBuildHouse(_)
+                | MapMakeConnection { .. }
+                | MapMakeMultipleConnections(..)
+                | MapBuildBlueprint(_)
+                | MapUpdateIntersectionPolicy { .. }
+                | MapUpdateRoadPattern { .. }
+                | MapBuildSpecialBuilding { .. }
+                | UpdateZone { .. }
+                | Terraform { .. }
+        )
+    }
+
+    /// Checks the indices and prototypes the cost and the application rely on, so that a
+    /// malformed command is rejected instead of panicking on every client.
+    fn check_well_formed(&self) -> Result<(), CommandError> {
+        let links_valid = |n: usize, links: &[(usize, usize, Option<Vec2>, LanePattern)]| {
+            if links
+                .iter()
+                .all(|&(from, to, _, _)| from < n && to < n && from != to)
+            {
+                Ok(())
+            } else {
+                Err(CommandError::InvalidLink)
+            }
+        };
+        let kind_exists = |kind: BuildingKind| {
+            let exists = match kind {
+                BuildingKind::GoodsCompany(id) => try_prototype(id).is_some(),
+                BuildingKind::RailFreightStation(id) => try_prototype(id).is_some(),
+                BuildingKind::Leisure(id) => try_prototype(id).is_some(),
+                _ => true,
+            };
+            if exists {
+                Ok(())
+            } else {
+                Err(CommandError::MissingEntity("building prototype"))
+            }
+        };
+
+        match *self {
+            MapMakeMultipleConnections(ref projects, ref links) => {
+                links_valid(projects.len(), links)
+            }
+            MapBuildBlueprint(ref paste) => {
+                links_valid(paste.projects.len(), &paste.links)?;
+                paste.buildings.iter().try_for_each(|b| kind_exists(b.kind))
+            }
+            MapBuildSpecialBuilding { kind, .. } => kind_exists(kind),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the command can be applied and that the government can afford it.
+    /// Must be deterministic as it is run on every client.
+    pub fn validate(&self, sim: &Simulation) -> Result<(), CommandError> {
+        self.check_well_formed()?;
         let cost = Government::action_cost(self, sim);
-        sim.write::<Government>().money -= cost;
+        let gvt = sim.read::<Government>();
+        if gvt.bankrupt && (cost > Money::ZERO || matches!(self, TakeLoan { .. })) {
+            return Err(CommandError::Bankrupt);
+        }
+        let available = gvt.money;
+        if cost > Money::ZERO && cost > available {
+            return Err(CommandError::InsufficientFunds { cost, available });
+        }
+
+        if let TakeLoan {
+            principal,
+            term_days,
+        } = *self
+        {
+            if principal <= Money::ZERO || term_days == 0 {
+                return Err(CommandError::InvalidLoan);
+            }
+            let available = gvt.available_credit();
+            if principal > available {
+                return Err(CommandError::CreditLimitExceeded { available });
+            }
+        }
+        drop(gvt);
+
+        let map = sim.map();
+        let exists = |proj: &MapProject| {
+            if proj.kind.check_valid(&map) {
+                Ok(())
+            } else {
+                Err(CommandError::MissingEntity("map object"))
+            }
+        };
+
+        match *self {
+            MapRemoveIntersection(id) if !map.intersections().contains_key(id) => {
+                Err(CommandError::MissingEntity("intersection"))
+            }
+            MapRemoveRoad(id) if !map.roads().contains_key(id) => {
+                Err(CommandError::MissingEntity("road"))
+            }
+            MapRemoveBuilding(id) if !map.buildings().contains_key(id) => {
+                Err(CommandError::MissingEntity("building"))
+            }
+            MapBuildHouse(id) if !map.lots().contains_key(id) => {
+                Err(CommandError::MissingEntity("lot"))
+            }
+            MapUpdateIntersectionPolicy { inter, .. }
+                if !map.intersections().contains_key(inter) =>
+            {
+                Err(CommandError::MissingEntity("intersection"))
+            }
+            MapUpdateRoadPattern { road, .. } if !map.roads().contains_key(road) => {
+                Err(CommandError::MissingEntity("road"))
+            }
+            UpdateZone { building, .. } if !map.buildings().contains_key(building) => {
+                Err(CommandError::MissingEntity("building"))
+            }
+            SpawnTrain { lane, .. } if !map.lanes().contains_key(lane) => {
+                Err(CommandError::MissingEntity("lane"))
+            }
+            MapMakeConnection {
+                ref from,
+                ref to,
+                inter,
+                ref pat,
+            } => {
+                exists(from)?;
+                exists(to)?;
+                if from.pos.distance(to.pos) < 1.0 {
+                    return Err(CommandError::Overlap);
+                }
+
+                let segment = match inter {
+                    Some(x) => RoadSegmentKind::from_elbow(from.pos.xy(), to.pos.xy(), x),
+                    None => RoadSegmentKind::Straight,
+                };
+                let is_rail = pat.lanes().any(|(kind, _, _)| kind.is_rail());
+                let (_, err) =
+                    Road::generate_points(from.pos, to.pos, segment, is_rail, &map.environment);
+                if let Some(PointGenerateError::TooSteep) = err {
+                    return Err(CommandError::InvalidSlope);
+                }
+                Ok(())
+            }
+            MapMakeMultipleConnections(ref projects, _) => projects.iter().try_for_each(exists),
+            MapBuildBlueprint(ref paste) => paste.projects.iter().try_for_each(exists),
+            MapBuildSpecialBuilding {
+                ref pos,
+                connected_road,
+                ..
+            } => {
+                if map.building_overlaps(*pos) {
+                    return Err(CommandError::Overlap);
+                }
+                if let Some(road) = connected_road {
+                    if !map.roads().contains_key(road) {
+                        return Err(CommandError::MissingEntity("road"));
+                    }
+                }
+                Ok(())
+            }
+            AddFreightRoute {
+                ref stations,
+                ref items,
+                ..
+            } => validate_freight_route(&map, stations, items),
+            UpdateFreightRoute { id, .. } if sim.read::<FreightRoutes>().get(id).is_none() => {
+                Err(CommandError::MissingEntity("freight route"))
+            }
+            UpdateFreightRoute {
+                ref stations,
+                ref items,
+                ..
+            } => validate_freight_route(&map, stations, items),
+            DespawnTrain(id) | RecallTrain(id) | ReleaseTrain(id) | ReverseTrain(id)
+                if sim.world().trains.get(id).is_none() =>
+            {
+                Err(CommandError::MissingEntity("train"))
+            }
+            RecallTrain(_) if !has_depot(&map) => Err(CommandError::MissingEntity("train depot")),
+            ReverseTrain(id) if sim.world().trains.get(id).unwrap().speed.0 > 0.1 => {
+                Err(CommandError::TrainMoving)
+            }
+            RecomposeTrain { train, ref wagons } => {
+                let Some(t) = sim.world().trains.get(train) else {
+                    return Err(CommandError::MissingEntity("train"));
+                };
+                if !in_depot(t) {
+                    return Err(CommandError::TrainNotInDepot);
+                }
+                if wagons.is_empty() || wagons.iter().any(|&w| try_prototype(w).is_none()) {
+                    return Err(CommandError::MissingEntity("rolling stock"));
+                }
+                if !has_locomotive(wagons) {
+                    return Err(CommandError::NoLocomotive);
+                }
+                // the train is paid for before being put back on the rails, make sure it can be
+                if recompose_place(sim, train).is_none() {
+                    return Err(CommandError::MissingEntity("rail lane"));
+                }
+                Ok(())
+            }
+            SpawnTrain { ref wagons, .. } if wagons.iter().any(|&w| try_prototype(w).is_none()) => {
+                Err(CommandError::MissingEntity("rolling stock"))
+            }
+            SpawnTrain { ref wagons, .. } if !has_locomotive(wagons) => {
+                Err(CommandError::NoLocomotive)
+            }
+            SetRailSignal { lane, .. }
+                if !map.lanes().get(lane).map_or(false, |l| l.kind.is_rail()) =>
+            {
+                Err(CommandError::MissingEntity("rail lane"))
+            }
+            RemoveFreightRoute(id) if sim.read::<FreightRoutes>().get(id).is_none() => {
+                Err(CommandError::MissingEntity("freight route"))
+            }
+            MapUndo
+                if !sim
+                    .read::<MapHistory>()
+                    .can_undo(&sim.read::<CommandSender>().name) =>
+            {
+                Err(CommandError::NothingToRevert)
+            }
+            MapRedo
+                if !sim
+                    .read::<MapHistory>()
+                    .can_redo(&sim.read::<CommandSender>().name) =>
+            {
+                Err(CommandError::NothingToRevert)
+            }
+            _ => Ok(()),
+        }
+    }
 
+    /// Validates then applies the command, only charging the government if it is valid
+    pub fn apply(&self, sim: &mut Simulation) -> Result<(), CommandError> {
         let mut rep = sim.resources.write::<Replay>();
         if rep.enabled {
             let tick = sim.read::<GameTime>().tick;
@@ -231,14 +636,39 @@ impl WorldCommand {
         }
         drop(rep);
 
+        self.execute(sim)
+    }
+
+    /// Validates then applies the command without recording it in the replay,
+    /// for the commands issued by the simulation itself like the ones of the scripts
+    pub(crate) fn execute(&self, sim: &mut Simulation) -> Result<(), CommandError> {
+        if let Err(e) = self.validate(sim) {
+            log::info!("rejected {:?}: {}", self, e);
+            return Err(e);
+        }
+
+        let cost = Government::action_cost(self, sim);
+        sim.write::<Government>().money -= cost;
+
+        let undoable = self.is_undoable();
+        if undoable {
+            sim.map_mut().open_journal();
+        }
+
         match *self {
             MapRemoveIntersection(id) => sim.map_mut().remove_intersection(id),
             MapRemoveRoad(id) => drop(sim.map_mut().remove_road(id)),
             MapRemoveBuilding(id) => drop(sim.map_mut().remove_building(id)),
             MapBuildHouse(id) => {
-                if let Some(build) = sim.map_mut().build_house(id) {
+                let build = sim.map_mut().build_house(id);
+                if let Some(build) = build {
                     let mut infos = sim.write::<BuildingInfos>();
                     infos.insert(build);
+                    let kind = sim.map().buildings[build].kind;
+                    sim.write::<Scripts>().push(ScriptEvent::BuildingBuilt {
+                        building: build,
+                        kind,
+                    });
                 }
             }
             MapMakeConnection {
@@ -250,26 +680,32 @@ impl WorldCommand {
                 sim.write::<Map>().make_connection(from, to, inter, pat);
             }
             MapMakeMultipleConnections(ref projects, ref links) => {
+                make_multiple_connections(&mut sim.map_mut(), projects, links);
+            }
+            MapBuildBlueprint(ref paste) => {
                 let mut map = sim.map_mut();
-                let mut inters = BTreeMap::new();
-                for (from, to, interpoint, pat) in links {
-                    let mut fromproj = projects[*from];
-                    let mut toproj = projects[*to];
+                let roads = make_multiple_connections(&mut map, &paste.projects, &paste.links);
+                let mut infos = sim.write::<BuildingInfos>();
+                let mut scripts = sim.write::<Scripts>();
 
-                    if let Some(i) = inters.get(from) {
-                        fromproj.kind = ProjectKind::Intersection(*i);
-                    }
-                    if let Some(i) = inters.get(to) {
-                        toproj.kind = ProjectKind::Intersection(*i);
-                    }
+                for b in &paste.buildings {
+                    let connected_road = b
+                        .road
+                        .and_then(|i| roads.get(i).copied().flatten())
+                        .filter(|r| map.roads.contains_key(*r));
 
-                    if let Some((_, r)) = map.make_connection(fromproj, toproj, *interpoint, pat) {
-                        if fromproj.kind.is_ground() {
-                            inters.insert(*from, map.roads[r].src);
-                        }
-                        if toproj.kind.is_ground() {
-                            inters.insert(*to, map.roads[r].dst);
-                        }
+                    if let Some(id) = map.build_special_building(
+                        &b.obb,
+                        b.kind,
+                        b.gen,
+                        b.zone.clone(),
+                        connected_road,
+                    ) {
+                        infos.insert(id);
+                        scripts.push(ScriptEvent::BuildingBuilt {
+                            building: id,
+                            kind: b.kind,
+                        });
                     }
                 }
             }
@@ -281,6 +717,14 @@ impl WorldCommand {
                 i.light_policy = lp;
                 i.turn_policy = tp;
             }),
+            MapUpdateRoadPattern { road, ref pattern } => {
+                let removed = sim.map_mut().update_road_pattern(road, pattern);
+                if let Some(removed) = removed {
+                    for (it, _, _) in sim.world_mut_unchecked().query_it_trans_speed() {
+                        it.reroute_if_uses(&removed);
+                    }
+                }
+            }
             MapBuildSpecialBuilding {
                 pos: obb,
                 kind,
@@ -296,6 +740,8 @@ impl WorldCommand {
                     connected_road,
                 ) {
                     sim.write::<BuildingInfos>().insert(id);
+                    sim.write::<Scripts>()
+                        .push(ScriptEvent::BuildingBuilt { building: id, kind });
                 }
             }
             SetGameTime(gt) => *sim.write::<GameTime>() = gt,
@@ -312,6 +758,40 @@ impl WorldCommand {
                 spawn_train(sim, wagons, RailWagonKind::Freight, lane, dist);
             }
 
+            AddFreightRoute {
+                ref stations,
+                ref items,
+                timetable,
+            } => {
+                let route = FreightRoute::new(stations.clone(), items.clone(), timetable);
+                sim.write::<FreightRoutes>().insert(route);
+            }
+            UpdateFreightRoute {
+                id,
+                ref stations,
+                ref items,
+                timetable,
+            } => {
+                sim.write::<FreightRoutes>()
+                    .update(id, stations.clone(), items.clone(), timetable);
+            }
+            RemoveFreightRoute(id) => {
+                let route = sim.write::<FreightRoutes>().remove(id);
+                if let Some(train) = route.and_then(|r| r.train) {
+                    sim.write::<Dispatcher>().free(train);
+                }
+            }
+
+            SetRailSignal { lane, kind } => sim.write::<RailSignals>().set(lane, kind),
+            DespawnTrain(id) => despawn_train(sim, id),
+            RecallTrain(id) => {
+                if !recall_train(sim, id) {
+                    log::info!("no depot reachable to recall {:?}", id);
+                }
+            }
+            ReleaseTrain(id) => release_train(sim, id),
+            ReverseTrain(id) => drop(reverse_train(sim, id)),
+            RecomposeTrain { train, ref wagons } => drop(recompose_train(sim, train, wagons)),
             MapLoadParis => load_parismap(&mut sim.map_mut()),
             MapLoadTestField { pos, size, spacing } => {
                 load_testfield(&mut sim.map_mut(), pos, size, spacing)
@@ -375,8 +855,73 @@ impl WorldCommand {
                 sim.map_mut()
                     .terraform(tick, kind, center, radius, amount, level, slope);
             }
+            MapUndo => {
+                let player = sim.read::<CommandSender>().name.clone();
+                let recreated = sim.write::<MapHistory>().undo(&player, &mut sim.map_mut());
+                let mut infos = sim.write::<BuildingInfos>();
+                for id in recreated {
+                    infos.insert(id);
+                }
+            }
+            MapRedo => {
+                let player = sim.read::<CommandSender>().name.clone();
+                let recreated = sim.write::<MapHistory>().redo(&player, &mut sim.map_mut());
+                let mut infos = sim.write::<BuildingInfos>();
+                for id in recreated {
+                    infos.insert(id);
+                }
+            }
+            TakeLoan {
+                principal,
+                term_days,
+            } => {
+                sim.write::<Government>().take_loan(principal, term_days);
+            }
+            SentBy(ref name) => sim.write::<CommandSender>().name = name.clone(),
+        }
+
+        if undoable {
+            let mut edit = sim.map_mut().close_journal();
+            edit.cost = cost;
+            let player = sim.read::<CommandSender>().name.clone();
+            sim.write::<MapHistory>().push(&player, edit);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the road built for each link, if any
+fn make_multiple_connections(
+    map: &mut Map,
+    projects: &[MapProject],
+    links: &[(usize, usize, Option<Vec2>, LanePattern)],
+) -> Vec<Option<RoadID>> {
+    let mut inters = BTreeMap::new();
+    let mut roads = Vec::with_capacity(links.len());
+    for (from, to, interpoint, pat) in links {
+        let mut fromproj = projects[*from];
+        let mut toproj = projects[*to];
+
+        if let Some(i) = inters.get(from) {
+            fromproj.kind = ProjectKind::Intersection(*i);
+        }
+        if let Some(i) = inters.get(to) {
+            toproj.kind = ProjectKind::Intersection(*i);
+        }
+
+        let r = map.make_connection(fromproj, toproj, *interpoint, pat);
+        if let Some((_, r)) = r {
+            if fromproj.kind.is_ground() {
+                inters.insert(*from, map.roads[r].src);
+            }
+            if toproj.kind.is_ground() {
+                inters.insert(*to, map.roads[r].dst);
+            }
         }
+        roads.push(r.map(|(_, r)| r));
     }
+    roads
 }
 
 fn generate_terrain(sim: &mut Simulation, size: u16) {
@@ -434,8 +979,175 @@ impl From<Vec<WorldCommand>> for WorldCommands {
     }
 }
 
+/// Chat messages of players are shown with the name and color the server knows them by,
+/// and the commands are marked as sent by the player so that they undo their own edits only
+impl common::StampSender for WorldCommands {
+    fn stamp_sender(&mut self, name: &str, color: u32) {
+        self.commands
+            .retain(|command| !matches!(command, SentBy(_)));
+        if !self.commands.is_empty() {
+            self.commands.insert(0, SentBy(name.to_string()));
+        }
+        for command in &mut self.commands {
+            if let SendMessage { ref mut message } = *command {
+                message.name = name.to_string();
+                message.color = geom::Color::from_hex(color as u64);
+                message.kind = MessageKind::PlayerChat;
+            }
+        }
+    }
+}
+
 impl From<&WorldCommands> for Vec<WorldCommand> {
     fn from(x: &WorldCommands) -> Self {
         x.commands.clone()
     }
 }
+
+/// Whether one of the wagons can pull the train, they must all exist
+fn has_locomotive(wagons: &[RollingStockID]) -> bool {
+    wagons.iter().any(|&w| w.prototype().acc_force > 0.0)
+}
+
+fn validate_freight_route(
+    map: &Map,
+    stations: &[BuildingID],
+    items: &[ItemID],
+) -> Result<(), CommandError> {
+    for &station in stations {
+        let is_station = map.buildings().get(station).map_or(false, |b| {
+            matches!(b.kind, BuildingKind::RailFreightStation(_))
+        });
+        if !is_station {
+            return Err(CommandError::MissingEntity("freight station"));
+        }
+    }
+    if items.iter().any(|&item| try_prototype(item).is_none()) {
+        return Err(CommandError::MissingEntity("item"));
+    }
+    if stations.len() < 2 || stations.iter().all(|&s| s == stations[0]) {
+        return Err(CommandError::InvalidFreightRoute);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+    use prototypes::Money;
+
+    use crate::economy::Government;
+    use crate::map::{LanePatternBuilder, MapProject, RoadID};
+    use crate::tests::TestCtx;
+    use crate::world_command::CommandError;
+    use crate::WorldCommand;
+
+    #[test]
+    fn rejected_commands_are_not_charged() {
+        let mut test = TestCtx::new();
+
+        let cmd = WorldCommand::MapRemoveRoad(RoadID::default());
+        assert_eq!(
+            cmd.validate(&test.g),
+            Err(CommandError::MissingEntity("road"))
+        );
+
+        test.g.write::<Government>().money = Money::new_bucks(10);
+        let before = test.g.read::<Government>().money;
+
+        let cmd = WorldCommand::MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::new().build(),
+        };
+        assert!(matches!(
+            cmd.validate(&test.g),
+            Err(CommandError::InsufficientFunds { .. })
+        ));
+
+        assert!(cmd.apply(&mut test.g).is_err());
+        assert_eq!(test.g.read::<Government>().money, before);
+    }
+
+    #[test]
+    fn malformed_links_are_rejected() {
+        use crate::map::{BlueprintBuilding, BlueprintPaste, BuildingKind};
+        use geom::{Vec2, OBB};
+        use prototypes::{BuildingGen, GoodsCompanyID};
+
+        let test = TestCtx::new();
+        let projects = vec![
+            MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            MapProject::ground(vec3(100.0, 0.0, 0.0)),
+        ];
+        let pat = LanePatternBuilder::new().build();
+
+        for link in [(0, 2), (5, 1), (1, 1)] {
+            let cmd = WorldCommand::MapMakeMultipleConnections(
+                projects.clone(),
+                vec![(link.0, link.1, None, pat.clone())],
+            );
+            assert_eq!(cmd.validate(&test.g), Err(CommandError::InvalidLink));
+
+            let cmd = WorldCommand::MapBuildBlueprint(BlueprintPaste {
+                projects: projects.clone(),
+                links: vec![(link.0, link.1, None, pat.clone())],
+                buildings: vec![],
+            });
+            assert_eq!(cmd.validate(&test.g), Err(CommandError::InvalidLink));
+        }
+
+        let cmd = WorldCommand::MapBuildBlueprint(BlueprintPaste {
+            projects,
+            links: vec![(0, 1, None, pat)],
+            buildings: vec![BlueprintBuilding {
+                obb: OBB::new_corners([Vec2::ZERO; 4]),
+                kind: BuildingKind::GoodsCompany(GoodsCompanyID::new("unknown-company")),
+                gen: BuildingGen::NoWalkway {
+                    door_pos: Vec2::ZERO,
+                },
+                zone: None,
+                road: Some(0),
+            }],
+        });
+        assert_eq!(
+            cmd.validate(&test.g),
+            Err(CommandError::MissingEntity("building prototype"))
+        );
+    }
+
+    #[test]
+    fn chat_messages_get_the_sender_identity() {
+        use crate::multiplayer::chat::{Message, MessageKind};
+        use crate::world_command::WorldCommands;
+        use common::StampSender;
+        use prototypes::{GameInstant, Tick};
+
+        let mut commands: WorldCommands = vec![
+            WorldCommand::SentBy("alice".to_string()),
+            WorldCommand::SendMessage {
+                message: Message {
+                    name: "server".to_string(),
+                    text: "hello".to_string(),
+                    sent_at: GameInstant(Tick(0)),
+                    color: geom::Color::WHITE,
+                    kind: MessageKind::Warning,
+                },
+            },
+        ]
+        .into();
+        commands.stamp_sender("bob", 0xff0000);
+
+        // the sender the client claims is replaced too
+        assert_eq!(commands.as_ref().len(), 2);
+        assert!(matches!(commands.as_ref()[0], WorldCommand::SentBy(ref name) if name == "bob"));
+        let WorldCommand::SendMessage { ref message } = commands.as_ref()[1] else {
+            unreachable!()
+        };
+        assert_eq!(message.name, "bob");
+        assert_eq!(message.color, geom::Color::from_hex(0xff0000));
+        assert!(matches!(message.kind, MessageKind::PlayerChat));
+        assert_eq!(message.text, "hello");
+    }
+}